@@ -0,0 +1,104 @@
+//! Бенчмарк: длительность checkpoint'а батча из 256 страниц при
+//! `async_checkpoint = false` (pwrite) и `true` (io_uring).
+//!
+//! ```text
+//! cargo run --release --example checkpoint_io_bench -- [ITERATIONS] [DIR]
+//! ```
+//! DIR должен лежать на проверяемом диске (по умолчанию — временный каталог;
+//! на tmpfs fsync бесплатен и разница между режимами почти не видна).
+
+use datyredb::buffer_pool::BufferPool;
+use datyredb::checkpoint::CheckpointManager;
+use datyredb::config::{CheckpointConfig, DatabaseConfig};
+use datyredb::wal::WalWriter;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Страниц в батче: `checkpoint_batch_size` по умолчанию
+const BATCH_PAGES: usize = 256;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let iterations: usize = match args.next().map(|s| s.parse()) {
+        None => 50,
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            eprintln!("usage: checkpoint_io_bench [ITERATIONS] [DIR]");
+            std::process::exit(2);
+        }
+    };
+    let root = args.next().map(PathBuf::from).unwrap_or_else(|| {
+        std::env::temp_dir().join(format!("datyredb_bench_{}", std::process::id()))
+    });
+
+    println!(
+        "checkpoint of {BATCH_PAGES} dirty 4 KiB pages, {iterations} iterations, dir {}",
+        root.display()
+    );
+    println!("{:<22} {:>10} {:>10} {:>10} {:>10}", "mode", "min", "median", "mean", "max");
+    for async_checkpoint in [false, true] {
+        let dir = root.join(if async_checkpoint { "async" } else { "sync" });
+        match run(&dir, async_checkpoint, iterations) {
+            Ok((backend, mut durations)) => {
+                durations.sort();
+                let mean = durations.iter().sum::<Duration>() / durations.len() as u32;
+                let label = format!("async={async_checkpoint} ({backend})");
+                println!(
+                    "{label:<22} {:>10} {:>10} {:>10} {:>10}",
+                    ms(durations[0]),
+                    ms(durations[durations.len() / 2]),
+                    ms(mean),
+                    ms(durations[durations.len() - 1])
+                );
+            }
+            Err(e) => eprintln!("async={async_checkpoint}: {e}"),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    let _ = std::fs::remove_dir(&root);
+}
+
+fn run(
+    dir: &std::path::Path,
+    async_checkpoint: bool,
+    iterations: usize,
+) -> Result<(&'static str, Vec<Duration>), Box<dyn std::error::Error>> {
+    let _ = std::fs::remove_dir_all(dir);
+    let config = DatabaseConfig {
+        data_path: dir.to_path_buf(),
+        buffer_pool_size: 2 * BATCH_PAGES * 4096,
+        page_size: 4096,
+        checkpoint: CheckpointConfig {
+            checkpoint_batch_size: BATCH_PAGES,
+            async_checkpoint,
+            ..CheckpointConfig::default()
+        },
+        ..DatabaseConfig::default()
+    };
+    let pool = Arc::new(BufferPool::new(&config)?);
+    let wal = Arc::new(Mutex::new(WalWriter::create(config.wal_dir(), config.wal_segment_size)?));
+    let manager = CheckpointManager::new(config.checkpoint.clone(), Arc::clone(&pool), wal);
+
+    let pages: Vec<_> = (0..BATCH_PAGES)
+        .map(|_| pool.new_page().map(|page| page.page_id()))
+        .collect::<Result<_, _>>()?;
+    manager.manual_checkpoint()?; // Прогрев: файл выделен и записан целиком
+
+    let mut backend = "";
+    let mut durations = Vec::with_capacity(iterations);
+    for i in 0..iterations {
+        for &page_id in &pages {
+            pool.fetch_page(page_id)?.write()[4095] = i as u8;
+        }
+        let outcome = manager.manual_checkpoint()?;
+        assert_eq!(outcome.pages_written, BATCH_PAGES);
+        backend = outcome.io_backend;
+        durations.push(outcome.duration);
+    }
+    Ok((backend, durations))
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+}
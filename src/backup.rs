@@ -0,0 +1,594 @@
+//! Онлайн базовые копии, согласованные с WAL.
+//!
+//! C++ `StorageEngine::create_backup(path)` копирует файлы как есть и ничего
+//! не гарантирует, если движок в это время пишет. `create_backup` работает
+//! на живой базе:
+//!
+//! 1. запрещает удалять WAL с текущей позиции и с первой записи самой старой
+//!    активной транзакции, затем выполняет checkpoint: его CHECKPOINT_BEGIN —
+//!    стартовый LSN копии;
+//! 2. копирует файл данных постранично, проверяя checksum каждой страницы.
+//!    Страница, прочитанная посреди записи, с checksum'ом не сходится и
+//!    перечитывается;
+//! 3. между checkpoint'ами сбрасывает WAL: его последний LSN — конечный,
+//!    ни одна скопированная страница не новее его;
+//! 4. копирует сегменты от стартового LSN до конечного и последним пишет
+//!    манифест с LSN и CRC32 файлов: копия без манифеста не завершена.
+//!
+//! Восстановление копии (`restore`) начинает redo с checkpoint'а из
+//! манифеста и должно дойти хотя бы до конечного LSN. `verify_backup`
+//! сверяет файлы с манифестом и проигрывает копию в чистой директории.
+//!
+//! Запрет на удаление WAL у writer'а один: две копии одновременно не
+//! делаются.
+
+use crate::checkpoint::{CheckpointError, CheckpointManager};
+use crate::config::units::{format_timestamp, parse_timestamp};
+use crate::config::DatabaseConfig;
+use crate::disk_manager::DiskManager;
+use crate::fsck::{self, FsckReport, Status};
+use crate::page::{crc32, crc32_update, verify_checksum, Lsn, PageId};
+use crate::restore::{restore, RestoreError, RestoreOptions, RestoreReport, RestoreTarget};
+use crate::wal::{segment_file_name, WalReader, WalWriter};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Имя манифеста в директории копии
+pub const MANIFEST_FILE: &str = "backup_manifest";
+
+const MANIFEST_HEADER: &str = "# DatyreDB base backup";
+const MANIFEST_FORMAT: u32 = 1;
+
+/// Сколько раз перечитывать страницу, checksum которой не сходится
+const PAGE_READ_ATTEMPTS: usize = 10;
+
+// ============================================================================
+// Манифест
+// ============================================================================
+
+/// Файл копии и его контрольная сумма
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    /// Путь относительно директории копии, через '/'
+    pub path: String,
+    pub size: u64,
+    pub crc32: u32,
+}
+
+/// Манифест базовой копии
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupManifest {
+    pub created: SystemTime,
+    pub page_size: usize,
+    /// CHECKPOINT_BEGIN стартового checkpoint'а: с него начинается redo
+    pub start_lsn: Lsn,
+    /// Восстановление копии должно дойти хотя бы до этого LSN
+    pub end_lsn: Lsn,
+    /// Страниц в скопированном файле данных
+    pub pages: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+impl BackupManifest {
+    /// Манифест из директории копии
+    pub fn read(dir: &Path) -> Result<Self, BackupError> {
+        let path = dir.join(MANIFEST_FILE);
+        let text = std::fs::read_to_string(&path).map_err(io_err(&path))?;
+        Self::parse(&text).map_err(|message| BackupError::Manifest { path, message })
+    }
+
+    /// Манифест, если директория — копия с манифестом
+    pub fn read_if_present(dir: &Path) -> Result<Option<Self>, BackupError> {
+        if dir.join(MANIFEST_FILE).is_file() {
+            Self::read(dir).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        if lines.next().map(|(_, line)| line) != Some(MANIFEST_HEADER) {
+            return Err(format!("expected '{MANIFEST_HEADER}' on the first line"));
+        }
+        let (mut created, mut page_size, mut start_lsn, mut end_lsn, mut pages) =
+            (None, None, None, None, None);
+        let mut files = Vec::new();
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('#')) {
+            let bad = |what: &str| format!("line {n}: {what}: '{line}'");
+            if let Some(file) = line.strip_prefix("file ") {
+                let mut fields = file.rsplitn(3, ' ');
+                let (Some(crc), Some(size), Some(path)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    return Err(bad("expected 'file PATH SIZE CRC32'"));
+                };
+                let crc = crc.strip_prefix("0x").ok_or_else(|| bad("CRC32 must be hex"))?;
+                files.push(ManifestFile {
+                    path: path.to_string(),
+                    size: size.parse().map_err(|_| bad("invalid size"))?,
+                    crc32: u32::from_str_radix(crc, 16).map_err(|_| bad("invalid CRC32"))?,
+                });
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| bad("expected KEY = VALUE"))?;
+            let value = value.trim();
+            let number = || value.parse::<u64>().map_err(|_| bad("expected a number"));
+            match key.trim() {
+                "format" if number()? == u64::from(MANIFEST_FORMAT) => {}
+                "format" => return Err(bad("unsupported manifest format")),
+                "created" => created = Some(parse_timestamp(value).map_err(|e| bad(&e))?),
+                "page_size" => page_size = Some(number()? as usize),
+                "start_lsn" => start_lsn = Some(number()?),
+                "end_lsn" => end_lsn = Some(number()?),
+                "pages" => pages = Some(number()?),
+                other => return Err(bad(&format!("unknown key '{other}'"))),
+            }
+        }
+        let missing = |key: &str| format!("missing '{key}'");
+        Ok(Self {
+            created: created.ok_or_else(|| missing("created"))?,
+            page_size: page_size.ok_or_else(|| missing("page_size"))?,
+            start_lsn: start_lsn.ok_or_else(|| missing("start_lsn"))?,
+            end_lsn: end_lsn.ok_or_else(|| missing("end_lsn"))?,
+            pages: pages.ok_or_else(|| missing("pages"))?,
+            files,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "{MANIFEST_HEADER}\nformat = {MANIFEST_FORMAT}\ncreated = {}\npage_size = {}\n\
+             start_lsn = {}\nend_lsn = {}\npages = {}\n",
+            format_timestamp(self.created),
+            self.page_size,
+            self.start_lsn,
+            self.end_lsn,
+            self.pages
+        );
+        for file in &self.files {
+            out.push_str(&format!("file {} {} {:#010x}\n", file.path, file.size, file.crc32));
+        }
+        out
+    }
+}
+
+// ============================================================================
+// Ошибки
+// ============================================================================
+
+/// Копия не создана или не прошла проверку
+#[derive(Debug)]
+pub enum BackupError {
+    Io { path: PathBuf, source: io::Error },
+    /// Стартовый checkpoint не выполнен
+    Checkpoint(CheckpointError),
+    /// Checksum страницы не сходится ни при одном чтении
+    CorruptPage { page_id: PageId },
+    /// В директории назначения уже есть файлы
+    TargetNotEmpty(PathBuf),
+    /// Манифест не читается
+    Manifest { path: PathBuf, message: String },
+    /// Файл копии не совпадает с манифестом
+    Damaged { path: PathBuf, message: String },
+    /// Проигрывание копии не удалось
+    Restore(RestoreError),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            BackupError::Checkpoint(e) => write!(f, "start checkpoint: {e}"),
+            BackupError::CorruptPage { page_id } => write!(
+                f,
+                "page {page_id} fails its checksum on {PAGE_READ_ATTEMPTS} reads; \
+                 run datyre-fsck on the source"
+            ),
+            BackupError::TargetNotEmpty(path) => {
+                write!(f, "{} is not empty; a backup needs a new directory", path.display())
+            }
+            BackupError::Manifest { path, message } => write!(f, "{}: {message}", path.display()),
+            BackupError::Damaged { path, message } => write!(f, "{}: {message}", path.display()),
+            BackupError::Restore(e) => write!(f, "replay: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BackupError::Io { source, .. } => Some(source),
+            BackupError::Checkpoint(e) => Some(e),
+            BackupError::Restore(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CheckpointError> for BackupError {
+    fn from(e: CheckpointError) -> Self {
+        BackupError::Checkpoint(e)
+    }
+}
+
+impl From<RestoreError> for BackupError {
+    fn from(e: RestoreError) -> Self {
+        BackupError::Restore(e)
+    }
+}
+
+fn io_err(path: &Path) -> impl FnOnce(io::Error) -> BackupError + '_ {
+    move |source| BackupError::Io { path: path.to_path_buf(), source }
+}
+
+// ============================================================================
+// Создание копии
+// ============================================================================
+
+/// Снимает запрет на удаление WAL и при ошибке копирования
+struct RetainGuard<'a>(&'a Mutex<WalWriter>);
+
+impl Drop for RetainGuard<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().set_retain_from(None);
+    }
+}
+
+/// Базовая копия базы, которой управляет `manager`, в новую директорию `dest`
+pub fn create_backup(
+    manager: &CheckpointManager,
+    dest: &Path,
+) -> Result<BackupManifest, BackupError> {
+    match std::fs::read_dir(dest) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(BackupError::TargetNotEmpty(dest.to_path_buf()));
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(io_err(dest)(e)),
+    }
+    let dest_wal = dest.join("wal");
+    std::fs::create_dir_all(&dest_wal).map_err(io_err(&dest_wal))?;
+    let created = UNIX_EPOCH
+        + Duration::from_micros(
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64,
+        );
+
+    // ---- Стартовый LSN ----
+    let wal = manager.wal();
+    let retain_from = {
+        let mut wal = wal.lock().unwrap();
+        let next = wal.next_lsn();
+        let lsn = wal.oldest_active_lsn().map_or(next, |oldest| oldest.min(next));
+        wal.set_retain_from(Some(lsn));
+        lsn
+    };
+    let _retain = RetainGuard(wal);
+    let start_lsn = manager.manual_checkpoint()?.begin_lsn;
+
+    // ---- Файл данных ----
+    let disk = manager.pool().disk();
+    let data_copy = dest.join("data.db");
+    let (pages, data_crc) = copy_pages(disk, &data_copy)?;
+    let mut files = vec![ManifestFile {
+        path: "data.db".into(),
+        size: pages * disk.page_size() as u64,
+        crc32: data_crc,
+    }];
+
+    // ---- WAL до конечного LSN ----
+    let (end_lsn, wal_dir, first_segment) = manager.between_checkpoints(|| {
+        let mut wal = wal.lock().unwrap();
+        wal.flush().map_err(io_err(wal.dir()))?;
+        Ok::<_, BackupError>((
+            wal.next_lsn() - 1,
+            wal.dir().to_path_buf(),
+            wal.segment_of(retain_from),
+        ))
+    })?;
+    let reader = WalReader::open_dir(&wal_dir).map_err(io_err(&wal_dir))?;
+    for segment in reader.segments().iter().filter(|s| s.id >= first_segment) {
+        let copy = dest_wal.join(segment_file_name(segment.id));
+        std::fs::copy(&segment.path, &copy).map_err(io_err(&copy))?;
+    }
+    cut_wal(&dest_wal, end_lsn)?;
+    for segment in WalReader::open_dir(&dest_wal).map_err(io_err(&dest_wal))?.segments() {
+        let bytes = std::fs::read(&segment.path).map_err(io_err(&segment.path))?;
+        File::open(&segment.path).and_then(|f| f.sync_all()).map_err(io_err(&segment.path))?;
+        files.push(ManifestFile {
+            path: format!("wal/{}", segment_file_name(segment.id)),
+            size: bytes.len() as u64,
+            crc32: crc32(&bytes),
+        });
+    }
+
+    // ---- Манифест ----
+    let manifest =
+        BackupManifest { created, page_size: disk.page_size(), start_lsn, end_lsn, pages, files };
+    let tmp = dest.join(format!("{MANIFEST_FILE}.tmp"));
+    std::fs::write(&tmp, manifest.to_text()).map_err(io_err(&tmp))?;
+    File::open(&tmp).and_then(|f| f.sync_all()).map_err(io_err(&tmp))?;
+    let path = dest.join(MANIFEST_FILE);
+    std::fs::rename(&tmp, &path).map_err(io_err(&path))?;
+    File::open(dest).and_then(|f| f.sync_all()).map_err(io_err(dest))?;
+    Ok(manifest)
+}
+
+/// Постраничная копия файла данных; возвращает число страниц и CRC32 копии
+fn copy_pages(disk: &DiskManager, dest: &Path) -> Result<(u64, u32), BackupError> {
+    let source = File::open(disk.path()).map_err(io_err(disk.path()))?;
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .map_err(io_err(dest))?;
+    let page_size = disk.page_size();
+    let pages = disk.page_count();
+    let mut buf = vec![0u8; page_size];
+    let mut crc = 0xFFFF_FFFF;
+    for page_id in 0..pages {
+        let offset = u64::from(page_id) * page_size as u64;
+        let mut attempt = 0;
+        loop {
+            source.read_exact_at(&mut buf, offset).map_err(io_err(disk.path()))?;
+            // Нулевая страница выделена, но ещё не записана
+            if buf.iter().all(|&b| b == 0) || verify_checksum(&buf) {
+                break;
+            }
+            attempt += 1;
+            if attempt == PAGE_READ_ATTEMPTS {
+                return Err(BackupError::CorruptPage { page_id });
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        out.write_all(&buf).map_err(io_err(dest))?;
+        crc = crc32_update(crc, &buf);
+    }
+    out.sync_all().map_err(io_err(dest))?;
+    Ok((u64::from(pages), crc ^ 0xFFFF_FFFF))
+}
+
+/// Обрезать копию WAL после записи `end_lsn`
+fn cut_wal(dir: &Path, end_lsn: Lsn) -> Result<(), BackupError> {
+    let scan = WalReader::open_dir(dir).and_then(|r| r.scan()).map_err(io_err(dir))?;
+    let Some(last) = scan.records.iter().find(|r| r.record.lsn == end_lsn) else {
+        return Err(BackupError::Damaged {
+            path: dir.to_path_buf(),
+            message: format!(
+                "WAL copy ends at LSN {} before the end LSN {end_lsn}",
+                scan.end.last_valid_lsn
+            ),
+        });
+    };
+    let path = dir.join(segment_file_name(last.segment_id));
+    let file = OpenOptions::new().write(true).open(&path).map_err(io_err(&path))?;
+    file.set_len(last.offset + last.size as u64).map_err(io_err(&path))?;
+    for segment in scan.records.iter().map(|r| r.segment_id).filter(|&id| id > last.segment_id) {
+        let path = dir.join(segment_file_name(segment));
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(io_err(&path)(e)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Проверка копии
+// ============================================================================
+
+/// Итог `verify_backup`
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub manifest: BackupManifest,
+    pub files_checked: usize,
+    pub restore: RestoreReport,
+    /// Проверка восстановленной директории
+    pub fsck: FsckReport,
+}
+
+impl VerifyReport {
+    /// Копия проигрывается в чистую базу
+    pub fn is_consistent(&self) -> bool {
+        self.fsck.status() == Status::Clean
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "backup of {}: LSN {}..{}, {} pages, {} files match the manifest",
+            format_timestamp(self.manifest.created),
+            self.manifest.start_lsn,
+            self.manifest.end_lsn,
+            self.manifest.pages,
+            self.files_checked
+        )?;
+        writeln!(f, "{}", self.restore)?;
+        write!(
+            f,
+            "fsck of the replayed copy: {} ({} findings)",
+            self.fsck.status().name(),
+            self.fsck.findings.len()
+        )
+    }
+}
+
+/// Сверить файлы копии с манифестом и проиграть её в `scratch` (новая
+/// директория, остаётся после проверки)
+pub fn verify_backup(backup: &Path, scratch: &Path) -> Result<VerifyReport, BackupError> {
+    let manifest = BackupManifest::read(backup)?;
+    for file in &manifest.files {
+        let path = backup.join(&file.path);
+        let bytes = std::fs::read(&path).map_err(io_err(&path))?;
+        let crc = crc32(&bytes);
+        if bytes.len() as u64 != file.size || crc != file.crc32 {
+            return Err(BackupError::Damaged {
+                message: format!(
+                    "{} bytes with CRC32 {crc:#010x}, the manifest lists {} bytes with {:#010x}",
+                    bytes.len(),
+                    file.size,
+                    file.crc32
+                ),
+                path,
+            });
+        }
+    }
+
+    let config = DatabaseConfig {
+        data_path: scratch.to_path_buf(),
+        page_size: manifest.page_size,
+        ..DatabaseConfig::default()
+    };
+    let options = RestoreOptions {
+        base: backup.to_path_buf(),
+        wal_sources: Vec::new(),
+        target: RestoreTarget::Lsn(manifest.end_lsn),
+    };
+    let restore = restore(&config, &options)?;
+    let fsck = fsck::check(&config).map_err(io_err(scratch))?;
+    Ok(VerifyReport { files_checked: manifest.files.len(), manifest, restore, fsck })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_pool::BufferPool;
+    use crate::config::CheckpointConfig;
+    use crate::page::{PageHeader, TxnId, PAGE_HEADER_SIZE};
+    use crate::wal::{LogRecord, LogRecordType};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const PAGES: PageId = 8;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("datyredb_backup_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn start_engine(dir: &Path) -> Arc<CheckpointManager> {
+        std::fs::create_dir_all(dir).unwrap();
+        let disk = DiskManager::open_path(dir.join("data.db"), 512).unwrap();
+        let pool = Arc::new(BufferPool::with_disk(disk, 16));
+        let wal = Arc::new(Mutex::new(WalWriter::create(dir.join("wal"), 1024).unwrap()));
+        for _ in 0..PAGES {
+            pool.new_page().unwrap();
+        }
+        Arc::new(CheckpointManager::new(CheckpointConfig::default(), pool, wal))
+    }
+
+    /// Транзакция, записывающая `value` в начало тела страницы; LSN её COMMIT
+    fn set_value(manager: &CheckpointManager, txn_id: TxnId, page_id: PageId, value: u64) -> Lsn {
+        let page = manager.pool().fetch_page(page_id).unwrap();
+        let before = page.read()[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + 8].to_vec();
+        let after = value.to_le_bytes();
+        let lsn = {
+            let mut wal = manager.wal().lock().unwrap();
+            let begin = wal.append(&LogRecord::new(LogRecordType::TxnBegin, txn_id)).unwrap();
+            let offset = PAGE_HEADER_SIZE as u16;
+            wal.append(&LogRecord::update(txn_id, begin, page_id, offset, &before, &after))
+                .unwrap()
+        };
+        {
+            let mut data = page.write();
+            data[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + 8].copy_from_slice(&after);
+            let mut header = PageHeader::decode(&data);
+            header.page_lsn = lsn;
+            header.encode(&mut data);
+        }
+        manager.wal().lock().unwrap().append(&LogRecord::commit(txn_id, lsn)).unwrap()
+    }
+
+    #[test]
+    fn online_backup_replays_to_the_end_lsn() {
+        let root = temp_dir("online");
+        let manager = start_engine(&root.join("live"));
+        manager.manual_checkpoint().unwrap();
+
+        // Транзакции и checkpoint'ы идут всё время, пока делается копия
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let (manager, stop) = (Arc::clone(&manager), Arc::clone(&stop));
+            std::thread::spawn(move || {
+                let mut commits = Vec::new();
+                let mut txn_id = 0;
+                while !stop.load(Ordering::Relaxed) || txn_id < 200 {
+                    txn_id += 1;
+                    let page_id = (txn_id % u64::from(PAGES)) as PageId;
+                    commits.push((set_value(&manager, txn_id, page_id, txn_id), page_id, txn_id));
+                    if txn_id % 50 == 0 {
+                        manager.manual_checkpoint().unwrap();
+                    }
+                }
+                commits
+            })
+        };
+        std::thread::sleep(Duration::from_millis(5));
+        let manifest = create_backup(&manager, &root.join("backup")).unwrap();
+        stop.store(true, Ordering::Relaxed);
+        let commits = writer.join().unwrap();
+        assert!(manifest.end_lsn > manifest.start_lsn);
+        assert_eq!(manifest.pages, u64::from(PAGES));
+        assert_eq!(BackupManifest::parse(&manifest.to_text()).unwrap(), manifest);
+        assert_eq!(BackupManifest::read(&root.join("backup")).unwrap(), manifest);
+        assert!(matches!(
+            create_backup(&manager, &root.join("backup")),
+            Err(BackupError::TargetNotEmpty(_))
+        ));
+
+        let report = verify_backup(&root.join("backup"), &root.join("scratch")).unwrap();
+        assert!(report.is_consistent(), "{report}");
+        assert_eq!(report.restore.stop_lsn, manifest.end_lsn);
+        assert_eq!(report.restore.backup_checkpoint_lsn, Some(manifest.start_lsn));
+
+        // В восстановленной копии — последние значения, зафиксированные до end_lsn
+        let disk = DiskManager::open_path(root.join("scratch/data.db"), 512).unwrap();
+        let mut buf = vec![0u8; 512];
+        for page_id in 0..PAGES {
+            let expected = commits
+                .iter()
+                .rev()
+                .find(|&&(lsn, page, _)| page == page_id && lsn <= manifest.end_lsn)
+                .map_or(0, |&(_, _, value)| value);
+            disk.read_page(page_id, &mut buf).unwrap();
+            let value = u64::from_le_bytes(buf[PAGE_HEADER_SIZE..][..8].try_into().unwrap());
+            assert_eq!(value, expected, "page {page_id}");
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn verify_detects_damaged_files() {
+        let root = temp_dir("damaged");
+        let manager = start_engine(&root.join("live"));
+        for txn_id in 1..=20 {
+            set_value(&manager, txn_id, (txn_id % 3) as PageId, txn_id);
+        }
+        create_backup(&manager, &root.join("backup")).unwrap();
+        let report = verify_backup(&root.join("backup"), &root.join("scratch")).unwrap();
+        assert!(report.is_consistent(), "{report}");
+
+        let data = root.join("backup/data.db");
+        let mut bytes = std::fs::read(&data).unwrap();
+        bytes[600] ^= 0x40;
+        std::fs::write(&data, bytes).unwrap();
+        let err = verify_backup(&root.join("backup"), &root.join("scratch2")).unwrap_err();
+        assert!(matches!(&err, BackupError::Damaged { path, .. } if *path == data), "{err}");
+
+        std::fs::remove_file(root.join("backup").join(MANIFEST_FILE)).unwrap();
+        let err = verify_backup(&root.join("backup"), &root.join("scratch3")).unwrap_err();
+        assert!(matches!(err, BackupError::Io { .. }));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+//! datyre-backup — базовая копия с манифестом (см. `backup`) и её проверка.
+//!
+//! Работающий движок вызывает `backup::create_backup` сам; утилита делает
+//! то же для базы, которую не держит открытой ни один процесс.
+
+use datyredb::backup::{create_backup, verify_backup};
+use datyredb::buffer_pool::BufferPool;
+use datyredb::checkpoint::CheckpointManager;
+use datyredb::cli::{Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::recovery::recover;
+use datyredb::wal::{WalArchive, WalWriter};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+const USAGE: &str = "\
+Usage: datyre-backup [OPTIONS] DEST
+       datyre-backup --verify BACKUP [--scratch DIR] [--keep-scratch]
+
+The first form recovers the database (it must not be open in another
+process), takes a checkpoint and writes a base backup to DEST (must be new
+or empty): the data file copied page by page with checksums verified, the
+WAL segments up to a consistent end LSN and a backup_manifest listing the
+LSN range and the CRC32 of every file.
+
+--verify checks BACKUP against its manifest, replays it into a scratch
+directory and runs fsck on the result.
+
+Options:
+      --verify BACKUP    verify BACKUP instead of creating a backup
+      --scratch DIR      where to replay (default: BACKUP.verify; must be new)
+      --keep-scratch     keep the replayed copy after verification
+  -h, --help             show this help
+
+Exit status:
+  0  backup written / backup verified
+  1  the backup failed or did not replay to a clean database
+  2  bad arguments
+";
+
+struct Options {
+    config: ConfigArgs,
+    dest: Option<PathBuf>,
+    verify: Option<PathBuf>,
+    scratch: Option<PathBuf>,
+    keep_scratch: bool,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options {
+        config: ConfigArgs::default(),
+        dest: None,
+        verify: None,
+        scratch: None,
+        keep_scratch: false,
+    };
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(path) if opts.dest.is_none() => {
+                opts.dest = Some(path.into());
+                continue;
+            }
+            Arg::Pos(extra) => return Err(CliError(format!("unexpected argument '{extra}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        match opt.as_str() {
+            "--verify" => opts.verify = Some(args.value(&opt)?.into()),
+            "--scratch" => opts.scratch = Some(args.value(&opt)?.into()),
+            "--keep-scratch" => opts.keep_scratch = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        }
+    }
+    match (&opts.dest, &opts.verify) {
+        (None, None) => Err(CliError("missing DEST".into())),
+        (Some(_), Some(_)) => Err(CliError("--verify takes no DEST".into())),
+        (None, Some(_)) => Ok(Some(opts)),
+        (Some(_), None) if opts.scratch.is_some() || opts.keep_scratch => {
+            Err(CliError("--scratch and --keep-scratch need --verify".into()))
+        }
+        (Some(_), None) => Ok(Some(opts)),
+    }
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-backup: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(2);
+        }
+    };
+    let result = match opts.verify.clone() {
+        Some(backup) => verify(opts, backup),
+        None => backup(opts),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("datyre-backup: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn backup(opts: Options) -> Result<bool, Box<dyn std::error::Error>> {
+    let config = opts.config.load()?;
+    let dest = opts.dest.expect("checked in parse_args");
+    let recovery = recover(&config)?;
+    if recovery.redone > 0 || !recovery.losers.is_empty() {
+        println!("{recovery}");
+    }
+
+    let pool = Arc::new(BufferPool::new(&config)?);
+    let mut wal = WalWriter::open(config.wal_dir(), config.wal_segment_size)?;
+    wal.set_archive(WalArchive::from_config(&config)?);
+    let wal = Arc::new(Mutex::new(wal));
+    let manager = CheckpointManager::new(config.checkpoint.clone(), pool, wal);
+    let manifest = create_backup(&manager, &dest)?;
+    println!(
+        "backup of {} written to {}: LSN {}..{}, {} pages, {} files",
+        config.data_path.display(),
+        dest.display(),
+        manifest.start_lsn,
+        manifest.end_lsn,
+        manifest.pages,
+        manifest.files.len()
+    );
+    Ok(true)
+}
+
+fn verify(opts: Options, backup: PathBuf) -> Result<bool, Box<dyn std::error::Error>> {
+    let scratch = opts.scratch.unwrap_or_else(|| {
+        let mut name = backup.file_name().unwrap_or_default().to_os_string();
+        name.push(".verify");
+        backup.with_file_name(name)
+    });
+    // Чужую директорию не удаляем, даже пустую
+    let keep = opts.keep_scratch || scratch.exists();
+    let result = verify_backup(&backup, &scratch);
+    if !keep {
+        let _ = std::fs::remove_dir_all(&scratch);
+    }
+    let report = result?;
+    println!("{report}");
+    if keep {
+        println!("replayed copy kept in {}", scratch.display());
+    }
+    Ok(report.is_consistent())
+}
@@ -0,0 +1,151 @@
+//! datyre-checkpoint-sim — проигрывание трассы нагрузки против политики
+//! checkpoint'ов (см. `checkpoint::sim`) и сравнение конфигов рядом.
+
+use datyredb::checkpoint::sim::{simulate, IoModel, Trace};
+use datyredb::cli::{Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::config::units::{parse_bytes, parse_duration};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: datyre-checkpoint-sim [OPTIONS] TRACE
+
+Replays a recorded workload against the checkpoint policy of the configured
+buffer pool and WAL and reports how often each trigger fired, how long the
+workload was blocked by the hard limit, the peak WAL size and pages written.
+The run is deterministic: the same trace and config give the same report.
+
+TRACE has one event per line ('#' starts a comment), times are offsets from
+the start of the recording and must not decrease:
+  TIME dirty PAGE_ID     a page became dirty      (e.g. '1500us dirty 42')
+  TIME wal SIZE          bytes appended to the WAL (e.g. '1500us wal 4KiB')
+
+Options:
+      --variant KEY=VALUE[,KEY=VALUE...]
+                         add a column: the base config with these overrides
+                         (repeatable)
+      --disk-throughput SIZE
+                         sustained page write rate per second (default: 200MiB)
+      --fsync-latency DURATION
+                         data file fsync cost (default: 2ms)
+  -h, --help             show this help
+";
+
+struct Options {
+    config: ConfigArgs,
+    trace: Option<PathBuf>,
+    variants: Vec<String>,
+    io: IoModel,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options {
+        config: ConfigArgs::default(),
+        trace: None,
+        variants: Vec::new(),
+        io: IoModel::default(),
+    };
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(path) if opts.trace.is_none() => {
+                opts.trace = Some(path.into());
+                continue;
+            }
+            Arg::Pos(extra) => return Err(CliError(format!("unexpected argument '{extra}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        match opt.as_str() {
+            "--variant" => opts.variants.push(args.value(&opt)?),
+            "--disk-throughput" => {
+                opts.io.disk_write_throughput = parse_bytes(&args.value(&opt)?)
+                    .map_err(|e| CliError(format!("{opt}: {e}")))?
+            }
+            "--fsync-latency" => {
+                opts.io.fsync_latency = parse_duration(&args.value(&opt)?)
+                    .map_err(|e| CliError(format!("{opt}: {e}")))?
+            }
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        }
+    }
+    if opts.trace.is_none() {
+        return Err(CliError("missing TRACE".into()));
+    }
+    Ok(Some(opts))
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-checkpoint-sim: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("datyre-checkpoint-sim: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(opts: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let trace_path = opts.trace.as_ref().expect("checked in parse_args");
+    let trace = Trace::read_file(trace_path)
+        .map_err(|e| CliError(format!("{}: {e}", trace_path.display())))?;
+
+    // Колонки: базовый конфиг и по одной на каждый --variant
+    let mut columns = vec![("base".to_string(), opts.config.load()?)];
+    for variant in &opts.variants {
+        let overrides = variant.split(',').map(str::trim).filter(|s| !s.is_empty());
+        let config = opts
+            .config
+            .loader()
+            .override_args(overrides)
+            .load()
+            .map_err(|e| CliError(format!("--variant {variant}: {e}")))?;
+        columns.push((variant.clone(), config.config));
+    }
+
+    let reports: Vec<_> =
+        columns.iter().map(|(_, config)| simulate(config, &opts.io, &trace)).collect();
+
+    println!(
+        "{}: {} events over {:.1}s",
+        trace_path.display(),
+        trace.entries.len(),
+        trace.duration().as_secs_f64()
+    );
+    let rows: Vec<Vec<(&str, String)>> = reports.iter().map(|r| r.rows()).collect();
+    let label_width = rows[0].iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = columns
+        .iter()
+        .zip(&rows)
+        .map(|((label, _), rows)| {
+            rows.iter().map(|(_, v)| v.len()).chain([label.len()]).max().unwrap_or(0)
+        })
+        .collect();
+
+    let mut line = format!("{:<label_width$}", "");
+    for ((label, _), width) in columns.iter().zip(&widths) {
+        line.push_str(&format!("  {label:>width$}"));
+    }
+    println!("{}", line.trim_end());
+    for (i, (name, _)) in rows[0].iter().enumerate() {
+        let mut line = format!("{name:<label_width$}");
+        for (column, width) in rows.iter().zip(&widths) {
+            line.push_str(&format!("  {:>width$}", column[i].1));
+        }
+        println!("{line}");
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! datyre-compact — перенос страниц в освобождённые места и обрезка файла
+//! данных остановленной базы (см. `free_space`).
+
+use datyredb::buffer_pool::BufferPool;
+use datyredb::catalog::{Catalog, CATALOG_ROOT};
+use datyredb::cli::{Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::config::DatabaseConfig;
+use datyredb::disk_manager::DiskManager;
+use datyredb::page::{PageFlags, PageHeader, PageId};
+use datyredb::recovery::recover;
+use datyredb::wal::WalWriter;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+const USAGE: &str = "\
+Usage: datyre-compact [OPTIONS]
+
+Recovers a stopped DatyreDB directory, then moves pages from the end of the
+data file into freed pages and truncates the file. Moved pages get new page
+ids and the WAL does not record the moves: take a new base backup afterwards.
+References to moved pages are not rewritten, so pages that may be referenced
+stay where they are: B+tree nodes (tables, indexes, the catalog), overflow
+pages and the table and index roots named in the catalog. The file is not
+truncated below the last of them.
+
+Options:
+  -n, --dry-run          only report what would be freed
+  -h, --help             show this help
+";
+
+struct Options {
+    config: ConfigArgs,
+    dry_run: bool,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options { config: ConfigArgs::default(), dry_run: false };
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(p) => return Err(CliError(format!("unexpected argument '{p}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        match opt.as_str() {
+            "-n" | "--dry-run" => opts.dry_run = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        }
+    }
+    Ok(Some(opts))
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-compact: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("datyre-compact: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(opts: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let config = opts.config.load()?;
+    let recovery = recover(&config)?;
+    if recovery.redone > 0 || !recovery.losers.is_empty() {
+        println!("{recovery}");
+    }
+
+    let roots = catalog_roots(&config)?;
+    let disk = DiskManager::open(&config)?;
+    {
+        let map = disk.free_space();
+        println!(
+            "{}: {} pages, {} free, {} bytes free in used pages",
+            disk.path().display(),
+            map.page_count(),
+            map.free_pages(),
+            map.free_bytes()
+        );
+    }
+    if opts.dry_run {
+        return Ok(());
+    }
+    let compaction = disk.compact(&roots)?;
+    println!("{compaction}");
+    Ok(())
+}
+
+/// Корни из каталога; файл без каталога (например, после
+/// datyre-migrate-wal) корней не называет
+fn catalog_roots(config: &DatabaseConfig) -> Result<Vec<PageId>, Box<dyn std::error::Error>> {
+    let pool = Arc::new(BufferPool::new(config)?);
+    if pool.disk().page_count() == 0 {
+        return Ok(Vec::new());
+    }
+    let mut page = vec![0u8; config.page_size];
+    pool.disk().read_page(CATALOG_ROOT, &mut page)?;
+    let flags = PageHeader::decode(&page).flags;
+    if flags.bits() & (PageFlags::LEAF | PageFlags::INTERNAL).bits() == 0 {
+        return Ok(Vec::new());
+    }
+    let wal = WalWriter::open(config.wal_dir(), config.wal_segment_size)?;
+    Ok(Catalog::open(pool, Arc::new(Mutex::new(wal)))?.roots())
+}
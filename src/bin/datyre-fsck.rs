@@ -0,0 +1,112 @@
+//! datyre-fsck — офлайн-проверка остановленной базы (файл данных + WAL).
+
+use datyredb::cli::{Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::fsck;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: datyre-fsck [OPTIONS]
+
+Checks a stopped DatyreDB directory: page checksums, page_id placement,
+page_lsn against the WAL, torn WAL tails and unfinished checkpoints.
+
+Options:
+      --report FILE      write a JSON report to FILE ('-' for stdout; the text
+                         report then goes to stderr)
+  -q, --quiet            print only the final status line
+  -h, --help             show this help
+
+Exit status:
+  0  clean
+  1  repairable (crash recovery or tail truncation fixes it)
+  2  corrupt
+  3  the check could not run (bad arguments, unreadable files)
+";
+
+/// Проверка не выполнена
+const EXIT_ERROR: u8 = 3;
+
+struct Options {
+    config: ConfigArgs,
+    report: Option<PathBuf>,
+    quiet: bool,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options { config: ConfigArgs::default(), report: None, quiet: false };
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(p) => return Err(CliError(format!("unexpected argument '{p}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        match opt.as_str() {
+            "--report" => opts.report = Some(args.value(&opt)?.into()),
+            "-q" | "--quiet" => opts.quiet = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        }
+    }
+    Ok(Some(opts))
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-fsck: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let config = match opts.config.load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("datyre-fsck: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    let report = match fsck::check(&config) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("datyre-fsck: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let text = if opts.quiet {
+        format!("status: {}", report.status().name())
+    } else {
+        report.to_string()
+    };
+    // stdout занят JSON: текст не должен мешать его разбору
+    let json_to_stdout = opts.report.as_ref().is_some_and(|path| path.as_os_str() == "-");
+    if json_to_stdout {
+        eprintln!("{text}");
+    } else {
+        println!("{text}");
+    }
+
+    if let Some(path) = &opts.report {
+        let json = report.to_json();
+        let written = if json_to_stdout {
+            print!("{json}");
+            Ok(())
+        } else {
+            std::fs::write(path, json)
+        };
+        if let Err(e) = written {
+            eprintln!("datyre-fsck: cannot write report {}: {e}", path.display());
+            return ExitCode::from(EXIT_ERROR);
+        }
+    }
+
+    ExitCode::from(report.status().exit_code())
+}
@@ -0,0 +1,98 @@
+//! datyre-migrate-wal — перенос legacy `datyre.wal` в новую директорию данных.
+
+use datyredb::cli::{Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::legacy_wal::{migrate, MigrateOptions, LEGACY_WAL_FILE};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: datyre-migrate-wal [OPTIONS] [LEGACY_FILE]
+
+Converts a legacy text datyre.wal (default: ./datyre.wal) into a fresh data
+directory at the configured data_path: data.db pages plus a binary WAL.
+Lines that cannot be migrated are listed with their line numbers.
+
+Options:
+      --strict           refuse to migrate if any line would be dropped
+  -n, --dry-run          parse and report, create nothing
+  -h, --help             show this help
+
+Exit status:
+  0  every line migrated
+  1  migrated, but some lines were dropped (see report)
+  2  nothing migrated (bad arguments, I/O error, non-empty target, --strict)
+";
+
+struct Options {
+    config: ConfigArgs,
+    legacy: PathBuf,
+    migrate: MigrateOptions,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options {
+        config: ConfigArgs::default(),
+        legacy: PathBuf::from(LEGACY_WAL_FILE),
+        migrate: MigrateOptions::default(),
+    };
+    let mut legacy_set = false;
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(path) if !legacy_set => {
+                opts.legacy = path.into();
+                legacy_set = true;
+                continue;
+            }
+            Arg::Pos(extra) => return Err(CliError(format!("unexpected argument '{extra}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        match opt.as_str() {
+            "--strict" => opts.migrate.strict = true,
+            "-n" | "--dry-run" => opts.migrate.dry_run = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        }
+    }
+    Ok(Some(opts))
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-migrate-wal: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(2);
+        }
+    };
+    let config = match opts.config.load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("datyre-migrate-wal: {e}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match migrate(&opts.legacy, &config, &opts.migrate) {
+        Ok(report) => {
+            if opts.migrate.dry_run {
+                print!("dry run: ");
+            }
+            println!("{report}");
+            if !opts.migrate.dry_run {
+                println!("target: {}", config.data_path.display());
+            }
+            ExitCode::from(if report.rejected.is_empty() { 0 } else { 1 })
+        }
+        Err(e) => {
+            eprintln!("datyre-migrate-wal: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
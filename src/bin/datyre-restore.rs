@@ -0,0 +1,144 @@
+//! datyre-restore — восстановление базы на момент времени из базовой копии
+//! и архива WAL (см. `restore`).
+
+use datyredb::cli::{Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::config::units::parse_timestamp;
+use datyredb::config::DatabaseConfig;
+use datyredb::restore::{restore, RestoreOptions, RestoreTarget};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: datyre-restore [OPTIONS] BASE TARGET
+
+Rebuilds a data directory in TARGET (must be new or empty) from the base
+backup BASE (a copy of a stopped data directory: data.db and wal/, or a
+backup written by datyre-backup) and the archived WAL segments written
+after it. Transactions that did not commit
+before the recovery target are rolled back.
+
+The restored database starts a new WAL history: give it its own
+wal_archive_dir rather than the archive it was restored from.
+
+Options:
+      --archive DIR      archived segments (default: wal_archive_dir)
+      --wal DIR          more segments, e.g. the surviving WAL of the damaged
+                         database (repeatable)
+      --to-lsn LSN       keep records up to LSN inclusive
+      --to-time TIME     keep transactions committed at or before TIME:
+                         'YYYY-MM-DD HH:MM[:SS]' (UTC unless +HH:MM is given)
+                         or @UNIX_SECONDS
+                         (default: replay everything available)
+  -h, --help             show this help
+";
+
+struct Options {
+    config: ConfigArgs,
+    base: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
+    archive: Option<PathBuf>,
+    wal: Vec<PathBuf>,
+    target: RestoreTarget,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options {
+        config: ConfigArgs::default(),
+        base: None,
+        target_dir: None,
+        archive: None,
+        wal: Vec::new(),
+        target: RestoreTarget::End,
+    };
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(path) if opts.base.is_none() => {
+                opts.base = Some(path.into());
+                continue;
+            }
+            Arg::Pos(path) if opts.target_dir.is_none() => {
+                opts.target_dir = Some(path.into());
+                continue;
+            }
+            Arg::Pos(extra) => return Err(CliError(format!("unexpected argument '{extra}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        let target = match opt.as_str() {
+            "--archive" => {
+                opts.archive = Some(args.value(&opt)?.into());
+                None
+            }
+            "--wal" => {
+                opts.wal.push(args.value(&opt)?.into());
+                None
+            }
+            "--to-lsn" => Some(RestoreTarget::Lsn(args.parse(&opt)?)),
+            "--to-time" => Some(RestoreTarget::Time(
+                parse_timestamp(&args.value(&opt)?).map_err(|e| CliError(format!("{opt}: {e}")))?,
+            )),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        };
+        if let Some(target) = target {
+            if opts.target != RestoreTarget::End {
+                return Err(CliError("--to-lsn and --to-time are mutually exclusive".into()));
+            }
+            opts.target = target;
+        }
+    }
+    if opts.target_dir.is_none() {
+        return Err(CliError("missing BASE and TARGET".into()));
+    }
+    Ok(Some(opts))
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-restore: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("datyre-restore: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(opts: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let loaded = opts.config.load()?;
+    let archive = opts.archive.or_else(|| loaded.wal_archive_dir.clone());
+    let config = DatabaseConfig {
+        data_path: opts.target_dir.expect("checked in parse_args"),
+        ..loaded
+    };
+    let options = RestoreOptions {
+        base: opts.base.expect("checked in parse_args"),
+        wal_sources: archive.into_iter().chain(opts.wal).collect(),
+        target: opts.target,
+    };
+    if options.wal_sources.is_empty() {
+        eprintln!("datyre-restore: no --archive or wal_archive_dir, replaying the backup alone");
+    }
+
+    println!(
+        "restoring {} into {} up to {}",
+        options.base.display(),
+        config.data_path.display(),
+        options.target
+    );
+    let report = restore(&config, &options)?;
+    println!("{report}");
+    Ok(())
+}
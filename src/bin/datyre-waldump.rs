@@ -0,0 +1,375 @@
+//! datyre-waldump — просмотр WAL сегментов (аналог `pg_waldump`).
+//!
+//! Директория WAL и размер сегмента берутся из `DatabaseConfig`; фильтры
+//! по LSN, транзакции, странице и типу применяются и к записям, и к
+//! статистике. Пары checkpoint'ов и конец WAL считаются по всему логу.
+
+use datyredb::cli::{json_string, Arg, Args, CliError, ConfigArgs, CONFIG_HELP};
+use datyredb::page::{Lsn, PageId, TxnId, INVALID_PAGE_ID};
+use datyredb::wal::{CheckpointPair, CheckpointTracker, LogRecordType, WalEnd, WalReader, WalRecord};
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: datyre-waldump [OPTIONS] [WAL_DIR]
+
+Dumps WAL records in LSN order. WAL_DIR defaults to the configured data_path/wal.
+
+Filters:
+  -s, --start LSN        skip records before LSN
+  -e, --end LSN          skip records after LSN (the summary still covers the whole WAL)
+  -x, --xid TXN          only records of this transaction
+  -p, --page PAGE        only records touching this page
+  -t, --type TYPE        only this record type (repeatable), e.g. INSERT, txn_commit
+
+Output:
+      --format FORMAT    text (default) or json (one JSON object per line)
+  -z, --stats            print per-type statistics only
+  -q, --quiet            print only the summary (statistics, checkpoints, end of WAL)
+  -h, --help             show this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Default)]
+struct Filter {
+    start: Option<Lsn>,
+    end: Option<Lsn>,
+    txn: Option<TxnId>,
+    page: Option<PageId>,
+    types: Vec<LogRecordType>,
+}
+
+impl Filter {
+    fn matches(&self, r: &WalRecord) -> bool {
+        let rec = &r.record;
+        self.start.is_none_or(|s| rec.lsn >= s)
+            && self.end.is_none_or(|e| rec.lsn <= e)
+            && self.txn.is_none_or(|t| rec.txn_id == t)
+            && self.page.is_none_or(|p| rec.page_id == p)
+            && (self.types.is_empty() || r.record_type().is_some_and(|t| self.types.contains(&t)))
+    }
+}
+
+struct Options {
+    config: ConfigArgs,
+    wal_dir: Option<PathBuf>,
+    filter: Filter,
+    format: Format,
+    records: bool,
+    stats_only: bool,
+}
+
+fn parse_args(mut args: Args) -> Result<Option<Options>, CliError> {
+    let mut opts = Options {
+        config: ConfigArgs::default(),
+        wal_dir: None,
+        filter: Filter::default(),
+        format: Format::Text,
+        records: true,
+        stats_only: false,
+    };
+    while let Some(arg) = args.next_arg()? {
+        let opt = match arg {
+            Arg::Pos(dir) if opts.wal_dir.is_none() => {
+                opts.wal_dir = Some(dir.into());
+                continue;
+            }
+            Arg::Pos(extra) => return Err(CliError(format!("unexpected argument '{extra}'"))),
+            Arg::Opt(opt) => opt,
+        };
+        if opts.config.accept(&opt, &mut args)? {
+            continue;
+        }
+        match opt.as_str() {
+            "-s" | "--start" => opts.filter.start = Some(args.parse(&opt)?),
+            "-e" | "--end" => opts.filter.end = Some(args.parse(&opt)?),
+            "-x" | "--xid" => opts.filter.txn = Some(args.parse(&opt)?),
+            "-p" | "--page" => opts.filter.page = Some(args.parse(&opt)?),
+            "-t" | "--type" => opts.filter.types.push(args.parse(&opt)?),
+            "--format" => {
+                opts.format = match args.value(&opt)?.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    other => return Err(CliError(format!("unknown format '{other}' (text, json)"))),
+                }
+            }
+            "-z" | "--stats" => {
+                opts.stats_only = true;
+                opts.records = false;
+            }
+            "-q" | "--quiet" => opts.records = false,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(CliError(format!("unknown option '{opt}'"))),
+        }
+    }
+    Ok(Some(opts))
+}
+
+/// Счётчики по типу записи
+#[derive(Debug, Default, Clone, Copy)]
+struct TypeStats {
+    count: u64,
+    bytes: u64,
+    data_bytes: u64,
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(Args::from_env()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("datyre-waldump: {e}\n\n{USAGE}\n{CONFIG_HELP}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("datyre-waldump: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(opts: &Options) -> io::Result<()> {
+    let config = opts
+        .config
+        .load()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let wal_dir = opts.wal_dir.clone().unwrap_or_else(|| config.wal_dir());
+    let reader = WalReader::open_dir(&wal_dir)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", wal_dir.display())))?;
+    let segment_size = config.wal_segment_size;
+
+    let out = io::stdout().lock();
+    let mut out = BufWriter::new(out);
+    let json = opts.format == Format::Json;
+
+    for segment in reader.segments() {
+        if segment.len > segment_size {
+            eprintln!(
+                "warning: {} is {} bytes, larger than wal_segment_size {}; wrong config?",
+                segment.path.display(),
+                segment.len,
+                segment_size
+            );
+        }
+    }
+
+    if opts.records && !json {
+        writeln!(
+            out,
+            "{:>10} {:>10} {:>8} {:<16} {:>8} {:>6} {:>6} {:>7}  POSITION",
+            "LSN", "PREV", "TXN", "TYPE", "PAGE", "OFFSET", "LENGTH", "SIZE"
+        )?;
+    }
+
+    let mut stats: BTreeMap<String, TypeStats> = BTreeMap::new();
+    let mut checkpoints = CheckpointTracker::default();
+    let mut records = reader.records();
+    for record in records.by_ref() {
+        let record = record?;
+        checkpoints.observe(&record);
+        if !opts.filter.matches(&record) {
+            continue;
+        }
+
+        let entry = stats.entry(record.type_name()).or_default();
+        entry.count += 1;
+        entry.bytes += record.size as u64;
+        entry.data_bytes += record.record.data.len() as u64;
+
+        if opts.records {
+            let position = record.segment_id * segment_size + record.offset;
+            if json {
+                write_record_json(&mut out, &record, position)?;
+            } else {
+                write_record_text(&mut out, &record, position)?;
+            }
+        }
+    }
+    let end = records.end();
+    let checkpoints = checkpoints.finish();
+
+    if json {
+        write_summary_json(&mut out, &stats, &checkpoints, &end, opts.stats_only)
+    } else {
+        write_summary_text(&mut out, &stats, &checkpoints, &end, opts.stats_only)
+    }?;
+    out.flush()
+}
+
+fn page_text(page_id: PageId) -> String {
+    if page_id == INVALID_PAGE_ID {
+        "-".to_string()
+    } else {
+        page_id.to_string()
+    }
+}
+
+fn write_record_text(out: &mut impl Write, r: &WalRecord, position: u64) -> io::Result<()> {
+    let rec = &r.record;
+    writeln!(
+        out,
+        "{:>10} {:>10} {:>8} {:<16} {:>8} {:>6} {:>6} {:>7}  {:08x}/{:08x} (seg {} +{})",
+        rec.lsn,
+        rec.prev_lsn,
+        rec.txn_id,
+        r.type_name(),
+        page_text(rec.page_id),
+        rec.offset,
+        rec.length,
+        r.size,
+        position >> 32,
+        position & 0xFFFF_FFFF,
+        r.segment_id,
+        r.offset
+    )
+}
+
+fn write_record_json(out: &mut impl Write, r: &WalRecord, position: u64) -> io::Result<()> {
+    let rec = &r.record;
+    let page = if rec.page_id == INVALID_PAGE_ID {
+        "null".to_string()
+    } else {
+        rec.page_id.to_string()
+    };
+    writeln!(
+        out,
+        "{{\"kind\":\"record\",\"lsn\":{},\"prev_lsn\":{},\"txn_id\":{},\"type\":{},\"page_id\":{},\
+         \"offset\":{},\"length\":{},\"size\":{},\"data_size\":{},\"segment\":{},\"segment_offset\":{},\
+         \"position\":{}}}",
+        rec.lsn,
+        rec.prev_lsn,
+        rec.txn_id,
+        json_string(&r.type_name()),
+        page,
+        rec.offset,
+        rec.length,
+        r.size,
+        rec.data.len(),
+        r.segment_id,
+        r.offset,
+        position
+    )
+}
+
+fn write_summary_text(
+    out: &mut impl Write,
+    stats: &BTreeMap<String, TypeStats>,
+    checkpoints: &[CheckpointPair],
+    end: &WalEnd,
+    stats_only: bool,
+) -> io::Result<()> {
+    let total_count: u64 = stats.values().map(|s| s.count).sum();
+    let total_bytes: u64 = stats.values().map(|s| s.bytes).sum();
+
+    writeln!(out)?;
+    writeln!(out, "{:<16} {:>10} {:>7} {:>14} {:>7} {:>14}", "TYPE", "COUNT", "%", "BYTES", "%", "DATA BYTES")?;
+    for (name, s) in stats {
+        writeln!(
+            out,
+            "{:<16} {:>10} {:>6.2}% {:>14} {:>6.2}% {:>14}",
+            name,
+            s.count,
+            percent(s.count, total_count),
+            s.bytes,
+            percent(s.bytes, total_bytes),
+            s.data_bytes
+        )?;
+    }
+    writeln!(out, "{:<16} {:>10} {:>7} {:>14}", "TOTAL", total_count, "", total_bytes)?;
+    if stats_only {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "checkpoints:")?;
+    if checkpoints.is_empty() {
+        writeln!(out, "  (none)")?;
+    }
+    for cp in checkpoints {
+        match (cp.begin_found, cp.end_lsn) {
+            (true, Some(end)) => writeln!(out, "  BEGIN {} -> END {}", cp.begin_lsn, end)?,
+            (true, None) => writeln!(out, "  BEGIN {} -> (no END, incomplete)", cp.begin_lsn)?,
+            (false, Some(end)) => {
+                writeln!(out, "  BEGIN {} (not in WAL) -> END {}", cp.begin_lsn, end)?
+            }
+            (false, None) => {}
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "last valid LSN: {} ({} records read)", end.last_valid_lsn, end.records)?;
+    match &end.torn {
+        Some(torn) => writeln!(out, "torn tail: {torn}"),
+        None => writeln!(out, "end of WAL: clean"),
+    }
+}
+
+fn write_summary_json(
+    out: &mut impl Write,
+    stats: &BTreeMap<String, TypeStats>,
+    checkpoints: &[CheckpointPair],
+    end: &WalEnd,
+    stats_only: bool,
+) -> io::Result<()> {
+    for (name, s) in stats {
+        writeln!(
+            out,
+            "{{\"kind\":\"stats\",\"type\":{},\"count\":{},\"bytes\":{},\"data_bytes\":{}}}",
+            json_string(name),
+            s.count,
+            s.bytes,
+            s.data_bytes
+        )?;
+    }
+    if stats_only {
+        return Ok(());
+    }
+    for cp in checkpoints {
+        writeln!(
+            out,
+            "{{\"kind\":\"checkpoint\",\"begin_lsn\":{},\"begin_found\":{},\"end_lsn\":{}}}",
+            cp.begin_lsn,
+            cp.begin_found,
+            cp.end_lsn.map_or("null".to_string(), |l| l.to_string())
+        )?;
+    }
+    let torn = match &end.torn {
+        Some(t) => format!(
+            "{{\"segment\":{},\"path\":{},\"offset\":{},\"reason\":{},\"discarded_bytes\":{},\"unread_segments\":{}}}",
+            t.segment_id,
+            json_string(&t.path.display().to_string()),
+            t.offset,
+            json_string(&t.reason.to_string()),
+            t.discarded_bytes,
+            t.unread_segments
+        ),
+        None => "null".to_string(),
+    };
+    writeln!(
+        out,
+        "{{\"kind\":\"end\",\"last_valid_lsn\":{},\"records\":{},\"torn\":{}}}",
+        end.last_valid_lsn, end.records, torn
+    )
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
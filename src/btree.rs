@@ -0,0 +1,1416 @@
+//! B+tree поверх страниц buffer pool: ключи и значения — байтовые строки,
+//! ключи уникальны и сравниваются побайтно.
+//!
+//! Узел занимает страницу с флагом `PageFlags::LEAF` или
+//! `PageFlags::INTERNAL`; тело узла:
+//!
+//! ```text
+//! offset  size  field
+//!     24     1  kind (1 — лист, 2 — внутренний узел)
+//!     25     1  0
+//!     26     2  entry_count
+//!     28     4  link: у листа — следующий лист (INVALID_PAGE_ID — последний),
+//!               у внутреннего узла — самый левый потомок
+//!     32     …  записи по возрастанию ключей
+//!
+//! лист:       key_len u16, value_len u16, key, value
+//! внутренний: key_len u16, child u32, key      (ключи в child >= key)
+//! ```
+//! Корень не переезжает: при разделении его половины уходят в две новые
+//! страницы, а когда у корня остаётся один потомок, тот копируется в
+//! корень. Номер корня и есть имя дерева.
+//!
+//! Узел делится пополам по байтам, когда записи не помещаются в страницу.
+//! Узел, занятый меньше чем на четверть, сливается с соседом, а если
+//! вместе они не помещаются — делит с ним записи поровну. Запись не больше
+//! `max_entry_size` (четверть места под записи), поэтому в переполненном
+//! узле всегда есть что делить.
+//!
+//! Каждая изменяющая операция (и каждый пакет `apply`) — отдельная
+//! транзакция в WAL: TXN_BEGIN, UPDATE с образами до и после изменённого
+//! диапазона каждого узла, TXN_COMMIT. Recovery повторяет завершённые операции и целиком
+//! откатывает оборванную, ошибка посреди операции откатывает её сразу (CLR
+//! и TXN_ABORT). Освобождённый узел обнуляется в той же транзакции и
+//! возвращается `delete_page` после COMMIT. Страницы, выделенные
+//! операцией, которую откатывает recovery, остаются занятыми с пустым
+//! телом. COMMIT сбрасывается на диск, только если операция освободила
+//! узлы (их нельзя затирать, пока откат ещё возможен), TXN_ABORT — если
+//! откат вернул выделенные страницы; в остальном это решает политика sync
+//! (`WalWriter::flush`, `GroupCommitWal`). Запись UPDATE (и CLR) делается
+//! под защёлкой уже грязной страницы, и WAL отпускается после её изменения,
+//! так что checkpoint не пропустит страницу с записью старше его BEGIN.
+//!
+//! Заголовок страницы WAL не покрывает. Флаг вида узла и `free_space` = 0
+//! (карта свободного места не предлагает узлы под кортежи) ставятся при
+//! каждой записи узла; вид узла читается из тела.
+
+use crate::buffer_pool::{BufferPool, BufferPoolError};
+use crate::page::{Lsn, PageFlags, PageHeader, PageId, TxnId, INVALID_PAGE_ID, PAGE_HEADER_SIZE};
+use crate::wal::{LogRecord, LogRecordType, WalWriter};
+use std::fmt;
+use std::io;
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+
+const LEAF: u8 = 1;
+const INTERNAL: u8 = 2;
+
+/// Начало записей узла
+const NODE_HEADER_SIZE: usize = PAGE_HEADER_SIZE + 8;
+
+const LEAF_ENTRY_OVERHEAD: usize = 4;
+const INTERNAL_ENTRY_OVERHEAD: usize = 6;
+
+/// Больше уровней не бывает даже у дерева из записей по 4 байта
+const MAX_HEIGHT: usize = 32;
+
+/// `bulk_load` заполняет узлы на 90%: первые вставки после загрузки не
+/// делят их сразу
+const BULK_FILL_PERCENT: usize = 90;
+
+/// Самая длинная запись (ключ и значение вместе)
+pub fn max_entry_size(page_size: usize) -> usize {
+    capacity(page_size) / 4 - INTERNAL_ENTRY_OVERHEAD
+}
+
+/// Место под записи узла
+fn capacity(page_size: usize) -> usize {
+    page_size - NODE_HEADER_SIZE
+}
+
+/// Ошибка операции с деревом; изменения операции к этому моменту откачены
+#[derive(Debug)]
+pub enum BTreeError {
+    Pool(BufferPoolError),
+    Wal(io::Error),
+    EntryTooLarge { len: usize, max: usize },
+    /// Ключи `bulk_load` не возрастают строго
+    Unsorted { index: usize },
+    Corrupt { page_id: PageId, message: String },
+}
+
+impl fmt::Display for BTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BTreeError::Pool(e) => e.fmt(f),
+            BTreeError::Wal(e) => write!(f, "B-tree WAL: {e}"),
+            BTreeError::EntryTooLarge { len, max } => {
+                write!(f, "entry of {len} bytes exceeds the {max}-byte limit")
+            }
+            BTreeError::Unsorted { index } => {
+                write!(f, "bulk load input is not strictly ascending at entry {index}")
+            }
+            BTreeError::Corrupt { page_id, message } => {
+                write!(f, "B-tree page {page_id}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BTreeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BTreeError::Pool(e) => Some(e),
+            BTreeError::Wal(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BufferPoolError> for BTreeError {
+    fn from(e: BufferPoolError) -> Self {
+        BTreeError::Pool(e)
+    }
+}
+
+fn corrupt(page_id: PageId, message: impl Into<String>) -> BTreeError {
+    BTreeError::Corrupt { page_id, message: message.into() }
+}
+
+// ============================================================================
+// Узлы
+// ============================================================================
+
+/// Разделитель и правая половина разделённого узла (узел или его страница)
+type Split<T> = Option<(Vec<u8>, T)>;
+
+/// Узел в памяти
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Leaf { entries: Vec<(Vec<u8>, Vec<u8>)>, next: PageId },
+    /// Потомок `i` (`child(i)`) — `first` для 0 и `entries[i - 1].1` дальше
+    Internal { first: PageId, entries: Vec<(Vec<u8>, PageId)> },
+}
+
+impl Node {
+    fn empty_leaf() -> Node {
+        Node::Leaf { entries: Vec::new(), next: INVALID_PAGE_ID }
+    }
+
+    fn decode(page_id: PageId, page: &[u8]) -> Result<Node, BTreeError> {
+        let count = usize::from(u16::from_le_bytes([page[26], page[27]]));
+        let link = PageId::from_le_bytes(page[28..32].try_into().unwrap());
+        let mut cursor = Cursor { page, at: NODE_HEADER_SIZE };
+        let overrun = |i: usize| corrupt(page_id, format!("entry {i} overruns the page"));
+        let node = match page[PAGE_HEADER_SIZE] {
+            LEAF => {
+                let mut entries = Vec::with_capacity(count);
+                for i in 0..count {
+                    entries.push(cursor.leaf_entry().ok_or_else(|| overrun(i))?);
+                }
+                Node::Leaf { entries, next: link }
+            }
+            INTERNAL => {
+                let mut entries = Vec::with_capacity(count);
+                for i in 0..count {
+                    entries.push(cursor.internal_entry().ok_or_else(|| overrun(i))?);
+                }
+                Node::Internal { first: link, entries }
+            }
+            kind => return Err(corrupt(page_id, format!("unknown node kind {kind}"))),
+        };
+        if let Some(i) = (1..node.len()).find(|&i| node.key(i - 1) >= node.key(i)) {
+            return Err(corrupt(page_id, format!("keys out of order at entry {i}")));
+        }
+        Ok(node)
+    }
+
+    /// Записать узел в тело страницы (заголовок не меняется)
+    fn encode(&self, page: &mut [u8]) {
+        page[PAGE_HEADER_SIZE..].fill(0);
+        let (kind, link) = match self {
+            Node::Leaf { next, .. } => (LEAF, *next),
+            Node::Internal { first, .. } => (INTERNAL, *first),
+        };
+        page[PAGE_HEADER_SIZE] = kind;
+        page[26..28].copy_from_slice(&(self.len() as u16).to_le_bytes());
+        page[28..32].copy_from_slice(&link.to_le_bytes());
+        let mut at = NODE_HEADER_SIZE;
+        let mut put = |bytes: &[u8]| {
+            page[at..at + bytes.len()].copy_from_slice(bytes);
+            at += bytes.len();
+        };
+        match self {
+            Node::Leaf { entries, .. } => {
+                for (key, value) in entries {
+                    put(&(key.len() as u16).to_le_bytes());
+                    put(&(value.len() as u16).to_le_bytes());
+                    put(key);
+                    put(value);
+                }
+            }
+            Node::Internal { entries, .. } => {
+                for (key, child) in entries {
+                    put(&(key.len() as u16).to_le_bytes());
+                    put(&child.to_le_bytes());
+                    put(key);
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf { entries, .. } => entries.len(),
+            Node::Internal { entries, .. } => entries.len(),
+        }
+    }
+
+    fn key(&self, i: usize) -> &[u8] {
+        match self {
+            Node::Leaf { entries, .. } => &entries[i].0,
+            Node::Internal { entries, .. } => &entries[i].0,
+        }
+    }
+
+    /// Байт под записи
+    fn size(&self) -> usize {
+        match self {
+            Node::Leaf { entries, .. } => {
+                entries.iter().map(|(k, v)| LEAF_ENTRY_OVERHEAD + k.len() + v.len()).sum()
+            }
+            Node::Internal { entries, .. } => {
+                entries.iter().map(|(k, _)| INTERNAL_ENTRY_OVERHEAD + k.len()).sum()
+            }
+        }
+    }
+
+    /// Номер потомка, в поддереве которого лежит `key`
+    fn child_index(&self, key: &[u8]) -> usize {
+        match self {
+            Node::Internal { entries, .. } => entries.partition_point(|(k, _)| k.as_slice() <= key),
+            Node::Leaf { .. } => 0,
+        }
+    }
+
+    fn child(&self, i: usize) -> PageId {
+        match self {
+            Node::Internal { first, .. } if i == 0 => *first,
+            Node::Internal { entries, .. } => entries[i - 1].1,
+            Node::Leaf { .. } => INVALID_PAGE_ID,
+        }
+    }
+
+    /// Следующий лист (у внутреннего узла ничего не меняет)
+    fn link(&mut self, page_id: PageId) {
+        if let Node::Leaf { next, .. } = self {
+            *next = page_id;
+        }
+    }
+
+    /// Разделить пополам по байтам: левая часть, разделитель (первый ключ
+    /// правой части), правая часть. `next` левого листа ставит вызывающий.
+    fn split(self) -> (Node, Vec<u8>, Node) {
+        let half = self.size() / 2;
+        match self {
+            Node::Leaf { mut entries, next } => {
+                let sizes = entries.iter().map(|(k, v)| LEAF_ENTRY_OVERHEAD + k.len() + v.len());
+                let mid = midpoint(sizes, half).clamp(1, entries.len() - 1);
+                let right = entries.split_off(mid);
+                let separator = right[0].0.clone();
+                let left = Node::Leaf { entries, next: INVALID_PAGE_ID };
+                (left, separator, Node::Leaf { entries: right, next })
+            }
+            Node::Internal { first, mut entries } => {
+                let sizes = entries.iter().map(|(k, _)| INTERNAL_ENTRY_OVERHEAD + k.len());
+                let mid = midpoint(sizes, half).clamp(1, entries.len() - 2);
+                let mut right = entries.split_off(mid);
+                let (separator, right_first) = right.remove(0);
+                let left = Node::Internal { first, entries };
+                (left, separator, Node::Internal { first: right_first, entries: right })
+            }
+        }
+    }
+}
+
+/// Первая запись, до которой набирается `half` байт
+fn midpoint(sizes: impl Iterator<Item = usize>, half: usize) -> usize {
+    let mut total = 0;
+    for (i, size) in sizes.enumerate() {
+        total += size;
+        if total >= half {
+            return i + 1;
+        }
+    }
+    0
+}
+
+/// Соседние узлы и разделитель между ними: один узел, если всё помещается в
+/// `capacity`, иначе две половины и новый разделитель. `None` — узлы разного
+/// вида.
+fn rebalance(
+    left: Node,
+    separator: Vec<u8>,
+    right: Node,
+    capacity: usize,
+) -> Option<(Node, Split<Node>)> {
+    let combined = match (left, right) {
+        (Node::Leaf { mut entries, .. }, Node::Leaf { entries: more, next }) => {
+            entries.extend(more);
+            Node::Leaf { entries, next }
+        }
+        (Node::Internal { first, mut entries }, Node::Internal { first: mid, entries: more }) => {
+            entries.push((separator, mid));
+            entries.extend(more);
+            Node::Internal { first, entries }
+        }
+        _ => return None,
+    };
+    if combined.size() <= capacity {
+        return Some((combined, None));
+    }
+    let (left, separator, right) = combined.split();
+    Some((left, Some((separator, right))))
+}
+
+struct Cursor<'a> {
+    page: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.page.get(self.at..self.at + len)?;
+        self.at += len;
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<usize> {
+        self.take(2).map(|b| usize::from(u16::from_le_bytes([b[0], b[1]])))
+    }
+
+    fn leaf_entry(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (key_len, value_len) = (self.u16()?, self.u16()?);
+        Some((self.take(key_len)?.to_vec(), self.take(value_len)?.to_vec()))
+    }
+
+    fn internal_entry(&mut self) -> Option<(Vec<u8>, PageId)> {
+        let key_len = self.u16()?;
+        let child = PageId::from_le_bytes(self.take(4)?.try_into().unwrap());
+        Some((self.take(key_len)?.to_vec(), child))
+    }
+}
+
+// ============================================================================
+// Дерево
+// ============================================================================
+
+/// Изменения одной операции; транзакция начинается первой записью в WAL
+#[derive(Debug, Default)]
+struct Txn {
+    id: TxnId,
+    last_lsn: Lsn,
+    /// Записанные UPDATE (с LSN) — для отката при ошибке
+    done: Vec<LogRecord>,
+    allocated: Vec<PageId>,
+    /// Обнулённые узлы; возвращаются после COMMIT
+    freed: Vec<PageId>,
+}
+
+/// B+tree с корнем в странице `root`.
+///
+/// Поиск и просмотр идут параллельно, изменяющие операции — по одной.
+#[derive(Debug)]
+pub struct BTree {
+    pool: Arc<BufferPool>,
+    wal: Arc<Mutex<WalWriter>>,
+    root: PageId,
+    latch: RwLock<()>,
+}
+
+impl BTree {
+    /// Новое пустое дерево
+    pub fn create(pool: Arc<BufferPool>, wal: Arc<Mutex<WalWriter>>) -> Result<Self, BTreeError> {
+        let mut tree = Self::open_unchecked(pool, wal, INVALID_PAGE_ID);
+        tree.root = tree.run(|txn| {
+            let root = tree.allocate(txn)?;
+            tree.write_node(txn, root, &Node::empty_leaf())?;
+            Ok(root)
+        })?;
+        Ok(tree)
+    }
+
+    /// Дерево с корнем в `root`
+    pub fn open(
+        pool: Arc<BufferPool>,
+        wal: Arc<Mutex<WalWriter>>,
+        root: PageId,
+    ) -> Result<Self, BTreeError> {
+        let tree = Self::open_unchecked(pool, wal, root);
+        tree.read_node(root)?;
+        Ok(tree)
+    }
+
+    /// Новое дерево из записей по строго возрастающим ключам. Узлы
+    /// заполняются снизу вверх, без поиска и разделений; загрузка — одна
+    /// транзакция.
+    pub fn bulk_load(
+        pool: Arc<BufferPool>,
+        wal: Arc<Mutex<WalWriter>>,
+        entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<Self, BTreeError> {
+        let mut tree = Self::open_unchecked(pool, wal, INVALID_PAGE_ID);
+        tree.root = tree.run(|txn| tree.load(txn, entries.into_iter()))?;
+        Ok(tree)
+    }
+
+    fn open_unchecked(pool: Arc<BufferPool>, wal: Arc<Mutex<WalWriter>>, root: PageId) -> Self {
+        Self { pool, wal, root, latch: RwLock::new(()) }
+    }
+
+    pub fn root(&self) -> PageId {
+        self.root
+    }
+
+    /// Значение по ключу
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BTreeError> {
+        let _latch = self.latch.read().unwrap();
+        let (_, leaf) = self.find_leaf(Some(key))?;
+        let Node::Leaf { mut entries, .. } = leaf else {
+            unreachable!("find_leaf returns leaves")
+        };
+        let found = entries.binary_search_by(|(k, _)| k.as_slice().cmp(key));
+        Ok(found.ok().map(|i| entries.swap_remove(i).1))
+    }
+
+    /// Записи с ключами из `range` по возрастанию. Пока итератор жив,
+    /// изменяющие операции ждут.
+    pub fn range<K: AsRef<[u8]>>(
+        &self,
+        range: impl RangeBounds<K>,
+    ) -> Result<Range<'_>, BTreeError> {
+        let owned = |bound: Bound<&K>| match bound {
+            Bound::Included(k) => Bound::Included(k.as_ref().to_vec()),
+            Bound::Excluded(k) => Bound::Excluded(k.as_ref().to_vec()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let (start, end) = (owned(range.start_bound()), owned(range.end_bound()));
+        let latch = self.latch.read().unwrap();
+        let start_key = match &start {
+            Bound::Included(k) | Bound::Excluded(k) => Some(k.as_slice()),
+            Bound::Unbounded => None,
+        };
+        let (_, leaf) = self.find_leaf(start_key)?;
+        let Node::Leaf { mut entries, next } = leaf else {
+            unreachable!("find_leaf returns leaves")
+        };
+        entries.retain(|(k, _)| match &start {
+            Bound::Included(s) => k >= s,
+            Bound::Excluded(s) => k > s,
+            Bound::Unbounded => true,
+        });
+        let entries = entries.into_iter();
+        Ok(Range { tree: self, _latch: latch, entries, next, end, done: false })
+    }
+
+    /// Все записи по возрастанию ключей
+    pub fn iter(&self) -> Result<Range<'_>, BTreeError> {
+        self.range::<&[u8]>(..)
+    }
+
+    /// Вставить или заменить значение; возвращает прежнее
+    pub fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, BTreeError> {
+        self.check_entry(key, value)?;
+        let _latch = self.latch.write().unwrap();
+        self.run(|txn| Ok(self.insert_in(txn, self.root, key, value)?.0))
+    }
+
+    /// Удалить ключ; возвращает значение
+    pub fn remove(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BTreeError> {
+        let _latch = self.latch.write().unwrap();
+        self.run(|txn| {
+            let (old, _) = self.remove_in(txn, self.root, key)?;
+            if old.is_some() {
+                self.shrink_root(txn)?;
+            }
+            Ok(old)
+        })
+    }
+
+    /// Вставки (`Some`) и удаления (`None`) по порядку одной транзакцией:
+    /// recovery повторяет или откатывает их вместе
+    pub fn apply(
+        &self,
+        batch: impl IntoIterator<Item = (Vec<u8>, Option<Vec<u8>>)>,
+    ) -> Result<(), BTreeError> {
+        let batch: Vec<_> = batch.into_iter().collect();
+        for (key, value) in &batch {
+            if let Some(value) = value {
+                self.check_entry(key, value)?;
+            }
+        }
+        let _latch = self.latch.write().unwrap();
+        self.run(|txn| {
+            for (key, value) in &batch {
+                match value {
+                    Some(value) => {
+                        self.insert_in(txn, self.root, key, value)?;
+                    }
+                    None => {
+                        if self.remove_in(txn, self.root, key)?.0.is_some() {
+                            self.shrink_root(txn)?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn check_entry(&self, key: &[u8], value: &[u8]) -> Result<(), BTreeError> {
+        let (len, max) = (key.len() + value.len(), max_entry_size(self.page_size()));
+        if len > max {
+            return Err(BTreeError::EntryTooLarge { len, max });
+        }
+        Ok(())
+    }
+
+    fn page_size(&self) -> usize {
+        self.pool.disk().page_size()
+    }
+
+    fn capacity(&self) -> usize {
+        capacity(self.page_size())
+    }
+
+    // ========================================================================
+    // Поиск
+    // ========================================================================
+
+    fn read_node(&self, page_id: PageId) -> Result<Node, BTreeError> {
+        let page = self.pool.fetch_page(page_id)?;
+        let data = page.read();
+        Node::decode(page_id, &data)
+    }
+
+    /// Лист, в котором лежит (или лежал бы) `key`; `None` — самый левый
+    fn find_leaf(&self, key: Option<&[u8]>) -> Result<(PageId, Node), BTreeError> {
+        let mut page_id = self.root;
+        for _ in 0..MAX_HEIGHT {
+            let node = self.read_node(page_id)?;
+            if let Node::Leaf { .. } = node {
+                return Ok((page_id, node));
+            }
+            page_id = node.child(key.map_or(0, |key| node.child_index(key)));
+        }
+        Err(corrupt(self.root, format!("no leaf within {MAX_HEIGHT} levels")))
+    }
+
+    // ========================================================================
+    // Вставка и удаление
+    // ========================================================================
+
+    /// Вставка в поддерево `page_id`: прежнее значение и, если узел
+    /// разделился, разделитель и новая правая страница
+    fn insert_in(
+        &self,
+        txn: &mut Txn,
+        page_id: PageId,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(Option<Vec<u8>>, Split<PageId>), BTreeError> {
+        let mut node = self.read_node(page_id)?;
+        let i = node.child_index(key);
+        let child = node.child(i);
+        let old = match &mut node {
+            Node::Leaf { entries, .. } => {
+                match entries.binary_search_by(|(k, _)| k.as_slice().cmp(key)) {
+                    Ok(i) if entries[i].1 == value => return Ok((Some(value.to_vec()), None)),
+                    Ok(i) => Some(std::mem::replace(&mut entries[i].1, value.to_vec())),
+                    Err(i) => {
+                        entries.insert(i, (key.to_vec(), value.to_vec()));
+                        None
+                    }
+                }
+            }
+            Node::Internal { entries, .. } => {
+                let (old, split) = self.insert_in(txn, child, key, value)?;
+                let Some(split) = split else {
+                    return Ok((old, None));
+                };
+                entries.insert(i, split);
+                old
+            }
+        };
+        Ok((old, self.store(txn, page_id, node)?))
+    }
+
+    /// Записать узел, разделив его, если он не помещается в страницу.
+    /// Разделённый корень остаётся на месте и получает две новые страницы
+    /// потомков, остальные узлы — правого соседа, которого возвращают.
+    fn store(
+        &self,
+        txn: &mut Txn,
+        page_id: PageId,
+        node: Node,
+    ) -> Result<Split<PageId>, BTreeError> {
+        if node.size() <= self.capacity() {
+            self.write_node(txn, page_id, &node)?;
+            return Ok(None);
+        }
+        let (mut left, separator, right) = node.split();
+        if page_id == self.root {
+            let (left_id, right_id) = (self.allocate(txn)?, self.allocate(txn)?);
+            left.link(right_id);
+            self.write_node(txn, left_id, &left)?;
+            self.write_node(txn, right_id, &right)?;
+            let root = Node::Internal { first: left_id, entries: vec![(separator, right_id)] };
+            self.write_node(txn, page_id, &root)?;
+            return Ok(None);
+        }
+        let right_id = self.allocate(txn)?;
+        left.link(right_id);
+        self.write_node(txn, right_id, &right)?;
+        self.write_node(txn, page_id, &left)?;
+        Ok(Some((separator, right_id)))
+    }
+
+    /// Удаление из поддерева `page_id`: значение и занят ли узел теперь
+    /// меньше чем на четверть
+    fn remove_in(
+        &self,
+        txn: &mut Txn,
+        page_id: PageId,
+        key: &[u8],
+    ) -> Result<(Option<Vec<u8>>, bool), BTreeError> {
+        let mut node = self.read_node(page_id)?;
+        let old = match &mut node {
+            Node::Leaf { entries, .. } => {
+                let Ok(i) = entries.binary_search_by(|(k, _)| k.as_slice().cmp(key)) else {
+                    return Ok((None, false));
+                };
+                Some(entries.remove(i).1)
+            }
+            Node::Internal { .. } => {
+                let i = node.child_index(key);
+                let (old, underflow) = self.remove_in(txn, node.child(i), key)?;
+                if !underflow {
+                    return Ok((old, false));
+                }
+                self.fix_child(txn, page_id, &mut node, i)?;
+                old
+            }
+        };
+        self.write_node(txn, page_id, &node)?;
+        Ok((old, node.size() < self.capacity() / 4))
+    }
+
+    /// Слить недозаполненного потомка `i` с соседом или поделить с ним
+    /// записи; `parent` меняется только в памяти
+    fn fix_child(
+        &self,
+        txn: &mut Txn,
+        parent_id: PageId,
+        parent: &mut Node,
+        i: usize,
+    ) -> Result<(), BTreeError> {
+        if parent.len() == 0 {
+            return Ok(());
+        }
+        let l = i.min(parent.len() - 1);
+        let (left_id, right_id) = (parent.child(l), parent.child(l + 1));
+        let left = self.read_node(left_id)?;
+        let right = self.read_node(right_id)?;
+        let Node::Internal { entries, .. } = parent else {
+            unreachable!("only internal nodes have children")
+        };
+        let separator = entries[l].0.clone();
+        match rebalance(left, separator, right, self.capacity()) {
+            Some((merged, None)) => {
+                self.write_node(txn, left_id, &merged)?;
+                self.free_node(txn, right_id)?;
+                entries.remove(l);
+            }
+            Some((mut left, Some((separator, right)))) => {
+                left.link(right_id);
+                self.write_node(txn, left_id, &left)?;
+                self.write_node(txn, right_id, &right)?;
+                entries[l].0 = separator;
+            }
+            None => {
+                let message = format!("children {left_id} and {right_id} differ in kind");
+                return Err(corrupt(parent_id, message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Корень с единственным потомком забирает его содержимое
+    fn shrink_root(&self, txn: &mut Txn) -> Result<(), BTreeError> {
+        if let Node::Internal { first, entries } = self.read_node(self.root)? {
+            if entries.is_empty() {
+                let child = self.read_node(first)?;
+                self.write_node(txn, self.root, &child)?;
+                self.free_node(txn, first)?;
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Загрузка
+    // ========================================================================
+
+    /// Построить дерево уровень за уровнем; возвращает корень
+    fn load(
+        &self,
+        txn: &mut Txn,
+        entries: impl Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<PageId, BTreeError> {
+        let root = self.allocate(txn)?;
+        let fill = self.capacity() * BULK_FILL_PERCENT / 100;
+
+        // Уровень — узлы с наименьшими ключами их поддеревьев
+        let mut level: Vec<(Vec<u8>, Node)> = Vec::new();
+        let mut leaf = Vec::new();
+        let mut size = 0;
+        for (index, (key, value)) in entries.enumerate() {
+            self.check_entry(&key, &value)?;
+            let prev = leaf.last().or_else(|| level.last().and_then(|(_, n)| last_leaf_entry(n)));
+            if prev.is_some_and(|(k, _)| *k >= key) {
+                return Err(BTreeError::Unsorted { index });
+            }
+            let entry_size = LEAF_ENTRY_OVERHEAD + key.len() + value.len();
+            if size + entry_size > fill && !leaf.is_empty() {
+                let entries = std::mem::take(&mut leaf);
+                level.push((first_key(&entries), Node::Leaf { entries, next: INVALID_PAGE_ID }));
+                size = 0;
+            }
+            size += entry_size;
+            leaf.push((key, value));
+        }
+        if !leaf.is_empty() || level.is_empty() {
+            level.push((first_key(&leaf), Node::Leaf { entries: leaf, next: INVALID_PAGE_ID }));
+        }
+
+        loop {
+            self.balance_last(&mut level);
+            if level.len() == 1 {
+                let (_, node) = level.pop().unwrap();
+                self.write_node(txn, root, &node)?;
+                return Ok(root);
+            }
+            let mut ids = Vec::with_capacity(level.len());
+            for _ in 0..level.len() {
+                ids.push(self.allocate(txn)?);
+            }
+            let mut parents: Vec<(Vec<u8>, Node)> = Vec::new();
+            let mut size = 0;
+            for (i, (min_key, mut node)) in level.into_iter().enumerate() {
+                node.link(ids.get(i + 1).copied().unwrap_or(INVALID_PAGE_ID));
+                self.write_node(txn, ids[i], &node)?;
+                let entry_size = INTERNAL_ENTRY_OVERHEAD + min_key.len();
+                match parents.last_mut() {
+                    Some((_, Node::Internal { entries, .. })) if size + entry_size <= fill => {
+                        entries.push((min_key, ids[i]));
+                        size += entry_size;
+                    }
+                    _ => {
+                        let node = Node::Internal { first: ids[i], entries: Vec::new() };
+                        parents.push((min_key, node));
+                        size = 0;
+                    }
+                }
+            }
+            level = parents;
+        }
+    }
+
+    /// Недозаполненный последний узел уровня сливается с предыдущим или
+    /// забирает у него половину записей
+    fn balance_last(&self, level: &mut Vec<(Vec<u8>, Node)>) {
+        let n = level.len();
+        if n < 2 || level[n - 1].1.size() >= self.capacity() / 4 {
+            return;
+        }
+        let (right_min, right) = level.pop().unwrap();
+        let (left_min, left) = level.pop().unwrap();
+        match rebalance(left, right_min, right, self.capacity()).expect("one level, one kind") {
+            (merged, None) => level.push((left_min, merged)),
+            (left, Some((separator, right))) => {
+                level.push((left_min, left));
+                level.push((separator, right));
+            }
+        }
+    }
+
+    // ========================================================================
+    // Страницы и WAL
+    // ========================================================================
+
+    /// Выполнить операцию как транзакцию: COMMIT при успехе, откат при ошибке
+    fn run<R>(&self, op: impl FnOnce(&mut Txn) -> Result<R, BTreeError>) -> Result<R, BTreeError> {
+        let mut txn = Txn::default();
+        match op(&mut txn) {
+            Ok(result) => {
+                self.commit(txn)?;
+                Ok(result)
+            }
+            Err(e) => {
+                let _ = self.rollback(txn);
+                Err(e)
+            }
+        }
+    }
+
+    fn commit(&self, txn: Txn) -> Result<(), BTreeError> {
+        if txn.id == 0 {
+            return Ok(());
+        }
+        let commit = LogRecord::commit(txn.id, txn.last_lsn);
+        let mut wal = self.wal.lock().unwrap();
+        let lsn = wal.append(&commit).map_err(BTreeError::Wal)?;
+        // Освобождённый узел можно затереть, только когда откат операции
+        // уже невозможен: иначе undo запишет образы до в чужую страницу
+        if !txn.freed.is_empty() {
+            wal.flush_to(lsn).map_err(BTreeError::Wal)?;
+        }
+        drop(wal);
+        for page_id in txn.freed {
+            self.pool.delete_page(page_id)?;
+        }
+        Ok(())
+    }
+
+    /// CLR на каждое изменение от последнего к первому, TXN_ABORT и возврат
+    /// выделенных страниц
+    fn rollback(&self, mut txn: Txn) -> Result<(), BTreeError> {
+        for undone in std::mem::take(&mut txn.done).iter().rev() {
+            let clr = LogRecord::compensation(undone, txn.last_lsn).expect("UPDATE is undoable");
+            let image = clr.redo_image().expect("CLR built from a valid record");
+            let start = usize::from(clr.offset);
+            let page = self.pool.fetch_page(clr.page_id)?;
+            // Порядок как в `write_page`
+            let mut data = page.write();
+            let mut wal = self.wal.lock().unwrap();
+            let lsn = wal.append(&clr).map_err(BTreeError::Wal)?;
+            txn.last_lsn = lsn;
+            data[start..start + image.len()].copy_from_slice(&image);
+            stamp(&mut data, lsn);
+        }
+        if txn.id != 0 {
+            let abort = LogRecord::new(LogRecordType::TxnAbort, txn.id);
+            let abort = LogRecord { prev_lsn: txn.last_lsn, ..abort };
+            let mut wal = self.wal.lock().unwrap();
+            let lsn = wal.append(&abort).map_err(BTreeError::Wal)?;
+            // То же, что в `commit`: без TXN_ABORT на диске recovery
+            // откатит операцию повторно и тронет возвращённые страницы
+            if !txn.allocated.is_empty() {
+                wal.flush_to(lsn).map_err(BTreeError::Wal)?;
+            }
+        }
+        for page_id in txn.allocated {
+            self.pool.delete_page(page_id)?;
+        }
+        Ok(())
+    }
+
+    fn allocate(&self, txn: &mut Txn) -> Result<PageId, BTreeError> {
+        let page_id = self.pool.new_page()?.page_id();
+        txn.allocated.push(page_id);
+        Ok(page_id)
+    }
+
+    fn free_node(&self, txn: &mut Txn, page_id: PageId) -> Result<(), BTreeError> {
+        self.write_page(txn, page_id, &vec![0; self.page_size()])?;
+        txn.freed.push(page_id);
+        Ok(())
+    }
+
+    fn write_node(&self, txn: &mut Txn, page_id: PageId, node: &Node) -> Result<(), BTreeError> {
+        debug_assert!(node.size() <= self.capacity(), "node does not fit its page");
+        let mut page = vec![0; self.page_size()];
+        node.encode(&mut page);
+        self.write_page(txn, page_id, &page)
+    }
+
+    /// Заменить тело страницы телом `after`: UPDATE с образами изменённого
+    /// диапазона, затем сама страница с `page_lsn` этой записи.
+    ///
+    /// Страница становится грязной до записи в WAL, а WAL отпускается только
+    /// после `stamp`: иначе checkpoint, начатый между ними, не увидит
+    /// страницу в снимке, а redo начнётся уже после её записи.
+    fn write_page(&self, txn: &mut Txn, page_id: PageId, after: &[u8]) -> Result<(), BTreeError> {
+        let page = self.pool.fetch_page(page_id)?;
+        let before = page.read()[PAGE_HEADER_SIZE..].to_vec();
+        let after = &after[PAGE_HEADER_SIZE..];
+        let differs = |(a, b): (&u8, &u8)| a != b;
+        let Some(start) = before.iter().zip(after).position(differs) else {
+            return Ok(());
+        };
+        let unchanged_tail = before.iter().rev().zip(after.iter().rev()).position(differs);
+        let end = before.len() - unchanged_tail.unwrap();
+
+        let mut data = page.write();
+        let mut wal = self.wal.lock().unwrap();
+        if txn.id == 0 {
+            txn.id = wal.next_txn_id();
+            let begin = LogRecord::new(LogRecordType::TxnBegin, txn.id);
+            txn.last_lsn = wal.append(&begin).map_err(BTreeError::Wal)?;
+        }
+        let offset = (PAGE_HEADER_SIZE + start) as u16;
+        let (old, new) = (&before[start..end], &after[start..end]);
+        let record = LogRecord::update(txn.id, txn.last_lsn, page_id, offset, old, new);
+        let lsn = wal.append(&record).map_err(BTreeError::Wal)?;
+        txn.last_lsn = lsn;
+        txn.done.push(LogRecord { lsn, ..record });
+        data[PAGE_HEADER_SIZE..].copy_from_slice(after);
+        stamp(&mut data, lsn);
+        Ok(())
+    }
+}
+
+/// `page_lsn` и поля заголовка, которые следуют из тела узла
+fn stamp(data: &mut [u8], lsn: Lsn) {
+    let mut header = PageHeader::decode(data);
+    header.page_lsn = lsn;
+    header.free_space = 0;
+    header.flags = match data[PAGE_HEADER_SIZE] {
+        LEAF => PageFlags::LEAF,
+        INTERNAL => PageFlags::INTERNAL,
+        _ => PageFlags::NONE,
+    };
+    header.encode(data);
+}
+
+fn first_key(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    entries.first().map(|(k, _)| k.clone()).unwrap_or_default()
+}
+
+fn last_leaf_entry(node: &Node) -> Option<&(Vec<u8>, Vec<u8>)> {
+    match node {
+        Node::Leaf { entries, .. } => entries.last(),
+        Node::Internal { .. } => None,
+    }
+}
+
+// ============================================================================
+// Просмотр
+// ============================================================================
+
+/// Итератор `BTree::range`: идёт по цепочке листьев
+#[derive(Debug)]
+pub struct Range<'a> {
+    tree: &'a BTree,
+    _latch: RwLockReadGuard<'a, ()>,
+    entries: std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+    next: PageId,
+    end: Bound<Vec<u8>>,
+    done: bool,
+}
+
+impl Iterator for Range<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>), BTreeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if let Some((key, value)) = self.entries.next() {
+                let inside = match &self.end {
+                    Bound::Included(end) => key <= *end,
+                    Bound::Excluded(end) => key < *end,
+                    Bound::Unbounded => true,
+                };
+                self.done = !inside;
+                return inside.then_some(Ok((key, value)));
+            }
+            if self.next == INVALID_PAGE_ID {
+                break;
+            }
+            match self.tree.read_node(self.next) {
+                Ok(Node::Leaf { entries, next }) => {
+                    self.entries = entries.into_iter();
+                    self.next = next;
+                }
+                Ok(Node::Internal { .. }) => {
+                    self.done = true;
+                    return Some(Err(corrupt(self.next, "leaf chain leads to an internal node")));
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointManager;
+    use crate::config::DatabaseConfig;
+    use crate::disk_manager::DiskManager;
+    use crate::recovery::recover;
+    use std::collections::BTreeMap;
+
+    type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn config(name: &str, frames: usize) -> DatabaseConfig {
+        let dir =
+            std::env::temp_dir().join(format!("datyredb_btree_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        DatabaseConfig {
+            data_path: dir,
+            page_size: 512,
+            buffer_pool_size: frames * 512,
+            wal_segment_size: 64 * 1024,
+            ..DatabaseConfig::default()
+        }
+    }
+
+    fn open(config: &DatabaseConfig) -> (Arc<BufferPool>, Arc<Mutex<WalWriter>>) {
+        let pool = Arc::new(BufferPool::new(config).unwrap());
+        let wal = WalWriter::open(config.wal_dir(), config.wal_segment_size).unwrap();
+        (pool, Arc::new(Mutex::new(wal)))
+    }
+
+    fn key(i: u32) -> Vec<u8> {
+        format!("key{i:06}").into_bytes()
+    }
+
+    fn value(i: u32) -> Vec<u8> {
+        vec![i as u8; i as usize % 40]
+    }
+
+    /// `0..n` в детерминированно перемешанном порядке
+    fn shuffled(n: u32, seed: u64) -> Vec<u32> {
+        let mut rng = seed;
+        let mut items: Vec<u32> = (0..n).collect();
+        for i in (1..items.len()).rev() {
+            rng = rng
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            items.swap(i, (rng >> 33) as usize % (i + 1));
+        }
+        items
+    }
+
+    /// Проверить границы ключей в поддеревьях, одинаковую глубину листьев и
+    /// цепочку листьев; высота дерева и все записи
+    fn check(tree: &BTree) -> (usize, Entries) {
+        let (mut leaves, mut entries) = (Vec::new(), Vec::new());
+        let height = walk(tree, tree.root(), None, None, &mut leaves, &mut entries);
+        for pair in leaves.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0, "leaf {} links to the wrong page", pair[0].0);
+        }
+        assert_eq!(leaves.last().unwrap().1, INVALID_PAGE_ID);
+        let scanned: Vec<_> = tree.iter().unwrap().map(Result::unwrap).collect();
+        assert_eq!(scanned, entries);
+        (height, entries)
+    }
+
+    fn walk(
+        tree: &BTree,
+        page_id: PageId,
+        low: Option<&[u8]>,
+        high: Option<&[u8]>,
+        leaves: &mut Vec<(PageId, PageId)>,
+        entries: &mut Entries,
+    ) -> usize {
+        let node = tree.read_node(page_id).unwrap();
+        assert!(page_id == tree.root() || node.len() > 0, "page {page_id} is empty");
+        for i in 0..node.len() {
+            let key = node.key(i);
+            assert!(low.is_none_or(|low| key >= low), "page {page_id}: key below its range");
+            assert!(high.is_none_or(|high| key < high), "page {page_id}: key above its range");
+        }
+        match &node {
+            Node::Leaf { entries: own, next } => {
+                leaves.push((page_id, *next));
+                entries.extend(own.iter().cloned());
+                1
+            }
+            Node::Internal { .. } => {
+                let mut heights = Vec::new();
+                for i in 0..=node.len() {
+                    let low = if i == 0 { low } else { Some(node.key(i - 1)) };
+                    let high = if i == node.len() { high } else { Some(node.key(i)) };
+                    heights.push(walk(tree, node.child(i), low, high, leaves, entries));
+                }
+                assert!(heights.windows(2).all(|h| h[0] == h[1]), "page {page_id}: {heights:?}");
+                heights[0] + 1
+            }
+        }
+    }
+
+    #[test]
+    fn inserts_removes_and_scans_match_a_model() {
+        let config = config("model", 16);
+        let (pool, wal) = open(&config);
+        let tree = BTree::create(pool.clone(), wal.clone()).unwrap();
+        let mut model = BTreeMap::new();
+        for i in shuffled(3000, 1) {
+            assert_eq!(tree.insert(&key(i), &value(i)).unwrap(), None);
+            model.insert(key(i), value(i));
+        }
+        assert_eq!(tree.insert(&key(7), b"seven").unwrap(), Some(value(7)));
+        model.insert(key(7), b"seven".to_vec());
+        let (height, entries) = check(&tree);
+        assert!(height >= 3, "height {height}");
+        assert_eq!(entries, model.clone().into_iter().collect::<Vec<_>>());
+        let header = pool.fetch_page(tree.root()).unwrap().header();
+        assert!(header.flags.contains(PageFlags::INTERNAL));
+        assert_eq!(header.free_space, 0);
+        assert_eq!(tree.get(&key(1234)).unwrap(), Some(value(1234)));
+        assert_eq!(tree.get(b"key").unwrap(), None);
+
+        let keys = |range: Range<'_>| range.map(|e| e.unwrap().0).collect::<Vec<_>>();
+        let middle = tree.range(key(100)..key(200)).unwrap();
+        assert_eq!(keys(middle), (100..200).map(key).collect::<Vec<_>>());
+        let tail = (Bound::Excluded(key(2995)), Bound::Unbounded);
+        assert_eq!(keys(tree.range(tail).unwrap()), (2996..3000).map(key).collect::<Vec<_>>());
+        assert_eq!(keys(tree.range(..=key(2)).unwrap()), (0..=2).map(key).collect::<Vec<_>>());
+
+        // Слияния уменьшают высоту, освобождённые узлы возвращаются в файл
+        // только после сброса COMMIT
+        for i in shuffled(3000, 2).into_iter().filter(|i| i % 50 != 0) {
+            let freed = pool.disk().pending_free_pages();
+            assert_eq!(tree.remove(&key(i)).unwrap(), model.remove(&key(i)), "key {i}");
+            if pool.disk().pending_free_pages() > freed {
+                let wal = wal.lock().unwrap();
+                assert_eq!(wal.flushed_lsn(), wal.next_lsn() - 1, "key {i}");
+            }
+        }
+        assert_eq!(tree.remove(b"key").unwrap(), None);
+        let (lower, entries) = check(&tree);
+        assert!(lower < height, "height {lower}");
+        assert_eq!(entries, model.into_iter().collect::<Vec<_>>());
+        assert!(pool.disk().pending_free_pages() > 0);
+
+        for i in (0..3000).step_by(50) {
+            assert_eq!(tree.remove(&key(i)).unwrap(), Some(value(i)));
+        }
+        let (height, entries) = check(&tree);
+        assert_eq!((height, entries.len()), (1, 0));
+        assert!(pool.fetch_page(tree.root()).unwrap().header().flags.contains(PageFlags::LEAF));
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn bulk_load_packs_sorted_input() {
+        let config = config("bulk", 16);
+        let (pool, wal) = open(&config);
+        let entries: Vec<_> = (0..3000).map(|i| (key(i), value(i))).collect();
+        let tree = BTree::bulk_load(pool.clone(), wal.clone(), entries.clone()).unwrap();
+        let (height, loaded) = check(&tree);
+        assert!(height >= 2, "height {height}");
+        assert_eq!(loaded, entries);
+        let loaded_pages = pool.disk().page_count();
+
+        // Вставка по одной делит узлы пополам и занимает больше страниц
+        let inserted = BTree::create(pool.clone(), wal.clone()).unwrap();
+        for (key, value) in &entries {
+            inserted.insert(key, value).unwrap();
+        }
+        assert!(pool.disk().page_count() - loaded_pages > loaded_pages, "{loaded_pages} pages");
+
+        // Загруженное дерево — обычное дерево
+        assert_eq!(tree.insert(&key(5000), b"x").unwrap(), None);
+        assert_eq!(tree.remove(&key(10)).unwrap(), Some(value(10)));
+        let root = tree.root();
+        drop(tree);
+        let tree = BTree::open(pool.clone(), wal.clone(), root).unwrap();
+        assert_eq!(check(&tree).1.len(), 3000);
+        assert_eq!(tree.get(&key(5000)).unwrap(), Some(b"x".to_vec()));
+
+        // Неотсортированный вход откатывается, страницы возвращаются
+        let freed = pool.disk().pending_free_pages();
+        let unsorted = (0..2000).map(|i| (key(i % 1500), value(i)));
+        let err = BTree::bulk_load(pool.clone(), wal.clone(), unsorted).unwrap_err();
+        assert!(matches!(err, BTreeError::Unsorted { index: 1500 }), "{err}");
+        assert!(pool.disk().pending_free_pages() > freed);
+
+        let err = tree.insert(&[1; 100], &[2; 20]).unwrap_err();
+        assert!(matches!(err, BTreeError::EntryTooLarge { len: 120, max: 114 }), "{err}");
+        let empty = BTree::bulk_load(pool, wal, Vec::new()).unwrap();
+        assert_eq!(check(&empty), (1, Vec::new()));
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn recovery_keeps_committed_operations_and_undoes_a_torn_one() {
+        // Восемь фреймов: узлы вытесняются на диск посреди операций
+        let config = config("recovery", 8);
+        let (pool, wal) = open(&config);
+        let tree = BTree::create(pool.clone(), wal.clone()).unwrap();
+        let root = tree.root();
+        let mut model = BTreeMap::new();
+        for i in shuffled(1500, 3) {
+            tree.insert(&key(i), &value(i)).unwrap();
+            model.insert(key(i), value(i));
+        }
+        for i in (0..1500).step_by(3) {
+            tree.remove(&key(i)).unwrap();
+            model.remove(&key(i));
+        }
+
+        // Операция без COMMIT: разделения уже в WAL и частично на диске
+        let mut txn = Txn::default();
+        for i in 1500..1800 {
+            tree.insert_in(&mut txn, root, &key(i), &value(i)).unwrap();
+        }
+        assert!(!txn.allocated.is_empty());
+        wal.lock().unwrap().flush().unwrap();
+        // Сбой: ни pool, ни WAL не закрываются
+        std::mem::forget(tree);
+        std::mem::forget(pool);
+        std::mem::forget(wal);
+
+        let report = recover(&config).unwrap();
+        assert_eq!(report.losers, vec![txn.id]);
+        assert!(report.redone > 0 && report.clrs_written > 0, "{report}");
+        let (pool, wal) = open(&config);
+        let tree = BTree::open(pool, wal, root).unwrap();
+        assert_eq!(check(&tree).1, model.into_iter().collect::<Vec<_>>());
+        assert_eq!(tree.insert(&key(1600), b"again").unwrap(), None);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn pages_are_dirty_before_their_records_are_appended() {
+        let config = config("dirty_first", 16);
+        let (pool, wal) = open(&config);
+        let tree = BTree::create(pool.clone(), wal.clone()).unwrap();
+        pool.flush_pages(&pool.get_dirty_pages()).unwrap();
+
+        // Checkpoint между записью в WAL и изменением страницы: пока WAL
+        // занят, BEGIN не записать, но снимок грязных страниц уже видит корень
+        let held = wal.lock().unwrap();
+        std::thread::scope(|scope| {
+            let insert = scope.spawn(|| tree.insert(&key(1), &value(1)).unwrap());
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+            while !pool.get_dirty_pages().contains(&tree.root()) {
+                assert!(std::time::Instant::now() < deadline, "root is not dirty");
+                std::thread::yield_now();
+            }
+            drop(held);
+            assert_eq!(insert.join().unwrap(), None);
+        });
+        assert_eq!(tree.get(&key(1)).unwrap(), Some(value(1)));
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn compaction_does_not_move_tree_nodes() {
+        let config = config("compact", 16);
+        let (pool, wal) = open(&config);
+        let tree = BTree::create(pool.clone(), wal.clone()).unwrap();
+        let root = tree.root();
+        for i in 0..1000 {
+            tree.insert(&key(i), &value(i)).unwrap();
+        }
+        for i in (0..900).filter(|i| i % 10 != 0) {
+            tree.remove(&key(i)).unwrap();
+        }
+        let model: Vec<_> = check(&tree).1;
+        pool.flush_pages(&pool.get_dirty_pages()).unwrap();
+        pool.disk().seal_freed();
+        pool.disk().release_freed().unwrap();
+        pool.sync_all().unwrap();
+        drop(tree);
+        drop(pool);
+
+        // Дыры ниже живых узлов есть, но ссылки на узлы не переписываются:
+        // обрезается только хвост за последним узлом
+        let disk = DiskManager::open(&config).unwrap();
+        assert!(disk.free_space().free_pages() > 0);
+        let compaction = disk.compact(&[root]).unwrap();
+        assert!(compaction.moves.is_empty());
+        assert!(disk.free_space().free_pages() > 0);
+        drop(disk);
+
+        let pool = Arc::new(BufferPool::new(&config).unwrap());
+        let tree = BTree::open(pool, wal, root).unwrap();
+        assert_eq!(check(&tree).1, model);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn range_bounds_match_a_model() {
+        type Bounds = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+        let config = config("range", 16);
+        let (pool, wal) = open(&config);
+        let tree = BTree::create(pool, wal).unwrap();
+        let mut model = BTreeMap::new();
+        for i in (0..600).step_by(2) {
+            tree.insert(&key(i), &value(i)).unwrap();
+            model.insert(key(i), value(i));
+        }
+        assert!(check(&tree).0 >= 2);
+        let scan =
+            |range: Bounds| -> Entries { tree.range(range).unwrap().map(Result::unwrap).collect() };
+
+        // Начала на ключах и между ними, в том числе на границах листьев
+        for s in 0..602 {
+            for range in [
+                (Bound::Included(key(s)), Bound::Excluded(key(s + 7))),
+                (Bound::Excluded(key(s)), Bound::Included(key(s + 7))),
+                (Bound::Included(key(s)), Bound::Unbounded),
+            ] {
+                let expected: Entries =
+                    model.range(range.clone()).map(|(k, v)| (k.clone(), v.clone())).collect();
+                assert_eq!(scan(range), expected, "start {s}");
+            }
+        }
+
+        // Пустые и перевёрнутые диапазоны, начало за последним листом
+        assert!(scan((Bound::Included(key(10)), Bound::Excluded(key(10)))).is_empty());
+        assert!(scan((Bound::Excluded(key(10)), Bound::Included(key(10)))).is_empty());
+        assert!(scan((Bound::Included(key(20)), Bound::Included(key(10)))).is_empty());
+        let single = scan((Bound::Included(key(10)), Bound::Included(key(10))));
+        assert_eq!(single, vec![(key(10), value(10))]);
+        assert!(scan((Bound::Excluded(key(598)), Bound::Unbounded)).is_empty());
+        assert!(scan((Bound::Included(key(1000)), Bound::Unbounded)).is_empty());
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn rollback_after_a_split_restores_the_tree() {
+        let config = config("rollback", 16);
+        let (pool, wal) = open(&config);
+        let tree = BTree::create(pool.clone(), wal.clone()).unwrap();
+        let root = tree.root();
+        for i in (0..600).step_by(2) {
+            tree.insert(&key(i), &value(i)).unwrap();
+        }
+        let before = check(&tree);
+
+        // Вставки делят листья и внутренние узлы, затем операция откатывается
+        let mut txn = Txn::default();
+        for i in (1..600).step_by(2) {
+            tree.insert_in(&mut txn, root, &key(i), &value(i)).unwrap();
+        }
+        let allocated = txn.allocated.len();
+        assert!(allocated > 0);
+        let freed = pool.disk().pending_free_pages();
+        tree.rollback(txn).unwrap();
+        assert_eq!(check(&tree), before);
+        assert_eq!(pool.disk().pending_free_pages(), freed + allocated);
+        wal.lock().unwrap().flush().unwrap();
+        // Сбой: откат завершён TXN_ABORT, recovery его не повторяет
+        std::mem::forget(tree);
+        std::mem::forget(pool);
+        std::mem::forget(wal);
+
+        let report = recover(&config).unwrap();
+        assert!(report.losers.is_empty(), "{report}");
+        let (pool, wal) = open(&config);
+        let tree = BTree::open(pool, wal, root).unwrap();
+        assert_eq!(check(&tree), before);
+        assert_eq!(tree.insert(&key(1), &value(1)).unwrap(), None);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn freed_nodes_are_reused_after_a_checkpoint() {
+        let config = config("reuse", 16);
+        let (pool, wal) = open(&config);
+        let manager = CheckpointManager::new(config.checkpoint.clone(), pool.clone(), wal.clone());
+        let tree = BTree::create(pool.clone(), wal.clone()).unwrap();
+        let root = tree.root();
+        let mut model = BTreeMap::new();
+        for i in 0..1500 {
+            tree.insert(&key(i), &value(i)).unwrap();
+            model.insert(key(i), value(i));
+        }
+        for i in (0..1500).filter(|i| i % 10 != 0) {
+            tree.remove(&key(i)).unwrap();
+            model.remove(&key(i));
+        }
+        assert!(pool.disk().pending_free_pages() > 0);
+
+        // До checkpoint'а освобождённые узлы не выдаются: файл растёт
+        let pages = pool.disk().page_count();
+        for i in 1500..1800 {
+            tree.insert(&key(i), &value(i)).unwrap();
+            model.insert(key(i), value(i));
+        }
+        assert!(pool.disk().page_count() > pages);
+
+        manager.manual_checkpoint().unwrap();
+        assert_eq!(pool.disk().pending_free_pages(), 0);
+        let (pages, free) = (pool.disk().page_count(), pool.disk().free_space().free_pages());
+        assert!(free > 0);
+        for i in 1800..2100 {
+            tree.insert(&key(i), &value(i)).unwrap();
+            model.insert(key(i), value(i));
+        }
+        assert_eq!(pool.disk().page_count(), pages);
+        assert!(pool.disk().free_space().free_pages() < free);
+        assert_eq!(check(&tree).1, model.clone().into_iter().collect::<Vec<_>>());
+        wal.lock().unwrap().flush().unwrap();
+        // Сбой: redo не пишет старые образы поверх переиспользованных страниц
+        std::mem::forget(manager);
+        std::mem::forget(tree);
+        std::mem::forget(pool);
+        std::mem::forget(wal);
+
+        recover(&config).unwrap();
+        let (pool, wal) = open(&config);
+        let tree = BTree::open(pool, wal, root).unwrap();
+        assert_eq!(check(&tree).1, model.into_iter().collect::<Vec<_>>());
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+}
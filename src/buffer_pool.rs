@@ -0,0 +1,731 @@
+//! Buffer pool с Clock-Sweep вытеснением (аналог `storage::BufferPool`).
+//!
+//! Страницы выдаются как [`PageGuard`]: гарда держит pin, пока жива, и
+//! снимает его в `Drop` — это и есть `unpin_page` из C++. Грязной страница
+//! становится при взятии [`PageGuard::write`], поэтому флаг `is_dirty`
+//! вручную передавать не нужно.
+//!
+//! Блокировки: таблица страниц под одним mutex (как `latch_` в C++),
+//! данные каждого фрейма под своим `RwLock`. Pin увеличивается только под
+//! mutex таблицы, поэтому незакреплённый фрейм, найденный Clock-Sweep,
+//! никто не закрепит до конца вытеснения.
+//!
+//! WAL-правило: перед записью грязной страницы (flush, батч checkpoint'а,
+//! вытеснение) пул вызывает `set_wal_flush`-хук с её `page_lsn`, и страница
+//! пишется, только когда WAL долговечен до этого LSN. Хук вызывается и под
+//! mutex таблицы, поэтому он не должен обращаться к пулу.
+
+use crate::config::DatabaseConfig;
+use crate::disk_manager::DiskManager;
+use crate::page::{Lsn, PageHeader, PageId, INVALID_LSN, INVALID_PAGE_ID};
+use crate::page_io::PageIo;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Ошибка buffer pool
+#[derive(Debug)]
+pub enum BufferPoolError {
+    /// Все фреймы закреплены гардами — вытеснять нечего
+    NoFreeFrames,
+    /// Страница закреплена и не может быть удалена
+    Pinned(PageId),
+    Io(io::Error),
+}
+
+impl fmt::Display for BufferPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoFreeFrames => write!(f, "no available frames: every page in the pool is pinned"),
+            Self::Pinned(page_id) => write!(f, "page {page_id} is pinned"),
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for BufferPoolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BufferPoolError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// ============================================================================
+// Фреймы
+// ============================================================================
+
+/// Фрейм buffer pool
+#[derive(Debug)]
+struct Frame {
+    /// Число живых гард; увеличивается только под `BufferPool::state`
+    pin_count: AtomicU32,
+    /// Reference bit для Clock-Sweep
+    referenced: AtomicBool,
+    dirty: AtomicBool,
+    data: RwLock<Box<[u8]>>,
+}
+
+/// Состояние под mutex пула
+#[derive(Debug)]
+struct PoolState {
+    /// Page ID -> индекс фрейма
+    page_table: HashMap<PageId, usize>,
+    /// Страница в каждом фрейме (`INVALID_PAGE_ID` — свободен)
+    frame_pages: Vec<PageId>,
+    free_list: VecDeque<usize>,
+    clock_hand: usize,
+}
+
+/// Сброс WAL до LSN включительно (`BufferPool::set_wal_flush`)
+pub type WalFlush = Box<dyn Fn(Lsn) -> io::Result<()> + Send + Sync>;
+
+/// Buffer pool поверх файла данных
+pub struct BufferPool {
+    disk: DiskManager,
+    frames: Box<[Frame]>,
+    state: Mutex<PoolState>,
+    dirty_count: AtomicUsize,
+    wal_flush: Option<WalFlush>,
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("disk", &self.disk)
+            .field("capacity", &self.capacity())
+            .field("dirty_pages", &self.dirty_page_count())
+            .field("wal_flush", &self.wal_flush.is_some())
+            .finish()
+    }
+}
+
+impl BufferPool {
+    /// Пул на `data_path/data.db`: `buffer_pool_size / page_size` фреймов
+    pub fn new(config: &DatabaseConfig) -> io::Result<Self> {
+        let pool_size = config.buffer_pool_pages();
+        if pool_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "buffer_pool_size {} is smaller than one {}-byte page",
+                    config.buffer_pool_size, config.page_size
+                ),
+            ));
+        }
+        Ok(Self::with_disk(DiskManager::open(config)?, pool_size))
+    }
+
+    /// Пул из `pool_size` фреймов над уже открытым файлом
+    pub fn with_disk(disk: DiskManager, pool_size: usize) -> Self {
+        assert!(pool_size > 0, "buffer pool needs at least one frame");
+        let page_size = disk.page_size();
+        let frames = (0..pool_size)
+            .map(|_| Frame {
+                pin_count: AtomicU32::new(0),
+                referenced: AtomicBool::new(false),
+                dirty: AtomicBool::new(false),
+                data: RwLock::new(vec![0u8; page_size].into_boxed_slice()),
+            })
+            .collect();
+        let state = PoolState {
+            page_table: HashMap::with_capacity(pool_size),
+            frame_pages: vec![INVALID_PAGE_ID; pool_size],
+            free_list: (0..pool_size).collect(),
+            clock_hand: 0,
+        };
+        Self {
+            disk,
+            frames,
+            state: Mutex::new(state),
+            dirty_count: AtomicUsize::new(0),
+            wal_flush: None,
+        }
+    }
+
+    /// Хук WAL-правила: вызывается с `page_lsn` перед записью грязной
+    /// страницы и должен сделать WAL долговечным до этого LSN, например
+    /// `move |lsn| wal.lock().unwrap().flush_to(lsn)`. Без хука (recovery,
+    /// офлайн-утилиты) страницы пишутся сразу.
+    pub fn set_wal_flush(&mut self, flush: impl Fn(Lsn) -> io::Result<()> + Send + Sync + 'static) {
+        self.wal_flush = Some(Box::new(flush));
+    }
+
+    pub fn disk(&self) -> &DiskManager {
+        &self.disk
+    }
+
+    // ========================================================================
+    // Page access
+    // ========================================================================
+
+    /// Получить страницу (загружает с диска, если её нет в пуле)
+    pub fn fetch_page(&self, page_id: PageId) -> Result<PageGuard<'_>, BufferPoolError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(&idx) = state.page_table.get(&page_id) {
+            return Ok(self.pin(idx, page_id));
+        }
+
+        let idx = self.find_victim_frame(&mut state)?;
+        let read = self.disk.read_page(page_id, &mut self.frames[idx].data.write().unwrap());
+        if let Err(e) = read {
+            state.free_list.push_back(idx);
+            return Err(e.into());
+        }
+        state.page_table.insert(page_id, idx);
+        state.frame_pages[idx] = page_id;
+        Ok(self.pin(idx, page_id))
+    }
+
+    /// Создать новую страницу: освобождённую или в конце файла.
+    ///
+    /// Страница сразу грязная: заголовок должен попасть на диск, даже
+    /// если её больше не изменят.
+    pub fn new_page(&self) -> Result<PageGuard<'_>, BufferPoolError> {
+        let mut state = self.state.lock().unwrap();
+        let idx = self.find_victim_frame(&mut state)?;
+        let page_id = match self.disk.allocate_page() {
+            Ok(page_id) => page_id,
+            Err(e) => {
+                state.free_list.push_back(idx);
+                return Err(e.into());
+            }
+        };
+
+        let frame = &self.frames[idx];
+        {
+            let mut data = frame.data.write().unwrap();
+            data.fill(0);
+            PageHeader::new(page_id, data.len()).encode(&mut data);
+        }
+        self.set_dirty(frame);
+        state.page_table.insert(page_id, idx);
+        state.frame_pages[idx] = page_id;
+        Ok(self.pin(idx, page_id))
+    }
+
+    /// Записать страницу на диск, если она грязная.
+    ///
+    /// Нельзя вызывать, держа `PageGuard::write` на ту же страницу.
+    pub fn flush_page(&self, page_id: PageId) -> Result<(), BufferPoolError> {
+        let Some((guard, mut copy)) = self.take_dirty(page_id) else {
+            return Ok(());
+        };
+        let written =
+            self.flush_wal(page_lsn(&copy)).and_then(|()| self.disk.write_page(page_id, &mut copy));
+        if let Err(e) = written {
+            self.set_dirty(guard.frame());
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Удалить страницу из пула и освободить её в файле (см.
+    /// `DiskManager::deallocate_page`)
+    pub fn delete_page(&self, page_id: PageId) -> Result<(), BufferPoolError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(&idx) = state.page_table.get(&page_id) {
+            let frame = &self.frames[idx];
+            if frame.pin_count.load(Ordering::Acquire) > 0 {
+                return Err(BufferPoolError::Pinned(page_id));
+            }
+            if frame.dirty.swap(false, Ordering::AcqRel) {
+                self.dirty_count.fetch_sub(1, Ordering::Relaxed);
+            }
+            frame.referenced.store(false, Ordering::Relaxed);
+            state.page_table.remove(&page_id);
+            state.frame_pages[idx] = INVALID_PAGE_ID;
+            state.free_list.push_back(idx);
+        }
+        self.disk.deallocate_page(page_id)?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Checkpoint support
+    // ========================================================================
+
+    /// Снимок грязных страниц для checkpoint
+    pub fn get_dirty_pages(&self) -> Vec<PageId> {
+        let state = self.state.lock().unwrap();
+        let mut pages: Vec<PageId> = state
+            .page_table
+            .iter()
+            .filter(|&(_, &idx)| self.frames[idx].dirty.load(Ordering::Acquire))
+            .map(|(&page_id, _)| page_id)
+            .collect();
+        pages.sort_unstable();
+        pages
+    }
+
+    /// Flush батча страниц; при ошибке остальные всё равно пишутся,
+    /// возвращается первая ошибка
+    pub fn flush_pages(&self, pages: &[PageId]) -> Result<(), BufferPoolError> {
+        let mut first_error = None;
+        for &page_id in pages {
+            if let Err(e) = self.flush_page(page_id) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Записать грязные страницы из `pages` одним батчем через `io`.
+    ///
+    /// При ошибке все страницы батча снова помечаются грязными. Возвращает
+    /// число записанных страниц.
+    pub fn flush_batch(&self, pages: &[PageId], io: &mut PageIo) -> Result<usize, BufferPoolError> {
+        self.flush_batch_with(pages, io, |lsn| self.flush_wal(lsn))
+    }
+
+    /// `flush_batch`, но WAL сбрасывает `wal_flush` (вместо хука пула): он
+    /// получает наибольший `page_lsn` батча после того, как страницы
+    /// скопированы, и до их записи
+    pub fn flush_batch_with(
+        &self,
+        pages: &[PageId],
+        io: &mut PageIo,
+        wal_flush: impl FnOnce(Lsn) -> io::Result<()>,
+    ) -> Result<usize, BufferPoolError> {
+        let (guards, mut batch): (Vec<_>, Vec<_>) = pages
+            .iter()
+            .filter_map(|&page_id| self.take_dirty(page_id))
+            .map(|(guard, copy)| {
+                let page_id = guard.page_id();
+                (guard, (page_id, copy))
+            })
+            .unzip();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        let max_lsn = batch.iter().map(|(_, copy)| page_lsn(copy)).max().unwrap_or(INVALID_LSN);
+        let written = match max_lsn {
+            INVALID_LSN => Ok(()),
+            lsn => wal_flush(lsn),
+        };
+        let written = written.and_then(|()| self.disk.write_batch(io, &mut batch));
+        if let Err(e) = written {
+            for guard in &guards {
+                self.set_dirty(guard.frame());
+            }
+            return Err(e.into());
+        }
+        Ok(batch.len())
+    }
+
+    /// fsync файла данных
+    pub fn sync_all(&self) -> io::Result<()> {
+        self.disk.sync()
+    }
+
+    /// fsync файла данных через бэкенд `io`
+    pub fn sync_with(&self, io: &mut PageIo) -> io::Result<()> {
+        self.disk.sync_with(io)
+    }
+
+    // ========================================================================
+    // Stats
+    // ========================================================================
+
+    /// Общее количество фреймов
+    pub fn capacity(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Текущее количество грязных страниц
+    pub fn dirty_page_count(&self) -> usize {
+        self.dirty_count.load(Ordering::Relaxed)
+    }
+
+    /// Текущее количество страниц в пуле
+    pub fn page_count(&self) -> usize {
+        self.state.lock().unwrap().page_table.len()
+    }
+
+    // ========================================================================
+    // Внутреннее
+    // ========================================================================
+
+    /// Вызывается только под `state`
+    fn pin(&self, idx: usize, page_id: PageId) -> PageGuard<'_> {
+        let frame = &self.frames[idx];
+        frame.pin_count.fetch_add(1, Ordering::AcqRel);
+        frame.referenced.store(true, Ordering::Relaxed);
+        PageGuard { pool: self, frame: idx, page_id }
+    }
+
+    /// Закрепить страницу, снять с неё флаг dirty и скопировать данные.
+    /// `None`, если страницы нет в пуле или она чистая. Счётчик грязных
+    /// уменьшается сразу; если запись не удалась, `set_dirty` вернёт
+    /// страницу в грязные (и в счётчик, если её уже не испачкали снова).
+    ///
+    /// Таблица отпускается до записи на диск, а checksum считается на копии:
+    /// flush не блокирует ни остальные страницы, ни читателей этой.
+    fn take_dirty(&self, page_id: PageId) -> Option<(PageGuard<'_>, Vec<u8>)> {
+        let guard = {
+            let state = self.state.lock().unwrap();
+            let &idx = state.page_table.get(&page_id)?;
+            self.pin(idx, page_id)
+        };
+        let frame = guard.frame();
+        let data = frame.data.read().unwrap();
+        if !frame.dirty.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        self.dirty_count.fetch_sub(1, Ordering::Relaxed);
+        let copy = data.to_vec();
+        drop(data);
+        Some((guard, copy))
+    }
+
+    fn set_dirty(&self, frame: &Frame) {
+        if !frame.dirty.swap(true, Ordering::AcqRel) {
+            self.dirty_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// WAL-правило перед записью страниц с `page_lsn` до `lsn`
+    fn flush_wal(&self, lsn: Lsn) -> io::Result<()> {
+        match &self.wal_flush {
+            Some(flush) if lsn != INVALID_LSN => flush(lsn),
+            _ => Ok(()),
+        }
+    }
+
+    /// Свободный фрейм или жертва Clock-Sweep
+    fn find_victim_frame(&self, state: &mut PoolState) -> Result<usize, BufferPoolError> {
+        if let Some(idx) = state.free_list.pop_front() {
+            return Ok(idx);
+        }
+        self.clock_sweep(state)
+    }
+
+    /// Два прохода: первый сбрасывает reference bit, второй находит жертву
+    fn clock_sweep(&self, state: &mut PoolState) -> Result<usize, BufferPoolError> {
+        let pool_size = self.frames.len();
+        for _pass in 0..2 {
+            for i in 0..pool_size {
+                let idx = (state.clock_hand + i) % pool_size;
+                let frame = &self.frames[idx];
+
+                if frame.pin_count.load(Ordering::Acquire) > 0
+                    || state.frame_pages[idx] == INVALID_PAGE_ID
+                {
+                    continue;
+                }
+                if frame.referenced.swap(false, Ordering::Relaxed) {
+                    continue; // Второй шанс
+                }
+
+                self.evict_frame(state, idx)?;
+                state.clock_hand = (idx + 1) % pool_size;
+                return Ok(idx);
+            }
+        }
+        Err(BufferPoolError::NoFreeFrames)
+    }
+
+    /// Вытеснить незакреплённый фрейм, записав его, если он грязный
+    fn evict_frame(&self, state: &mut PoolState, idx: usize) -> io::Result<()> {
+        let frame = &self.frames[idx];
+        let page_id = state.frame_pages[idx];
+        if frame.dirty.load(Ordering::Acquire) {
+            let mut data = frame.data.write().unwrap();
+            self.flush_wal(page_lsn(&data))?;
+            self.disk.write_page(page_id, &mut data)?;
+            frame.dirty.store(false, Ordering::Release);
+            self.dirty_count.fetch_sub(1, Ordering::Relaxed);
+        }
+        state.page_table.remove(&page_id);
+        state.frame_pages[idx] = INVALID_PAGE_ID;
+        Ok(())
+    }
+}
+
+fn page_lsn(page: &[u8]) -> Lsn {
+    PageHeader::decode(page).page_lsn
+}
+
+impl Drop for BufferPool {
+    /// Как деструктор C++: грязные страницы пишутся при закрытии
+    fn drop(&mut self) {
+        let dirty = self.get_dirty_pages();
+        if !dirty.is_empty() {
+            let _ = self.flush_pages(&dirty);
+            let _ = self.sync_all();
+        }
+    }
+}
+
+// ============================================================================
+// Гарды
+// ============================================================================
+
+/// Закреплённая страница; pin снимается в `Drop`
+#[derive(Debug)]
+pub struct PageGuard<'a> {
+    pool: &'a BufferPool,
+    frame: usize,
+    page_id: PageId,
+}
+
+impl<'a> PageGuard<'a> {
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    /// Доступ на чтение
+    pub fn read(&self) -> PageRef<'_> {
+        PageRef(self.frame().data.read().unwrap())
+    }
+
+    /// Доступ на запись; страница помечается грязной
+    pub fn write(&self) -> PageMut<'_> {
+        let data = self.frame().data.write().unwrap();
+        self.pool.set_dirty(self.frame());
+        PageMut(data)
+    }
+
+    /// Заголовок страницы
+    pub fn header(&self) -> PageHeader {
+        PageHeader::decode(&self.read())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.frame().dirty.load(Ordering::Acquire)
+    }
+
+    fn frame(&self) -> &'a Frame {
+        &self.pool.frames[self.frame]
+    }
+}
+
+impl Drop for PageGuard<'_> {
+    fn drop(&mut self) {
+        self.frame().pin_count.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Байты страницы на чтение (`PageGuard::read`)
+#[derive(Debug)]
+pub struct PageRef<'a>(RwLockReadGuard<'a, Box<[u8]>>);
+
+impl Deref for PageRef<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Байты страницы на запись (`PageGuard::write`)
+#[derive(Debug)]
+pub struct PageMut<'a>(RwLockWriteGuard<'a, Box<[u8]>>);
+
+impl Deref for PageMut<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerefMut for PageMut<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page_file::{PageFile, PageStatus};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    const PAGE: usize = 512;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("datyredb_buffer_pool_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn open_pool(dir: &std::path::Path, frames: usize) -> BufferPool {
+        BufferPool::with_disk(DiskManager::open_path(dir.join("data.db"), PAGE).unwrap(), frames)
+    }
+
+    fn cached(pool: &BufferPool, page_id: PageId) -> bool {
+        pool.state.lock().unwrap().page_table.contains_key(&page_id)
+    }
+
+    #[test]
+    fn sized_from_config() {
+        let dir = temp_dir("config");
+        let mut config = DatabaseConfig {
+            data_path: dir.clone(),
+            buffer_pool_size: 8 * PAGE,
+            page_size: PAGE,
+            ..DatabaseConfig::default()
+        };
+        assert_eq!(BufferPool::new(&config).unwrap().capacity(), 8);
+        config.buffer_pool_size = PAGE - 1;
+        assert!(BufferPool::new(&config).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn clock_sweep_gives_second_chance() {
+        let dir = temp_dir("clock");
+        let pool = open_pool(&dir, 3);
+        for expected in 0..3 {
+            let page = pool.new_page().unwrap();
+            assert_eq!(page.page_id(), expected);
+            page.write()[100] = expected as u8 + 1;
+        }
+        assert_eq!(pool.dirty_page_count(), 3);
+
+        // Все reference bit выставлены: первый проход их сбрасывает,
+        // второй вытесняет фрейм под стрелкой
+        drop(pool.new_page().unwrap());
+        assert!(!cached(&pool, 0));
+        assert_eq!(pool.dirty_page_count(), 3);
+
+        // Страница 1 снова использована и получает второй шанс
+        drop(pool.fetch_page(1).unwrap());
+        drop(pool.new_page().unwrap());
+        assert!(cached(&pool, 1));
+        assert!(!cached(&pool, 2));
+
+        // Вытесненная грязная страница записана на диск
+        assert_eq!(pool.fetch_page(0).unwrap().read()[100], 1);
+        assert_eq!(pool.page_count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn guards_hold_pins() {
+        let dir = temp_dir("pins");
+        let pool = open_pool(&dir, 2);
+        let a = pool.new_page().unwrap();
+        let b = pool.new_page().unwrap();
+        assert!(matches!(pool.new_page(), Err(BufferPoolError::NoFreeFrames)));
+        assert!(matches!(pool.delete_page(b.page_id()), Err(BufferPoolError::Pinned(1))));
+
+        // Две гарды на одну страницу — два pin
+        let a2 = pool.fetch_page(a.page_id()).unwrap();
+        drop(a);
+        assert!(matches!(pool.delete_page(0), Err(BufferPoolError::Pinned(0))));
+        drop(a2);
+
+        pool.delete_page(0).unwrap();
+        assert_eq!(pool.page_count(), 1);
+        assert_eq!(pool.dirty_page_count(), 1);
+        drop(b);
+        // Удалённая страница переиспользуется после checkpoint'а
+        assert_eq!(pool.disk().pending_free_pages(), 1);
+        pool.disk().seal_freed();
+        pool.disk().release_freed().unwrap();
+        assert_eq!(pool.new_page().unwrap().page_id(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flushes_dirty_pages_for_checkpoint() {
+        let dir = temp_dir("flush");
+        let pool = open_pool(&dir, 4);
+        for _ in 0..3 {
+            pool.new_page().unwrap();
+        }
+        pool.flush_pages(&pool.get_dirty_pages()).unwrap();
+        assert_eq!(pool.dirty_page_count(), 0);
+
+        let page = pool.fetch_page(1).unwrap();
+        assert!(!page.is_dirty());
+        page.write()[200] = 0xAB;
+        drop(page);
+        drop(pool.fetch_page(2).unwrap().read());
+        assert_eq!(pool.get_dirty_pages(), vec![1]);
+
+        pool.flush_page(1).unwrap();
+        pool.sync_all().unwrap();
+        assert!(pool.get_dirty_pages().is_empty());
+
+        let file = PageFile::open_path(dir.join("data.db"), PAGE).unwrap();
+        for page in file.pages() {
+            assert_eq!(page.unwrap().status, PageStatus::Valid);
+        }
+        assert_eq!(file.read_page(1).unwrap()[200], 0xAB);
+
+        // Незаписанное изменение сбрасывается при закрытии пула
+        pool.fetch_page(2).unwrap().write()[300] = 7;
+        drop(pool);
+        let pool = open_pool(&dir, 1);
+        let page = pool.fetch_page(2).unwrap();
+        assert_eq!(page.read()[300], 7);
+        assert_eq!(page.header().page_id, 2);
+        drop(page);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn wal_is_flushed_before_pages() {
+        let dir = temp_dir("wal_rule");
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let wal_down = Arc::new(AtomicBool::new(false));
+        let mut pool = open_pool(&dir, 2);
+        {
+            let (flushed, wal_down) = (flushed.clone(), wal_down.clone());
+            pool.set_wal_flush(move |lsn| {
+                if wal_down.load(Ordering::Relaxed) {
+                    return Err(io::Error::other("WAL is unavailable"));
+                }
+                flushed.lock().unwrap().push(lsn);
+                Ok(())
+            });
+        }
+        let stamp = |page: PageGuard<'_>, lsn: Lsn| {
+            let mut data = page.write();
+            let mut header = PageHeader::decode(&data);
+            header.page_lsn = lsn;
+            header.encode(&mut data);
+        };
+        stamp(pool.new_page().unwrap(), 5);
+        stamp(pool.new_page().unwrap(), 7);
+        pool.flush_page(0).unwrap();
+        assert_eq!(*flushed.lock().unwrap(), [5]);
+
+        // WAL не сброшен — страница не пишется и остаётся грязной
+        wal_down.store(true, Ordering::Relaxed);
+        assert!(pool.flush_page(1).is_err());
+        assert!(pool.flush_batch(&[1], &mut PageIo::Pwrite).is_err());
+        assert_eq!(pool.get_dirty_pages(), [1]);
+        assert_eq!(pool.dirty_page_count(), 1);
+        let file = PageFile::open_path(dir.join("data.db"), PAGE).unwrap();
+        assert_eq!(file.check_page(1).unwrap().status, PageStatus::Unwritten);
+
+        // Вытеснение грязной страницы тоже сбрасывает WAL; у новой
+        // страницы page_lsn нет, и WAL для неё не нужен
+        wal_down.store(false, Ordering::Relaxed);
+        stamp(pool.fetch_page(0).unwrap(), 9);
+        drop(pool.new_page().unwrap());
+        assert_eq!(flushed.lock().unwrap().len(), 2);
+        pool.flush_batch(&pool.get_dirty_pages(), &mut PageIo::Pwrite).unwrap();
+        assert_eq!(pool.dirty_page_count(), 0);
+        let mut flushed = flushed.lock().unwrap().clone();
+        flushed.sort_unstable();
+        assert_eq!(flushed, [5, 7, 9]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
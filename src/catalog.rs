@@ -0,0 +1,1018 @@
+//! Системный каталог: таблицы, их столбцы, индексы и корневые страницы
+//! хранятся в файле данных, а не в памяти `StorageEngine` и не в текстовом
+//! WAL.
+//!
+//! Каталог — B+tree (`btree`) с корнем в зарезервированной странице
+//! `CATALOG_ROOT`: её создаёт первое открытие пустого файла. Записи дерева:
+//!
+//! ```text
+//! key                                  value
+//! 'v'                                  версия каталога u64 LE
+//! 't' имя_в_нижнем_регистре 0x00 n u16 BE   часть n определения таблицы
+//! ```
+//! Определение таблицы (столбцы, первичный ключ, индексы) делится на части
+//! по `max_entry_size`, а каждое изменение — новые части, удаление лишних
+//! и новая версия — один пакет `BTree::apply`, то есть одна транзакция
+//! WAL. Версия каталога растёт на единицу с каждым изменением; у таблицы
+//! хранится версия её последнего изменения.
+//!
+//! При открытии (после `recover`) каталог читается из дерева целиком,
+//! без повтора каких-либо операторов. Страницы данных таблиц и индексов
+//! каталог не выделяет и не освобождает: корни передаёт и забирает
+//! вызывающий.
+//!
+//! Каталог виден как системные таблицы только для чтения `datyre_tables`,
+//! `datyre_columns` и `datyre_indexes` (`Catalog::system_table`); имена с
+//! префиксом `datyre_` зарезервированы.
+//!
+//! ```text
+//! определение таблицы
+//!   version u64, root u32, name str
+//!   column_count u16, столбцы: name str, type u8 (номер в DataType::ALL),
+//!       not_null u8, default: 0 | 1 + str (строка из одного столбца, schema)
+//!   key_count u16, номера столбцов первичного ключа u16
+//!   index_count u16, индексы: name str, unique u8, root u32,
+//!       column_count u16, номера столбцов u16
+//! str — длина u16 LE и байты; числа little-endian
+//! ```
+
+use crate::btree::{max_entry_size, BTree, BTreeError};
+use crate::buffer_pool::BufferPool;
+use crate::page::PageId;
+use crate::schema::{Column, SchemaError, TableSchema};
+use crate::value::{DataType, Value};
+use crate::wal::WalWriter;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Корень дерева каталога — первая страница файла данных
+pub const CATALOG_ROOT: PageId = 0;
+
+/// Системные таблицы каталога
+pub const SYSTEM_TABLES: [&str; 3] = ["datyre_tables", "datyre_columns", "datyre_indexes"];
+
+/// Самое длинное имя таблицы или индекса в байтах
+pub const MAX_NAME_LEN: usize = 64;
+
+const RESERVED_PREFIX: &str = "datyre_";
+const VERSION_KEY: &[u8] = b"v";
+const TABLE_PREFIX: u8 = b't';
+
+/// Ошибка каталога
+#[derive(Debug)]
+pub enum CatalogError {
+    Tree(BTreeError),
+    Schema(SchemaError),
+    TableExists(String),
+    NoSuchTable(String),
+    IndexExists(String),
+    NoSuchIndex(String),
+    /// Имя с префиксом системных таблиц
+    ReservedName(String),
+    /// Пустое, длиннее `MAX_NAME_LEN` или с нулевым байтом
+    InvalidName(String),
+    /// Определение не помещается в формат каталога
+    TooLarge(String),
+    Corrupt(String),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Tree(e) => write!(f, "catalog storage: {e}"),
+            CatalogError::Schema(e) => write!(f, "{e}"),
+            CatalogError::TableExists(name) => write!(f, "table '{name}' already exists"),
+            CatalogError::NoSuchTable(name) => write!(f, "table '{name}' does not exist"),
+            CatalogError::IndexExists(name) => write!(f, "index '{name}' already exists"),
+            CatalogError::NoSuchIndex(name) => write!(f, "index '{name}' does not exist"),
+            CatalogError::ReservedName(name) => {
+                write!(f, "name '{name}' is reserved for system tables")
+            }
+            CatalogError::InvalidName(name) => write!(f, "invalid name '{name}'"),
+            CatalogError::TooLarge(message) => write!(f, "{message}"),
+            CatalogError::Corrupt(message) => write!(f, "corrupt catalog: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CatalogError::Tree(e) => Some(e),
+            CatalogError::Schema(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BTreeError> for CatalogError {
+    fn from(e: BTreeError) -> Self {
+        CatalogError::Tree(e)
+    }
+}
+
+impl From<SchemaError> for CatalogError {
+    fn from(e: SchemaError) -> Self {
+        CatalogError::Schema(e)
+    }
+}
+
+fn corrupt(message: impl Into<String>) -> CatalogError {
+    CatalogError::Corrupt(message.into())
+}
+
+// ============================================================================
+// Определения
+// ============================================================================
+
+/// Таблица в каталоге
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableEntry {
+    pub schema: TableSchema,
+    /// Корень дерева строк
+    pub root: PageId,
+    pub indexes: Vec<IndexEntry>,
+    /// Версия каталога, в которой таблица изменилась последний раз
+    pub version: u64,
+}
+
+/// Индекс таблицы
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    /// Номера столбцов таблицы по порядку ключа индекса
+    pub columns: Vec<usize>,
+    pub unique: bool,
+    pub root: PageId,
+}
+
+impl TableEntry {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.root.to_le_bytes());
+        put_str(&mut out, self.schema.name());
+        put_u16(&mut out, self.schema.columns().len());
+        for column in self.schema.columns() {
+            put_str(&mut out, &column.name);
+            let type_no = DataType::ALL.iter().position(|&t| t == column.data_type).unwrap();
+            out.push(type_no as u8);
+            out.push(u8::from(column.not_null));
+            match &column.default {
+                Some(value) => {
+                    out.push(1);
+                    let row = value_schema(column.data_type)
+                        .encode_row(std::slice::from_ref(value))
+                        .expect("defaults are coerced by TableSchema");
+                    put_bytes(&mut out, &row);
+                }
+                None => out.push(0),
+            }
+        }
+        put_positions(&mut out, self.schema.primary_key());
+        put_u16(&mut out, self.indexes.len());
+        for index in &self.indexes {
+            put_str(&mut out, &index.name);
+            out.push(u8::from(index.unique));
+            out.extend_from_slice(&index.root.to_le_bytes());
+            put_positions(&mut out, &index.columns);
+        }
+        out
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, CatalogError> {
+        let mut reader = Reader { bytes };
+        let version = u64::from_le_bytes(reader.array()?);
+        let root = PageId::from_le_bytes(reader.array()?);
+        let name = reader.str()?;
+        let mut columns = Vec::new();
+        for _ in 0..reader.u16()? {
+            let column_name = reader.str()?;
+            let type_no = usize::from(reader.u8()?);
+            let data_type = *DataType::ALL
+                .get(type_no)
+                .ok_or_else(|| corrupt(format!("column '{column_name}' has type {type_no}")))?;
+            let not_null = reader.u8()? != 0;
+            let default = match reader.u8()? {
+                0 => None,
+                _ => {
+                    let row = value_schema(data_type).decode_row(reader.bytes()?)?;
+                    row.into_iter().next()
+                }
+            };
+            columns.push(Column { name: column_name, data_type, not_null, default });
+        }
+        let key = reader.positions(columns.len())?;
+        let key: Vec<String> = key.iter().map(|&i| columns[i].name.clone()).collect();
+        let key: Vec<&str> = key.iter().map(String::as_str).collect();
+        let schema = TableSchema::new(name, columns, &key)?;
+        let mut indexes = Vec::new();
+        for _ in 0..reader.u16()? {
+            let name = reader.str()?;
+            let unique = reader.u8()? != 0;
+            let root = PageId::from_le_bytes(reader.array()?);
+            let columns = reader.positions(schema.columns().len())?;
+            indexes.push(IndexEntry { name, columns, unique, root });
+        }
+        if !reader.bytes.is_empty() {
+            let message =
+                format!("{} trailing bytes in table '{}'", reader.bytes.len(), schema.name());
+            return Err(corrupt(message));
+        }
+        Ok(Self { schema, root, indexes, version })
+    }
+}
+
+/// Длины и количества в определении хранятся в u16
+const MAX_FIELD: usize = u16::MAX as usize;
+
+/// Проверить, что `entry` кодируется без усечения длин и количеств
+fn check_sizes(entry: &TableEntry) -> Result<(), CatalogError> {
+    let table = entry.schema.name();
+    let limit = |what: String, n: usize| {
+        if n > MAX_FIELD {
+            let message = format!("table '{table}': {what} is {n}, at most {MAX_FIELD}");
+            return Err(CatalogError::TooLarge(message));
+        }
+        Ok(())
+    };
+    limit("number of columns".to_string(), entry.schema.columns().len())?;
+    for column in entry.schema.columns() {
+        limit(format!("name length of column '{}'", column.name), column.name.len())?;
+        if let Some(value) = &column.default {
+            let row = value_schema(column.data_type).encode_row(std::slice::from_ref(value))?;
+            limit(format!("DEFAULT size of column '{}'", column.name), row.len())?;
+        }
+    }
+    limit("number of indexes".to_string(), entry.indexes.len())
+}
+
+/// Схема из одного столбца: значение DEFAULT хранится как её строка
+fn value_schema(data_type: DataType) -> TableSchema {
+    TableSchema::new("default", vec![Column::new("value", data_type)], &[]).unwrap()
+}
+
+fn put_u16(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u16).to_le_bytes());
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_u16(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_bytes(out, s.as_bytes());
+}
+
+fn put_positions(out: &mut Vec<u8>, positions: &[usize]) {
+    put_u16(out, positions.len());
+    for &i in positions {
+        put_u16(out, i);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CatalogError> {
+        let (head, tail) = self
+            .bytes
+            .split_at_checked(len)
+            .ok_or_else(|| corrupt("table definition is truncated"))?;
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CatalogError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, CatalogError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize, CatalogError> {
+        Ok(usize::from(u16::from_le_bytes(self.array()?)))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], CatalogError> {
+        let len = self.u16()?;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Result<String, CatalogError> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupt("name is not UTF-8"))
+    }
+
+    /// Номера столбцов, меньшие `columns`
+    fn positions(&mut self, columns: usize) -> Result<Vec<usize>, CatalogError> {
+        let mut positions = Vec::new();
+        for _ in 0..self.u16()? {
+            let i = self.u16()?;
+            if i >= columns {
+                return Err(corrupt(format!("column {i} of {columns}")));
+            }
+            positions.push(i);
+        }
+        Ok(positions)
+    }
+}
+
+// ============================================================================
+// Каталог
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct State {
+    version: u64,
+    /// По имени в нижнем регистре
+    tables: BTreeMap<String, TableEntry>,
+}
+
+/// Системный каталог.
+///
+/// Чтение идёт параллельно, изменения — по одному.
+#[derive(Debug)]
+pub struct Catalog {
+    tree: BTree,
+    page_size: usize,
+    state: RwLock<State>,
+}
+
+impl Catalog {
+    /// Открыть каталог файла данных; в пустом файле он создаётся
+    pub fn open(pool: Arc<BufferPool>, wal: Arc<Mutex<WalWriter>>) -> Result<Self, CatalogError> {
+        let page_size = pool.disk().page_size();
+        if pool.disk().page_count() == 0 {
+            let tree = BTree::create(pool, wal)?;
+            if tree.root() != CATALOG_ROOT {
+                return Err(corrupt(format!("catalog root landed on page {}", tree.root())));
+            }
+            return Ok(Self { tree, page_size, state: RwLock::default() });
+        }
+        let tree = BTree::open(pool, wal, CATALOG_ROOT)?;
+        let state = load(&tree)?;
+        Ok(Self { tree, page_size, state: RwLock::new(state) })
+    }
+
+    /// Версия каталога: число изменений с его создания
+    pub fn version(&self) -> u64 {
+        self.state.read().unwrap().version
+    }
+
+    /// Таблица по имени (без учёта регистра ASCII)
+    pub fn table(&self, name: &str) -> Option<TableEntry> {
+        self.state.read().unwrap().tables.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    /// Все таблицы по именам
+    pub fn tables(&self) -> Vec<TableEntry> {
+        self.state.read().unwrap().tables.values().cloned().collect()
+    }
+
+    /// Корень каталога и корни всех таблиц и индексов: страницы, на которые
+    /// ссылается каталог (их не переносит `DiskManager::compact`)
+    pub fn roots(&self) -> Vec<PageId> {
+        let mut roots = vec![CATALOG_ROOT];
+        for table in self.state.read().unwrap().tables.values() {
+            roots.push(table.root);
+            roots.extend(table.indexes.iter().map(|index| index.root));
+        }
+        roots
+    }
+
+    /// Добавить таблицу со строками в дереве `root`; возвращает новую
+    /// версию каталога
+    pub fn create_table(&self, schema: TableSchema, root: PageId) -> Result<u64, CatalogError> {
+        check_name(schema.name())?;
+        let mut state = self.state.write().unwrap();
+        let name = schema.name().to_ascii_lowercase();
+        if state.tables.contains_key(&name) {
+            return Err(CatalogError::TableExists(schema.name().to_string()));
+        }
+        let entry = TableEntry { schema, root, indexes: Vec::new(), version: 0 };
+        check_sizes(&entry)?;
+        self.store(&mut state, &name, Some(entry))
+    }
+
+    /// Убрать таблицу вместе с её индексами; страницы их деревьев
+    /// освобождает вызывающий
+    pub fn drop_table(&self, name: &str) -> Result<TableEntry, CatalogError> {
+        let mut state = self.state.write().unwrap();
+        let key = name.to_ascii_lowercase();
+        let entry = state
+            .tables
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| CatalogError::NoSuchTable(name.to_string()))?;
+        self.store(&mut state, &key, None)?;
+        Ok(entry)
+    }
+
+    /// Добавить индекс по столбцам `columns` таблицы `table` с деревом
+    /// `root`; возвращает новую версию каталога
+    pub fn create_index(
+        &self,
+        table: &str,
+        name: &str,
+        columns: &[&str],
+        unique: bool,
+        root: PageId,
+    ) -> Result<u64, CatalogError> {
+        check_name(name)?;
+        let mut state = self.state.write().unwrap();
+        if find_index(&state, name).is_some() {
+            return Err(CatalogError::IndexExists(name.to_string()));
+        }
+        let key = table.to_ascii_lowercase();
+        let mut entry = state
+            .tables
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| CatalogError::NoSuchTable(table.to_string()))?;
+        if columns.is_empty() {
+            return Err(SchemaError::NoColumns { table: name.to_string() }.into());
+        }
+        let columns = entry.schema.column_indexes(columns)?;
+        entry.indexes.push(IndexEntry { name: name.to_string(), columns, unique, root });
+        check_sizes(&entry)?;
+        self.store(&mut state, &key, Some(entry))
+    }
+
+    /// Убрать индекс; страницы его дерева освобождает вызывающий
+    pub fn drop_index(&self, name: &str) -> Result<IndexEntry, CatalogError> {
+        let mut state = self.state.write().unwrap();
+        let (key, i) =
+            find_index(&state, name).ok_or_else(|| CatalogError::NoSuchIndex(name.to_string()))?;
+        let mut entry = state.tables[&key].clone();
+        let index = entry.indexes.remove(i);
+        self.store(&mut state, &key, Some(entry))?;
+        Ok(index)
+    }
+
+    /// Записать определение таблицы `key` (`None` — удалить) с новой
+    /// версией каталога одной транзакцией
+    fn store(
+        &self,
+        state: &mut State,
+        key: &str,
+        entry: Option<TableEntry>,
+    ) -> Result<u64, CatalogError> {
+        let version = state.version + 1;
+        let chunk_size = max_entry_size(self.page_size) - chunk_key(key, 0).len();
+        let old_chunks =
+            state.tables.get(key).map_or(0, |old| old.encode().len().div_ceil(chunk_size));
+        let entry = entry.map(|entry| TableEntry { version, ..entry });
+
+        let mut batch = Vec::new();
+        let mut new_chunks = 0;
+        if let Some(entry) = &entry {
+            let bytes = entry.encode();
+            // Номер части в ключе — u16
+            if bytes.len().div_ceil(chunk_size) > MAX_FIELD + 1 {
+                let message =
+                    format!("table '{}': definition is {} bytes", entry.schema.name(), bytes.len());
+                return Err(CatalogError::TooLarge(message));
+            }
+            for (n, chunk) in bytes.chunks(chunk_size).enumerate() {
+                batch.push((chunk_key(key, n), Some(chunk.to_vec())));
+                new_chunks = n + 1;
+            }
+        }
+        for n in new_chunks..old_chunks {
+            batch.push((chunk_key(key, n), None));
+        }
+        batch.push((VERSION_KEY.to_vec(), Some(version.to_le_bytes().to_vec())));
+        self.tree.apply(batch)?;
+
+        state.version = version;
+        match entry {
+            Some(entry) => state.tables.insert(key.to_string(), entry),
+            None => state.tables.remove(key),
+        };
+        Ok(version)
+    }
+
+    // ========================================================================
+    // Системные таблицы
+    // ========================================================================
+
+    /// Схема и строки системной таблицы из `SYSTEM_TABLES`
+    pub fn system_table(&self, name: &str) -> Option<(TableSchema, Vec<Vec<Value>>)> {
+        let state = self.state.read().unwrap();
+        let tables = state.tables.values();
+        let (columns, key, rows) = match name.to_ascii_lowercase().as_str() {
+            "datyre_tables" => tables_table(tables),
+            "datyre_columns" => columns_table(tables),
+            "datyre_indexes" => indexes_table(tables),
+            _ => return None,
+        };
+        let name = SYSTEM_TABLES.iter().find(|t| t.eq_ignore_ascii_case(name)).unwrap();
+        let schema = TableSchema::new(*name, columns, key).expect("system tables are valid");
+        Some((schema, rows))
+    }
+}
+
+/// Столбцы, первичный ключ и строки системной таблицы
+type SystemTable = (Vec<Column>, &'static [&'static str], Vec<Vec<Value>>);
+
+fn tables_table<'a>(tables: impl Iterator<Item = &'a TableEntry>) -> SystemTable {
+    let columns = vec![
+        required("table_name", DataType::Text),
+        required("root_page", DataType::BigInt),
+        required("column_count", DataType::Int),
+        required("index_count", DataType::Int),
+        required("version", DataType::BigInt),
+    ];
+    let rows = tables.map(|table| {
+        vec![
+            text(table.schema.name()),
+            page(table.root),
+            int(table.schema.columns().len()),
+            int(table.indexes.len()),
+            Value::Int64(table.version as i64),
+        ]
+    });
+    (columns, &["table_name"], rows.collect())
+}
+
+fn columns_table<'a>(tables: impl Iterator<Item = &'a TableEntry>) -> SystemTable {
+    let columns = vec![
+        required("table_name", DataType::Text),
+        required("column_name", DataType::Text),
+        required("position", DataType::Int),
+        required("data_type", DataType::Text),
+        required("not_null", DataType::Boolean),
+        Column::new("default_value", DataType::Text),
+        Column::new("primary_key", DataType::Int),
+    ];
+    let rows = tables.flat_map(|table| {
+        let schema = &table.schema;
+        schema.columns().iter().enumerate().map(move |(i, column)| {
+            let key = schema.primary_key().iter().position(|&k| k == i);
+            vec![
+                text(schema.name()),
+                text(&column.name),
+                int(i + 1),
+                text(column.data_type.name()),
+                Value::Bool(column.not_null),
+                column.default.as_ref().map_or(Value::Null, |value| text(&value.to_string())),
+                key.map_or(Value::Null, |k| int(k + 1)),
+            ]
+        })
+    });
+    (columns, &["table_name", "position"], rows.collect())
+}
+
+fn indexes_table<'a>(tables: impl Iterator<Item = &'a TableEntry>) -> SystemTable {
+    let columns = vec![
+        required("index_name", DataType::Text),
+        required("table_name", DataType::Text),
+        required("columns", DataType::Text),
+        required("is_unique", DataType::Boolean),
+        required("root_page", DataType::BigInt),
+    ];
+    let rows = tables.flat_map(|table| {
+        table.indexes.iter().map(move |index| {
+            let names: Vec<&str> =
+                index.columns.iter().map(|&i| table.schema.columns()[i].name.as_str()).collect();
+            vec![
+                text(&index.name),
+                text(table.schema.name()),
+                text(&names.join(", ")),
+                Value::Bool(index.unique),
+                page(index.root),
+            ]
+        })
+    });
+    (columns, &["index_name"], rows.collect())
+}
+
+fn text(s: &str) -> Value {
+    Value::Text(s.to_string())
+}
+
+fn int(n: usize) -> Value {
+    Value::Int32(n as i32)
+}
+
+fn page(page_id: PageId) -> Value {
+    Value::Int64(i64::from(page_id))
+}
+
+fn required(name: &str, data_type: DataType) -> Column {
+    Column { not_null: true, ..Column::new(name, data_type) }
+}
+
+fn check_name(name: &str) -> Result<(), CatalogError> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains('\0') {
+        return Err(CatalogError::InvalidName(name.to_string()));
+    }
+    let prefix = name.as_bytes().get(..RESERVED_PREFIX.len());
+    if prefix.is_some_and(|p| p.eq_ignore_ascii_case(RESERVED_PREFIX.as_bytes())) {
+        return Err(CatalogError::ReservedName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Таблица и номер индекса `name`
+fn find_index(state: &State, name: &str) -> Option<(String, usize)> {
+    state.tables.iter().find_map(|(key, table)| {
+        let i = table.indexes.iter().position(|index| index.name.eq_ignore_ascii_case(name))?;
+        Some((key.clone(), i))
+    })
+}
+
+fn chunk_key(table: &str, n: usize) -> Vec<u8> {
+    let mut key = vec![TABLE_PREFIX];
+    key.extend_from_slice(table.as_bytes());
+    key.push(0);
+    key.extend_from_slice(&(n as u16).to_be_bytes());
+    key
+}
+
+/// Прочитать каталог из дерева: части определения таблицы идут подряд
+fn load(tree: &BTree) -> Result<State, CatalogError> {
+    let mut state = State::default();
+    let mut parts: BTreeMap<String, (usize, Vec<u8>)> = BTreeMap::new();
+    for entry in tree.iter()? {
+        let (key, value) = entry?;
+        if key == VERSION_KEY {
+            let bytes = value.try_into().map_err(|_| corrupt("version is not 8 bytes"))?;
+            state.version = u64::from_le_bytes(bytes);
+            continue;
+        }
+        let parsed = match key.split_first() {
+            Some((&TABLE_PREFIX, rest)) if rest.len() > 3 && rest[rest.len() - 3] == 0 => {
+                let (name, n) = rest.split_at(rest.len() - 3);
+                let n = usize::from(u16::from_be_bytes([n[1], n[2]]));
+                String::from_utf8(name.to_vec()).ok().map(|name| (name, n))
+            }
+            _ => None,
+        };
+        let Some((name, n)) = parsed else {
+            return Err(corrupt(format!("unexpected key {key:02x?}")));
+        };
+        let (count, bytes) = parts.entry(name).or_default();
+        if n != *count {
+            return Err(corrupt(format!("part {n} follows {count} parts")));
+        }
+        *count += 1;
+        bytes.extend_from_slice(&value);
+    }
+    for (name, (_, bytes)) in parts {
+        let entry = TableEntry::decode(&bytes)?;
+        if entry.schema.name().to_ascii_lowercase() != name {
+            let message = format!("table '{}' is stored as '{name}'", entry.schema.name());
+            return Err(corrupt(message));
+        }
+        // Версия каталога не меньше версии любой его таблицы
+        if entry.version > state.version {
+            let message =
+                format!("table '{name}' has version {}, catalog {}", entry.version, state.version);
+            return Err(corrupt(message));
+        }
+        state.tables.insert(name, entry);
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::disk_manager::DiskManager;
+    use crate::page::PAGE_HEADER_SIZE;
+    use crate::recovery::recover;
+
+    fn config(name: &str) -> DatabaseConfig {
+        let dir =
+            std::env::temp_dir().join(format!("datyredb_catalog_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        DatabaseConfig {
+            data_path: dir,
+            page_size: 512,
+            buffer_pool_size: 16 * 512,
+            wal_segment_size: 64 * 1024,
+            ..DatabaseConfig::default()
+        }
+    }
+
+    fn open(config: &DatabaseConfig) -> (Arc<BufferPool>, Arc<Mutex<WalWriter>>) {
+        let pool = Arc::new(BufferPool::new(config).unwrap());
+        let wal = WalWriter::open(config.wal_dir(), config.wal_segment_size).unwrap();
+        (pool, Arc::new(Mutex::new(wal)))
+    }
+
+    fn users() -> TableSchema {
+        let columns = vec![
+            Column::new("id", DataType::BigInt),
+            Column { not_null: true, ..Column::new("name", DataType::Text) },
+            Column { default: Some(Value::from("it's")), ..Column::new("note", DataType::Text) },
+        ];
+        TableSchema::new("Users", columns, &["id"]).unwrap()
+    }
+
+    /// Таблица на несколько частей определения
+    fn wide() -> TableSchema {
+        let columns = (0..40)
+            .map(|i| Column {
+                default: Some(Value::Int32(i)),
+                ..Column::new(format!("column_{i}"), DataType::Int)
+            })
+            .collect();
+        TableSchema::new("wide", columns, &["column_3", "column_1"]).unwrap()
+    }
+
+    #[test]
+    fn changes_survive_a_crash_and_show_in_system_tables() {
+        let config = config("crash");
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool.clone(), wal.clone()).unwrap();
+        assert_eq!(catalog.version(), 0);
+        assert_eq!(catalog.create_table(users(), 7).unwrap(), 1);
+        assert_eq!(catalog.create_table(wide(), 8).unwrap(), 2);
+        catalog.create_index("wide", "wide_a", &["column_39", "column_0"], false, 9).unwrap();
+        catalog.create_index("USERS", "users_name", &["name"], true, 10).unwrap();
+        catalog
+            .create_table(
+                TableSchema::new("tmp", vec![wide().columns()[0].clone()], &[]).unwrap(),
+                11,
+            )
+            .unwrap();
+        assert_eq!(catalog.drop_index("WIDE_A").unwrap().columns, vec![39, 0]);
+        assert_eq!(catalog.drop_table("tmp").unwrap().root, 11);
+        let tables = catalog.tables();
+        assert_eq!(catalog.version(), 7);
+        wal.lock().unwrap().flush().unwrap();
+        // Сбой: каталог есть только в WAL
+        std::mem::forget(catalog);
+        std::mem::forget(pool);
+        std::mem::forget(wal);
+
+        recover(&config).unwrap();
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool, wal).unwrap();
+        assert_eq!(catalog.version(), 7);
+        assert_eq!(catalog.tables(), tables);
+        let users = catalog.table("users").unwrap();
+        assert_eq!((users.root, users.version), (7, 4));
+        assert_eq!(users.schema, self::users());
+        assert_eq!(catalog.table("WIDE").unwrap().schema, wide());
+        assert!(catalog.table("tmp").is_none());
+
+        let (schema, rows) = catalog.system_table("DATYRE_TABLES").unwrap();
+        assert_eq!(schema.name(), "datyre_tables");
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    "Users".into(),
+                    Value::Int64(7),
+                    Value::Int32(3),
+                    Value::Int32(1),
+                    4i64.into()
+                ],
+                vec![
+                    "wide".into(),
+                    Value::Int64(8),
+                    Value::Int32(40),
+                    Value::Int32(0),
+                    6i64.into()
+                ],
+            ]
+        );
+        let (schema, rows) = catalog.system_table("datyre_columns").unwrap();
+        assert_eq!(rows.len(), 43);
+        assert_eq!(
+            rows[2],
+            vec![
+                "Users".into(),
+                "note".into(),
+                Value::Int32(3),
+                "TEXT".into(),
+                false.into(),
+                "'it''s'".into(),
+                Value::Null
+            ]
+        );
+        assert_eq!(rows[4][6], Value::Int32(2));
+        for row in rows {
+            assert_eq!(schema.row(row.clone()).unwrap(), row);
+        }
+        let (_, rows) = catalog.system_table("datyre_indexes").unwrap();
+        assert_eq!(
+            rows,
+            vec![vec![
+                "users_name".into(),
+                "Users".into(),
+                "name".into(),
+                true.into(),
+                Value::Int64(10)
+            ]]
+        );
+        assert!(catalog.system_table("users").is_none());
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn invalid_changes_are_rejected() {
+        let config = config("invalid");
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool, wal).unwrap();
+        catalog.create_table(users(), 1).unwrap();
+        catalog.create_index("users", "by_name", &["name"], false, 2).unwrap();
+
+        let err = |result: Result<u64, CatalogError>| result.unwrap_err().to_string();
+        assert_eq!(err(catalog.create_table(users(), 3)), "table 'Users' already exists");
+        let system = TableSchema::new("Datyre_Tables", users().columns().to_vec(), &[]).unwrap();
+        assert_eq!(
+            err(catalog.create_table(system, 3)),
+            "name 'Datyre_Tables' is reserved for system tables"
+        );
+        let long = TableSchema::new("t".repeat(65), users().columns().to_vec(), &[]).unwrap();
+        assert!(matches!(catalog.create_table(long, 3), Err(CatalogError::InvalidName(_))));
+        // Имя не ASCII: префикс проверяется по байтам
+        let cyrillic = TableSchema::new("таблица", users().columns().to_vec(), &[]).unwrap();
+        assert_eq!(catalog.create_table(cyrillic, 3).unwrap(), 3);
+
+        let bio = Column {
+            default: Some(Value::Text("x".repeat(70_000))),
+            ..Column::new("bio", DataType::Text)
+        };
+        let huge = TableSchema::new("profiles", vec![bio], &[]).unwrap();
+        assert_eq!(
+            err(catalog.create_table(huge, 4)),
+            "table 'profiles': DEFAULT size of column 'bio' is 70004, at most 65535"
+        );
+        let column = Column::new("c".repeat(70_000), DataType::Int);
+        let wide = TableSchema::new("wide_names", vec![column], &[]).unwrap();
+        assert!(matches!(catalog.create_table(wide, 4), Err(CatalogError::TooLarge(_))));
+        assert_eq!(
+            err(catalog.create_index("users", "BY_NAME", &["id"], false, 3)),
+            "index 'BY_NAME' already exists"
+        );
+        assert_eq!(
+            err(catalog.create_index("users", "by_age", &["age"], false, 3)),
+            "unknown column 'age'"
+        );
+        assert_eq!(
+            err(catalog.create_index("orders", "by_id", &["id"], false, 3)),
+            "table 'orders' does not exist"
+        );
+        assert!(matches!(catalog.drop_table("orders"), Err(CatalogError::NoSuchTable(_))));
+        assert!(matches!(catalog.drop_index("by_id"), Err(CatalogError::NoSuchIndex(_))));
+        assert_eq!(catalog.version(), 3);
+        assert!(catalog.table("таблица").is_some());
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    /// Части определения таблицы `key` в дереве
+    fn chunks(catalog: &Catalog, key: &str) -> usize {
+        let prefix = chunk_key(key, 0);
+        let prefix = &prefix[..prefix.len() - 2];
+        catalog.tree.iter().unwrap().filter(|e| e.as_ref().unwrap().0.starts_with(prefix)).count()
+    }
+
+    #[test]
+    fn dropped_table_can_be_recreated() {
+        let config = config("recreate");
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool.clone(), wal.clone()).unwrap();
+        catalog.create_table(wide(), 7).unwrap();
+        catalog.create_index("wide", "wide_a", &["column_0"], false, 8).unwrap();
+        assert!(chunks(&catalog, "wide") > 1);
+        catalog.drop_table("wide").unwrap();
+        assert_eq!(chunks(&catalog, "wide"), 0);
+
+        // Та же таблица в другом регистре и с одним столбцом: от прежнего
+        // определения не остаётся ни частей, ни индексов
+        let narrow = TableSchema::new("WIDE", vec![Column::new("id", DataType::Int)], &[]).unwrap();
+        assert_eq!(catalog.create_table(narrow.clone(), 9).unwrap(), 4);
+        assert_eq!(chunks(&catalog, "wide"), 1);
+        assert!(matches!(catalog.drop_index("wide_a"), Err(CatalogError::NoSuchIndex(_))));
+        catalog.create_index("wide", "wide_a", &["id"], true, 10).unwrap();
+        drop(catalog);
+
+        let catalog = Catalog::open(pool, wal).unwrap();
+        let table = catalog.table("wide").unwrap();
+        assert_eq!((table.schema, table.root, table.version), (narrow, 9, 5));
+        assert_eq!(table.indexes[0].root, 10);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn definitions_split_at_exact_chunk_boundaries() {
+        let config = config("chunks");
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool.clone(), wal.clone()).unwrap();
+        let chunk_size = max_entry_size(config.page_size) - chunk_key("t0", 0).len();
+
+        // Длина определения подгоняется длиной имени последнего столбца
+        let schema = |name: &str, len: usize| {
+            let mut columns: Vec<_> =
+                (0..8).map(|i| Column::new(format!("c{i}"), DataType::BigInt)).collect();
+            columns.push(Column::new("x".repeat(len), DataType::Text));
+            TableSchema::new(name, columns, &["c0"]).unwrap()
+        };
+        let encoded = |schema: TableSchema| {
+            TableEntry { schema, root: 0, indexes: Vec::new(), version: 0 }.encode().len()
+        };
+        let base = encoded(schema("t0", 1)) - 1;
+        let sizes = [2 * chunk_size - 1, 2 * chunk_size, 2 * chunk_size + 1, 3 * chunk_size];
+        let mut tables = Vec::new();
+        for (i, &size) in sizes.iter().enumerate() {
+            let table = schema(&format!("t{i}"), size - base);
+            assert_eq!(encoded(table.clone()), size);
+            catalog.create_table(table.clone(), i as PageId + 1).unwrap();
+            assert_eq!(chunks(&catalog, &format!("t{i}")), size.div_ceil(chunk_size), "{size}");
+            tables.push(table);
+        }
+        // Определение уменьшается ровно до двух частей: третья удаляется
+        catalog.drop_table("t3").unwrap();
+        let shorter = schema("t3", 2 * chunk_size - base);
+        catalog.create_table(shorter.clone(), 4).unwrap();
+        assert_eq!(chunks(&catalog, "t3"), 2);
+        tables[3] = shorter;
+        drop(catalog);
+
+        let catalog = Catalog::open(pool, wal).unwrap();
+        let loaded: Vec<_> = catalog.tables().into_iter().map(|t| t.schema).collect();
+        assert_eq!(loaded, tables);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn version_mismatch_is_corrupt() {
+        let config = config("version");
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool.clone(), wal.clone()).unwrap();
+        catalog.create_table(users(), 7).unwrap();
+        catalog.create_table(wide(), 8).unwrap();
+
+        // Версия каталога ниже версии таблицы
+        catalog.tree.insert(VERSION_KEY, &1u64.to_le_bytes()).unwrap();
+        let err = Catalog::open(pool.clone(), wal.clone()).unwrap_err();
+        assert_eq!(err.to_string(), "corrupt catalog: table 'wide' has version 2, catalog 1");
+
+        catalog.tree.insert(VERSION_KEY, &[2, 0, 0, 0]).unwrap();
+        let err = Catalog::open(pool.clone(), wal.clone()).unwrap_err();
+        assert_eq!(err.to_string(), "corrupt catalog: version is not 8 bytes");
+
+        catalog.tree.remove(VERSION_KEY).unwrap();
+        assert!(matches!(Catalog::open(pool.clone(), wal.clone()), Err(CatalogError::Corrupt(_))));
+
+        catalog.tree.insert(VERSION_KEY, &2u64.to_le_bytes()).unwrap();
+        assert_eq!(Catalog::open(pool, wal).unwrap().version(), 2);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn compaction_keeps_catalog_roots() {
+        let config = config("compact");
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool.clone(), wal.clone()).unwrap();
+        let index = BTree::create(pool.clone(), wal).unwrap().root();
+        // Страницы 2..=6 без флагов; корень таблицы — страница 5
+        for marker in 2..=6u8 {
+            let page = pool.new_page().unwrap();
+            page.write()[PAGE_HEADER_SIZE] = marker;
+        }
+        catalog.create_table(users(), 5).unwrap();
+        catalog.create_index("users", "users_name", &["name"], true, index).unwrap();
+        assert_eq!(catalog.roots(), [CATALOG_ROOT, 5, index]);
+        pool.delete_page(2).unwrap();
+        pool.delete_page(3).unwrap();
+        pool.flush_pages(&pool.get_dirty_pages()).unwrap();
+        pool.disk().seal_freed();
+        pool.disk().release_freed().unwrap();
+        pool.sync_all().unwrap();
+        let roots = catalog.roots();
+        drop(catalog);
+        drop(pool);
+
+        // Страница 6 ни на что не названа и переезжает; корень таблицы и
+        // узлы деревьев остаются на месте
+        let disk = DiskManager::open(&config).unwrap();
+        let compaction = disk.compact(&roots).unwrap();
+        assert_eq!(compaction.moves, vec![(6, 2)]);
+        assert_eq!((compaction.pages_before, compaction.pages_after), (7, 6));
+        let mut page = vec![0u8; config.page_size];
+        for (page_id, marker) in [(2, 6), (4, 4), (5, 5)] {
+            disk.read_page(page_id, &mut page).unwrap();
+            assert_eq!(page[PAGE_HEADER_SIZE], marker, "page {page_id}");
+        }
+        drop(disk);
+
+        let (pool, wal) = open(&config);
+        let catalog = Catalog::open(pool, wal).unwrap();
+        assert_eq!(catalog.table("users").unwrap().root, 5);
+        assert_eq!(catalog.roots(), roots);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+}
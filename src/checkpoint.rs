@@ -0,0 +1,707 @@
+//! Checkpoint manager (аналог `storage::CheckpointManager`).
+//!
+//! Фоновый поток раз в секунду проверяет триггеры `CheckpointConfig` в том
+//! же порядке, что и C++: hard limit, `min_interval`, размер WAL, soft limit,
+//! таймер. Checkpoint пишет CHECKPOINT_BEGIN, снимает список грязных страниц,
+//! сбрасывает их батчами по `checkpoint_batch_size`, делает fsync, пишет
+//! CHECKPOINT_END и удаляет ненужные сегменты WAL, после чего отдаёт карте
+//! свободного места страницы, освобождённые до BEGIN. Страницы снимка могут
+//! измениться уже после BEGIN, поэтому перед каждым батчем WAL сбрасывается
+//! до наибольшего `page_lsn` в нём. При `async_checkpoint`
+//! батчи и fsync идут через io_uring (`page_io`), иначе — через `pwrite`.
+//!
+//! Пока идёт checkpoint по hard limit, `check_pressure()` задерживает новые
+//! транзакции.
+//!
+//! Те же правила можно проиграть офлайн на записанной нагрузке — см. [`sim`].
+
+pub mod sim;
+
+use crate::buffer_pool::{BufferPool, BufferPoolError};
+use crate::config::CheckpointConfig;
+use crate::page::Lsn;
+use crate::page_io::PageIo;
+use crate::wal::WalWriter;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Пауза между проверками триггеров фоновым потоком
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Причина checkpoint'а (`storage::CheckpointTrigger`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CheckpointTrigger {
+    /// Периодический по таймеру
+    Timer,
+    /// WAL превысил лимит
+    WalSize,
+    /// Мягкий лимит dirty pages (фоновый)
+    DirtySoftLimit,
+    /// Жёсткий лимит (блокирующий)
+    DirtyHardLimit,
+    /// Ручной вызов
+    Manual,
+    /// При остановке
+    Shutdown,
+}
+
+impl CheckpointTrigger {
+    pub const ALL: [CheckpointTrigger; 6] = [
+        CheckpointTrigger::Timer,
+        CheckpointTrigger::WalSize,
+        CheckpointTrigger::DirtySoftLimit,
+        CheckpointTrigger::DirtyHardLimit,
+        CheckpointTrigger::Manual,
+        CheckpointTrigger::Shutdown,
+    ];
+
+    /// Имя как в логах C++ (`checkpoint_trigger_name`)
+    pub fn name(self) -> &'static str {
+        match self {
+            CheckpointTrigger::Timer => "timer",
+            CheckpointTrigger::WalSize => "wal_size",
+            CheckpointTrigger::DirtySoftLimit => "soft_limit",
+            CheckpointTrigger::DirtyHardLimit => "HARD_LIMIT",
+            CheckpointTrigger::Manual => "manual",
+            CheckpointTrigger::Shutdown => "shutdown",
+        }
+    }
+
+    /// Вынужденный checkpoint: не по таймеру и не вручную
+    pub fn is_forced(self) -> bool {
+        !matches!(self, CheckpointTrigger::Timer | CheckpointTrigger::Manual)
+    }
+}
+
+impl fmt::Display for CheckpointTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Нужен ли checkpoint (`CheckpointManager::should_checkpoint`).
+///
+/// Отдельная функция без состояния, чтобы те же правила можно было
+/// применять к смоделированной нагрузке.
+pub fn should_checkpoint(
+    config: &CheckpointConfig,
+    dirty_pages: usize,
+    capacity: usize,
+    wal_size: u64,
+    since_last: Duration,
+) -> Option<CheckpointTrigger> {
+    let dirty_ratio = dirty_pages as f32 / capacity.max(1) as f32;
+
+    // 1. Hard limit — не ждёт min_interval
+    if dirty_ratio >= config.dirty_page_hard_limit_pct {
+        return Some(CheckpointTrigger::DirtyHardLimit);
+    }
+    // 2. Защита от checkpoint storm
+    if since_last < config.min_interval {
+        return None;
+    }
+    // 3. Размер WAL
+    if wal_size >= config.max_wal_size {
+        return Some(CheckpointTrigger::WalSize);
+    }
+    // 4. Soft limit
+    if dirty_ratio >= config.dirty_page_soft_limit_pct {
+        return Some(CheckpointTrigger::DirtySoftLimit);
+    }
+    // 5. Таймер
+    if since_last >= config.max_interval {
+        return Some(CheckpointTrigger::Timer);
+    }
+    None
+}
+
+// ============================================================================
+// Результат и ошибки
+// ============================================================================
+
+/// Завершённый checkpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointOutcome {
+    pub trigger: CheckpointTrigger,
+    pub begin_lsn: Lsn,
+    pub end_lsn: Lsn,
+    /// Грязных страниц в снимке
+    pub dirty_pages: usize,
+    pub pages_written: usize,
+    /// Байт WAL, освобождённых после END
+    pub wal_freed: u64,
+    /// Бэкенд записи страниц ("pwrite" / "io_uring")
+    pub io_backend: &'static str,
+    pub duration: Duration,
+}
+
+impl fmt::Display for CheckpointOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "checkpoint (trigger={}, pages={}/{}, io={}, duration={}ms, LSN {}..{})",
+            self.trigger,
+            self.pages_written,
+            self.dirty_pages,
+            self.io_backend,
+            self.duration.as_millis(),
+            self.begin_lsn,
+            self.end_lsn
+        )
+    }
+}
+
+/// Checkpoint не завершён: CHECKPOINT_END не записан
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// Ошибка записи WAL
+    Wal(io::Error),
+    /// Не удалось сбросить батч (остальные батчи всё равно пишутся)
+    Flush(BufferPoolError),
+    /// fsync файла данных
+    Sync(io::Error),
+    /// Менеджер остановлен посреди фонового checkpoint'а
+    Interrupted,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Wal(e) => write!(f, "checkpoint failed: WAL write: {e}"),
+            CheckpointError::Flush(e) => write!(f, "checkpoint failed: page flush: {e}"),
+            CheckpointError::Sync(e) => write!(f, "checkpoint failed: data file sync: {e}"),
+            CheckpointError::Interrupted => write!(f, "checkpoint interrupted by shutdown"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheckpointError::Wal(e) | CheckpointError::Sync(e) => Some(e),
+            CheckpointError::Flush(e) => Some(e),
+            CheckpointError::Interrupted => None,
+        }
+    }
+}
+
+/// Накопленные метрики (`storage::CheckpointMetrics`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckpointStats {
+    pub checkpoint_count: u64,
+    pub total_checkpoint_time_ms: u64,
+    pub forced_checkpoint_count: u64,
+    pub blocking_checkpoint_count: u64,
+    pub failed_checkpoint_count: u64,
+    pub pages_written_total: u64,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    checkpoint_count: AtomicU64,
+    total_checkpoint_time_ms: AtomicU64,
+    forced_checkpoint_count: AtomicU64,
+    blocking_checkpoint_count: AtomicU64,
+    failed_checkpoint_count: AtomicU64,
+    pages_written_total: AtomicU64,
+}
+
+// ============================================================================
+// Менеджер
+// ============================================================================
+
+struct Shared {
+    config: RwLock<CheckpointConfig>,
+    pool: Arc<BufferPool>,
+    wal: Arc<Mutex<WalWriter>>,
+    running: AtomicBool,
+    /// Идёт checkpoint по hard limit — новые транзакции ждут
+    blocking: Mutex<bool>,
+    /// Будит фоновый поток и ждущих в `check_pressure`
+    wakeup: Condvar,
+    /// Сериализует checkpoint'ы; хранит кольцо io_uring между ними
+    checkpoint_lock: Mutex<Option<PageIo>>,
+    last_checkpoint: Mutex<Instant>,
+    metrics: Metrics,
+}
+
+/// Фоновый checkpoint manager
+pub struct CheckpointManager {
+    shared: Arc<Shared>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl CheckpointManager {
+    pub fn new(config: CheckpointConfig, pool: Arc<BufferPool>, wal: Arc<Mutex<WalWriter>>) -> Self {
+        let shared = Shared {
+            config: RwLock::new(config),
+            pool,
+            wal,
+            running: AtomicBool::new(false),
+            blocking: Mutex::new(false),
+            wakeup: Condvar::new(),
+            checkpoint_lock: Mutex::new(None),
+            last_checkpoint: Mutex::new(Instant::now()),
+            metrics: Metrics::default(),
+        };
+        Self { shared: Arc::new(shared), thread: Mutex::new(None) }
+    }
+
+    /// Buffer pool, страницы которого сбрасывает менеджер
+    pub fn pool(&self) -> &Arc<BufferPool> {
+        &self.shared.pool
+    }
+
+    /// Общий writer WAL
+    pub fn wal(&self) -> &Arc<Mutex<WalWriter>> {
+        &self.shared.wal
+    }
+
+    /// Запуск фонового потока
+    pub fn start(&self) {
+        if self.shared.running.swap(true, Ordering::SeqCst) {
+            return; // Уже запущен
+        }
+        let shared = Arc::clone(&self.shared);
+        let handle = std::thread::Builder::new()
+            .name("datyre-checkpoint".into())
+            .spawn(move || shared.background_loop())
+            .expect("failed to spawn checkpoint thread");
+        *self.thread.lock().unwrap() = Some(handle);
+    }
+
+    /// Остановка с финальным checkpoint'ом; `None`, если менеджер не запущен
+    pub fn shutdown(&self) -> Option<Result<CheckpointOutcome, CheckpointError>> {
+        if !self.shared.running.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        self.shared.notify();
+        if let Some(handle) = self.thread.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+        Some(self.shared.checkpoint(CheckpointTrigger::Shutdown))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::Relaxed)
+    }
+
+    /// Ручной checkpoint (выполняется в вызывающем потоке)
+    pub fn manual_checkpoint(&self) -> Result<CheckpointOutcome, CheckpointError> {
+        self.shared.checkpoint(CheckpointTrigger::Manual)
+    }
+
+    /// Выполнить `f`, пока ни один checkpoint не идёт: WAL не заканчивается
+    /// CHECKPOINT_BEGIN без CHECKPOINT_END
+    pub fn between_checkpoints<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _ring = self.shared.checkpoint_lock.lock().unwrap();
+        f()
+    }
+
+    /// Проверка давления перед началом транзакции.
+    ///
+    /// Если грязных страниц не меньше hard limit, будит фоновый поток и ждёт
+    /// окончания блокирующего checkpoint'а. Возвращает `true`, если ждал.
+    pub fn check_pressure(&self) -> bool {
+        let shared = &self.shared;
+        if !shared.running.load(Ordering::SeqCst) {
+            return false;
+        }
+        let mut blocking = shared.blocking.lock().unwrap();
+        if !*blocking && !shared.over_hard_limit() {
+            return false;
+        }
+        shared.wakeup.notify_all();
+        while (*blocking || shared.over_hard_limit()) && shared.running.load(Ordering::SeqCst) {
+            blocking = shared.wakeup.wait_timeout(blocking, POLL_INTERVAL).unwrap().0;
+        }
+        true
+    }
+
+    /// Применить новые пороги на лету (все поля `checkpoint.*` — `Reload::Live`)
+    pub fn update_config(&self, config: CheckpointConfig) {
+        *self.shared.config.write().unwrap() = config;
+        self.shared.notify();
+    }
+
+    pub fn config(&self) -> CheckpointConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn stats(&self) -> CheckpointStats {
+        let m = &self.shared.metrics;
+        CheckpointStats {
+            checkpoint_count: m.checkpoint_count.load(Ordering::Relaxed),
+            total_checkpoint_time_ms: m.total_checkpoint_time_ms.load(Ordering::Relaxed),
+            forced_checkpoint_count: m.forced_checkpoint_count.load(Ordering::Relaxed),
+            blocking_checkpoint_count: m.blocking_checkpoint_count.load(Ordering::Relaxed),
+            failed_checkpoint_count: m.failed_checkpoint_count.load(Ordering::Relaxed),
+            pages_written_total: m.pages_written_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for CheckpointManager {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+impl fmt::Debug for CheckpointManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckpointManager")
+            .field("running", &self.is_running())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl Shared {
+    fn notify(&self) {
+        let _blocking = self.blocking.lock().unwrap();
+        self.wakeup.notify_all();
+    }
+
+    fn over_hard_limit(&self) -> bool {
+        let limit = self.config.read().unwrap().dirty_page_hard_limit_pct;
+        self.pool.dirty_page_count() as f32 / self.pool.capacity() as f32 >= limit
+    }
+
+    fn background_loop(&self) {
+        let mut failed = false;
+        while self.running.load(Ordering::SeqCst) {
+            // Hard limit будит поток сразу, но не после неудачной попытки:
+            // иначе ошибка записи превратится в busy loop
+            {
+                let blocking = self.blocking.lock().unwrap();
+                let _ = self
+                    .wakeup
+                    .wait_timeout_while(blocking, POLL_INTERVAL, |_| {
+                        self.running.load(Ordering::SeqCst) && (failed || !self.over_hard_limit())
+                    })
+                    .unwrap();
+            }
+            if !self.running.load(Ordering::SeqCst) {
+                break;
+            }
+            // Ошибка учтена в метриках; следующая попытка — по триггерам
+            failed = match self.should_checkpoint() {
+                Some(trigger) => self.checkpoint(trigger).is_err(),
+                None => false,
+            };
+        }
+    }
+
+    fn should_checkpoint(&self) -> Option<CheckpointTrigger> {
+        let since_last = self.last_checkpoint.lock().unwrap().elapsed();
+        let wal_size = self.wal.lock().unwrap().current_size();
+        should_checkpoint(
+            &self.config.read().unwrap(),
+            self.pool.dirty_page_count(),
+            self.pool.capacity(),
+            wal_size,
+            since_last,
+        )
+    }
+
+    fn checkpoint(&self, trigger: CheckpointTrigger) -> Result<CheckpointOutcome, CheckpointError> {
+        let mut ring = self.checkpoint_lock.lock().unwrap();
+        if trigger == CheckpointTrigger::DirtyHardLimit {
+            *self.blocking.lock().unwrap() = true;
+            self.metrics.blocking_checkpoint_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let result = self.do_checkpoint(trigger, &mut ring);
+        match &result {
+            Ok(outcome) => {
+                let m = &self.metrics;
+                m.checkpoint_count.fetch_add(1, Ordering::Relaxed);
+                let ms = outcome.duration.as_millis() as u64;
+                m.total_checkpoint_time_ms.fetch_add(ms, Ordering::Relaxed);
+                m.pages_written_total.fetch_add(outcome.pages_written as u64, Ordering::Relaxed);
+                if trigger.is_forced() {
+                    m.forced_checkpoint_count.fetch_add(1, Ordering::Relaxed);
+                }
+                *self.last_checkpoint.lock().unwrap() = Instant::now();
+            }
+            Err(_) => {
+                self.metrics.failed_checkpoint_count.fetch_add(1, Ordering::Relaxed);
+                // Кольцо после ошибки не переиспользуется: следующий
+                // checkpoint создаст новое или перейдёт на pwrite
+                *ring = None;
+            }
+        }
+
+        // Снимаем блокировку
+        *self.blocking.lock().unwrap() = false;
+        self.wakeup.notify_all();
+        result
+    }
+
+    fn do_checkpoint(
+        &self,
+        trigger: CheckpointTrigger,
+        ring: &mut Option<PageIo>,
+    ) -> Result<CheckpointOutcome, CheckpointError> {
+        let start = Instant::now();
+        let config = self.config.read().unwrap().clone();
+
+        // Флаг перечитывается каждый раз: `async_checkpoint` меняется на лету
+        let mut pwrite = PageIo::Pwrite;
+        let io = if config.async_checkpoint {
+            ring.get_or_insert_with(|| PageIo::for_checkpoint(true))
+        } else {
+            &mut pwrite
+        };
+
+        // Фаза 1: CHECKPOINT_BEGIN. Страницы, освобождённые до него, redo
+        // после этого checkpoint'а не трогает
+        self.pool.disk().seal_freed();
+        let begin_lsn = {
+            let mut wal = self.wal.lock().unwrap();
+            let lsn = wal.checkpoint_begin().map_err(CheckpointError::Wal)?;
+            wal.flush().map_err(CheckpointError::Wal)?;
+            lsn
+        };
+
+        // Фаза 2: снимок грязных страниц
+        let dirty_pages = self.pool.get_dirty_pages();
+
+        // Фаза 3: flush батчами
+        let mut pages_written = 0;
+        let mut first_error = None;
+        let background = !matches!(trigger, CheckpointTrigger::Manual | CheckpointTrigger::Shutdown);
+        for batch in dirty_pages.chunks(config.checkpoint_batch_size.max(1)) {
+            if background && !self.running.load(Ordering::SeqCst) {
+                return Err(CheckpointError::Interrupted);
+            }
+            // WAL-правило: батч копируется после BEGIN и может содержать
+            // изменения новее него
+            let wal_flush = |lsn| self.wal.lock().unwrap().flush_to(lsn);
+            match self.pool.flush_batch_with(batch, io, wal_flush) {
+                Ok(written) => pages_written += written,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+            if trigger == CheckpointTrigger::DirtySoftLimit {
+                std::thread::sleep(config.batch_throttle_us);
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(CheckpointError::Flush(e));
+        }
+
+        // Фаза 4: fsync
+        self.pool.sync_with(io).map_err(CheckpointError::Sync)?;
+
+        // Фазы 5-6: CHECKPOINT_END и удаление старых сегментов
+        let (end_lsn, wal_freed) = {
+            let mut wal = self.wal.lock().unwrap();
+            let end_lsn = wal.checkpoint_end(begin_lsn).map_err(CheckpointError::Wal)?;
+            (end_lsn, wal.truncate_before(begin_lsn).map_err(CheckpointError::Wal)?)
+        };
+        self.pool.disk().release_freed().map_err(CheckpointError::Sync)?;
+
+        Ok(CheckpointOutcome {
+            trigger,
+            begin_lsn,
+            end_lsn,
+            dirty_pages: dirty_pages.len(),
+            pages_written,
+            wal_freed,
+            io_backend: io.name(),
+            duration: start.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk_manager::DiskManager;
+    use crate::wal::{LogRecord, LogRecordType, WalReader};
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("datyredb_checkpoint_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn setup(dir: &std::path::Path, frames: usize) -> (Arc<BufferPool>, Arc<Mutex<WalWriter>>) {
+        let disk = DiskManager::open_path(dir.join("data.db"), 512).unwrap();
+        let pool = Arc::new(BufferPool::with_disk(disk, frames));
+        let wal = Arc::new(Mutex::new(WalWriter::create(dir.join("wal"), 1 << 20).unwrap()));
+        (pool, wal)
+    }
+
+    fn dirty(pool: &BufferPool, count: usize) {
+        for _ in 0..count {
+            pool.new_page().unwrap();
+        }
+    }
+
+    #[test]
+    fn trigger_order_matches_cpp() {
+        let config = CheckpointConfig {
+            min_interval: Duration::from_secs(5),
+            max_interval: Duration::from_secs(60),
+            max_wal_size: 1000,
+            dirty_page_soft_limit_pct: 0.5,
+            dirty_page_hard_limit_pct: 0.9,
+            ..CheckpointConfig::default()
+        };
+        let check = |dirty, wal, secs| {
+            should_checkpoint(&config, dirty, 100, wal, Duration::from_secs(secs))
+        };
+
+        assert_eq!(check(95, 0, 0), Some(CheckpointTrigger::DirtyHardLimit));
+        assert_eq!(check(60, 5000, 1), None);
+        assert_eq!(check(60, 5000, 10), Some(CheckpointTrigger::WalSize));
+        assert_eq!(check(60, 0, 10), Some(CheckpointTrigger::DirtySoftLimit));
+        assert_eq!(check(10, 0, 10), None);
+        assert_eq!(check(10, 0, 60), Some(CheckpointTrigger::Timer));
+    }
+
+    #[test]
+    fn manual_checkpoint_flushes_in_batches() {
+        for async_checkpoint in [false, true] {
+            let dir = temp_dir(if async_checkpoint { "manual_async" } else { "manual_sync" });
+            let (pool, wal) = setup(&dir, 16);
+            dirty(&pool, 11);
+            pool.delete_page(10).unwrap();
+            let config = CheckpointConfig {
+                checkpoint_batch_size: 3,
+                async_checkpoint,
+                ..CheckpointConfig::default()
+            };
+            let manager = CheckpointManager::new(config, Arc::clone(&pool), Arc::clone(&wal));
+
+            let outcome = manager.manual_checkpoint().unwrap();
+            assert_eq!((outcome.dirty_pages, outcome.pages_written), (10, 10));
+            assert_eq!(outcome.end_lsn, outcome.begin_lsn + 1);
+            assert_eq!(outcome.io_backend, PageIo::for_checkpoint(async_checkpoint).name());
+            assert_eq!(pool.dirty_page_count(), 0);
+            // Удалённая до BEGIN страница отдана карте свободного места
+            assert_eq!(pool.disk().pending_free_pages(), 0);
+            assert!(pool.disk().free_space().is_free(10));
+            assert_eq!(manager.stats().checkpoint_count, 1);
+            assert_eq!(manager.stats().forced_checkpoint_count, 0);
+
+            let scan = WalReader::open_dir(dir.join("wal")).unwrap().scan().unwrap();
+            assert!(scan.checkpoints()[0].is_complete());
+            drop(manager);
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn hard_limit_blocks_until_checkpoint() {
+        let dir = temp_dir("hard");
+        let (pool, wal) = setup(&dir, 10);
+        let config = CheckpointConfig {
+            min_interval: Duration::from_secs(3600),
+            max_interval: Duration::from_secs(3600),
+            dirty_page_hard_limit_pct: 0.5,
+            dirty_page_soft_limit_pct: 0.3,
+            ..CheckpointConfig::default()
+        };
+        let manager = CheckpointManager::new(config, Arc::clone(&pool), Arc::clone(&wal));
+        assert!(!manager.check_pressure());
+
+        manager.start();
+        dirty(&pool, 4);
+        assert!(!manager.check_pressure());
+
+        // Soft limit превышен, но min_interval не прошёл; hard limit — ждём
+        dirty(&pool, 2);
+        assert!(manager.check_pressure());
+        assert_eq!(pool.dirty_page_count(), 0);
+        let stats = manager.stats();
+        assert_eq!(stats.blocking_checkpoint_count, 1);
+        assert_eq!(stats.forced_checkpoint_count, 1);
+
+        let last = manager.shutdown().unwrap().unwrap();
+        assert_eq!(last.trigger, CheckpointTrigger::Shutdown);
+        assert!(manager.shutdown().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wal_size_trigger_truncates_segments() {
+        let dir = temp_dir("wal_size");
+        let disk = DiskManager::open_path(dir.join("data.db"), 512).unwrap();
+        let pool = Arc::new(BufferPool::with_disk(disk, 8));
+        let wal = Arc::new(Mutex::new(WalWriter::create(dir.join("wal"), 100).unwrap()));
+        {
+            let mut wal = wal.lock().unwrap();
+            for txn in 1..=6 {
+                wal.append(&LogRecord::new(LogRecordType::TxnBegin, txn)).unwrap();
+                wal.append(&LogRecord::new(LogRecordType::TxnCommit, txn)).unwrap();
+            }
+        }
+        let config = CheckpointConfig {
+            min_interval: Duration::ZERO,
+            max_wal_size: 300,
+            ..CheckpointConfig::default()
+        };
+        let manager = CheckpointManager::new(config, pool, Arc::clone(&wal));
+        let trigger = manager.shared.should_checkpoint();
+        assert_eq!(trigger, Some(CheckpointTrigger::WalSize));
+
+        let outcome = manager.shared.checkpoint(trigger.unwrap()).unwrap();
+        assert_eq!(outcome.wal_freed, 12 * 37);
+        // BEGIN и END с пустой таблицей транзакций
+        assert_eq!(wal.lock().unwrap().current_size(), 2 * 37 + 16);
+        assert_eq!(manager.shared.should_checkpoint(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pages_changed_after_begin_wait_for_their_wal() {
+        let dir = temp_dir("late_change");
+        let (pool, wal) = setup(&dir, 8);
+        dirty(&pool, 3);
+        // Батчи по одной странице с паузой: пока идёт пауза после первого
+        // батча, страница 1 из снимка меняется записью новее BEGIN
+        let config = CheckpointConfig {
+            checkpoint_batch_size: 1,
+            batch_throttle_us: Duration::from_millis(500),
+            ..CheckpointConfig::default()
+        };
+        let manager = Arc::new(CheckpointManager::new(config, Arc::clone(&pool), Arc::clone(&wal)));
+        manager.shared.running.store(true, Ordering::SeqCst);
+        let checkpoint = {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || {
+                manager.shared.checkpoint(CheckpointTrigger::DirtySoftLimit).map(|_| ())
+            })
+        };
+
+        std::thread::sleep(Duration::from_millis(250));
+        let lsn = wal.lock().unwrap().append(&LogRecord::new(LogRecordType::TxnBegin, 1)).unwrap();
+        {
+            let page = pool.fetch_page(1).unwrap();
+            let mut data = page.write();
+            let mut header = crate::page::PageHeader::decode(&data);
+            header.page_lsn = lsn;
+            header.encode(&mut data);
+        }
+        // Страница 1 записана вторым батчем; третий не начнётся
+        std::thread::sleep(Duration::from_millis(500));
+        manager.shared.running.store(false, Ordering::SeqCst);
+        assert!(matches!(checkpoint.join().unwrap(), Err(CheckpointError::Interrupted)));
+
+        let file = crate::page_file::PageFile::open_path(dir.join("data.db"), 512).unwrap();
+        assert_eq!(file.check_page(1).unwrap().header.unwrap().page_lsn, lsn);
+        assert!(wal.lock().unwrap().flushed_lsn() >= lsn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,509 @@
+//! Симулятор политики checkpoint'ов для планирования мощностей.
+//!
+//! Записанная нагрузка (трасса грязнящихся страниц и записей в WAL)
+//! проигрывается в виртуальном времени против модели buffer pool и WAL.
+//! Триггеры проверяются теми же правилами, что и в `CheckpointManager`
+//! (`should_checkpoint`), раз в `POLL_INTERVAL` и перед каждым событием,
+//! как `check_pressure`. Длительность checkpoint'а считается по модели
+//! диска [`IoModel`]. Случайности нет: одна и та же трасса с одним и тем же
+//! конфигом всегда даёт один и тот же отчёт.
+//!
+//! Формат трассы — по событию на строку, `#` начинает комментарий:
+//! ```text
+//! # время  событие  аргумент
+//! 0ms      dirty    17          # страница 17 стала грязной
+//! 150us    wal      4KiB        # в WAL дописано 4 KiB
+//! ```
+//! Время — от начала записи, в единицах `parse_duration`, по неубыванию.
+
+use super::{should_checkpoint, CheckpointTrigger, POLL_INTERVAL};
+use crate::config::units::{format_duration, parse_bytes, parse_duration};
+use crate::config::DatabaseConfig;
+use crate::page::PageId;
+use crate::wal::RECORD_HEADER_SIZE;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+// ============================================================================
+// Трасса
+// ============================================================================
+
+/// Событие нагрузки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Страница изменена (стала грязной)
+    Dirty(PageId),
+    /// В WAL дописано столько байт
+    Wal(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Время от начала записи
+    pub at: Duration,
+    pub event: TraceEvent,
+}
+
+/// Ошибка чтения трассы
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(e) => write!(f, "{e}"),
+            TraceError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TraceError::Io(e) => Some(e),
+            TraceError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Записанная нагрузка
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        Self::parse(&std::fs::read_to_string(path).map_err(TraceError::Io)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, TraceError> {
+        let mut entries: Vec<TraceEntry> = Vec::new();
+        for (i, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| TraceError::Parse { line: i + 1, message };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [at, kind, arg] = fields[..] else {
+                return Err(error(format!(
+                    "expected 'TIME dirty PAGE' or 'TIME wal SIZE', got '{line}'"
+                )));
+            };
+            let at = parse_duration(at).map_err(error)?;
+            let event = match kind {
+                "dirty" => TraceEvent::Dirty(
+                    arg.parse().map_err(|_| error(format!("invalid page id '{arg}'")))?,
+                ),
+                "wal" => TraceEvent::Wal(parse_bytes(arg).map_err(error)?),
+                other => {
+                    return Err(error(format!("unknown event '{other}' (expected dirty or wal)")))
+                }
+            };
+            if let Some(prev) = entries.last() {
+                if at < prev.at {
+                    return Err(error(format!(
+                        "time {} goes backwards (previous event at {})",
+                        format_duration(at),
+                        format_duration(prev.at)
+                    )));
+                }
+            }
+            entries.push(TraceEntry { at, event });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Время последнего события
+    pub fn duration(&self) -> Duration {
+        self.entries.last().map_or(Duration::ZERO, |e| e.at)
+    }
+}
+
+// ============================================================================
+// Модель
+// ============================================================================
+
+/// Модель диска: во сколько обходится сброс страниц
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoModel {
+    /// Устойчивая скорость записи (байт/с)
+    pub disk_write_throughput: u64,
+    /// fsync файла данных в конце checkpoint'а
+    pub fsync_latency: Duration,
+}
+
+impl Default for IoModel {
+    fn default() -> Self {
+        Self { disk_write_throughput: 200 * 1024 * 1024, fsync_latency: Duration::from_millis(2) }
+    }
+}
+
+impl IoModel {
+    /// Длительность checkpoint'а из `pages` страниц
+    pub fn checkpoint_duration(
+        &self,
+        config: &DatabaseConfig,
+        pages: usize,
+        trigger: CheckpointTrigger,
+    ) -> Duration {
+        let bytes = (pages as u64 * config.page_size as u64) as f64;
+        let write = Duration::from_secs_f64(bytes / self.disk_write_throughput.max(1) as f64);
+        // Пауза между батчами есть только при soft limit
+        let throttle = if trigger == CheckpointTrigger::DirtySoftLimit {
+            let batches = pages.div_ceil(config.checkpoint.checkpoint_batch_size.max(1));
+            config.checkpoint.batch_throttle_us * batches as u32
+        } else {
+            Duration::ZERO
+        };
+        write + throttle + self.fsync_latency
+    }
+}
+
+/// Итог прогона трассы
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationReport {
+    /// Сколько раз сработал каждый фоновый триггер
+    pub triggers: BTreeMap<CheckpointTrigger, u64>,
+    pub checkpoints: u64,
+    pub pages_written: u64,
+    /// Суммарная длительность checkpoint'ов
+    pub checkpoint_time: Duration,
+    /// Насколько нагрузка была задержана `check_pressure`
+    pub blocked_time: Duration,
+    pub peak_wal_size: u64,
+    pub peak_dirty_pages: usize,
+    pub final_wal_size: u64,
+    /// Виртуальное время до конца трассы (с задержками) и последнего checkpoint'а
+    pub simulated_time: Duration,
+}
+
+impl SimulationReport {
+    /// Срабатывания триггера
+    pub fn fired(&self, trigger: CheckpointTrigger) -> u64 {
+        self.triggers.get(&trigger).copied().unwrap_or(0)
+    }
+
+    /// Строки отчёта (метрика, значение) — для таблицы сравнения
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let mut rows: Vec<(&'static str, String)> = BACKGROUND_TRIGGERS
+            .iter()
+            .map(|&t| (t.name(), self.fired(t).to_string()))
+            .collect();
+        rows.extend([
+            ("checkpoints", self.checkpoints.to_string()),
+            ("pages written", self.pages_written.to_string()),
+            ("checkpoint time", format_millis(self.checkpoint_time)),
+            ("blocked time", format_millis(self.blocked_time)),
+            ("peak WAL size", format_mib(self.peak_wal_size)),
+            ("final WAL size", format_mib(self.final_wal_size)),
+            ("peak dirty pages", self.peak_dirty_pages.to_string()),
+            ("simulated time", format_millis(self.simulated_time)),
+        ]);
+        rows
+    }
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.rows() {
+            writeln!(f, "{name:<18} {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Триггеры, которые срабатывают сами по себе (без ручного вызова и остановки)
+pub const BACKGROUND_TRIGGERS: [CheckpointTrigger; 4] = [
+    CheckpointTrigger::Timer,
+    CheckpointTrigger::WalSize,
+    CheckpointTrigger::DirtySoftLimit,
+    CheckpointTrigger::DirtyHardLimit,
+];
+
+fn format_millis(d: Duration) -> String {
+    format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+}
+
+fn format_mib(bytes: u64) -> String {
+    format!("{:.1}MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+// ============================================================================
+// Прогон
+// ============================================================================
+
+/// Идущий checkpoint
+struct Running {
+    trigger: CheckpointTrigger,
+    end: Duration,
+    snapshot: Vec<PageId>,
+    /// Смещение CHECKPOINT_BEGIN от начала WAL
+    begin_offset: u64,
+}
+
+struct Simulation<'a> {
+    config: &'a DatabaseConfig,
+    io: &'a IoModel,
+    capacity: usize,
+    dirty: HashSet<PageId>,
+    /// Страницы снимка, снова изменённые во время checkpoint'а
+    redirtied: HashSet<PageId>,
+    /// Всего байт записано в WAL / удалено из его начала
+    wal_end: u64,
+    wal_start: u64,
+    running: Option<Running>,
+    last_checkpoint: Duration,
+    next_poll: Duration,
+    report: SimulationReport,
+}
+
+/// Проиграть трассу против `config`
+pub fn simulate(config: &DatabaseConfig, io: &IoModel, trace: &Trace) -> SimulationReport {
+    let mut sim = Simulation {
+        config,
+        io,
+        capacity: config.buffer_pool_pages().max(1),
+        dirty: HashSet::new(),
+        redirtied: HashSet::new(),
+        wal_end: 0,
+        wal_start: 0,
+        running: None,
+        last_checkpoint: Duration::ZERO,
+        next_poll: POLL_INTERVAL,
+        report: SimulationReport {
+            triggers: BACKGROUND_TRIGGERS.iter().map(|&t| (t, 0)).collect(),
+            checkpoints: 0,
+            pages_written: 0,
+            checkpoint_time: Duration::ZERO,
+            blocked_time: Duration::ZERO,
+            peak_wal_size: 0,
+            peak_dirty_pages: 0,
+            final_wal_size: 0,
+            simulated_time: Duration::ZERO,
+        },
+    };
+
+    let mut lag = Duration::ZERO;
+    for entry in &trace.entries {
+        let mut now = entry.at + lag;
+        sim.advance(now);
+
+        // check_pressure: ждём конца блокирующего checkpoint'а, пока грязных
+        // страниц не меньше hard limit
+        loop {
+            let blocking =
+                sim.running.as_ref().is_some_and(|r| r.trigger == CheckpointTrigger::DirtyHardLimit);
+            if !blocking && !sim.over_hard_limit() {
+                break;
+            }
+            if sim.running.is_none() {
+                sim.start(CheckpointTrigger::DirtyHardLimit, now);
+            }
+            let end = sim.running.as_ref().map_or(now, |r| r.end);
+            lag += end - now;
+            now = end;
+            sim.advance(now);
+        }
+
+        match entry.event {
+            TraceEvent::Dirty(page_id) => {
+                sim.dirty.insert(page_id);
+                if sim.running.is_some() {
+                    sim.redirtied.insert(page_id);
+                }
+                sim.report.peak_dirty_pages = sim.report.peak_dirty_pages.max(sim.dirty.len());
+            }
+            TraceEvent::Wal(bytes) => sim.append_wal(bytes),
+        }
+        sim.report.simulated_time = now;
+    }
+
+    if let Some(end) = sim.running.as_ref().map(|r| r.end) {
+        sim.finish(end);
+        sim.report.simulated_time = end;
+    }
+    sim.report.blocked_time = lag;
+    sim.report.final_wal_size = sim.wal_end - sim.wal_start;
+    sim.report
+}
+
+impl Simulation<'_> {
+    fn over_hard_limit(&self) -> bool {
+        let ratio = self.dirty.len() as f32 / self.capacity as f32;
+        ratio >= self.config.checkpoint.dirty_page_hard_limit_pct
+    }
+
+    /// Все события фонового потока до момента `t` включительно: окончания
+    /// checkpoint'ов и проверки триггеров по таймеру опроса
+    fn advance(&mut self, t: Duration) {
+        loop {
+            let end = self.running.as_ref().map(|r| r.end);
+            match end {
+                Some(end) if end <= t && end <= self.next_poll => self.finish(end),
+                _ if self.next_poll <= t => {
+                    let poll = self.next_poll;
+                    self.next_poll += POLL_INTERVAL;
+                    if self.running.is_none() {
+                        let trigger = should_checkpoint(
+                            &self.config.checkpoint,
+                            self.dirty.len(),
+                            self.capacity,
+                            self.wal_end - self.wal_start,
+                            poll - self.last_checkpoint,
+                        );
+                        if let Some(trigger) = trigger {
+                            self.start(trigger, poll);
+                        }
+                    }
+                }
+                Some(end) if end <= t => self.finish(end),
+                _ => return,
+            }
+        }
+    }
+
+    fn start(&mut self, trigger: CheckpointTrigger, at: Duration) {
+        self.append_wal(RECORD_HEADER_SIZE as u64); // CHECKPOINT_BEGIN
+        let snapshot: Vec<PageId> = self.dirty.iter().copied().collect();
+        let duration = self.io.checkpoint_duration(self.config, snapshot.len(), trigger);
+        *self.report.triggers.entry(trigger).or_insert(0) += 1;
+        self.redirtied.clear();
+        self.running = Some(Running {
+            trigger,
+            end: at + duration,
+            snapshot,
+            begin_offset: self.wal_end - RECORD_HEADER_SIZE as u64,
+        });
+    }
+
+    fn finish(&mut self, at: Duration) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        for page_id in &running.snapshot {
+            if !self.redirtied.contains(page_id) {
+                self.dirty.remove(page_id);
+            }
+        }
+        self.redirtied.clear();
+        self.append_wal(RECORD_HEADER_SIZE as u64); // CHECKPOINT_END
+
+        // truncate_before(begin): удаляются только целые сегменты до BEGIN
+        let segment = self.config.wal_segment_size.max(1);
+        self.wal_start = self.wal_start.max(running.begin_offset / segment * segment);
+
+        let pages = running.snapshot.len();
+        let duration = self.io.checkpoint_duration(self.config, pages, running.trigger);
+        self.report.checkpoints += 1;
+        self.report.pages_written += running.snapshot.len() as u64;
+        self.report.checkpoint_time += duration;
+        self.last_checkpoint = at;
+    }
+
+    fn append_wal(&mut self, bytes: u64) {
+        self.wal_end += bytes;
+        self.report.peak_wal_size = self.report.peak_wal_size.max(self.wal_end - self.wal_start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CheckpointConfig;
+
+    fn config(checkpoint: CheckpointConfig) -> DatabaseConfig {
+        DatabaseConfig {
+            buffer_pool_size: 100 * 4096,
+            page_size: 4096,
+            wal_segment_size: 1024 * 1024,
+            checkpoint,
+            ..DatabaseConfig::default()
+        }
+    }
+
+    /// `pages` разных страниц каждые `step`, `events` раз, с 8 KiB WAL на событие
+    fn steady(pages: u32, step: Duration, events: u32) -> Trace {
+        let mut text = String::new();
+        for i in 0..events {
+            let at = format_duration(step * i);
+            text.push_str(&format!("{at} dirty {}\n{at} wal 8KiB\n", i % pages));
+        }
+        Trace::parse(&text).unwrap()
+    }
+
+    #[test]
+    fn parses_trace() {
+        let text = "# header\n0ms dirty 3\n\n1500us wal 4KiB # append\n2s dirty 4\n";
+        let trace = Trace::parse(text).unwrap();
+        assert_eq!(trace.entries.len(), 3);
+        assert_eq!(
+            trace.entries[1],
+            TraceEntry { at: Duration::from_micros(1500), event: TraceEvent::Wal(4096) }
+        );
+        assert_eq!(trace.duration(), Duration::from_secs(2));
+
+        let err = Trace::parse("1s dirty 1\n500ms dirty 2\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2: time 500ms goes backwards"), "{err}");
+        assert!(Trace::parse("1s flush 1\n").is_err());
+        assert!(Trace::parse("1s dirty\n").is_err());
+    }
+
+    #[test]
+    fn timer_fires_on_quiet_workload() {
+        let cp =
+            CheckpointConfig { max_interval: Duration::from_secs(10), ..CheckpointConfig::default() };
+        let trace = steady(5, Duration::from_secs(1), 35);
+        let report = simulate(&config(cp), &IoModel::default(), &trace);
+        assert_eq!(report.fired(CheckpointTrigger::Timer), 3);
+        assert_eq!(report.checkpoints, 3);
+        assert_eq!(report.blocked_time, Duration::ZERO);
+        assert_eq!(report.peak_dirty_pages, 5);
+    }
+
+    #[test]
+    fn hard_limit_blocks_workload() {
+        let cp = CheckpointConfig {
+            min_interval: Duration::from_secs(60),
+            max_interval: Duration::from_secs(600),
+            dirty_page_soft_limit_pct: 0.5,
+            dirty_page_hard_limit_pct: 0.8,
+            ..CheckpointConfig::default()
+        };
+        // Медленный диск: 80 страниц пишутся 312.5 мс
+        let io = IoModel { disk_write_throughput: 1024 * 1024, fsync_latency: Duration::ZERO };
+        let report = simulate(&config(cp), &io, &steady(1000, Duration::from_millis(1), 300));
+
+        // Soft limit ждёт min_interval, hard limit — нет
+        assert_eq!(report.fired(CheckpointTrigger::DirtySoftLimit), 0);
+        assert_eq!(report.fired(CheckpointTrigger::DirtyHardLimit), 3);
+        assert_eq!(report.pages_written, 240);
+        assert_eq!(report.blocked_time, Duration::from_micros(3 * 312_500));
+        assert_eq!(report.peak_dirty_pages, 80);
+        assert!(report.simulated_time >= Duration::from_micros(299_000 + 937_500));
+    }
+
+    #[test]
+    fn wal_size_trigger_and_determinism() {
+        let cp = CheckpointConfig {
+            min_interval: Duration::ZERO,
+            max_wal_size: 2 * 1024 * 1024,
+            ..CheckpointConfig::default()
+        };
+        let config = config(cp);
+        // 8 KiB каждые 10 мс: 2 MiB за 2.56 с
+        let trace = steady(10, Duration::from_millis(10), 1000);
+        let report = simulate(&config, &IoModel::default(), &trace);
+        assert!(report.fired(CheckpointTrigger::WalSize) >= 3, "{report}");
+        assert!(report.peak_wal_size < 3 * 1024 * 1024, "{report}");
+        assert!(report.peak_wal_size >= 2 * 1024 * 1024);
+        assert_eq!(simulate(&config, &IoModel::default(), &trace), report);
+    }
+}
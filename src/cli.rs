@@ -0,0 +1,223 @@
+//! Общий разбор командной строки для утилит из `src/bin`.
+//!
+//! Каждая утилита получает `DatabaseConfig` одинаково: `--config FILE`
+//! (по умолчанию `./datyredb.toml`, если он есть), `--preset`, `--data-dir`
+//! и повторяемый `--set key=value` поверх `DATYRE_*` переменных.
+
+use crate::config::{ConfigLoader, DatabaseConfig, Preset};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Файл конфигурации, который подхватывается без `--config`
+pub const DEFAULT_CONFIG_FILE: &str = "datyredb.toml";
+
+/// Справка по общим опциям конфигурации
+pub const CONFIG_HELP: &str = "\
+Configuration:
+  -c, --config FILE      datyredb.toml to load (default: ./datyredb.toml if present)
+      --preset NAME      base preset (oltp_small, bulk_load, low_latency, embedded)
+  -D, --data-dir DIR     override data_path
+      --set KEY=VALUE    override any config key (repeatable)";
+
+/// Ошибка разбора аргументов
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError(pub String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Один аргумент командной строки
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    /// `-x`, `--name` (значение `--name=value` доступно через `Args::value`)
+    Opt(String),
+    /// Позиционный аргумент
+    Pos(String),
+}
+
+/// Поток аргументов
+#[derive(Debug)]
+pub struct Args {
+    items: std::vec::IntoIter<String>,
+    inline: Option<String>,
+    positional_only: bool,
+}
+
+impl Args {
+    pub fn new<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let items: Vec<String> = args.into_iter().map(Into::into).collect();
+        Self { items: items.into_iter(), inline: None, positional_only: false }
+    }
+
+    /// Аргументы процесса без имени программы
+    pub fn from_env() -> Self {
+        Self::new(std::env::args().skip(1))
+    }
+
+    /// Следующий аргумент; неиспользованное `=value` — ошибка
+    pub fn next_arg(&mut self) -> Result<Option<Arg>, CliError> {
+        if let Some(value) = self.inline.take() {
+            return Err(CliError(format!("unexpected value '{value}'")));
+        }
+        let Some(item) = self.items.next() else {
+            return Ok(None);
+        };
+        if self.positional_only || item == "-" || !item.starts_with('-') {
+            return Ok(Some(Arg::Pos(item)));
+        }
+        if item == "--" {
+            self.positional_only = true;
+            return self.next_arg();
+        }
+        match item.split_once('=') {
+            Some((opt, value)) if opt.starts_with("--") => {
+                self.inline = Some(value.to_string());
+                Ok(Some(Arg::Opt(opt.to_string())))
+            }
+            _ => Ok(Some(Arg::Opt(item))),
+        }
+    }
+
+    /// Значение опции `opt`
+    pub fn value(&mut self, opt: &str) -> Result<String, CliError> {
+        self.inline
+            .take()
+            .or_else(|| self.items.next())
+            .ok_or_else(|| CliError(format!("{opt} requires a value")))
+    }
+
+    /// Значение опции с разбором через `FromStr`
+    pub fn parse<T>(&mut self, opt: &str) -> Result<T, CliError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.value(opt)?;
+        value
+            .parse()
+            .map_err(|e| CliError(format!("invalid value '{value}' for {opt}: {e}")))
+    }
+}
+
+/// Общие опции конфигурации
+#[derive(Debug, Clone, Default)]
+pub struct ConfigArgs {
+    pub file: Option<PathBuf>,
+    pub preset: Option<Preset>,
+    pub data_dir: Option<PathBuf>,
+    pub overrides: Vec<String>,
+}
+
+impl ConfigArgs {
+    /// Разобрать `opt`, если это опция конфигурации; `Ok(false)` — не наша
+    pub fn accept(&mut self, opt: &str, args: &mut Args) -> Result<bool, CliError> {
+        match opt {
+            "-c" | "--config" => self.file = Some(args.value(opt)?.into()),
+            "--preset" => self.preset = Some(args.parse(opt)?),
+            "-D" | "--data-dir" => self.data_dir = Some(args.value(opt)?.into()),
+            "--set" => self.overrides.push(args.value(opt)?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Загрузчик со всеми слоями
+    pub fn loader(&self) -> ConfigLoader {
+        let mut loader = ConfigLoader::new();
+        if let Some(preset) = self.preset {
+            loader = loader.preset(preset);
+        }
+        match &self.file {
+            Some(file) => loader = loader.file(file),
+            None if std::path::Path::new(DEFAULT_CONFIG_FILE).is_file() => {
+                loader = loader.file(DEFAULT_CONFIG_FILE)
+            }
+            None => {}
+        }
+        if let Some(dir) = &self.data_dir {
+            loader = loader.override_arg(format!("data_path={}", dir.display()));
+        }
+        loader.override_args(self.overrides.iter().cloned())
+    }
+
+    pub fn load(&self) -> Result<DatabaseConfig, CliError> {
+        self.loader()
+            .load()
+            .map(|loaded| loaded.config)
+            .map_err(|e| CliError(e.to_string()))
+    }
+}
+
+/// Строка в JSON-литерал
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_options_and_config_args() {
+        let mut args = Args::new([
+            "--start=5",
+            "-D",
+            "/srv/db",
+            "--set",
+            "wal_segment_size=16MiB",
+            "dir",
+            "--",
+            "-x",
+        ]);
+        let mut config = ConfigArgs::default();
+        let mut start = None;
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next_arg().unwrap() {
+            match arg {
+                Arg::Opt(opt) if config.accept(&opt, &mut args).unwrap() => {}
+                Arg::Opt(opt) if opt == "--start" => start = Some(args.parse::<u64>(&opt).unwrap()),
+                Arg::Opt(opt) => panic!("unexpected {opt}"),
+                Arg::Pos(p) => positional.push(p),
+            }
+        }
+        assert_eq!(start, Some(5));
+        assert_eq!(positional, ["dir", "-x"]);
+
+        let cfg = config.loader().env_vars(Vec::<(String, String)>::new()).load().unwrap().config;
+        assert_eq!(cfg.data_path, PathBuf::from("/srv/db"));
+        assert_eq!(cfg.wal_segment_size, 16 << 20);
+
+        let mut bad = Args::new(["--help=yes"]);
+        assert!(matches!(bad.next_arg(), Ok(Some(Arg::Opt(_)))));
+        assert!(bad.next_arg().is_err());
+    }
+
+    #[test]
+    fn escapes_json() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
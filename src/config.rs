@@ -1,6 +1,28 @@
+mod fields;
+pub mod interchange;
+pub mod loader;
+pub mod presets;
+pub mod reload;
+pub mod toml;
+pub mod tuner;
+pub mod units;
+pub mod validate;
+
+pub use loader::{ConfigError, ConfigLoader, ConfigSource, LoadedConfig};
+pub use presets::Preset;
+pub use reload::{ConfigHandle, ConfigUpdate, ReloadError};
+pub use tuner::{tune, TuningInputs, TuningReport};
+pub use validate::{ConfigViolation, ValidationErrors};
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+/// Размер сегмента WAL по умолчанию (как `StorageConfig::wal_segment_size` в C++)
+pub const DEFAULT_WAL_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CheckpointConfig {
     /// Максимальный интервал между checkpoint'ами (fuzzy checkpoint)
     pub max_interval: Duration,
@@ -23,6 +45,9 @@ pub struct CheckpointConfig {
     /// Размер буфера для checkpoint write batching (страниц за раз)
     pub checkpoint_batch_size: usize,
     
+    /// Пауза между батчами при soft limit (в C++ — микросекунды)
+    pub batch_throttle_us: Duration,
+    
     /// Использовать ли io_uring для асинхронного checkpoint I/O
     pub async_checkpoint: bool,
 }
@@ -36,15 +61,120 @@ impl Default for CheckpointConfig {
             dirty_page_soft_limit_pct: 0.70,        // 70% buffer pool
             dirty_page_hard_limit_pct: 0.90,        // 90% buffer pool (паника!)
             checkpoint_batch_size: 256,              // 256 страниц = 1MB при 4KB pages
+            batch_throttle_us: Duration::from_micros(100),
             async_checkpoint: true,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Как WAL сбрасывается на диск при commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalSyncMode {
+    /// `fsync`: данные и метаданные файла
+    Fsync,
+    /// `fdatasync`: только данные (как `WriteAheadLog::force` в C++)
+    #[default]
+    Fdatasync,
+    /// Без сброса — только для тестов, commit не переживает сбой ОС
+    None,
+}
+
+impl WalSyncMode {
+    pub const ALL: [WalSyncMode; 3] =
+        [WalSyncMode::Fsync, WalSyncMode::Fdatasync, WalSyncMode::None];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WalSyncMode::Fsync => "fsync",
+            WalSyncMode::Fdatasync => "fdatasync",
+            WalSyncMode::None => "none",
+        }
+    }
+}
+
+impl fmt::Display for WalSyncMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WalSyncMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        WalSyncMode::ALL.into_iter().find(|m| m.name() == name).ok_or_else(|| {
+            format!("unknown WAL sync mode '{s}' (expected fsync, fdatasync or none)")
+        })
+    }
+}
+
+/// Конфигурация всего storage layer (зеркало `storage::StorageConfig` в C++)
+#[derive(Debug, Clone, PartialEq)]
 pub struct DatabaseConfig {
+    /// Каталог данных: `data.db` и `wal/`
+    pub data_path: PathBuf,
+    
+    /// Размер buffer pool в байтах (в C++ — `buffer_pool_pages` страниц)
     pub buffer_pool_size: usize,
+    
+    /// Размер страницы (в C++ — константа `PAGE_SIZE`)
     pub page_size: usize,
+    
+    /// Размер сегмента WAL (байты)
+    pub wal_segment_size: u64,
+    
+    /// Сколько лидер группы commit'ов ждёт остальных перед сбросом WAL
+    /// (0 — не ждёт: группа собирается, пока идёт предыдущий сброс)
+    pub wal_commit_delay: Duration,
+    
+    /// Сколько commit'ов в очереди прерывают ожидание `wal_commit_delay`
+    pub wal_max_group_size: usize,
+    
+    /// Способ сброса WAL на диск
+    pub wal_sync_mode: WalSyncMode,
+    
+    /// Архив завершённых сегментов WAL (копируются перед удалением);
+    /// `None` — сегменты после checkpoint'а просто удаляются, как в C++
+    pub wal_archive_dir: Option<PathBuf>,
+    
     pub checkpoint: CheckpointConfig,
-    // ... остальные поля
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            data_path: PathBuf::from("./data"),
+            buffer_pool_size: 10_000 * 4096,  // ~40 MB, как buffer_pool_pages в C++
+            page_size: 4096,                  // storage::PAGE_SIZE
+            wal_segment_size: DEFAULT_WAL_SEGMENT_SIZE,
+            wal_commit_delay: Duration::ZERO,
+            wal_max_group_size: 64,
+            wal_sync_mode: WalSyncMode::Fdatasync,
+            wal_archive_dir: None,
+            checkpoint: CheckpointConfig::default(),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Файл данных (`DiskManager`: `data_path / "data.db"`)
+    pub fn data_file(&self) -> PathBuf {
+        self.data_path.join("data.db")
+    }
+    
+    /// Каталог сегментов WAL (`StorageEngine`: `data_path / "wal"`)
+    pub fn wal_dir(&self) -> PathBuf {
+        self.data_path.join("wal")
+    }
+    
+    /// Количество фреймов buffer pool
+    pub fn buffer_pool_pages(&self) -> usize {
+        self.buffer_pool_size / self.page_size.max(1)
+    }
+}
+
+/// Все ключи конфигурации в порядке объявления ("checkpoint.max_interval", ...)
+pub fn config_keys() -> impl Iterator<Item = &'static str> {
+    fields::FIELDS.iter().map(|f| f.key)
 }
@@ -0,0 +1,281 @@
+//! Реестр полей `DatabaseConfig`: единое место, где ключ конфигурации
+//! связывается с полем структуры, разбором значения и его отображением.
+
+use super::toml::TomlValue;
+use super::units::{format_bytes, format_duration, parse_bytes, parse_duration};
+use super::DatabaseConfig;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Можно ли менять поле на работающем движке
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reload {
+    /// Применяется на лету (пороги checkpoint'а)
+    Live,
+    /// Требует перезапуска (геометрия страниц, размер пула, пути)
+    Restart,
+}
+
+/// Описание одного настраиваемого поля
+pub struct FieldSpec {
+    /// Dotted-ключ, как в `datyredb.toml` ("checkpoint.max_interval")
+    pub key: &'static str,
+
+    /// Можно ли применить без перезапуска
+    pub reload: Reload,
+
+    /// Применить значение к конфигу
+    pub apply: fn(&mut DatabaseConfig, &TomlValue) -> Result<(), String>,
+
+    /// Текущее значение в "человеческом" виде
+    pub render: fn(&DatabaseConfig) -> String,
+}
+
+impl FieldSpec {
+    /// Имя переменной окружения для ключа: `DATYRE_CHECKPOINT_MAX_INTERVAL`
+    pub fn env_var(&self, prefix: &str) -> String {
+        format!("{}{}", prefix, self.key.replace('.', "_").to_ascii_uppercase())
+    }
+}
+
+/// Все поля в порядке объявления
+pub const FIELDS: &[FieldSpec] = &[
+    FieldSpec {
+        key: "data_path",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.data_path = to_path(v)?;
+            Ok(())
+        },
+        render: |c| c.data_path.display().to_string(),
+    },
+    FieldSpec {
+        key: "buffer_pool_size",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.buffer_pool_size = to_size(v)?;
+            Ok(())
+        },
+        render: |c| format_bytes(c.buffer_pool_size as u64),
+    },
+    FieldSpec {
+        key: "page_size",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.page_size = to_size(v)?;
+            Ok(())
+        },
+        render: |c| format_bytes(c.page_size as u64),
+    },
+    FieldSpec {
+        key: "wal_segment_size",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.wal_segment_size = to_bytes(v)?;
+            Ok(())
+        },
+        render: |c| format_bytes(c.wal_segment_size),
+    },
+    FieldSpec {
+        key: "wal_commit_delay",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.wal_commit_delay = to_duration(v)?;
+            Ok(())
+        },
+        render: |c| format_duration(c.wal_commit_delay),
+    },
+    FieldSpec {
+        key: "wal_max_group_size",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.wal_max_group_size = to_count(v)?;
+            Ok(())
+        },
+        render: |c| c.wal_max_group_size.to_string(),
+    },
+    FieldSpec {
+        key: "wal_sync_mode",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.wal_sync_mode = match v {
+                TomlValue::String(s) => s.parse()?,
+                other => return Err(format!("expected fsync, fdatasync or none, got {other}")),
+            };
+            Ok(())
+        },
+        render: |c| c.wal_sync_mode.to_string(),
+    },
+    FieldSpec {
+        key: "wal_archive_dir",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.wal_archive_dir = match v {
+                TomlValue::String(s) if s.trim().is_empty() => None,
+                other => Some(to_path(other)?),
+            };
+            Ok(())
+        },
+        render: |c| c.wal_archive_dir.as_ref().map_or(String::new(), |p| p.display().to_string()),
+    },
+    FieldSpec {
+        key: "checkpoint.max_interval",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.max_interval = to_duration(v)?;
+            Ok(())
+        },
+        render: |c| format_duration(c.checkpoint.max_interval),
+    },
+    FieldSpec {
+        key: "checkpoint.min_interval",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.min_interval = to_duration(v)?;
+            Ok(())
+        },
+        render: |c| format_duration(c.checkpoint.min_interval),
+    },
+    FieldSpec {
+        key: "checkpoint.max_wal_size",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.max_wal_size = to_bytes(v)?;
+            Ok(())
+        },
+        render: |c| format_bytes(c.checkpoint.max_wal_size),
+    },
+    FieldSpec {
+        key: "checkpoint.dirty_page_soft_limit_pct",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.dirty_page_soft_limit_pct = to_ratio(v)?;
+            Ok(())
+        },
+        render: |c| c.checkpoint.dirty_page_soft_limit_pct.to_string(),
+    },
+    FieldSpec {
+        key: "checkpoint.dirty_page_hard_limit_pct",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.dirty_page_hard_limit_pct = to_ratio(v)?;
+            Ok(())
+        },
+        render: |c| c.checkpoint.dirty_page_hard_limit_pct.to_string(),
+    },
+    FieldSpec {
+        key: "checkpoint.checkpoint_batch_size",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.checkpoint_batch_size = to_count(v)?;
+            Ok(())
+        },
+        render: |c| c.checkpoint.checkpoint_batch_size.to_string(),
+    },
+    FieldSpec {
+        key: "checkpoint.batch_throttle_us",
+        reload: Reload::Live,
+        apply: |c, v| {
+            c.checkpoint.batch_throttle_us = to_micros(v)?;
+            Ok(())
+        },
+        render: |c| format_duration(c.checkpoint.batch_throttle_us),
+    },
+    FieldSpec {
+        key: "checkpoint.async_checkpoint",
+        reload: Reload::Restart,
+        apply: |c, v| {
+            c.checkpoint.async_checkpoint = to_bool(v)?;
+            Ok(())
+        },
+        render: |c| c.checkpoint.async_checkpoint.to_string(),
+    },
+];
+
+/// Поиск поля по ключу
+pub fn find(key: &str) -> Option<&'static FieldSpec> {
+    FIELDS.iter().find(|f| f.key == key)
+}
+
+// ============================================================================
+// Конвертеры значений
+// ============================================================================
+
+fn to_path(v: &TomlValue) -> Result<PathBuf, String> {
+    match v {
+        TomlValue::String(s) if !s.trim().is_empty() => Ok(PathBuf::from(s.trim())),
+        other => Err(format!("expected a non-empty path string, got {other}")),
+    }
+}
+
+fn to_bytes(v: &TomlValue) -> Result<u64, String> {
+    match v {
+        TomlValue::Integer(i) if *i >= 0 => Ok(*i as u64),
+        TomlValue::String(s) => parse_bytes(s),
+        other => Err(format!("expected a size like \"1GiB\", got {other}")),
+    }
+}
+
+fn to_size(v: &TomlValue) -> Result<usize, String> {
+    usize::try_from(to_bytes(v)?).map_err(|_| "size does not fit in usize".to_string())
+}
+
+fn to_count(v: &TomlValue) -> Result<usize, String> {
+    match v {
+        TomlValue::Integer(i) if *i >= 0 => Ok(*i as usize),
+        TomlValue::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| format!("expected a non-negative integer, got \"{s}\"")),
+        other => Err(format!("expected a non-negative integer, got {other}")),
+    }
+}
+
+fn to_duration(v: &TomlValue) -> Result<Duration, String> {
+    match v {
+        TomlValue::Integer(i) if *i >= 0 => Ok(Duration::from_secs(*i as u64)),
+        TomlValue::String(s) => parse_duration(s),
+        other => Err(format!("expected a duration like \"60s\", got {other}")),
+    }
+}
+
+/// Длительность ключа с суффиксом `_us`: число без единицы — микросекунды
+fn to_micros(v: &TomlValue) -> Result<Duration, String> {
+    match v {
+        TomlValue::Integer(i) if *i >= 0 => Ok(Duration::from_micros(*i as u64)),
+        TomlValue::String(s) => match s.trim().parse::<u64>() {
+            Ok(us) => Ok(Duration::from_micros(us)),
+            Err(_) => parse_duration(s),
+        },
+        other => Err(format!("expected microseconds or a duration like \"500us\", got {other}")),
+    }
+}
+
+/// Доля: 0.7, "0.7" или "70%"
+fn to_ratio(v: &TomlValue) -> Result<f32, String> {
+    match v {
+        TomlValue::Float(x) => Ok(*x as f32),
+        TomlValue::Integer(i) => Ok(*i as f32),
+        TomlValue::String(s) => {
+            let s = s.trim();
+            match s.strip_suffix('%') {
+                Some(pct) => pct.trim().parse::<f32>().map(|p| p / 100.0),
+                None => s.parse::<f32>(),
+            }
+            .map_err(|_| format!("expected a fraction like 0.7 or \"70%\", got \"{s}\""))
+        }
+        other => Err(format!("expected a fraction, got {other}")),
+    }
+}
+
+fn to_bool(v: &TomlValue) -> Result<bool, String> {
+    match v {
+        TomlValue::Bool(b) => Ok(*b),
+        TomlValue::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "on" | "yes" | "1" => Ok(true),
+            "false" | "off" | "no" | "0" => Ok(false),
+            _ => Err(format!("expected true/false, got \"{s}\"")),
+        },
+        other => Err(format!("expected true/false, got {other}")),
+    }
+}
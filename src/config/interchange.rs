@@ -0,0 +1,232 @@
+//! Interchange-файл `storage.conf` для C++ движка
+//! (`storage::load_storage_config` в `src/storage/config_file.cpp`).
+//!
+//! Формат стабилен и намеренно примитивен: строка версии, затем
+//! `key = value` по одной на строку. Ключи совпадают с именами полей
+//! `storage::StorageConfig` / `storage::CheckpointConfig`, значения —
+//! в единицах C++ полей (секунды, микросекунды, страницы, байты).
+//! Обе стороны требуют полный набор ключей и отвергают неизвестные.
+
+use super::{CheckpointConfig, DatabaseConfig};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Первая строка файла
+pub const HEADER: &str = "# datyre-storage-config v1";
+
+/// Пары (ключ interchange-файла, ключ `datyredb.toml`) в порядке записи.
+/// Ключи слева — имена полей C++ (`checkpoint.*` — поля `CheckpointConfig`),
+/// `page_size` соответствует константе `PAGE_SIZE`.
+pub const KEYS: &[(&str, &str)] = &[
+    ("data_path", "data_path"),
+    ("buffer_pool_pages", "buffer_pool_size"),
+    ("wal_segment_size", "wal_segment_size"),
+    ("page_size", "page_size"),
+    ("checkpoint.max_interval", "checkpoint.max_interval"),
+    ("checkpoint.min_interval", "checkpoint.min_interval"),
+    ("checkpoint.max_wal_size", "checkpoint.max_wal_size"),
+    ("checkpoint.dirty_page_soft_limit_pct", "checkpoint.dirty_page_soft_limit_pct"),
+    ("checkpoint.dirty_page_hard_limit_pct", "checkpoint.dirty_page_hard_limit_pct"),
+    ("checkpoint.checkpoint_batch_size", "checkpoint.checkpoint_batch_size"),
+    ("checkpoint.batch_throttle_us", "checkpoint.batch_throttle_us"),
+    ("checkpoint.async_checkpoint", "checkpoint.async_checkpoint"),
+];
+
+/// Ошибка экспорта / импорта
+#[derive(Debug)]
+pub enum InterchangeError {
+    Io { path: PathBuf, source: io::Error },
+    /// Нет строки версии или версия не поддерживается
+    BadHeader(String),
+    /// Строка не в формате `key = value`
+    Malformed { line: usize, text: String },
+    UnknownKey { line: usize, key: String },
+    DuplicateKey { line: usize, key: String },
+    MissingKey(&'static str),
+    InvalidValue { key: String, value: String },
+    /// Значение не представимо в единицах C++ поля без потерь
+    Precision { key: &'static str, value: Duration },
+    /// `buffer_pool_size` не кратен `page_size` — в C++ задаётся в страницах
+    UnalignedPool { buffer_pool_size: usize, page_size: usize },
+}
+
+impl fmt::Display for InterchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterchangeError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            InterchangeError::BadHeader(found) => {
+                write!(f, "expected '{HEADER}' header, found '{found}'")
+            }
+            InterchangeError::Malformed { line, text } => {
+                write!(f, "line {line}: expected 'key = value', found '{text}'")
+            }
+            InterchangeError::UnknownKey { line, key } => write!(f, "line {line}: unknown key '{key}'"),
+            InterchangeError::DuplicateKey { line, key } => {
+                write!(f, "line {line}: duplicate key '{key}'")
+            }
+            InterchangeError::MissingKey(key) => write!(f, "missing key '{key}'"),
+            InterchangeError::InvalidValue { key, value } => {
+                write!(f, "invalid value '{value}' for '{key}'")
+            }
+            InterchangeError::Precision { key, value } => {
+                write!(f, "{key}: {value:?} cannot be represented exactly in C++ units")
+            }
+            InterchangeError::UnalignedPool { buffer_pool_size, page_size } => write!(
+                f,
+                "buffer_pool_size {buffer_pool_size} is not a multiple of page_size {page_size}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InterchangeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InterchangeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Сериализация в формат interchange
+pub fn export(config: &DatabaseConfig) -> Result<String, InterchangeError> {
+    let cp = &config.checkpoint;
+    let data_path = config.data_path.to_string_lossy();
+    if data_path.contains('\n') {
+        return Err(InterchangeError::InvalidValue {
+            key: "data_path".to_string(),
+            value: data_path.into_owned(),
+        });
+    }
+
+    if config.page_size == 0 || !config.buffer_pool_size.is_multiple_of(config.page_size) {
+        return Err(InterchangeError::UnalignedPool {
+            buffer_pool_size: config.buffer_pool_size,
+            page_size: config.page_size,
+        });
+    }
+
+    let values: [String; 12] = [
+        data_path.into_owned(),
+        config.buffer_pool_pages().to_string(),
+        config.wal_segment_size.to_string(),
+        config.page_size.to_string(),
+        whole_seconds("checkpoint.max_interval", cp.max_interval)?.to_string(),
+        whole_seconds("checkpoint.min_interval", cp.min_interval)?.to_string(),
+        cp.max_wal_size.to_string(),
+        cp.dirty_page_soft_limit_pct.to_string(),
+        cp.dirty_page_hard_limit_pct.to_string(),
+        cp.checkpoint_batch_size.to_string(),
+        whole_micros("checkpoint.batch_throttle_us", cp.batch_throttle_us)?.to_string(),
+        u8::from(cp.async_checkpoint).to_string(),
+    ];
+
+    let mut out = String::from(HEADER);
+    out.push('\n');
+    for ((key, _), value) in KEYS.iter().zip(values) {
+        out.push_str(&format!("{key} = {value}\n"));
+    }
+    Ok(out)
+}
+
+/// Разбор interchange-файла; требуется полный набор ключей
+pub fn import(text: &str) -> Result<DatabaseConfig, InterchangeError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, first)) if first.trim() == HEADER => {}
+        Some((_, first)) => return Err(InterchangeError::BadHeader(first.to_string())),
+        None => return Err(InterchangeError::BadHeader(String::new())),
+    }
+
+    let mut values: BTreeMap<&'static str, String> = BTreeMap::new();
+    for (idx, raw) in lines {
+        let line = idx + 1;
+        let text = raw.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let (key, value) = text
+            .split_once('=')
+            .ok_or_else(|| InterchangeError::Malformed { line, text: text.to_string() })?;
+        let key = key.trim();
+        let Some(&(known, _)) = KEYS.iter().find(|(k, _)| *k == key) else {
+            return Err(InterchangeError::UnknownKey { line, key: key.to_string() });
+        };
+        if values.insert(known, value.trim().to_string()).is_some() {
+            return Err(InterchangeError::DuplicateKey { line, key: key.to_string() });
+        }
+    }
+
+    let page_size: usize = parse(&values, "page_size")?;
+    let buffer_pool_pages: usize = parse(&values, "buffer_pool_pages")?;
+
+    Ok(DatabaseConfig {
+        data_path: parse(&values, "data_path")?,
+        buffer_pool_size: buffer_pool_pages * page_size,
+        page_size,
+        wal_segment_size: parse(&values, "wal_segment_size")?,
+        checkpoint: CheckpointConfig {
+            max_interval: Duration::from_secs(parse(&values, "checkpoint.max_interval")?),
+            min_interval: Duration::from_secs(parse(&values, "checkpoint.min_interval")?),
+            max_wal_size: parse(&values, "checkpoint.max_wal_size")?,
+            dirty_page_soft_limit_pct: parse(&values, "checkpoint.dirty_page_soft_limit_pct")?,
+            dirty_page_hard_limit_pct: parse(&values, "checkpoint.dirty_page_hard_limit_pct")?,
+            checkpoint_batch_size: parse(&values, "checkpoint.checkpoint_batch_size")?,
+            batch_throttle_us: Duration::from_micros(parse(
+                &values,
+                "checkpoint.batch_throttle_us",
+            )?),
+            async_checkpoint: parse::<u8>(&values, "checkpoint.async_checkpoint")? != 0,
+        },
+        // Группового commit'а в C++ нет: поля остаются по умолчанию
+        ..DatabaseConfig::default()
+    })
+}
+
+/// Запись interchange-файла
+pub fn write_file(config: &DatabaseConfig, path: impl AsRef<Path>) -> Result<(), InterchangeError> {
+    let path = path.as_ref();
+    let text = export(config)?;
+    std::fs::write(path, text).map_err(|source| InterchangeError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Чтение interchange-файла
+pub fn read_file(path: impl AsRef<Path>) -> Result<DatabaseConfig, InterchangeError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|source| InterchangeError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    import(&text)
+}
+
+fn parse<T: std::str::FromStr>(
+    values: &BTreeMap<&'static str, String>,
+    key: &'static str,
+) -> Result<T, InterchangeError> {
+    let value = values.get(key).ok_or(InterchangeError::MissingKey(key))?;
+    value.parse().map_err(|_| InterchangeError::InvalidValue {
+        key: key.to_string(),
+        value: value.clone(),
+    })
+}
+
+fn whole_seconds(key: &'static str, d: Duration) -> Result<u64, InterchangeError> {
+    if d.subsec_nanos() != 0 {
+        return Err(InterchangeError::Precision { key, value: d });
+    }
+    Ok(d.as_secs())
+}
+
+fn whole_micros(key: &'static str, d: Duration) -> Result<u64, InterchangeError> {
+    if !d.subsec_nanos().is_multiple_of(1_000) {
+        return Err(InterchangeError::Precision { key, value: d });
+    }
+    Ok(d.as_micros() as u64)
+}
@@ -0,0 +1,402 @@
+//! Загрузка `DatabaseConfig` по слоям:
+//! значения по умолчанию → `datyredb.toml` → `DATYRE_*` → CLI `key=value`.
+//! Каждый следующий слой перекрывает предыдущий; для каждого поля
+//! запоминается слой, который установил итоговое значение.
+
+use super::fields::{self, FIELDS};
+use super::presets::Preset;
+use super::toml::{self, TomlValue};
+use super::validate::ValidationErrors;
+use super::DatabaseConfig;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Префикс переменных окружения по умолчанию
+pub const DEFAULT_ENV_PREFIX: &str = "DATYRE_";
+
+/// Слой, из которого пришло значение
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// `Default` для `DatabaseConfig`
+    Default,
+    /// Именованный пресет (`ConfigLoader::preset`)
+    Preset(&'static str),
+    /// Файл конфигурации, строка с 1
+    File { path: PathBuf, line: usize },
+    /// Переменная окружения
+    Env { var: String },
+    /// Явный override `key=value`
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Preset(name) => write!(f, "preset {name}"),
+            ConfigSource::File { path, line } => write!(f, "{}:{}", path.display(), line),
+            ConfigSource::Env { var } => write!(f, "env {var}"),
+            ConfigSource::Cli => write!(f, "cli override"),
+        }
+    }
+}
+
+/// Ошибка загрузки конфигурации
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Не удалось прочитать файл
+    Io { path: PathBuf, source: io::Error },
+    /// Синтаксическая ошибка в файле
+    Syntax { path: PathBuf, line: usize, message: String },
+    /// Неизвестный ключ (скорее всего опечатка)
+    UnknownKey { key: String, source: ConfigSource },
+    /// Значение не удалось разобрать
+    InvalidValue { key: String, value: String, source: ConfigSource, reason: String },
+    /// Override не в формате `key=value`
+    MalformedOverride(String),
+    /// Итоговый конфиг нарушает инварианты
+    Invalid(ValidationErrors),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            ConfigError::Syntax { path, line, message } => {
+                write!(f, "{}:{}: {}", path.display(), line, message)
+            }
+            ConfigError::UnknownKey { key, source } => {
+                write!(f, "unknown config key '{key}' ({source})")
+            }
+            ConfigError::InvalidValue { key, value, source, reason } => {
+                write!(f, "invalid value {value} for '{key}' ({source}): {reason}")
+            }
+            ConfigError::MalformedOverride(arg) => {
+                write!(f, "override '{arg}' is not in key=value form")
+            }
+            ConfigError::Invalid(errors) => write!(f, "{errors}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Invalid(errors) => Some(errors),
+            _ => None,
+        }
+    }
+}
+
+/// Итог загрузки: конфиг плюс происхождение каждого значения
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: DatabaseConfig,
+    sources: BTreeMap<&'static str, ConfigSource>,
+}
+
+impl LoadedConfig {
+    /// Слой, установивший значение ключа (`None` для неизвестного ключа)
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        self.sources.get(key)
+    }
+
+    /// Таблица "ключ = значение (источник)" для логов и `--print-config`
+    pub fn report(&self) -> String {
+        let width = FIELDS.iter().map(|f| f.key.len()).max().unwrap_or(0);
+        let mut out = String::new();
+        for field in FIELDS {
+            let source = self.sources.get(field.key).unwrap_or(&ConfigSource::Default);
+            out.push_str(&format!(
+                "{:<width$} = {:<12} # {}\n",
+                field.key,
+                (field.render)(&self.config),
+                source,
+            ));
+        }
+        out
+    }
+}
+
+/// Построитель загрузки конфигурации
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    base: DatabaseConfig,
+    base_source: ConfigSource,
+    file: Option<PathBuf>,
+    env_prefix: String,
+    env: Option<Vec<(String, String)>>,
+    overrides: Vec<String>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self {
+            base: DatabaseConfig::default(),
+            base_source: ConfigSource::Default,
+            file: None,
+            env_prefix: DEFAULT_ENV_PREFIX.to_string(),
+            env: None,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Базовый конфиг вместо `DatabaseConfig::default()`
+    pub fn base(mut self, config: DatabaseConfig) -> Self {
+        self.base = config;
+        self.base_source = ConfigSource::Default;
+        self
+    }
+
+    /// Пресет нагрузки как базовый слой (вместо значений по умолчанию)
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.base = preset.config();
+        self.base_source = ConfigSource::Preset(preset.name());
+        self
+    }
+
+    /// Файл `datyredb.toml`
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Путь к файлу конфигурации, если задан
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Префикс переменных окружения (по умолчанию `DATYRE_`)
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = prefix.into();
+        self
+    }
+
+    /// Явный набор переменных окружения вместо `std::env::vars()`
+    pub fn env_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env = Some(vars.into_iter().map(|(k, v)| (k.into(), v.into())).collect());
+        self
+    }
+
+    /// Override в форме `checkpoint.max_interval=30s`
+    pub fn override_arg(mut self, arg: impl Into<String>) -> Self {
+        self.overrides.push(arg.into());
+        self
+    }
+
+    /// Несколько overrides сразу (например, хвост аргументов командной строки)
+    pub fn override_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.overrides.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Применить все слои и проверить итоговый конфиг
+    pub fn load(self) -> Result<LoadedConfig, ConfigError> {
+        let mut loaded = LoadedConfig {
+            config: self.base,
+            sources: FIELDS.iter().map(|f| (f.key, self.base_source.clone())).collect(),
+        };
+
+        // 1. Файл
+        if let Some(path) = &self.file {
+            let text = std::fs::read_to_string(path)
+                .map_err(|source| ConfigError::Io { path: path.clone(), source })?;
+            let entries = toml::parse(&text).map_err(|e| ConfigError::Syntax {
+                path: path.clone(),
+                line: e.line,
+                message: e.message,
+            })?;
+            for entry in entries {
+                let source = ConfigSource::File { path: path.clone(), line: entry.line };
+                apply(&mut loaded, &entry.key, &entry.value, source)?;
+            }
+        }
+
+        // 2. Переменные окружения
+        let env: BTreeMap<String, String> = match self.env {
+            Some(vars) => vars.into_iter().collect(),
+            None => std::env::vars().collect(),
+        };
+        for field in FIELDS {
+            let var = field.env_var(&self.env_prefix);
+            if let Some(value) = env.get(&var) {
+                let value = TomlValue::String(value.clone());
+                apply(&mut loaded, field.key, &value, ConfigSource::Env { var })?;
+            }
+        }
+
+        // 3. Явные overrides
+        for arg in &self.overrides {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| ConfigError::MalformedOverride(arg.clone()))?;
+            let value = TomlValue::String(value.trim().to_string());
+            apply(&mut loaded, key.trim(), &value, ConfigSource::Cli)?;
+        }
+
+        loaded.config.validate().map_err(ConfigError::Invalid)?;
+        Ok(loaded)
+    }
+}
+
+fn apply(
+    loaded: &mut LoadedConfig,
+    key: &str,
+    value: &TomlValue,
+    source: ConfigSource,
+) -> Result<(), ConfigError> {
+    let Some(field) = fields::find(key) else {
+        return Err(ConfigError::UnknownKey { key: key.to_string(), source });
+    };
+    (field.apply)(&mut loaded.config, value).map_err(|reason| ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        source: source.clone(),
+        reason,
+    })?;
+    loaded.sources.insert(field.key, source);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("datyredb_config_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("datyredb.toml");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let path = write_temp(
+            "layers",
+            "buffer_pool_size = \"64MiB\"\n\
+             [checkpoint]\n\
+             max_interval = \"5m\"\n\
+             min_interval = \"10s\"\n\
+             max_wal_size = \"2GiB\"\n",
+        );
+
+        let loaded = ConfigLoader::new()
+            .file(&path)
+            .env_vars([
+                ("DATYRE_CHECKPOINT_MIN_INTERVAL", "20s"),
+                ("DATYRE_CHECKPOINT_MAX_WAL_SIZE", "512MiB"),
+            ])
+            .override_arg("checkpoint.max_wal_size=256MiB")
+            .load()
+            .unwrap();
+
+        let cfg = &loaded.config;
+        assert_eq!(cfg.buffer_pool_size, 64 << 20);
+        assert_eq!(cfg.checkpoint.max_interval, Duration::from_secs(300));
+        assert_eq!(cfg.checkpoint.min_interval, Duration::from_secs(20));
+        assert_eq!(cfg.checkpoint.max_wal_size, 256 << 20);
+
+        assert_eq!(loaded.source("page_size"), Some(&ConfigSource::Default));
+        assert_eq!(
+            loaded.source("checkpoint.max_interval"),
+            Some(&ConfigSource::File { path: path.clone(), line: 3 })
+        );
+        assert_eq!(
+            loaded.source("checkpoint.min_interval"),
+            Some(&ConfigSource::Env { var: "DATYRE_CHECKPOINT_MIN_INTERVAL".into() })
+        );
+        assert_eq!(loaded.source("checkpoint.max_wal_size"), Some(&ConfigSource::Cli));
+        assert!(loaded.report().contains("checkpoint.max_wal_size"));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn throttle_without_unit_is_in_microseconds() {
+        let path = write_temp("throttle", "[checkpoint]\nbatch_throttle_us = 500\n");
+        let loaded = ConfigLoader::new()
+            .file(&path)
+            .env_vars(Vec::<(String, String)>::new())
+            .load()
+            .unwrap();
+        assert_eq!(loaded.config.checkpoint.batch_throttle_us, Duration::from_micros(500));
+
+        for (arg, us) in [("250", 250), ("2ms", 2000)] {
+            let loaded = ConfigLoader::new()
+                .env_vars(Vec::<(String, String)>::new())
+                .override_arg(format!("checkpoint.batch_throttle_us={arg}"))
+                .load()
+                .unwrap();
+            assert_eq!(loaded.config.checkpoint.batch_throttle_us, Duration::from_micros(us));
+        }
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        let err = ConfigLoader::new()
+            .env_vars(Vec::<(String, String)>::new())
+            .override_arg("checkpoint.max_intreval=5s")
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::UnknownKey { .. }));
+
+        let err = ConfigLoader::new()
+            .env_vars([("DATYRE_CHECKPOINT_MAX_INTERVAL", "soon")])
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { .. }));
+
+        let err = ConfigLoader::new().override_arg("page_size").load().unwrap_err();
+        assert!(matches!(err, ConfigError::MalformedOverride(_)));
+    }
+
+    #[test]
+    fn preset_is_the_base_layer() {
+        let loaded = ConfigLoader::new()
+            .preset(Preset::Embedded)
+            .env_vars(Vec::<(String, String)>::new())
+            .override_arg("checkpoint.checkpoint_batch_size=16")
+            .load()
+            .unwrap();
+        assert_eq!(loaded.config.buffer_pool_size, 16 << 20);
+        assert_eq!(loaded.source("buffer_pool_size"), Some(&ConfigSource::Preset("embedded")));
+        assert_eq!(loaded.source("checkpoint.checkpoint_batch_size"), Some(&ConfigSource::Cli));
+    }
+
+    #[test]
+    fn rejects_invalid_result() {
+        let err = ConfigLoader::new()
+            .env_vars([("DATYRE_CHECKPOINT_MIN_INTERVAL", "10m")])
+            .override_arg("checkpoint.checkpoint_batch_size=0")
+            .load()
+            .unwrap_err();
+        let ConfigError::Invalid(errors) = err else {
+            panic!("expected validation error, got {err}");
+        };
+        assert!(errors.has_field("checkpoint.min_interval"));
+        assert!(errors.has_field("checkpoint.checkpoint_batch_size"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Именованные пресеты `DatabaseConfig` под типовые нагрузки.
+//!
+//! Все пресеты используют 4KB страницы (C++ `PAGE_SIZE`) и проходят
+//! `validate()`; отдельные поля можно перекрыть через `ConfigLoader`.
+
+use super::{CheckpointConfig, DatabaseConfig};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Профиль нагрузки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Небольшая OLTP-база: частые короткие транзакции, быстрый рестарт
+    OltpSmall,
+    /// Массовая загрузка: редкие крупные checkpoint'ы, большой WAL
+    BulkLoad,
+    /// Минимальные хвосты задержек: ранний фоновый flush, мелкие батчи
+    LowLatency,
+    /// Встраиваемый режим: мало памяти, синхронный I/O
+    Embedded,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::OltpSmall,
+        Preset::BulkLoad,
+        Preset::LowLatency,
+        Preset::Embedded,
+    ];
+
+    /// Имя для `--preset` и логов
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::OltpSmall => "oltp_small",
+            Preset::BulkLoad => "bulk_load",
+            Preset::LowLatency => "low_latency",
+            Preset::Embedded => "embedded",
+        }
+    }
+
+    /// Конфиг пресета
+    pub fn config(self) -> DatabaseConfig {
+        let base = DatabaseConfig::default();
+        match self {
+            Preset::OltpSmall => DatabaseConfig {
+                buffer_pool_size: (256 * MIB) as usize,
+                checkpoint: CheckpointConfig {
+                    max_interval: Duration::from_secs(60),
+                    min_interval: Duration::from_secs(5),
+                    max_wal_size: 512 * MIB,
+                    dirty_page_soft_limit_pct: 0.60,
+                    dirty_page_hard_limit_pct: 0.85,
+                    checkpoint_batch_size: 128,
+                    batch_throttle_us: Duration::from_micros(200),
+                    async_checkpoint: true,
+                },
+                ..base
+            },
+            Preset::BulkLoad => DatabaseConfig {
+                buffer_pool_size: (4096 * MIB) as usize,
+                wal_segment_size: 256 * MIB,
+                checkpoint: CheckpointConfig {
+                    max_interval: Duration::from_secs(15 * 60),
+                    min_interval: Duration::from_secs(30),
+                    max_wal_size: 8192 * MIB,
+                    dirty_page_soft_limit_pct: 0.80,
+                    dirty_page_hard_limit_pct: 0.95,
+                    checkpoint_batch_size: 1024,
+                    batch_throttle_us: Duration::ZERO,
+                    async_checkpoint: true,
+                },
+                ..base
+            },
+            Preset::LowLatency => DatabaseConfig {
+                buffer_pool_size: (1024 * MIB) as usize,
+                wal_segment_size: 32 * MIB,
+                checkpoint: CheckpointConfig {
+                    max_interval: Duration::from_secs(30),
+                    min_interval: Duration::from_secs(2),
+                    max_wal_size: 256 * MIB,
+                    dirty_page_soft_limit_pct: 0.30,
+                    dirty_page_hard_limit_pct: 0.60,
+                    checkpoint_batch_size: 64,
+                    batch_throttle_us: Duration::from_micros(50),
+                    async_checkpoint: true,
+                },
+                ..base
+            },
+            Preset::Embedded => DatabaseConfig {
+                buffer_pool_size: (16 * MIB) as usize,
+                wal_segment_size: 16 * MIB,
+                checkpoint: CheckpointConfig {
+                    max_interval: Duration::from_secs(5 * 60),
+                    min_interval: Duration::from_secs(10),
+                    max_wal_size: 64 * MIB,
+                    dirty_page_soft_limit_pct: 0.50,
+                    dirty_page_hard_limit_pct: 0.80,
+                    checkpoint_batch_size: 32,
+                    batch_throttle_us: Duration::from_micros(500),
+                    async_checkpoint: false,
+                },
+                ..base
+            },
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|p| p.name() == s.trim())
+            .ok_or_else(|| {
+                let names: Vec<_> = Preset::ALL.iter().map(|p| p.name()).collect();
+                format!("unknown preset '{s}' (expected one of: {})", names.join(", "))
+            })
+    }
+}
+
+impl DatabaseConfig {
+    /// Конфиг именованного пресета
+    pub fn preset(preset: Preset) -> Self {
+        preset.config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_preset_is_valid() {
+        for preset in Preset::ALL {
+            preset
+                .config()
+                .validate()
+                .unwrap_or_else(|e| panic!("preset {preset}: {e}"));
+            assert_eq!(preset.name().parse::<Preset>().unwrap(), preset);
+        }
+        assert!("oltp_large".parse::<Preset>().is_err());
+    }
+}
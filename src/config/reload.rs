@@ -0,0 +1,353 @@
+//! Горячая перезагрузка конфигурации.
+//!
+//! `ConfigHandle` держит текущий `DatabaseConfig` за `Arc` и подменяет его
+//! целиком: читатель всегда видит согласованный снимок. Новый конфиг
+//! загружается теми же слоями, что и при старте, проходит `validate()`
+//! и применяется только если изменились поля с `Reload::Live`.
+//! Изменение поля с `Reload::Restart` отвергает весь новый конфиг.
+
+use super::fields::{Reload, FIELDS};
+use super::loader::{ConfigError, ConfigLoader};
+use super::DatabaseConfig;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Опубликованное изменение конфига
+#[derive(Debug, Clone)]
+pub struct ConfigUpdate {
+    /// Новый снимок конфига
+    pub config: Arc<DatabaseConfig>,
+    /// Изменившиеся ключи ("checkpoint.max_interval", ...)
+    pub changed: Vec<&'static str>,
+}
+
+/// Поле, изменение которого требует перезапуска
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartField {
+    pub key: &'static str,
+    pub current: String,
+    pub requested: String,
+}
+
+/// Причина отказа в перезагрузке
+#[derive(Debug)]
+pub enum ReloadError {
+    /// Новый конфиг не загрузился или не прошёл проверку
+    Load(ConfigError),
+    /// Изменены поля, которые нельзя менять на работающем движке
+    RestartRequired(Vec<RestartField>),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Load(e) => write!(f, "config reload failed: {e}"),
+            ReloadError::RestartRequired(fields) => {
+                write!(f, "config reload rejected, restart required to change:")?;
+                for field in fields {
+                    write!(f, "\n  - {}: {} -> {}", field.key, field.current, field.requested)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReloadError::Load(e) => Some(e),
+            ReloadError::RestartRequired(_) => None,
+        }
+    }
+}
+
+struct Shared {
+    loader: ConfigLoader,
+    current: RwLock<Arc<DatabaseConfig>>,
+    subscribers: Mutex<Vec<Sender<ConfigUpdate>>>,
+    // Сериализует reload'ы: проверка и публикация — одна операция
+    reload_lock: Mutex<()>,
+}
+
+/// Разделяемый доступ к текущему конфигу с горячей перезагрузкой
+#[derive(Clone)]
+pub struct ConfigHandle {
+    shared: Arc<Shared>,
+}
+
+impl ConfigHandle {
+    /// Первичная загрузка; `loader` повторно используется при каждом reload
+    pub fn new(loader: ConfigLoader) -> Result<Self, ConfigError> {
+        let loaded = loader.clone().load()?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                loader,
+                current: RwLock::new(Arc::new(loaded.config)),
+                subscribers: Mutex::new(Vec::new()),
+                reload_lock: Mutex::new(()),
+            }),
+        })
+    }
+
+    /// Текущий снимок конфига
+    pub fn current(&self) -> Arc<DatabaseConfig> {
+        Arc::clone(&self.shared.current.read().unwrap())
+    }
+
+    /// Подписка на применённые изменения
+    pub fn subscribe(&self) -> Receiver<ConfigUpdate> {
+        let (tx, rx) = mpsc::channel();
+        self.shared.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Перечитать все слои и применить, если это возможно без перезапуска.
+    /// `Ok(None)` — ничего не изменилось.
+    pub fn reload(&self) -> Result<Option<ConfigUpdate>, ReloadError> {
+        let loaded = self.shared.loader.clone().load().map_err(ReloadError::Load)?;
+        self.apply(loaded.config)
+    }
+
+    /// Применить готовый конфиг (после `validate()`)
+    pub fn apply(&self, new: DatabaseConfig) -> Result<Option<ConfigUpdate>, ReloadError> {
+        new.validate()
+            .map_err(|e| ReloadError::Load(ConfigError::Invalid(e)))?;
+
+        let _guard = self.shared.reload_lock.lock().unwrap();
+        let old = self.current();
+
+        let mut changed = Vec::new();
+        let mut restart = Vec::new();
+        for field in FIELDS {
+            let current = (field.render)(&old);
+            let requested = (field.render)(&new);
+            if current == requested {
+                continue;
+            }
+            match field.reload {
+                Reload::Live => changed.push(field.key),
+                Reload::Restart => restart.push(RestartField { key: field.key, current, requested }),
+            }
+        }
+
+        if !restart.is_empty() {
+            return Err(ReloadError::RestartRequired(restart));
+        }
+        if changed.is_empty() {
+            return Ok(None);
+        }
+
+        let update = ConfigUpdate { config: Arc::new(new), changed };
+        *self.shared.current.write().unwrap() = Arc::clone(&update.config);
+        self.shared
+            .subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(update.clone()).is_ok());
+
+        Ok(Some(update))
+    }
+
+    /// Фоновое отслеживание файла конфигурации (опрос раз в `interval`).
+    /// Возвращает `None`, если загрузчик не привязан к файлу.
+    pub fn watch(&self, interval: Duration) -> Option<ConfigWatcher> {
+        let path = self.shared.loader.file_path()?.to_path_buf();
+        let initial = std::fs::read(&path).ok();
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(WatchState::default());
+
+        let handle = self.clone();
+        let thread_stop = Arc::clone(&stop);
+        let thread_state = Arc::clone(&state);
+        let thread = std::thread::Builder::new()
+            .name("datyre-config-watch".to_string())
+            .spawn(move || {
+                watch_loop(handle, path, initial, interval, thread_stop, thread_state)
+            })
+            .ok()?;
+
+        Some(ConfigWatcher { stop, state, thread: Some(thread) })
+    }
+}
+
+#[derive(Default)]
+struct WatchState {
+    applied: AtomicU64,
+    rejected: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Фоновый поток отслеживания файла; останавливается при drop
+pub struct ConfigWatcher {
+    stop: Arc<AtomicBool>,
+    state: Arc<WatchState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// Сколько изменений файла было применено
+    pub fn applied_count(&self) -> u64 {
+        self.state.applied.load(Ordering::Relaxed)
+    }
+
+    /// Сколько изменений файла было отвергнуто
+    pub fn rejected_count(&self) -> u64 {
+        self.state.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Текст последнего отказа (для логов / метрик)
+    pub fn last_error(&self) -> Option<String> {
+        self.state.last_error.lock().unwrap().clone()
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+fn watch_loop(
+    handle: ConfigHandle,
+    path: PathBuf,
+    mut last_seen: Option<Vec<u8>>,
+    interval: Duration,
+    stop: Arc<AtomicBool>,
+    state: Arc<WatchState>,
+) {
+    // Сравниваем содержимое, а не mtime: грубая гранулярность mtime
+    // на некоторых ФС пропускает быстрые повторные записи
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::park_timeout(interval);
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let contents = std::fs::read(&path).ok();
+        if contents.is_none() || contents == last_seen {
+            continue;
+        }
+        last_seen = contents;
+
+        match handle.reload() {
+            Ok(Some(_)) => {
+                state.applied.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                state.rejected.fetch_add(1, Ordering::Relaxed);
+                *state.last_error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempConfig {
+        dir: PathBuf,
+        path: PathBuf,
+    }
+
+    impl TempConfig {
+        fn new(name: &str, contents: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("datyredb_reload_{}_{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("datyredb.toml");
+            std::fs::write(&path, contents).unwrap();
+            Self { dir, path }
+        }
+
+        fn loader(&self) -> ConfigLoader {
+            ConfigLoader::new()
+                .file(&self.path)
+                .env_vars(Vec::<(String, String)>::new())
+        }
+    }
+
+    impl Drop for TempConfig {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn applies_live_fields_and_notifies() {
+        let tmp = TempConfig::new("live", "[checkpoint]\nmax_interval = \"60s\"\n");
+        let handle = ConfigHandle::new(tmp.loader()).unwrap();
+        let updates = handle.subscribe();
+
+        std::fs::write(
+            &tmp.path,
+            "[checkpoint]\nmax_interval = \"2m\"\ncheckpoint_batch_size = 64\n",
+        )
+        .unwrap();
+        let update = handle.reload().unwrap().expect("config changed");
+        assert_eq!(
+            update.changed,
+            ["checkpoint.max_interval", "checkpoint.checkpoint_batch_size"]
+        );
+
+        let received = updates.try_recv().unwrap();
+        assert_eq!(received.config.checkpoint.checkpoint_batch_size, 64);
+        assert_eq!(handle.current().checkpoint.max_interval, Duration::from_secs(120));
+
+        assert!(handle.reload().unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_restart_only_fields() {
+        let tmp = TempConfig::new("restart", "page_size = 4096\n");
+        let handle = ConfigHandle::new(tmp.loader()).unwrap();
+
+        std::fs::write(
+            &tmp.path,
+            "page_size = 8192\n[checkpoint]\ndirty_page_soft_limit_pct = 0.5\n",
+        )
+        .unwrap();
+        let Err(ReloadError::RestartRequired(fields)) = handle.reload() else {
+            panic!("page_size change must be rejected");
+        };
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].key, "page_size");
+        assert_eq!(fields[0].requested, "8KiB");
+
+        // Ни одно поле, включая live, не применено
+        assert_eq!(handle.current().page_size, 4096);
+        assert_eq!(handle.current().checkpoint.dirty_page_soft_limit_pct, 0.70);
+    }
+
+    #[test]
+    fn watcher_picks_up_file_changes() {
+        let tmp = TempConfig::new("watch", "[checkpoint]\nmin_interval = \"5s\"\n");
+        let handle = ConfigHandle::new(tmp.loader()).unwrap();
+        let updates = handle.subscribe();
+        let watcher = handle.watch(Duration::from_millis(10)).unwrap();
+
+        std::fs::write(&tmp.path, "[checkpoint]\nmin_interval = \"10s\"\n").unwrap();
+        let update = updates.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.config.checkpoint.min_interval, Duration::from_secs(10));
+
+        std::fs::write(&tmp.path, "buffer_pool_size = \"1GiB\"\n").unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while watcher.rejected_count() == 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(watcher.last_error().unwrap().contains("buffer_pool_size"));
+        assert_eq!(watcher.applied_count(), 1);
+    }
+}
@@ -0,0 +1,239 @@
+//! Минимальный разборщик TOML для `datyredb.toml`.
+//!
+//! Поддерживается подмножество, которого достаточно для конфигурации:
+//! таблицы `[section]`, пары `key = value` (в том числе dotted keys),
+//! строки, целые, дробные числа и булевы значения, комментарии `#`.
+//! Массивы и inline-таблицы не поддерживаются.
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// Скалярное значение из TOML (или из строки env / CLI)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TomlValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for TomlValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TomlValue::String(s) => write!(f, "\"{s}\""),
+            TomlValue::Integer(i) => write!(f, "{i}"),
+            TomlValue::Float(x) => write!(f, "{x}"),
+            TomlValue::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+/// Пара ключ/значение с полным dotted-путём и номером строки
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlEntry {
+    pub key: String,
+    pub value: TomlValue,
+    pub line: usize,
+}
+
+/// Синтаксическая ошибка с номером строки (с 1)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TomlError {}
+
+/// Разбор документа в плоский список записей
+pub fn parse(input: &str) -> Result<Vec<TomlEntry>, TomlError> {
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    let mut table = String::new();
+
+    for (idx, raw_line) in input.lines().enumerate() {
+        let line_no = idx + 1;
+        let err = |message: String| TomlError { line: line_no, message };
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let header = strip_comment(header);
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| err("unterminated table header".to_string()))?;
+            if name.starts_with('[') {
+                return Err(err("arrays of tables are not supported".to_string()));
+            }
+            table = parse_key(name).map_err(err)?;
+            continue;
+        }
+
+        let eq = line
+            .find('=')
+            .ok_or_else(|| err("expected 'key = value'".to_string()))?;
+        let key = parse_key(&line[..eq]).map_err(err)?;
+        let value = parse_value(line[eq + 1..].trim()).map_err(err)?;
+
+        let full_key = if table.is_empty() { key } else { format!("{table}.{key}") };
+        if !seen.insert(full_key.clone()) {
+            return Err(err(format!("duplicate key '{full_key}'")));
+        }
+
+        entries.push(TomlEntry { key: full_key, value, line: line_no });
+    }
+
+    Ok(entries)
+}
+
+/// Нормализация ключа: "a . b" -> "a.b", проверка допустимых символов
+fn parse_key(raw: &str) -> Result<String, String> {
+    let parts: Vec<&str> = raw.split('.').map(str::trim).collect();
+    for part in &parts {
+        if part.is_empty()
+            || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!("invalid key '{}'", raw.trim()));
+        }
+    }
+    Ok(parts.join("."))
+}
+
+/// Отбрасывает хвостовой комментарий вне строковых литералов
+fn strip_comment(s: &str) -> &str {
+    let mut in_basic = false;
+    let mut in_literal = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if in_basic && !escaped => {
+                escaped = true;
+                continue;
+            }
+            '"' if !in_literal && !escaped => in_basic = !in_basic,
+            '\'' if !in_basic => in_literal = !in_literal,
+            '#' if !in_basic && !in_literal => return s[..i].trim_end(),
+            _ => {}
+        }
+        escaped = false;
+    }
+    s.trim_end()
+}
+
+fn parse_value(raw: &str) -> Result<TomlValue, String> {
+    let raw = strip_comment(raw);
+    if raw.is_empty() {
+        return Err("missing value".to_string());
+    }
+
+    if let Some(body) = raw.strip_prefix('"') {
+        let body = body
+            .strip_suffix('"')
+            .ok_or_else(|| "unterminated string".to_string())?;
+        return unescape(body).map(TomlValue::String);
+    }
+    if let Some(body) = raw.strip_prefix('\'') {
+        let body = body
+            .strip_suffix('\'')
+            .ok_or_else(|| "unterminated literal string".to_string())?;
+        return Ok(TomlValue::String(body.to_string()));
+    }
+    if raw.starts_with('[') || raw.starts_with('{') {
+        return Err("arrays and inline tables are not supported".to_string());
+    }
+
+    match raw {
+        "true" => return Ok(TomlValue::Bool(true)),
+        "false" => return Ok(TomlValue::Bool(false)),
+        _ => {}
+    }
+
+    let number = raw.replace('_', "");
+    if let Ok(i) = number.parse::<i64>() {
+        return Ok(TomlValue::Integer(i));
+    }
+    if number.contains(['.', 'e', 'E']) {
+        if let Ok(x) = number.parse::<f64>() {
+            return Ok(TomlValue::Float(x));
+        }
+    }
+
+    Err(format!("invalid value '{raw}' (strings must be quoted)"))
+}
+
+fn unescape(body: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            if c == '"' {
+                return Err("unescaped quote in string".to_string());
+            }
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => return Err(format!("unsupported escape '\\{other}'")),
+            None => return Err("dangling escape".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tables_and_scalars() {
+        let doc = r#"
+            # DatyreDB
+            page_size = 4096
+            buffer_pool_size = "64MiB"   # комментарий
+
+            [checkpoint]
+            max_interval = "5m"
+            dirty_page_soft_limit_pct = 0.6
+            async_checkpoint = false
+        "#;
+        let entries = parse(doc).unwrap();
+        let keys: Vec<_> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "page_size",
+                "buffer_pool_size",
+                "checkpoint.max_interval",
+                "checkpoint.dirty_page_soft_limit_pct",
+                "checkpoint.async_checkpoint",
+            ]
+        );
+        assert_eq!(entries[0].value, TomlValue::Integer(4096));
+        assert_eq!(entries[1].value, TomlValue::String("64MiB".into()));
+        assert_eq!(entries[3].value, TomlValue::Float(0.6));
+        assert_eq!(entries[4].value, TomlValue::Bool(false));
+        assert_eq!(entries[2].line, 7);
+    }
+
+    #[test]
+    fn reports_errors_with_line() {
+        let err = parse("a = 1\na = 2").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(parse("x = bare").is_err());
+        assert!(parse("[checkpoint").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Автоподбор `DatabaseConfig` по ресурсам машины.
+//!
+//! Вход: доступная память, пропускная способность диска на запись и
+//! целевое время восстановления. Каждое решение сопровождается коротким
+//! объяснением, чтобы результат можно было проверить, а не принять на веру.
+
+use super::units::{format_bytes, format_duration};
+use super::{DatabaseConfig, DEFAULT_WAL_SEGMENT_SIZE};
+use std::fmt;
+use std::time::Duration;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Доля RAM под buffer pool (остальное — ОС, page cache, сессии)
+const POOL_RAM_FRACTION: f64 = 0.25;
+
+/// Минимальный buffer pool
+const MIN_POOL: u64 = 16 * MIB;
+
+/// Redo читает WAL и пишет страницы случайно: считаем, что реальная
+/// скорость replay — половина последовательной записи
+const REDO_EFFICIENCY: f64 = 0.5;
+
+/// Сколько должен занимать один batch checkpoint'а
+const BATCH_TARGET: Duration = Duration::from_millis(10);
+
+/// Ресурсы машины и цель по восстановлению
+#[derive(Debug, Clone)]
+pub struct TuningInputs {
+    /// Доступная память (байты)
+    pub available_ram: u64,
+    /// Устойчивая скорость записи на диск (байт/с)
+    pub disk_write_throughput: u64,
+    /// Допустимое время crash recovery
+    pub target_recovery: Duration,
+    /// Размер страницы
+    pub page_size: usize,
+}
+
+impl TuningInputs {
+    pub fn new(available_ram: u64, disk_write_throughput: u64, target_recovery: Duration) -> Self {
+        Self {
+            available_ram,
+            disk_write_throughput,
+            target_recovery,
+            page_size: DatabaseConfig::default().page_size,
+        }
+    }
+}
+
+/// Одно решение тюнера
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub key: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+/// Итог тюнинга: конфиг и объяснение каждого выбранного значения
+#[derive(Debug, Clone)]
+pub struct TuningReport {
+    pub config: DatabaseConfig,
+    pub decisions: Vec<Decision>,
+}
+
+impl fmt::Display for TuningReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.decisions.iter().map(|d| d.key.len()).max().unwrap_or(0);
+        for d in &self.decisions {
+            writeln!(f, "{:<width$} = {:<8} # {}", d.key, d.value, d.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Подбор конфига поверх `base` (пути, async-режим и прочее берутся из него)
+pub fn tune(inputs: &TuningInputs, base: DatabaseConfig) -> TuningReport {
+    let mut config = base;
+    let mut decisions = Vec::new();
+    let page = inputs.page_size.max(1) as u64;
+    let throughput = inputs.disk_write_throughput.max(1) as f64;
+    let recovery_secs = inputs.target_recovery.as_secs_f64().max(1.0);
+    config.page_size = page as usize;
+
+    // Buffer pool
+    let pool = ((inputs.available_ram as f64 * POOL_RAM_FRACTION) as u64).max(MIN_POOL);
+    let pool = pool / page * page;
+    config.buffer_pool_size = pool as usize;
+    decisions.push(Decision {
+        key: "buffer_pool_size",
+        value: format_bytes(pool),
+        reason: format!(
+            "{:.0}% of {} RAM, at least {}",
+            POOL_RAM_FRACTION * 100.0,
+            format_bytes_approx(inputs.available_ram),
+            format_bytes(MIN_POOL)
+        ),
+    });
+
+    // WAL: объём, который успеет проиграться redo за target_recovery
+    let redo_bytes = (throughput * REDO_EFFICIENCY * recovery_secs) as u64;
+    let segment = if redo_bytes >= 4 * DEFAULT_WAL_SEGMENT_SIZE {
+        DEFAULT_WAL_SEGMENT_SIZE
+    } else {
+        prev_power_of_two((redo_bytes / 4).max(MIB))
+    };
+    config.wal_segment_size = segment;
+    let max_wal = (redo_bytes / segment).max(1) * segment;
+    config.checkpoint.max_wal_size = max_wal;
+    decisions.push(Decision {
+        key: "checkpoint.max_wal_size",
+        value: format_bytes(max_wal),
+        reason: format!(
+            "redo replays ~{}/s, so {} of WAL fits in {}",
+            format_bytes_approx((throughput * REDO_EFFICIENCY) as u64),
+            format_bytes_approx(max_wal),
+            format_duration(inputs.target_recovery)
+        ),
+    });
+    decisions.push(Decision {
+        key: "wal_segment_size",
+        value: format_bytes(segment),
+        reason: "at least four segments per max_wal_size, so truncation keeps up".to_string(),
+    });
+
+    // Hard limit: блокирующий checkpoint должен уложиться в половину цели
+    let flushable = throughput * recovery_secs / 2.0;
+    let hard = (flushable / pool as f64).clamp(0.2, 0.9) as f32;
+    let soft = hard * 0.75;
+    config.checkpoint.dirty_page_hard_limit_pct = round2(hard);
+    config.checkpoint.dirty_page_soft_limit_pct = round2(soft);
+    decisions.push(Decision {
+        key: "checkpoint.dirty_page_hard_limit_pct",
+        value: round2(hard).to_string(),
+        reason: format!(
+            "a blocking flush of {} takes at most half the recovery target (clamped to 0.2..0.9)",
+            format_bytes_approx((pool as f64 * f64::from(round2(hard))) as u64)
+        ),
+    });
+    decisions.push(Decision {
+        key: "checkpoint.dirty_page_soft_limit_pct",
+        value: round2(soft).to_string(),
+        reason: "75% of the hard limit, so background flushing starts well before blocking"
+            .to_string(),
+    });
+
+    // Batch: ~10ms записи, степень двойки, не больше пула
+    let batch_pages = (throughput * BATCH_TARGET.as_secs_f64() / page as f64) as u64;
+    let batch = prev_power_of_two(batch_pages.clamp(16, 1024))
+        .min(prev_power_of_two((pool / page).max(1))) as usize;
+    config.checkpoint.checkpoint_batch_size = batch;
+    decisions.push(Decision {
+        key: "checkpoint.checkpoint_batch_size",
+        value: batch.to_string(),
+        reason: format!(
+            "about {} of writes per batch (clamped to 16..1024 pages)",
+            format_duration(BATCH_TARGET)
+        ),
+    });
+
+    // Таймер: страховка при низкой скорости записи, когда WAL-триггер молчит
+    let max_interval = Duration::from_secs(((recovery_secs * 3.0) as u64).clamp(30, 3600));
+    config.checkpoint.max_interval = max_interval;
+    decisions.push(Decision {
+        key: "checkpoint.max_interval",
+        value: format_duration(max_interval),
+        reason: "3x the recovery target (30s..1h); the WAL-size trigger bounds replay under load"
+            .to_string(),
+    });
+
+    // Минимальный интервал: время flush'а soft-limit'а dirty pages
+    let soft_flush = pool as f64 * f64::from(config.checkpoint.dirty_page_soft_limit_pct) / throughput;
+    let min_interval = Duration::from_secs((soft_flush.ceil() as u64).max(1))
+        .min(max_interval / 4)
+        .max(Duration::from_secs(1));
+    config.checkpoint.min_interval = min_interval;
+    decisions.push(Decision {
+        key: "checkpoint.min_interval",
+        value: format_duration(min_interval),
+        reason: "time to flush the soft limit once, so checkpoints cannot overlap into a storm"
+            .to_string(),
+    });
+
+    TuningReport { config, decisions }
+}
+
+/// Доступная память из /proc/meminfo (Linux)
+pub fn detect_available_ram() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo
+        .lines()
+        .find_map(|l| l.strip_prefix("MemAvailable:"))
+        .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+}
+
+fn prev_power_of_two(n: u64) -> u64 {
+    if n == 0 {
+        return 1;
+    }
+    1 << (63 - n.leading_zeros())
+}
+
+fn round2(x: f32) -> f32 {
+    (x * 100.0).round() / 100.0
+}
+
+/// Размер для текста отчёта: округление до целых MiB/GiB
+fn format_bytes_approx(bytes: u64) -> String {
+    if bytes >= 1 << 30 {
+        format!("{:.1}GiB", bytes as f64 / (1u64 << 30) as f64)
+    } else {
+        format!("{}MiB", bytes / MIB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuned_configs_validate() {
+        let cases = [
+            TuningInputs::new(512 * MIB, 50 * MIB, Duration::from_secs(10)),
+            TuningInputs::new(16 << 30, 500 * MIB, Duration::from_secs(60)),
+            TuningInputs::new(256 << 30, 3000 * MIB, Duration::from_secs(300)),
+            TuningInputs::new(64 * MIB, MIB, Duration::from_secs(1)),
+        ];
+        for inputs in cases {
+            let report = tune(&inputs, DatabaseConfig::default());
+            report
+                .config
+                .validate()
+                .unwrap_or_else(|e| panic!("{inputs:?}: {e}\n{report}"));
+            assert_eq!(report.decisions.len(), 8);
+        }
+    }
+
+    #[test]
+    fn faster_disk_allows_more_wal() {
+        let slow = tune(
+            &TuningInputs::new(8 << 30, 100 * MIB, Duration::from_secs(30)),
+            DatabaseConfig::default(),
+        );
+        let fast = tune(
+            &TuningInputs::new(8 << 30, 1000 * MIB, Duration::from_secs(30)),
+            DatabaseConfig::default(),
+        );
+        assert!(fast.config.checkpoint.max_wal_size > slow.config.checkpoint.max_wal_size);
+        assert_eq!(slow.config.buffer_pool_size, 2 << 30);
+        assert!(slow.to_string().contains("checkpoint.max_wal_size"));
+    }
+}
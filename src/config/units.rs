@@ -0,0 +1,304 @@
+//! Разбор "человеческих" значений: длительностей ("60s", "5m"),
+//! размеров ("1GiB", "64 MiB") и моментов времени ("2024-05-01 14:30:00").
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Единицы длительности в порядке убывания (для форматирования)
+const DURATION_UNITS: &[(&str, u64)] = &[
+    ("h", 3_600_000_000),
+    ("m", 60_000_000),
+    ("s", 1_000_000),
+    ("ms", 1_000),
+    ("us", 1),
+];
+
+/// Двоичные единицы размера в порядке убывания (для форматирования)
+const BINARY_UNITS: &[(&str, u64)] = &[
+    ("TiB", 1 << 40),
+    ("GiB", 1 << 30),
+    ("MiB", 1 << 20),
+    ("KiB", 1 << 10),
+];
+
+/// Разбор длительности: "60s", "5m", "250ms", "1h30m".
+/// Число без единицы трактуется как секунды (как в C++ `std::chrono::seconds`).
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let s = input.trim();
+    if s.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total_us: u64 = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!("invalid duration '{input}': expected a number"));
+        }
+        let value: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("invalid duration '{input}': number too large"))?;
+        rest = rest[digits..].trim_start();
+
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = rest[..unit_len].trim();
+        rest = &rest[unit_len..];
+
+        let multiplier = match unit {
+            "h" => 3_600_000_000,
+            "m" | "min" => 60_000_000,
+            "s" | "sec" => 1_000_000,
+            "ms" => 1_000,
+            "us" | "µs" => 1,
+            "" => return Err(format!("invalid duration '{input}': missing unit after {value}")),
+            other => return Err(format!("invalid duration '{input}': unknown unit '{other}'")),
+        };
+        total_us = value
+            .checked_mul(multiplier)
+            .and_then(|v| total_us.checked_add(v))
+            .ok_or_else(|| format!("invalid duration '{input}': overflow"))?;
+    }
+
+    Ok(Duration::from_micros(total_us))
+}
+
+/// Форматирование длительности крупнейшей целой единицей ("45s", "5m", "100us")
+pub fn format_duration(d: Duration) -> String {
+    let us = d.as_micros() as u64;
+    if us == 0 {
+        return "0s".to_string();
+    }
+    for &(unit, size) in DURATION_UNITS {
+        if us.is_multiple_of(size) {
+            return format!("{}{}", us / size, unit);
+        }
+    }
+    format!("{us}us")
+}
+
+/// Разбор размера: "1GiB", "512 MiB", "64KB", "4096".
+/// IEC-единицы (KiB, MiB, ...) — степени 1024, SI-единицы (KB, MB, ...) — степени 1000.
+pub fn parse_bytes(input: &str) -> Result<u64, String> {
+    let s = input.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit() && c != '_').unwrap_or(s.len());
+    if digits == 0 {
+        return Err(format!("invalid size '{input}': expected a number"));
+    }
+    let value: u64 = s[..digits]
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("invalid size '{input}': number too large"))?;
+
+    let multiplier: u64 = match s[digits..].trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        other => return Err(format!("invalid size '{input}': unknown unit '{other}'")),
+    };
+
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("invalid size '{input}': overflow"))
+}
+
+/// Форматирование размера крупнейшей целой двоичной единицей ("1GiB", "4096B")
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes == 0 {
+        return "0B".to_string();
+    }
+    for &(unit, size) in BINARY_UNITS {
+        if bytes.is_multiple_of(size) {
+            return format!("{}{}", bytes / size, unit);
+        }
+    }
+    format!("{bytes}B")
+}
+
+/// Разбор момента времени: `YYYY-MM-DD[( |T)HH:MM[:SS[.ffffff]]][Z|±HH:MM]`
+/// (без смещения — UTC) или `@SECONDS` от UNIX epoch.
+pub fn parse_timestamp(input: &str) -> Result<SystemTime, String> {
+    let s = input.trim();
+    let invalid = || {
+        format!("invalid time '{input}': expected YYYY-MM-DD[ HH:MM[:SS]][Z|+HH:MM] or @SECONDS")
+    };
+    if let Some(secs) = s.strip_prefix('@') {
+        let secs: u64 = secs.parse().map_err(|_| invalid())?;
+        return Ok(UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    let (date, rest) = s.split_at(s.find([' ', 'T']).unwrap_or(s.len()));
+    let date: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = date[..] else {
+        return Err(invalid());
+    };
+    let num = |v: &str, max: u32| v.parse::<u32>().ok().filter(|&n| n <= max).ok_or_else(invalid);
+    let (year, month, day) = (num(year, 9999)?, num(month, 12)?, num(day, 31)?);
+    if month == 0 || day == 0 || day > days_in_month(year, month) || year < 1970 {
+        return Err(invalid());
+    }
+
+    // Смещение зоны отделяется с конца: "Z", "+03:00", "-05:00"
+    let rest = rest.get(1..).unwrap_or("");
+    let (time, offset_secs) = if let Some(time) = rest.strip_suffix('Z') {
+        (time, 0)
+    } else {
+        match rest.rfind(['+', '-']) {
+            Some(at) => {
+                let (hh, mm) = rest[at + 1..].split_once(':').ok_or_else(invalid)?;
+                let secs = i64::from(num(hh, 23)? * 3600 + num(mm, 59)? * 60);
+                (&rest[..at], if rest.as_bytes()[at] == b'+' { secs } else { -secs })
+            }
+            None => (rest, 0),
+        }
+    };
+
+    let mut micros = 0;
+    let mut clock = [0u32; 3];
+    if !time.is_empty() {
+        let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+        let parts: Vec<&str> = hms.split(':').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return Err(invalid());
+        }
+        for (slot, (part, max)) in clock.iter_mut().zip(parts.iter().zip([23, 59, 60])) {
+            *slot = num(part, max)?;
+        }
+        if !frac.is_empty() {
+            if frac.len() > 6 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            micros = format!("{frac:0<6}").parse::<u64>().map_err(|_| invalid())?;
+        }
+    }
+
+    let days = days_from_civil(i64::from(year), month, day);
+    let secs = days * 86_400
+        + i64::from(clock[0] * 3600 + clock[1] * 60 + clock[2])
+        - offset_secs;
+    let secs = u64::try_from(secs).map_err(|_| invalid())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_micros(micros))
+}
+
+/// Форматирование момента времени в UTC: "2024-05-01T14:30:00Z"
+/// (с микросекундами, если они есть)
+pub fn format_timestamp(t: SystemTime) -> String {
+    let since = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let clock = secs % 86_400;
+    let mut out = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        clock / 3600,
+        clock / 60 % 60,
+        clock % 60
+    );
+    if since.subsec_micros() != 0 {
+        out.push_str(&format!(".{:06}", since.subsec_micros()));
+    }
+    out.push('Z');
+    out
+}
+
+/// Дней в месяце с учётом високосных лет
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Дней от 1970-01-01 до даты (пролептический григорианский календарь)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Дата по числу дней от 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("60s").unwrap(), Duration::from_secs(60));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("100us").unwrap(), Duration::from_micros(100));
+        assert_eq!(parse_duration("10").unwrap(), Duration::from_secs(10));
+        assert!(parse_duration("10 parsecs").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_bytes("1GiB").unwrap(), 1 << 30);
+        assert_eq!(parse_bytes("64 MiB").unwrap(), 64 << 20);
+        assert_eq!(parse_bytes("1gb").unwrap(), 1_000_000_000);
+        assert_eq!(parse_bytes("1_048_576").unwrap(), 1 << 20);
+        assert!(parse_bytes("1 XB").is_err());
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        for d in ["45s", "5m", "2h", "250ms", "100us"] {
+            assert_eq!(format_duration(parse_duration(d).unwrap()), d);
+        }
+        for b in ["1GiB", "64MiB", "4KiB", "4097B"] {
+            assert_eq!(format_bytes(parse_bytes(b).unwrap()), b);
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(parse_timestamp("1970-01-01").unwrap(), UNIX_EPOCH);
+        assert_eq!(parse_timestamp("2024-02-29 12:00").unwrap(), at(1_709_208_000));
+        assert_eq!(parse_timestamp("2024-02-29T15:00:00+03:00").unwrap(), at(1_709_208_000));
+        assert_eq!(parse_timestamp("2024-02-29T07:00:00-05:00").unwrap(), at(1_709_208_000));
+        assert_eq!(parse_timestamp("@1709208000").unwrap(), at(1_709_208_000));
+        assert!(parse_timestamp("2024-13-01").is_err());
+        // День сверяется с длиной месяца, а не только с 31
+        for t in ["2024-02-30", "2023-02-29", "2023-04-31", "2100-02-29 00:00"] {
+            assert!(parse_timestamp(t).is_err(), "{t}");
+        }
+        assert_eq!(parse_timestamp("2000-02-29").unwrap(), at(951_782_400));
+        assert_eq!(parse_timestamp("2023-12-31").unwrap(), at(1_703_980_800));
+        assert!(parse_timestamp("yesterday").is_err());
+        assert!(parse_timestamp("2024-02-29 25:00").is_err());
+
+        for t in ["2024-02-29T12:00:00Z", "1999-12-31T23:59:59.000250Z"] {
+            assert_eq!(format_timestamp(parse_timestamp(t).unwrap()), t);
+        }
+    }
+}
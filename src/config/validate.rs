@@ -0,0 +1,318 @@
+//! Проверка инвариантов конфигурации.
+//!
+//! Проверки не останавливаются на первой ошибке: `validate()` собирает
+//! все нарушенные правила, чтобы оператор исправил конфиг за один проход.
+
+use super::{CheckpointConfig, DatabaseConfig, DEFAULT_WAL_SEGMENT_SIZE};
+use crate::wal::RECORD_HEADER_SIZE;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Минимальный размер страницы (заголовок 24 байта + осмысленный payload)
+pub const MIN_PAGE_SIZE: usize = 512;
+
+/// Максимальный размер страницы (`PageHeader::free_space` — uint16_t,
+/// свободное место 64KiB - 24 ещё помещается)
+pub const MAX_PAGE_SIZE: usize = 64 * 1024;
+
+/// Минимальный сегмент WAL: меньше заголовка записи — ротация на каждой записи
+pub const MIN_WAL_SEGMENT_SIZE: u64 = RECORD_HEADER_SIZE as u64;
+
+/// Наибольшая задержка commit'а ради группировки
+pub const MAX_COMMIT_DELAY: Duration = Duration::from_millis(100);
+
+/// Нарушенное правило; `field()` — dotted-путь, как в `datyredb.toml`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigViolation {
+    /// `min_interval > max_interval`
+    IntervalOrder { min_interval: Duration, max_interval: Duration },
+    /// `max_interval == 0` — checkpoint по таймеру на каждой проверке
+    ZeroMaxInterval,
+    /// Доля вне 0..=1
+    RatioOutOfRange { field: &'static str, value: f32 },
+    /// `soft >= hard` — фоновый flush никогда не успеет раньше блокирующего
+    SoftLimitNotBelowHard { soft: f32, hard: f32 },
+    /// `checkpoint_batch_size == 0`
+    ZeroBatchSize,
+    /// `max_wal_size` меньше одного сегмента WAL — checkpoint после каждой ротации
+    WalSizeBelowSegment { max_wal_size: u64, wal_segment_size: u64 },
+    /// Сегмент WAL меньше `MIN_WAL_SEGMENT_SIZE` (в том числе 0)
+    WalSegmentTooSmall { wal_segment_size: u64 },
+    /// Размер страницы не степень двойки
+    PageSizeNotPowerOfTwo { page_size: usize },
+    /// Размер страницы вне [MIN_PAGE_SIZE, MAX_PAGE_SIZE]
+    PageSizeOutOfRange { page_size: usize },
+    /// Buffer pool не вмещает один checkpoint batch
+    PoolSmallerThanBatch { pool_pages: usize, batch_size: usize },
+    /// `wal_max_group_size == 0`
+    ZeroGroupSize,
+    /// `wal_commit_delay > MAX_COMMIT_DELAY` — каждый commit ждёт слишком долго
+    CommitDelayTooLong { delay: Duration },
+    /// Архив WAL совпадает с директорией WAL — удаление сегмента удалит и копию
+    ArchiveIsWalDir { dir: PathBuf },
+}
+
+impl ConfigViolation {
+    /// Путь к полю, нарушившему правило
+    pub fn field(&self) -> &'static str {
+        match self {
+            ConfigViolation::IntervalOrder { .. } => "checkpoint.min_interval",
+            ConfigViolation::ZeroMaxInterval => "checkpoint.max_interval",
+            ConfigViolation::RatioOutOfRange { field, .. } => field,
+            ConfigViolation::SoftLimitNotBelowHard { .. } => {
+                "checkpoint.dirty_page_soft_limit_pct"
+            }
+            ConfigViolation::ZeroBatchSize => "checkpoint.checkpoint_batch_size",
+            ConfigViolation::WalSizeBelowSegment { .. } => "checkpoint.max_wal_size",
+            ConfigViolation::WalSegmentTooSmall { .. } => "wal_segment_size",
+            ConfigViolation::PageSizeNotPowerOfTwo { .. }
+            | ConfigViolation::PageSizeOutOfRange { .. } => "page_size",
+            ConfigViolation::PoolSmallerThanBatch { .. } => "buffer_pool_size",
+            ConfigViolation::ZeroGroupSize => "wal_max_group_size",
+            ConfigViolation::CommitDelayTooLong { .. } => "wal_commit_delay",
+            ConfigViolation::ArchiveIsWalDir { .. } => "wal_archive_dir",
+        }
+    }
+}
+
+impl fmt::Display for ConfigViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.field())?;
+        match self {
+            ConfigViolation::IntervalOrder { min_interval, max_interval } => write!(
+                f,
+                "min_interval ({min_interval:?}) exceeds max_interval ({max_interval:?})"
+            ),
+            ConfigViolation::ZeroMaxInterval => write!(f, "must be greater than zero"),
+            ConfigViolation::RatioOutOfRange { value, .. } => {
+                write!(f, "{value} is outside 0..=1")
+            }
+            ConfigViolation::SoftLimitNotBelowHard { soft, hard } => write!(
+                f,
+                "soft limit {soft} must be below hard limit {hard}"
+            ),
+            ConfigViolation::ZeroBatchSize => write!(f, "must be at least 1 page"),
+            ConfigViolation::WalSizeBelowSegment { max_wal_size, wal_segment_size } => write!(
+                f,
+                "{max_wal_size} bytes is smaller than one WAL segment ({wal_segment_size} bytes)"
+            ),
+            ConfigViolation::WalSegmentTooSmall { wal_segment_size } => write!(
+                f,
+                "{wal_segment_size} bytes is smaller than one WAL record header \
+                 ({MIN_WAL_SEGMENT_SIZE} bytes)"
+            ),
+            ConfigViolation::PageSizeNotPowerOfTwo { page_size } => {
+                write!(f, "{page_size} is not a power of two")
+            }
+            ConfigViolation::PageSizeOutOfRange { page_size } => write!(
+                f,
+                "{page_size} is outside {MIN_PAGE_SIZE}..={MAX_PAGE_SIZE} bytes"
+            ),
+            ConfigViolation::PoolSmallerThanBatch { pool_pages, batch_size } => write!(
+                f,
+                "{pool_pages} pages cannot hold one checkpoint batch of {batch_size} pages"
+            ),
+            ConfigViolation::ZeroGroupSize => write!(f, "must be at least 1 commit"),
+            ConfigViolation::CommitDelayTooLong { delay } => {
+                write!(f, "{delay:?} exceeds the {MAX_COMMIT_DELAY:?} limit")
+            }
+            ConfigViolation::ArchiveIsWalDir { dir } => {
+                write!(f, "{} is the WAL directory itself", dir.display())
+            }
+        }
+    }
+}
+
+/// Все нарушения конфига (всегда непустой список)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationErrors {
+    pub violations: Vec<ConfigViolation>,
+}
+
+impl ValidationErrors {
+    fn into_result(violations: Vec<ConfigViolation>) -> Result<(), ValidationErrors> {
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors { violations })
+        }
+    }
+
+    /// Нарушено ли правило для поля
+    pub fn has_field(&self, field: &str) -> bool {
+        self.violations.iter().any(|v| v.field() == field)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration ({} problem(s))", self.violations.len())?;
+        for v in &self.violations {
+            write!(f, "\n  - {v}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl CheckpointConfig {
+    /// Проверка инвариантов checkpoint'а (при сегменте WAL по умолчанию).
+    /// Пути полей даны относительно `DatabaseConfig` ("checkpoint.*").
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut violations = Vec::new();
+        self.collect_violations(DEFAULT_WAL_SEGMENT_SIZE, &mut violations);
+        ValidationErrors::into_result(violations)
+    }
+
+    fn collect_violations(&self, wal_segment_size: u64, out: &mut Vec<ConfigViolation>) {
+        if self.max_interval.is_zero() {
+            out.push(ConfigViolation::ZeroMaxInterval);
+        }
+        if self.min_interval > self.max_interval {
+            out.push(ConfigViolation::IntervalOrder {
+                min_interval: self.min_interval,
+                max_interval: self.max_interval,
+            });
+        }
+
+        let soft = self.dirty_page_soft_limit_pct;
+        let hard = self.dirty_page_hard_limit_pct;
+        let soft_ok = (0.0..=1.0).contains(&soft);
+        let hard_ok = (0.0..=1.0).contains(&hard);
+        if !soft_ok {
+            out.push(ConfigViolation::RatioOutOfRange {
+                field: "checkpoint.dirty_page_soft_limit_pct",
+                value: soft,
+            });
+        }
+        if !hard_ok {
+            out.push(ConfigViolation::RatioOutOfRange {
+                field: "checkpoint.dirty_page_hard_limit_pct",
+                value: hard,
+            });
+        }
+        if soft_ok && hard_ok && soft >= hard {
+            out.push(ConfigViolation::SoftLimitNotBelowHard { soft, hard });
+        }
+
+        if self.checkpoint_batch_size == 0 {
+            out.push(ConfigViolation::ZeroBatchSize);
+        }
+        if self.max_wal_size < wal_segment_size {
+            out.push(ConfigViolation::WalSizeBelowSegment {
+                max_wal_size: self.max_wal_size,
+                wal_segment_size,
+            });
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Проверка всего конфига, включая перекрёстные правила
+    /// между buffer pool, размером страницы и checkpoint'ом
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut violations = Vec::new();
+
+        if !self.page_size.is_power_of_two() {
+            violations.push(ConfigViolation::PageSizeNotPowerOfTwo { page_size: self.page_size });
+        }
+        if !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&self.page_size) {
+            violations.push(ConfigViolation::PageSizeOutOfRange { page_size: self.page_size });
+        }
+
+        if self.wal_segment_size < MIN_WAL_SEGMENT_SIZE {
+            violations.push(ConfigViolation::WalSegmentTooSmall {
+                wal_segment_size: self.wal_segment_size,
+            });
+        }
+        if self.wal_max_group_size == 0 {
+            violations.push(ConfigViolation::ZeroGroupSize);
+        }
+        if self.wal_commit_delay > MAX_COMMIT_DELAY {
+            violations.push(ConfigViolation::CommitDelayTooLong { delay: self.wal_commit_delay });
+        }
+        if let Some(dir) = self.wal_archive_dir.as_ref().filter(|d| **d == self.wal_dir()) {
+            violations.push(ConfigViolation::ArchiveIsWalDir { dir: dir.clone() });
+        }
+
+        self.checkpoint.collect_violations(self.wal_segment_size, &mut violations);
+
+        let batch_size = self.checkpoint.checkpoint_batch_size;
+        if self.page_size > 0 && batch_size > 0 && self.buffer_pool_pages() < batch_size {
+            violations.push(ConfigViolation::PoolSmallerThanBatch {
+                pool_pages: self.buffer_pool_pages(),
+                batch_size,
+            });
+        }
+
+        ValidationErrors::into_result(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        DatabaseConfig::default().validate().unwrap();
+        CheckpointConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn collects_every_violation() {
+        let mut cfg = DatabaseConfig {
+            page_size: 3000,
+            buffer_pool_size: 3000 * 16,
+            ..DatabaseConfig::default()
+        };
+        cfg.checkpoint.min_interval = Duration::from_secs(120);
+        cfg.checkpoint.dirty_page_soft_limit_pct = 0.95;
+        cfg.checkpoint.dirty_page_hard_limit_pct = 1.5;
+        cfg.checkpoint.max_wal_size = 1024;
+        cfg.wal_max_group_size = 0;
+        cfg.wal_archive_dir = Some(cfg.wal_dir());
+
+        let err = cfg.validate().unwrap_err();
+        let fields: Vec<_> = err.violations.iter().map(ConfigViolation::field).collect();
+        assert_eq!(
+            fields,
+            [
+                "page_size",
+                "wal_max_group_size",
+                "wal_archive_dir",
+                "checkpoint.min_interval",
+                "checkpoint.dirty_page_hard_limit_pct",
+                "checkpoint.max_wal_size",
+                "buffer_pool_size",
+            ]
+        );
+    }
+
+    #[test]
+    fn soft_limit_must_be_below_hard() {
+        let cfg = CheckpointConfig {
+            dirty_page_soft_limit_pct: 0.9,
+            dirty_page_hard_limit_pct: 0.9,
+            checkpoint_batch_size: 0,
+            ..CheckpointConfig::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(err.has_field("checkpoint.dirty_page_soft_limit_pct"));
+        assert!(err.has_field("checkpoint.checkpoint_batch_size"));
+    }
+
+    #[test]
+    fn wal_segment_must_hold_a_record_header() {
+        for wal_segment_size in [0, MIN_WAL_SEGMENT_SIZE - 1] {
+            let cfg = DatabaseConfig { wal_segment_size, ..DatabaseConfig::default() };
+            let err = cfg.validate().unwrap_err();
+            assert!(err.has_field("wal_segment_size"), "{err}");
+        }
+        let cfg =
+            DatabaseConfig { wal_segment_size: MIN_WAL_SEGMENT_SIZE, ..DatabaseConfig::default() };
+        assert!(!cfg.validate().is_err_and(|e| e.has_field("wal_segment_size")));
+    }
+}
@@ -0,0 +1,366 @@
+//! Чтение и запись страниц файла данных (аналог `storage::DiskManager`).
+//!
+//! В отличие от `PageFile` (только чтение для офлайн-утилит) файл
+//! открывается на запись, checksum проверяется при чтении и пересчитывается
+//! при записи. Свободные страницы учитывает карта `free_space`: освобождённые
+//! страницы переиспользуются после checkpoint'а, `compact` обрезает файл.
+
+use crate::config::DatabaseConfig;
+use crate::free_space::{self, Compaction, FreeSpaceMap};
+use crate::page::{
+    update_checksum, verify_checksum, PageFlags, PageHeader, PageId, PAGE_HEADER_SIZE,
+};
+use crate::page_io::PageIo;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Файл данных, открытый на чтение и запись
+#[derive(Debug)]
+pub struct DiskManager {
+    file: File,
+    path: PathBuf,
+    page_size: usize,
+    /// Следующий page ID; под mutex, чтобы расширения файла не гонялись.
+    /// Берётся раньше `free_space`.
+    next_page_id: Mutex<PageId>,
+    free_space: Mutex<FreeSpaceMap>,
+    /// Освобождённые страницы до ближайшего CHECKPOINT_BEGIN и после него
+    /// (`seal_freed`): вторые отдаются карте после CHECKPOINT_END
+    freed: Mutex<(Vec<PageId>, Vec<PageId>)>,
+}
+
+impl DiskManager {
+    /// `data_path/data.db` (создаётся вместе с `data_path`, если его нет)
+    pub fn open(config: &DatabaseConfig) -> io::Result<Self> {
+        std::fs::create_dir_all(&config.data_path)?;
+        Self::open_path(config.data_file(), config.page_size)
+    }
+
+    /// Произвольный файл данных
+    pub fn open_path(path: impl AsRef<Path>, page_size: usize) -> io::Result<Self> {
+        if page_size <= PAGE_HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("page size {page_size} does not fit a {PAGE_HEADER_SIZE}-byte header"),
+            ));
+        }
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+        // Неполная последняя страница не считается, как в C++
+        let pages = file.metadata()?.len() / page_size as u64;
+        let mut next_page_id = PageId::try_from(pages).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{} has too many pages", path.display()))
+        })?;
+        let journal = free_space::journal_path(&path);
+        if let Some((pages, moves)) = free_space::read_journal(&journal)? {
+            // Прерванный compaction: переносы идемпотентны, доделываем
+            apply_compaction(&file, page_size, pages, &moves)?;
+            std::fs::remove_file(&journal)?;
+            next_page_id = next_page_id.min(pages);
+        }
+        let free_space = FreeSpaceMap::open(&file, &path, page_size, next_page_id)?;
+        Ok(Self {
+            file,
+            path,
+            page_size,
+            next_page_id: Mutex::new(next_page_id),
+            free_space: Mutex::new(free_space),
+            freed: Mutex::default(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Число выделенных страниц
+    pub fn page_count(&self) -> PageId {
+        *self.next_page_id.lock().unwrap()
+    }
+
+    /// Прочитать страницу в `buf` (`page_size` байт).
+    ///
+    /// Нулевая страница (выделена, но ни разу не записана) возвращается
+    /// с заголовком новой страницы; у остальных проверяется checksum.
+    pub fn read_page(&self, page_id: PageId, buf: &mut [u8]) -> io::Result<()> {
+        let count = self.page_count();
+        if page_id >= count {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("page {page_id} is not allocated ({count} pages in {})", self.path.display()),
+            ));
+        }
+        let buf = &mut buf[..self.page_size];
+        self.file.read_exact_at(buf, self.offset(page_id))?;
+
+        if buf.iter().all(|&b| b == 0) {
+            PageHeader::new(page_id, self.page_size).encode(buf);
+            return Ok(());
+        }
+        if !verify_checksum(buf) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("checksum mismatch for page {page_id} in {}", self.path.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Записать страницу, предварительно обновив её checksum
+    pub fn write_page(&self, page_id: PageId, buf: &mut [u8]) -> io::Result<()> {
+        let buf = &mut buf[..self.page_size];
+        update_checksum(buf);
+        self.file.write_all_at(buf, self.offset(page_id))?;
+        self.free_space.lock().unwrap().record(page_id, &PageHeader::decode(buf));
+        Ok(())
+    }
+
+    /// Записать батч страниц через `io` (checksum'ы обновляются)
+    pub fn write_batch(&self, io: &mut PageIo, pages: &mut [(PageId, Vec<u8>)]) -> io::Result<()> {
+        for (_, buf) in pages.iter_mut() {
+            update_checksum(&mut buf[..self.page_size]);
+        }
+        let writes: Vec<(u64, &[u8])> =
+            pages.iter().map(|(page_id, buf)| (self.offset(*page_id), &buf[..self.page_size])).collect();
+        io.write_all(&self.file, &writes)?;
+        let mut map = self.free_space.lock().unwrap();
+        for (page_id, buf) in pages.iter() {
+            map.record(*page_id, &PageHeader::decode(buf));
+        }
+        Ok(())
+    }
+
+    /// Страница для новых данных: свободная с наименьшим номером или новая
+    /// в конце файла (файл дополняется нулями)
+    pub fn allocate_page(&self) -> io::Result<PageId> {
+        let mut next = self.next_page_id.lock().unwrap();
+        let mut map = self.free_space.lock().unwrap();
+        if let Some(page_id) = map.take_free() {
+            return Ok(page_id);
+        }
+        let page_id = *next;
+        self.file.set_len(self.offset(page_id + 1))?;
+        map.push((self.page_size - PAGE_HEADER_SIZE) as u16);
+        *next += 1;
+        Ok(page_id)
+    }
+
+    /// Дорастить файл до `pages` страниц, не трогая свободные (redo пишет
+    /// страницу по номеру из WAL)
+    pub fn extend_to(&self, pages: PageId) -> io::Result<()> {
+        let mut next = self.next_page_id.lock().unwrap();
+        if pages <= *next {
+            return Ok(());
+        }
+        self.file.set_len(self.offset(pages))?;
+        let mut map = self.free_space.lock().unwrap();
+        for _ in *next..pages {
+            map.push((self.page_size - PAGE_HEADER_SIZE) as u16);
+        }
+        *next = pages;
+        Ok(())
+    }
+
+    /// Освободить страницу. В C++ `deallocate_page` ничего не освобождает.
+    ///
+    /// Освобождение в WAL не пишется, поэтому страница обнуляется и
+    /// достаётся `allocate_page` только после checkpoint'а, начатого позже
+    /// (`seal_freed`, `release_freed`): до него redo может записать в неё
+    /// образы из старых записей WAL поверх новых данных.
+    pub fn deallocate_page(&self, page_id: PageId) -> io::Result<()> {
+        let count = self.page_count();
+        if page_id >= count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("page {page_id} is not allocated ({count} pages in {})", self.path.display()),
+            ));
+        }
+        self.freed.lock().unwrap().0.push(page_id);
+        Ok(())
+    }
+
+    /// Освобождённые страницы, которые ещё ждут checkpoint'а
+    pub fn pending_free_pages(&self) -> usize {
+        let freed = self.freed.lock().unwrap();
+        freed.0.len() + freed.1.len()
+    }
+
+    /// Перед CHECKPOINT_BEGIN: страницы, освобождённые до сих пор, больше
+    /// не нужны redo после этого checkpoint'а
+    pub fn seal_freed(&self) {
+        let mut freed = self.freed.lock().unwrap();
+        let pending = std::mem::take(&mut freed.0);
+        freed.1.extend(pending);
+    }
+
+    /// После CHECKPOINT_END (сброшенного на диск): обнулить страницы,
+    /// отложенные `seal_freed`, и отдать их карте свободного места
+    pub fn release_freed(&self) -> io::Result<()> {
+        let mut freed = self.freed.lock().unwrap();
+        let zero = vec![0u8; self.page_size];
+        while let Some(&page_id) = freed.1.last() {
+            self.file.write_all_at(&zero, self.offset(page_id))?;
+            self.free_space.lock().unwrap().mark_free(page_id);
+            freed.1.pop();
+        }
+        Ok(())
+    }
+
+    /// Карта свободного места
+    pub fn free_space(&self) -> MutexGuard<'_, FreeSpaceMap> {
+        self.free_space.lock().unwrap()
+    }
+
+    /// Перенести занятые страницы в свободные с меньшими номерами и обрезать
+    /// файл. Страницы не должны быть в buffer pool; номера перенесённых
+    /// меняются (см. `free_space`).
+    ///
+    /// Ссылки на перенесённые страницы не переписываются, поэтому на месте
+    /// остаются страницы, на которые может быть ссылка: узлы B+tree,
+    /// overflow-страницы и `roots` — корни, названные каталогом
+    /// (`Catalog::roots`). Файл обрезается не ниже последней из них, а
+    /// переносятся только страницы за этой границей.
+    pub fn compact(&self, roots: &[PageId]) -> io::Result<Compaction> {
+        let mut next = self.next_page_id.lock().unwrap();
+        let mut map = self.free_space.lock().unwrap();
+        let pages_before = *next;
+        let live: Vec<PageId> = (0..pages_before).rev().filter(|&id| !map.is_free(id)).collect();
+        let boundary = live.len() as PageId;
+        let mut pages_after = boundary;
+        for &page_id in live.iter().take_while(|&&id| id >= boundary) {
+            if roots.contains(&page_id) || self.is_linked(page_id)? {
+                pages_after = page_id + 1;
+                break;
+            }
+        }
+        // Самая дальняя живая страница — в самую ближнюю дыру; дыр ниже
+        // границы хватает всем живым страницам за ней
+        let holes = (0..pages_after).filter(|&id| map.is_free(id));
+        let moves: Vec<(PageId, PageId)> =
+            live.iter().take_while(|&&id| id >= pages_after).copied().zip(holes).collect();
+
+        // Карта на диске должна знать о дырах: без журнала копии в них
+        // выглядели бы живыми страницами
+        map.save()?;
+        let journal = free_space::journal_path(&self.path);
+        free_space::write_journal(&journal, pages_after, &moves)?;
+        apply_compaction(&self.file, self.page_size, pages_after, &moves)?;
+        map.compacted(&moves, pages_after);
+        *next = pages_after;
+        map.save()?;
+        std::fs::remove_file(&journal)?;
+        Ok(Compaction { pages_before, pages_after, moves })
+    }
+
+    /// fsync файла данных, затем сохранение карты свободного места
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.free_space.lock().unwrap().save()
+    }
+
+    /// fsync через бэкенд `io`, затем сохранение карты свободного места
+    pub fn sync_with(&self, io: &mut PageIo) -> io::Result<()> {
+        io.sync(&self.file)?;
+        self.free_space.lock().unwrap().save()
+    }
+
+    /// Может ли на страницу ссылаться другая страница (узел B+tree или
+    /// overflow-страница)
+    fn is_linked(&self, page_id: PageId) -> io::Result<bool> {
+        let linked = PageFlags::LEAF | PageFlags::INTERNAL | PageFlags::OVERFLOW;
+        let mut buf = [0u8; PAGE_HEADER_SIZE];
+        self.file.read_exact_at(&mut buf, self.offset(page_id))?;
+        Ok(PageHeader::decode(&buf).flags.bits() & linked.bits() != 0)
+    }
+
+    fn offset(&self, page_id: PageId) -> u64 {
+        u64::from(page_id) * self.page_size as u64
+    }
+}
+
+/// Скопировать страницы по журналу compaction и обрезать файл до `pages`.
+/// Повторный вызов после сбоя безопасен: пока файл не обрезан, оригиналы
+/// на месте.
+fn apply_compaction(
+    file: &File,
+    page_size: usize,
+    pages: PageId,
+    moves: &[(PageId, PageId)],
+) -> io::Result<()> {
+    let offset = |page_id: PageId| u64::from(page_id) * page_size as u64;
+    let len = file.metadata()?.len();
+    let mut buf = vec![0u8; page_size];
+    for &(from, to) in moves.iter().filter(|&&(from, _)| offset(from + 1) <= len) {
+        file.read_exact_at(&mut buf, offset(from))?;
+        if buf.iter().all(|&b| b == 0) {
+            file.write_all_at(&buf, offset(to))?;
+            continue;
+        }
+        if !verify_checksum(&buf) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("page {from} fails its checksum; run datyre-fsck before compacting"),
+            ));
+        }
+        let mut header = PageHeader::decode(&buf);
+        header.page_id = to;
+        header.encode(&mut buf);
+        update_checksum(&mut buf);
+        file.write_all_at(&buf, offset(to))?;
+    }
+    file.sync_data()?;
+    if len > offset(pages) {
+        file.set_len(offset(pages))?;
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page_file::{PageFile, PageStatus};
+
+    #[test]
+    fn allocate_write_read() {
+        let dir = std::env::temp_dir().join(format!("datyredb_disk_manager_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.db");
+
+        let disk = DiskManager::open_path(&path, 512).unwrap();
+        assert_eq!(disk.allocate_page().unwrap(), 0);
+        assert_eq!(disk.allocate_page().unwrap(), 1);
+
+        let mut page = vec![0u8; 512];
+        disk.read_page(1, &mut page).unwrap();
+        assert_eq!(PageHeader::decode(&page), PageHeader::new(1, 512));
+        page[100] = 42;
+        disk.write_page(1, &mut page).unwrap();
+        assert!(disk.read_page(2, &mut page).is_err());
+        drop(disk);
+
+        let file = PageFile::open_path(&path, 512).unwrap();
+        assert_eq!(file.check_page(0).unwrap().status, PageStatus::Unwritten);
+        assert_eq!(file.check_page(1).unwrap().status, PageStatus::Valid);
+
+        let disk = DiskManager::open_path(&path, 512).unwrap();
+        assert_eq!(disk.page_count(), 2);
+        let mut page = vec![0u8; 512];
+        disk.read_page(1, &mut page).unwrap();
+        assert_eq!(page[100], 42);
+
+        // Порча байта данных ловится по checksum
+        disk.file.write_all_at(&[7], 512 + 200).unwrap();
+        let err = disk.read_page(1, &mut page).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+//! Карта свободного места файла данных: `data.fsm` рядом с `data.db`.
+//!
+//! C++ `DiskManager::deallocate_page` только пишет в лог, а `allocate_page`
+//! всегда дописывает страницу в конец, так что файл не уменьшается и место
+//! не переиспользуется. Карта хранит для каждой страницы
+//! `PageHeader::free_space` или отметку «страница свободна», и
+//! `DiskManager` ведёт её сам:
+//!
+//! - `allocate_page` берёт свободную страницу с наименьшим номером и только
+//!   без свободных дописывает новую;
+//! - `deallocate_page` откладывает страницу до checkpoint'а, после
+//!   CHECKPOINT_END она обнуляется на диске и отмечается свободной;
+//! - каждая запись страницы обновляет её свободное место и снимает отметку;
+//! - `find_page_with_space` ищет занятую страницу, где хватит места.
+//!
+//! Карта — подсказка, а не часть WAL: она сохраняется в `DiskManager::sync`
+//! (то есть на каждом checkpoint'е) и при открытии сверяется с файлом.
+//! Страницы, выросшие после сохранения, читаются с диска; свободная по
+//! карте, но не нулевая страница (её записали после сохранения) считается
+//! занятой; повреждённая карта строится заново по заголовкам, и нулевая
+//! страница в ней свободна. Худший исход сбоя — освобождённая страница
+//! остаётся занятой до перестроения карты.
+//!
+//! ```text
+//! offset  size  field
+//!      0     4  magic "DFSM"
+//!      4     4  version (1)
+//!      8     4  page_size
+//!     12     4  page_count
+//!     16     4  CRC32 записей
+//!     20   2*N  free_space каждой страницы, 0xFFFF — страница свободна
+//! ```
+//!
+//! `DiskManager::compact` переносит занятые страницы в свободные с меньшими
+//! номерами и обрезает файл. Переносы сначала пишутся в журнал
+//! `data.compact`; прерванный compaction доделывается при следующем
+//! открытии. Номера перенесённых страниц меняются, а WAL о переносах не
+//! знает, поэтому compaction идёт на остановленной базе после recovery
+//! (`datyre-compact`), и после него нужна новая базовая копия. Ссылки на
+//! перенесённые страницы не переписываются: узлы B+tree, overflow-страницы
+//! и корни из каталога не переносятся, и файл обрезается не ниже последней
+//! из них.
+
+use crate::page::{crc32, verify_checksum, PageHeader, PageId, PAGE_HEADER_SIZE};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"DFSM";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 20;
+
+/// Отметка свободной страницы вместо `free_space`
+const FREE: u16 = u16::MAX;
+
+/// Журнал compaction: число страниц после него и переносы `(откуда, куда)`
+pub(crate) type Journal = (PageId, Vec<(PageId, PageId)>);
+
+/// Карта для файла данных `data_file`
+pub fn map_path(data_file: &Path) -> PathBuf {
+    data_file.with_extension("fsm")
+}
+
+/// Журнал compaction для файла данных `data_file`
+pub fn journal_path(data_file: &Path) -> PathBuf {
+    data_file.with_extension("compact")
+}
+
+// ============================================================================
+// Карта
+// ============================================================================
+
+/// Свободное место по страницам файла данных
+#[derive(Debug)]
+pub struct FreeSpaceMap {
+    path: PathBuf,
+    page_size: usize,
+    /// `free_space` каждой страницы, `FREE` — страница свободна
+    entries: Vec<u16>,
+    /// Свободные страницы по возрастанию номеров
+    free: BTreeSet<PageId>,
+    /// Есть изменения после последнего `save`
+    dirty: bool,
+}
+
+impl FreeSpaceMap {
+    /// Карта файла `data` из `pages` страниц: сохранённая, сверенная с
+    /// файлом, или построенная заново
+    pub(crate) fn open(
+        data: &File,
+        data_path: &Path,
+        page_size: usize,
+        pages: PageId,
+    ) -> io::Result<Self> {
+        let path = map_path(data_path);
+        let saved = match std::fs::read(&path) {
+            Ok(bytes) => decode(&bytes, page_size),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let mut dirty = saved.as_ref().is_none_or(|saved| saved.len() != pages as usize);
+        let saved = saved.unwrap_or_default();
+        let mut map = Self {
+            path,
+            page_size,
+            entries: Vec::with_capacity(pages as usize),
+            free: BTreeSet::new(),
+            dirty: false,
+        };
+        let mut buf = vec![0u8; page_size];
+        for page_id in 0..pages {
+            let entry = match saved.get(page_id as usize) {
+                // Свободную страницу могли записать после сохранения карты
+                Some(&FREE) => {
+                    let entry = read_entry(data, page_id, &mut buf)?;
+                    dirty |= entry != FREE;
+                    entry
+                }
+                Some(&entry) => entry,
+                None => read_entry(data, page_id, &mut buf)?,
+            };
+            map.push(entry);
+        }
+        map.dirty = dirty;
+        Ok(map)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Страниц в карте (столько же, сколько в файле)
+    pub fn page_count(&self) -> PageId {
+        self.entries.len() as PageId
+    }
+
+    /// Свободных страниц
+    pub fn free_pages(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_free(&self, page_id: PageId) -> bool {
+        self.free.contains(&page_id)
+    }
+
+    /// Свободное место занятой страницы; `None` — страница свободна или её нет
+    pub fn free_space(&self, page_id: PageId) -> Option<u16> {
+        self.entries.get(page_id as usize).copied().filter(|&entry| entry != FREE)
+    }
+
+    /// Занятая страница с наименьшим номером, где свободно не меньше
+    /// `needed` байт
+    pub fn find_page_with_space(&self, needed: usize) -> Option<PageId> {
+        self.entries
+            .iter()
+            .position(|&entry| entry != FREE && usize::from(entry) >= needed)
+            .map(|page_id| page_id as PageId)
+    }
+
+    /// Байт свободно в занятых страницах
+    pub fn free_bytes(&self) -> u64 {
+        self.entries.iter().filter(|&&entry| entry != FREE).map(|&entry| u64::from(entry)).sum()
+    }
+
+    /// Сохранить карту, если она менялась (временный файл и rename)
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 2 * self.entries.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.page_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.page_count().to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        let crc = crc32(&bytes[HEADER_SIZE..]);
+        bytes[16..20].copy_from_slice(&crc.to_le_bytes());
+
+        let tmp = self.path.with_extension("fsm.tmp");
+        std::fs::write(&tmp, &bytes)?;
+        File::open(&tmp)?.sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    // ---- Изменения из `DiskManager` ----
+
+    /// Новая страница в конце файла
+    pub(crate) fn push(&mut self, entry: u16) {
+        if entry == FREE {
+            self.free.insert(self.page_count());
+        }
+        self.entries.push(entry);
+        self.dirty = true;
+    }
+
+    /// Занять свободную страницу с наименьшим номером
+    pub(crate) fn take_free(&mut self) -> Option<PageId> {
+        let page_id = self.free.pop_first()?;
+        self.entries[page_id as usize] = (self.page_size - PAGE_HEADER_SIZE) as u16;
+        self.dirty = true;
+        Some(page_id)
+    }
+
+    pub(crate) fn mark_free(&mut self, page_id: PageId) {
+        self.set(page_id, FREE);
+    }
+
+    /// Страница записана с заголовком `header`
+    pub(crate) fn record(&mut self, page_id: PageId, header: &PageHeader) {
+        // free_space == FREE не бывает: страница не больше 64 КБ с заголовком
+        self.set(page_id, header.free_space.min(FREE - 1));
+    }
+
+    /// Итог compaction: страницы перенесены, файл обрезан до `pages`
+    pub(crate) fn compacted(&mut self, moves: &[(PageId, PageId)], pages: PageId) {
+        for &(from, to) in moves {
+            let entry = self.entries[from as usize];
+            self.set(to, entry);
+        }
+        self.entries.truncate(pages as usize);
+        self.free = self.free.range(..pages).copied().collect();
+        self.dirty = true;
+    }
+
+    fn set(&mut self, page_id: PageId, entry: u16) {
+        let slot = &mut self.entries[page_id as usize];
+        if *slot == entry {
+            return;
+        }
+        *slot = entry;
+        if entry == FREE {
+            self.free.insert(page_id);
+        } else {
+            self.free.remove(&page_id);
+        }
+        self.dirty = true;
+    }
+}
+
+/// Записи сохранённой карты; `None` — карта не подходит к файлу
+fn decode(bytes: &[u8], page_size: usize) -> Option<Vec<u16>> {
+    let field = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    if bytes.len() < HEADER_SIZE
+        || &bytes[..4] != MAGIC
+        || field(4) != VERSION
+        || field(8) as usize != page_size
+        || bytes.len() != HEADER_SIZE + 2 * field(12) as usize
+        || field(16) != crc32(&bytes[HEADER_SIZE..])
+    {
+        return None;
+    }
+    Some(bytes[HEADER_SIZE..].chunks(2).map(|e| u16::from_le_bytes([e[0], e[1]])).collect())
+}
+
+/// Запись карты по странице на диске
+fn read_entry(data: &File, page_id: PageId, buf: &mut [u8]) -> io::Result<u16> {
+    data.read_exact_at(buf, u64::from(page_id) * buf.len() as u64)?;
+    if buf.iter().all(|&b| b == 0) {
+        return Ok(FREE);
+    }
+    // Повреждённую страницу не отдаём и места в ней не ищем
+    if !verify_checksum(buf) {
+        return Ok(0);
+    }
+    Ok(PageHeader::decode(buf).free_space.min(FREE - 1))
+}
+
+// ============================================================================
+// Compaction
+// ============================================================================
+
+/// Итог `DiskManager::compact`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compaction {
+    pub pages_before: PageId,
+    pub pages_after: PageId,
+    /// Перенесённые страницы: `(старый номер, новый номер)`
+    pub moves: Vec<(PageId, PageId)>,
+}
+
+impl fmt::Display for Compaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} pages -> {} pages, {} moved",
+            self.pages_before,
+            self.pages_after,
+            self.moves.len()
+        )
+    }
+}
+
+/// Записать журнал compaction (временный файл и rename)
+pub(crate) fn write_journal(
+    path: &Path,
+    pages: PageId,
+    moves: &[(PageId, PageId)],
+) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(12 + 8 * moves.len());
+    bytes.extend_from_slice(&pages.to_le_bytes());
+    bytes.extend_from_slice(&(moves.len() as u32).to_le_bytes());
+    for &(from, to) in moves {
+        bytes.extend_from_slice(&from.to_le_bytes());
+        bytes.extend_from_slice(&to.to_le_bytes());
+    }
+    bytes.extend_from_slice(&crc32(&bytes).to_le_bytes());
+
+    let tmp = path.with_extension("compact.tmp");
+    std::fs::write(&tmp, &bytes)?;
+    File::open(&tmp)?.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Журнал прерванного compaction, если он есть
+pub(crate) fn read_journal(path: &Path) -> io::Result<Option<Journal>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let invalid = || {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: damaged journal", path.display()))
+    };
+    let word = |at: usize| bytes.get(at..at + 4).map(|w| u32::from_le_bytes(w.try_into().unwrap()));
+    let (Some(pages), Some(count)) = (word(0), word(4)) else {
+        return Err(invalid());
+    };
+    let body = 8 + 8 * count as usize;
+    if bytes.len() != body + 4 || word(body) != Some(crc32(&bytes[..body])) {
+        return Err(invalid());
+    }
+    let moves = (0..count as usize)
+        .map(|i| (word(8 + 8 * i).unwrap(), word(12 + 8 * i).unwrap()))
+        .collect();
+    Ok(Some((pages, moves)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk_manager::DiskManager;
+
+    const PAGE: usize = 512;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("datyredb_free_space_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Записать страницу с `value` в теле и `free_space`
+    fn write(disk: &DiskManager, page_id: PageId, value: u8, free_space: u16) {
+        let mut page = vec![0u8; PAGE];
+        let mut header = PageHeader::new(page_id, PAGE);
+        header.free_space = free_space;
+        header.encode(&mut page);
+        page[PAGE_HEADER_SIZE..].fill(value);
+        disk.write_page(page_id, &mut page).unwrap();
+    }
+
+    /// Освободить страницы, как если бы после этого прошёл checkpoint
+    fn free(disk: &DiskManager, pages: &[PageId]) {
+        for &page_id in pages {
+            disk.deallocate_page(page_id).unwrap();
+        }
+        disk.seal_freed();
+        disk.release_freed().unwrap();
+    }
+
+    #[test]
+    fn reuses_freed_pages_across_reopen() {
+        let dir = temp_dir("reuse");
+        let path = dir.join("data.db");
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        for page_id in 0..6 {
+            assert_eq!(disk.allocate_page().unwrap(), page_id);
+            write(&disk, page_id, page_id as u8 + 1, 100 * page_id as u16);
+        }
+        // До checkpoint'а освобождённые страницы не переиспользуются
+        disk.deallocate_page(4).unwrap();
+        disk.deallocate_page(1).unwrap();
+        assert_eq!((disk.free_space().free_pages(), disk.pending_free_pages()), (0, 2));
+        disk.seal_freed();
+        disk.deallocate_page(5).unwrap();
+        disk.release_freed().unwrap();
+        assert_eq!(disk.pending_free_pages(), 1);
+        {
+            let map = disk.free_space();
+            assert_eq!(map.free_pages(), 2);
+            assert_eq!(map.free_space(1), None);
+            assert_eq!(map.free_space(3), Some(300));
+            assert_eq!(map.find_page_with_space(250), Some(3));
+            assert_eq!(map.find_page_with_space(600), None);
+        }
+        disk.sync().unwrap();
+        drop(disk);
+
+        // Карта сохранена: первой отдаётся свободная страница с меньшим номером
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        assert_eq!(disk.free_space().free_pages(), 2);
+        assert_eq!(disk.allocate_page().unwrap(), 1);
+        let mut page = vec![0u8; PAGE];
+        disk.read_page(1, &mut page).unwrap();
+        assert_eq!(PageHeader::decode(&page), PageHeader::new(1, PAGE));
+        write(&disk, 1, 9, 50);
+        drop(disk);
+
+        // Без sync карта на диске устарела: страница 1 записана после
+        // сохранения и не должна считаться свободной
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        assert_eq!(disk.free_space().free_space(1), Some(50));
+        assert_eq!(disk.allocate_page().unwrap(), 4);
+        assert_eq!(disk.allocate_page().unwrap(), 6);
+        drop(disk);
+
+        // Повреждённая карта строится заново по заголовкам
+        let map = map_path(&path);
+        let mut bytes = std::fs::read(&map).unwrap();
+        bytes[HEADER_SIZE] ^= 1;
+        std::fs::write(&map, bytes).unwrap();
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        let map = disk.free_space();
+        assert_eq!(map.page_count(), 7);
+        assert_eq!(map.free_space(3), Some(300));
+        assert!(map.is_free(4) && map.is_free(6));
+        drop(map);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compaction_moves_pages_down() {
+        let dir = temp_dir("compact");
+        let path = dir.join("data.db");
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        for page_id in 0..10 {
+            disk.allocate_page().unwrap();
+            write(&disk, page_id, page_id as u8 + 1, 10);
+        }
+        free(&disk, &[1, 2, 5, 8]);
+        let compaction = disk.compact(&[]).unwrap();
+        assert_eq!(compaction.pages_before, 10);
+        assert_eq!(compaction.pages_after, 6);
+        assert_eq!(compaction.moves, vec![(9, 1), (7, 2), (6, 5)]);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 6 * PAGE as u64);
+        assert!(!journal_path(&path).exists());
+
+        let mut page = vec![0u8; PAGE];
+        for (page_id, value) in [(0, 1), (1, 10), (2, 8), (3, 4), (4, 5), (5, 7)] {
+            disk.read_page(page_id, &mut page).unwrap();
+            assert_eq!(PageHeader::decode(&page).page_id, page_id);
+            assert_eq!(page[PAGE_HEADER_SIZE], value, "page {page_id}");
+        }
+        assert_eq!(disk.free_space().free_pages(), 0);
+        assert_eq!(disk.allocate_page().unwrap(), 6);
+        drop(disk);
+
+        // Прерванный после журнала compaction доделывается при открытии
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        free(&disk, &[0]);
+        disk.sync().unwrap();
+        drop(disk);
+        write_journal(&journal_path(&path), 5, &[(5, 0)]).unwrap();
+        let disk = DiskManager::open_path(&path, PAGE).unwrap();
+        assert!(!journal_path(&path).exists());
+        assert_eq!(disk.page_count(), 5);
+        disk.read_page(0, &mut page).unwrap();
+        assert_eq!((PageHeader::decode(&page).page_id, page[PAGE_HEADER_SIZE]), (0, 7));
+        assert_eq!(disk.free_space().free_pages(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,499 @@
+//! Офлайн-проверка остановленной базы: файл данных + WAL.
+//!
+//! Проверки:
+//! - checksum каждой страницы, оборванные и неполные страницы;
+//! - `page_id` в заголовке совпадает с позицией страницы в файле;
+//! - `page_lsn` не выходит за последний валидный LSN WAL (иначе нарушено
+//!   правило WAL: страница на диске новее журнала); без директории WAL
+//!   (WAL убран в архив или удалён) сравнивать не с чем, и проверка
+//!   пропускается;
+//! - оборванный хвост и повреждение середины WAL;
+//! - CHECKPOINT_BEGIN без CHECKPOINT_END и END без своего BEGIN.
+//!
+//! Итог — худшая `Severity` среди находок.
+
+use crate::cli::json_string;
+use crate::config::DatabaseConfig;
+use crate::page::{Lsn, PageId, INVALID_LSN};
+use crate::page_file::{PageFile, PageStatus};
+use crate::wal::{CheckpointTracker, RecordFormat, WalReader};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Серьёзность находки
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Не ошибка, но стоит знать
+    Info,
+    /// Восстановление (recovery, обрезка хвоста) приведёт базу в порядок
+    Repairable,
+    /// Данные потеряны или противоречат друг другу
+    Corrupt,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Repairable => "repairable",
+            Severity::Corrupt => "corrupt",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Вид находки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    PageChecksum,
+    TornPage,
+    TruncatedPage,
+    PageIdMismatch,
+    PageLsnAheadOfWal,
+    WalTornTail,
+    WalMidLogCorruption,
+    WalMissing,
+    WalUntyped,
+    CheckpointIncomplete,
+    CheckpointWithoutBegin,
+}
+
+impl FindingKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            FindingKind::PageChecksum => "page_checksum",
+            FindingKind::TornPage => "torn_page",
+            FindingKind::TruncatedPage => "truncated_page",
+            FindingKind::PageIdMismatch => "page_id_mismatch",
+            FindingKind::PageLsnAheadOfWal => "page_lsn_ahead_of_wal",
+            FindingKind::WalTornTail => "wal_torn_tail",
+            FindingKind::WalMidLogCorruption => "wal_mid_log_corruption",
+            FindingKind::WalMissing => "wal_missing",
+            FindingKind::WalUntyped => "wal_untyped",
+            FindingKind::CheckpointIncomplete => "checkpoint_incomplete",
+            FindingKind::CheckpointWithoutBegin => "checkpoint_without_begin",
+        }
+    }
+}
+
+/// Одна находка
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub kind: FindingKind,
+    pub page: Option<PageId>,
+    /// Смещение в файле данных или в сегменте WAL
+    pub offset: Option<u64>,
+    pub lsn: Option<Lsn>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.kind.name(), self.message)
+    }
+}
+
+/// Итог проверки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Clean,
+    Repairable,
+    Corrupt,
+}
+
+impl Status {
+    /// Код выхода `datyre-fsck`
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Status::Clean => 0,
+            Status::Repairable => 1,
+            Status::Corrupt => 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Clean => "clean",
+            Status::Repairable => "repairable",
+            Status::Corrupt => "corrupt",
+        }
+    }
+}
+
+/// Отчёт проверки
+#[derive(Debug, Clone)]
+pub struct FsckReport {
+    pub data_file: PathBuf,
+    pub wal_dir: PathBuf,
+    pub page_size: usize,
+    pub pages_checked: u64,
+    pub pages_unwritten: u64,
+    pub max_page_lsn: Lsn,
+    pub wal_segments: usize,
+    pub wal_records: u64,
+    /// LSN первой прочитанной записи WAL (`INVALID_LSN`, если WAL пуст)
+    pub wal_first_lsn: Lsn,
+    pub wal_last_lsn: Lsn,
+    pub findings: Vec<Finding>,
+}
+
+impl FsckReport {
+    pub fn status(&self) -> Status {
+        match self.findings.iter().map(|f| f.severity).max() {
+            Some(Severity::Corrupt) => Status::Corrupt,
+            Some(Severity::Repairable) => Status::Repairable,
+            _ => Status::Clean,
+        }
+    }
+
+    fn push(&mut self, severity: Severity, kind: FindingKind, message: String) -> &mut Finding {
+        self.findings.push(Finding { severity, kind, page: None, offset: None, lsn: None, message });
+        self.findings.last_mut().unwrap()
+    }
+
+    /// Отчёт одним JSON-объектом
+    pub fn to_json(&self) -> String {
+        let opt = |v: Option<u64>| v.map_or("null".to_string(), |v| v.to_string());
+        let findings: Vec<String> = self
+            .findings
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"severity\":{},\"kind\":{},\"page\":{},\"offset\":{},\"lsn\":{},\"message\":{}}}",
+                    json_string(f.severity.name()),
+                    json_string(f.kind.name()),
+                    opt(f.page.map(u64::from)),
+                    opt(f.offset),
+                    opt(f.lsn),
+                    json_string(&f.message)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"status\":{},\"exit_code\":{},\"data_file\":{},\"wal_dir\":{},\"page_size\":{},\
+             \"pages_checked\":{},\"pages_unwritten\":{},\"max_page_lsn\":{},\"wal_segments\":{},\
+             \"wal_records\":{},\"wal_first_lsn\":{},\"wal_last_lsn\":{},\"findings\":[{}]}}\n",
+            json_string(self.status().name()),
+            self.status().exit_code(),
+            json_string(&self.data_file.display().to_string()),
+            json_string(&self.wal_dir.display().to_string()),
+            self.page_size,
+            self.pages_checked,
+            self.pages_unwritten,
+            self.max_page_lsn,
+            self.wal_segments,
+            self.wal_records,
+            self.wal_first_lsn,
+            self.wal_last_lsn,
+            findings.join(",")
+        )
+    }
+}
+
+impl fmt::Display for FsckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "data file: {} ({} pages, {} unwritten)",
+            self.data_file.display(),
+            self.pages_checked,
+            self.pages_unwritten
+        )?;
+        writeln!(
+            f,
+            "wal:       {} ({} segments, {} records, LSN {}..{})",
+            self.wal_dir.display(),
+            self.wal_segments,
+            self.wal_records,
+            self.wal_first_lsn,
+            self.wal_last_lsn
+        )?;
+        for finding in &self.findings {
+            writeln!(f, "  {finding}")?;
+        }
+        write!(f, "status: {}", self.status().name())
+    }
+}
+
+/// Проверка базы из конфига (`data_file()`, `wal_dir()`, `page_size`)
+pub fn check(config: &DatabaseConfig) -> io::Result<FsckReport> {
+    check_paths(&config.data_file(), &config.wal_dir(), config.page_size)
+}
+
+/// Проверка по явным путям
+pub fn check_paths(data_file: &Path, wal_dir: &Path, page_size: usize) -> io::Result<FsckReport> {
+    let pages = PageFile::open_path(data_file, page_size)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", data_file.display())))?;
+    let mut report = FsckReport {
+        data_file: data_file.to_path_buf(),
+        wal_dir: wal_dir.to_path_buf(),
+        page_size,
+        pages_checked: 0,
+        pages_unwritten: 0,
+        max_page_lsn: INVALID_LSN,
+        wal_segments: 0,
+        wal_records: 0,
+        wal_first_lsn: INVALID_LSN,
+        wal_last_lsn: INVALID_LSN,
+        findings: Vec::new(),
+    };
+
+    let wal_found = check_wal(&mut report, wal_dir)?;
+    check_pages(&mut report, &pages, wal_found)?;
+    Ok(report)
+}
+
+/// Проверка WAL; `false` — директории WAL нет
+fn check_wal(report: &mut FsckReport, wal_dir: &Path) -> io::Result<bool> {
+    let reader = match WalReader::open_dir(wal_dir) {
+        Ok(reader) => reader,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            report.push(
+                Severity::Info,
+                FindingKind::WalMissing,
+                format!(
+                    "WAL directory {} does not exist; page_lsn is not checked",
+                    wal_dir.display()
+                ),
+            );
+            return Ok(false);
+        }
+        Err(e) => return Err(e),
+    };
+    report.wal_segments = reader.segments().len();
+
+    let mut checkpoints = CheckpointTracker::default();
+    let mut untyped = 0u64;
+    let mut records = reader.records();
+    for record in records.by_ref() {
+        let record = record?;
+        if report.wal_first_lsn == INVALID_LSN {
+            report.wal_first_lsn = record.record.lsn;
+        }
+        if record.format == RecordFormat::Framed {
+            untyped += 1;
+        }
+        checkpoints.observe(&record);
+    }
+    let end = records.end();
+    report.wal_records = end.records;
+    report.wal_last_lsn = end.last_valid_lsn;
+
+    if untyped > 0 {
+        report.push(
+            Severity::Info,
+            FindingKind::WalUntyped,
+            format!(
+                "{untyped} records use the checksummed framed format; their types are not \
+                 LogRecordType, checkpoint pairing skips them"
+            ),
+        );
+    }
+
+    if let Some(torn) = end.torn {
+        let (severity, kind) = if torn.unread_segments == 0 {
+            (Severity::Repairable, FindingKind::WalTornTail)
+        } else {
+            (Severity::Corrupt, FindingKind::WalMidLogCorruption)
+        };
+        let finding = report.push(severity, kind, torn.to_string());
+        finding.offset = Some(torn.offset);
+        finding.lsn = Some(end.last_valid_lsn);
+    }
+
+    let first = report.wal_first_lsn;
+    for cp in checkpoints.finish() {
+        if !cp.begin_found && cp.begin_lsn >= first {
+            let end_lsn = cp.end_lsn.unwrap_or(INVALID_LSN);
+            let finding = report.push(
+                Severity::Repairable,
+                FindingKind::CheckpointWithoutBegin,
+                format!(
+                    "CHECKPOINT_END at LSN {end_lsn} refers to BEGIN {} which is not in the WAL",
+                    cp.begin_lsn
+                ),
+            );
+            finding.lsn = Some(end_lsn);
+        } else if cp.begin_found && cp.end_lsn.is_none() {
+            let finding = report.push(
+                Severity::Repairable,
+                FindingKind::CheckpointIncomplete,
+                format!("CHECKPOINT_BEGIN at LSN {} has no CHECKPOINT_END", cp.begin_lsn),
+            );
+            finding.lsn = Some(cp.begin_lsn);
+        }
+    }
+    Ok(true)
+}
+
+fn check_pages(report: &mut FsckReport, pages: &PageFile, wal_found: bool) -> io::Result<()> {
+    let wal_last = report.wal_last_lsn;
+    for page in pages.pages() {
+        let page = page?;
+        report.pages_checked += 1;
+
+        let (severity, kind) = match &page.status {
+            PageStatus::Valid => (None, None),
+            PageStatus::Unwritten => {
+                report.pages_unwritten += 1;
+                continue;
+            }
+            PageStatus::Torn { .. } => (Some(Severity::Corrupt), Some(FindingKind::TornPage)),
+            PageStatus::Corrupt { .. } => (Some(Severity::Corrupt), Some(FindingKind::PageChecksum)),
+            // Обрыв при расширении файла (`allocate_page`): страница не успела
+            // появиться, файл обрезается до границы страницы
+            PageStatus::Truncated { .. } => (Some(Severity::Repairable), Some(FindingKind::TruncatedPage)),
+        };
+        if let (Some(severity), Some(kind)) = (severity, kind) {
+            let finding = report.push(severity, kind, page.to_string());
+            finding.page = Some(page.page_no);
+            finding.offset = Some(page.offset);
+        }
+
+        let Some(header) = page.header else { continue };
+        if page.status != PageStatus::Valid {
+            continue;
+        }
+        report.max_page_lsn = report.max_page_lsn.max(header.page_lsn);
+
+        if header.page_id != page.page_no {
+            let finding = report.push(
+                Severity::Corrupt,
+                FindingKind::PageIdMismatch,
+                format!(
+                    "page at position {} @ {:#x} has page_id {} in its header",
+                    page.page_no, page.offset, header.page_id
+                ),
+            );
+            finding.page = Some(page.page_no);
+            finding.offset = Some(page.offset);
+        }
+
+        if wal_found && header.page_lsn > wal_last {
+            let finding = report.push(
+                Severity::Corrupt,
+                FindingKind::PageLsnAheadOfWal,
+                format!(
+                    "page {} has page_lsn {} but the WAL ends at LSN {}",
+                    page.page_no, header.page_lsn, wal_last
+                ),
+            );
+            finding.page = Some(page.page_no);
+            finding.offset = Some(page.offset);
+            finding.lsn = Some(header.page_lsn);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::{update_checksum, PageHeader};
+    use crate::wal::{segment_file_name, LogRecord, LogRecordType};
+
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("datyredb_fsck_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(dir.join("wal")).unwrap();
+            Self(dir)
+        }
+
+        fn config(&self) -> DatabaseConfig {
+            DatabaseConfig { data_path: self.0.clone(), ..DatabaseConfig::default() }
+        }
+
+        fn write_pages(&self, lsns: &[Lsn]) {
+            let mut data = vec![0u8; 4096 * lsns.len()];
+            for (i, page) in data.chunks_mut(4096).enumerate() {
+                let mut header = PageHeader::new(i as PageId, 4096);
+                header.page_lsn = lsns[i];
+                header.encode(page);
+                update_checksum(page);
+            }
+            std::fs::write(self.0.join("data.db"), data).unwrap();
+        }
+
+        fn write_wal(&self, types: &[LogRecordType]) {
+            let mut buf = Vec::new();
+            for (i, &t) in types.iter().enumerate() {
+                let lsn = i as Lsn + 1;
+                let prev_lsn = if t == LogRecordType::CheckpointEnd { lsn - 1 } else { 0 };
+                LogRecord { lsn, prev_lsn, ..LogRecord::new(t, 0) }.serialize(&mut buf);
+            }
+            std::fs::write(self.0.join("wal").join(segment_file_name(0)), buf).unwrap();
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn clean_database() {
+        use LogRecordType::*;
+        let db = TempDb::new("clean");
+        db.write_pages(&[1, 3, 0]);
+        db.write_wal(&[Insert, Insert, Update, CheckpointBegin, CheckpointEnd]);
+
+        let report = check(&db.config()).unwrap();
+        assert_eq!(report.status(), Status::Clean, "{report}");
+        assert_eq!(report.pages_checked, 3);
+        assert_eq!(report.wal_last_lsn, 5);
+        assert!(report.to_json().contains("\"status\":\"clean\""));
+    }
+
+    #[test]
+    fn repairable_and_corrupt() {
+        use LogRecordType::*;
+        let db = TempDb::new("damage");
+        db.write_pages(&[1, 2]);
+        db.write_wal(&[Insert, Insert, CheckpointBegin]);
+        let report = check(&db.config()).unwrap();
+        assert_eq!(report.status(), Status::Repairable, "{report}");
+        assert_eq!(report.findings[0].kind, FindingKind::CheckpointIncomplete);
+
+        // Страница 1 записана на место страницы 0 и опережает WAL
+        let mut data = std::fs::read(db.0.join("data.db")).unwrap();
+        let mut header = PageHeader::decode(&data[4096..]);
+        header.page_lsn = 9;
+        header.encode(&mut data[..4096]);
+        update_checksum(&mut data[..4096]);
+        std::fs::write(db.0.join("data.db"), &data).unwrap();
+
+        let report = check(&db.config()).unwrap();
+        assert_eq!(report.status(), Status::Corrupt);
+        let kinds: Vec<_> = report.findings.iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            [FindingKind::CheckpointIncomplete, FindingKind::PageIdMismatch, FindingKind::PageLsnAheadOfWal]
+        );
+        assert_eq!(report.findings[1].page, Some(0));
+        assert_eq!(report.status().exit_code(), 2);
+    }
+
+    #[test]
+    fn missing_wal_skips_the_lsn_check() {
+        let db = TempDb::new("no_wal");
+        db.write_pages(&[1, 7, 0]);
+        std::fs::remove_dir_all(db.0.join("wal")).unwrap();
+
+        let report = check(&db.config()).unwrap();
+        assert_eq!(report.status(), Status::Clean, "{report}");
+        let kinds: Vec<_> = report.findings.iter().map(|f| f.kind).collect();
+        assert_eq!(kinds, [FindingKind::WalMissing]);
+        assert_eq!(report.max_page_lsn, 7);
+    }
+}
@@ -0,0 +1,543 @@
+//! Миграция текстового `datyre.wal` (legacy `DatabaseEngine`) в новую
+//! директорию данных с бинарным WAL.
+//!
+//! Legacy-файл — это строки `CREATE TABLE t (a, b)` и
+//! `INSERT INTO t VALUES (1, x)`, которые `DatabaseEngine::persist`
+//! дописывает после успешного выполнения. Разбор повторяет
+//! `internal_create` / `internal_insert` (значения — строки между запятыми,
+//! без кавычек), но в отличие от `recover()` каждая отвергнутая строка
+//! попадает в отчёт с номером и причиной.
+//!
+//! Результат миграции:
+//! - `data.db` — страницы с записями в порядке исходного файла
+//!   (формат записи описан у `encode_entry`);
+//! - `wal/` — каждая принятая строка становится транзакцией
+//!   `TXN_BEGIN → INSERT → TXN_COMMIT`; `INSERT` несёт `page_id`, `offset`,
+//!   `length` и ровно те байты, которые записаны в страницу; в конце —
+//!   завершённый checkpoint.
+
+use crate::config::DatabaseConfig;
+use crate::page::{update_checksum, Lsn, PageHeader, PageId, TxnId, PAGE_HEADER_SIZE};
+use crate::wal::writer::WalWriter;
+use crate::wal::{LogRecord, LogRecordType};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Имя legacy-файла (`WAL_FILE` в `engine.hpp`)
+pub const LEGACY_WAL_FILE: &str = "datyre.wal";
+
+/// Тип записи в странице: определение таблицы
+pub const ENTRY_TABLE: u8 = 1;
+/// Тип записи в странице: строка таблицы
+pub const ENTRY_ROW: u8 = 2;
+
+// ============================================================================
+// Разбор
+// ============================================================================
+
+/// Принятая строка legacy-файла
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LegacyStatement {
+    CreateTable { name: String, columns: Vec<String> },
+    Insert { table: String, values: Vec<String> },
+}
+
+impl fmt::Display for LegacyStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LegacyStatement::CreateTable { name, columns } => {
+                write!(f, "CREATE TABLE {name} ({})", columns.join(", "))
+            }
+            LegacyStatement::Insert { table, values } => {
+                write!(f, "INSERT INTO {table} VALUES ({})", values.join(", "))
+            }
+        }
+    }
+}
+
+/// Строка, которая не будет перенесена
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedLine {
+    /// Номер строки с 1
+    pub line: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+impl fmt::Display for RejectedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {} ({})", self.line, self.reason, self.text)
+    }
+}
+
+/// Разобранный legacy-файл
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegacyLog {
+    /// Принятые строки с номерами
+    pub statements: Vec<(usize, LegacyStatement)>,
+    pub rejected: Vec<RejectedLine>,
+}
+
+/// Разбор и проверка legacy-файла: строки, которые legacy-движок отверг бы
+/// при `recover()` (нет таблицы, повторный CREATE, число значений не
+/// совпадает с числом колонок), тоже попадают в `rejected`
+pub fn parse(bytes: &[u8]) -> LegacyLog {
+    let mut log = LegacyLog::default();
+    let mut tables: BTreeMap<String, usize> = BTreeMap::new();
+
+    for (i, raw) in bytes.split(|&b| b == b'\n').enumerate() {
+        let line = i + 1;
+        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        let text = match std::str::from_utf8(raw) {
+            Ok(text) => text,
+            Err(e) => {
+                log.rejected.push(RejectedLine {
+                    line,
+                    text: String::from_utf8_lossy(raw).into_owned(),
+                    reason: format!("invalid UTF-8 at byte {}", e.valid_up_to()),
+                });
+                continue;
+            }
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        let reject = |reason: String| RejectedLine { line, text: text.to_string(), reason };
+
+        let statement = match parse_line(text) {
+            Ok(statement) => statement,
+            Err(reason) => {
+                log.rejected.push(reject(reason));
+                continue;
+            }
+        };
+        let check = match &statement {
+            LegacyStatement::CreateTable { name, columns } => {
+                if tables.contains_key(name) {
+                    Err(format!("table '{name}' already exists"))
+                } else {
+                    tables.insert(name.clone(), columns.len());
+                    Ok(())
+                }
+            }
+            LegacyStatement::Insert { table, values } => match tables.get(table) {
+                None => Err(format!("table '{table}' not found")),
+                Some(&n) if n != values.len() => Err(format!(
+                    "table '{table}' has {n} columns, got {} values",
+                    values.len()
+                )),
+                Some(_) => Ok(()),
+            },
+        };
+        match check {
+            Ok(()) => log.statements.push((line, statement)),
+            Err(reason) => log.rejected.push(reject(reason)),
+        }
+    }
+    log
+}
+
+/// Разбор одной строки (без проверки существования таблиц)
+pub fn parse_line(line: &str) -> Result<LegacyStatement, String> {
+    let line = line.trim();
+    let line = line.strip_suffix(';').unwrap_or(line);
+    let command = line.split_whitespace().next().unwrap_or("").to_ascii_uppercase();
+
+    let (Some(open), Some(close)) = (line.find('('), line.find(')')) else {
+        return Err(match command.as_str() {
+            "CREATE" | "INSERT" => "syntax error: missing parentheses".to_string(),
+            _ => format!("unsupported statement '{command}'"),
+        });
+    };
+    if close < open {
+        return Err("syntax error: ')' before '('".to_string());
+    }
+    let words: Vec<&str> = line[..open].split_whitespace().collect();
+    let items = split_list(&line[open + 1..close]);
+    if !line[close + 1..].trim().is_empty() {
+        return Err(format!("unexpected text after ')': '{}'", line[close + 1..].trim()));
+    }
+
+    match command.as_str() {
+        "CREATE" => match words.as_slice() {
+            [_, kw, name] if kw.eq_ignore_ascii_case("TABLE") => {
+                if items.is_empty() {
+                    return Err(format!("table '{name}' has no columns"));
+                }
+                Ok(LegacyStatement::CreateTable { name: name.to_string(), columns: items })
+            }
+            _ => Err("syntax error: expected CREATE TABLE <name> (<columns>)".to_string()),
+        },
+        "INSERT" => match words.as_slice() {
+            [_, into, table, values]
+                if into.eq_ignore_ascii_case("INTO") && values.eq_ignore_ascii_case("VALUES") =>
+            {
+                Ok(LegacyStatement::Insert { table: table.to_string(), values: items })
+            }
+            _ => Err("syntax error: expected INSERT INTO <table> VALUES (<values>)".to_string()),
+        },
+        _ => Err(format!("unsupported statement '{command}'")),
+    }
+}
+
+/// `DatabaseEngine::split(str, ',')`: элементы обрезаются, пустые выбрасываются
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// ============================================================================
+// Миграция
+// ============================================================================
+
+/// Ошибка миграции
+#[derive(Debug)]
+pub enum MigrateError {
+    Io { path: PathBuf, source: io::Error },
+    /// В целевой директории уже есть файлы
+    TargetNotEmpty(PathBuf),
+    /// `strict`: в исходном файле есть отвергнутые строки
+    Rejected(Vec<RejectedLine>),
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            MigrateError::TargetNotEmpty(path) => {
+                write!(f, "target data directory {} is not empty", path.display())
+            }
+            MigrateError::Rejected(lines) => {
+                write!(f, "{} legacy lines cannot be migrated:", lines.len())?;
+                for line in lines {
+                    write!(f, "\n  {line}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrateError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Параметры миграции
+#[derive(Debug, Clone, Default)]
+pub struct MigrateOptions {
+    /// Не мигрировать, если хоть одна строка отвергнута
+    pub strict: bool,
+    /// Только разобрать и посчитать, ничего не создавая
+    pub dry_run: bool,
+}
+
+/// Итог миграции
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport {
+    pub tables: usize,
+    pub rows: usize,
+    pub pages: usize,
+    pub wal_records: u64,
+    pub last_lsn: Lsn,
+    pub rejected: Vec<RejectedLine>,
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "migrated {} tables and {} rows into {} pages, {} WAL records (last LSN {})",
+            self.tables, self.rows, self.pages, self.wal_records, self.last_lsn
+        )?;
+        if !self.rejected.is_empty() {
+            write!(f, "\n{} lines were not migrated:", self.rejected.len())?;
+            for line in &self.rejected {
+                write!(f, "\n  {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Перенести `legacy` в новую директорию `target.data_path`
+pub fn migrate(
+    legacy: &Path,
+    target: &DatabaseConfig,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, MigrateError> {
+    let io_err = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| MigrateError::Io { path, source }
+    };
+    let bytes = std::fs::read(legacy).map_err(io_err(legacy))?;
+    let log = parse(&bytes);
+    if options.strict && !log.rejected.is_empty() {
+        return Err(MigrateError::Rejected(log.rejected));
+    }
+
+    let mut pages = PageBuilder::new(target.page_size);
+    let mut report = MigrationReport { rejected: log.rejected, ..MigrationReport::default() };
+    let mut table_ids: BTreeMap<&str, u32> = BTreeMap::new();
+    let mut placed = Vec::new();
+    for (line, statement) in &log.statements {
+        let entry = match statement {
+            LegacyStatement::CreateTable { name, columns } => {
+                let id = table_ids.len() as u32 + 1;
+                encode_entry(ENTRY_TABLE, id, std::iter::once(name).chain(columns))
+            }
+            LegacyStatement::Insert { table, values } => match table_ids.get(table.as_str()) {
+                Some(&id) => encode_entry(ENTRY_ROW, id, values),
+                // Таблица не перенесена: строки без неё не переносятся
+                None => {
+                    report.rejected.push(RejectedLine {
+                        line: *line,
+                        text: statement.to_string(),
+                        reason: format!("table {table} was not migrated"),
+                    });
+                    continue;
+                }
+            },
+        };
+        match pages.place(&entry) {
+            Some((page_id, offset)) => {
+                match statement {
+                    LegacyStatement::CreateTable { name, .. } => {
+                        table_ids.insert(name, table_ids.len() as u32 + 1);
+                        report.tables += 1;
+                    }
+                    LegacyStatement::Insert { .. } => report.rows += 1,
+                }
+                placed.push((page_id, offset, entry));
+            }
+            None => report.rejected.push(RejectedLine {
+                line: *line,
+                text: statement.to_string(),
+                reason: format!(
+                    "{} bytes do not fit into a {}-byte page",
+                    entry.len(),
+                    target.page_size
+                ),
+            }),
+        }
+    }
+    report.rejected.sort_by_key(|r| r.line);
+    report.pages = pages.pages.len();
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    let data_path = &target.data_path;
+    match std::fs::read_dir(data_path) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(MigrateError::TargetNotEmpty(data_path.clone()));
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(io_err(data_path)(e)),
+    }
+
+    let wal_dir = target.wal_dir();
+    let mut wal = WalWriter::create(&wal_dir, target.wal_segment_size).map_err(io_err(&wal_dir))?;
+    let mut append = |record: LogRecord| wal.append(&record).map_err(io_err(&wal_dir));
+    for (i, (page_id, offset, entry)) in placed.iter().enumerate() {
+        let txn_id = i as TxnId + 1;
+        let begin = append(LogRecord::new(LogRecordType::TxnBegin, txn_id))?;
+        let insert = append(LogRecord {
+            page_id: *page_id,
+            offset: *offset,
+            length: entry.len() as u16,
+            prev_lsn: begin,
+            data: entry.clone(),
+            ..LogRecord::new(LogRecordType::Insert, txn_id)
+        })?;
+        append(LogRecord { prev_lsn: insert, ..LogRecord::new(LogRecordType::TxnCommit, txn_id) })?;
+        pages.set_lsn(*page_id, insert);
+    }
+    let begin = wal.checkpoint_begin().map_err(io_err(&wal_dir))?;
+    report.last_lsn = wal.checkpoint_end(begin).map_err(io_err(&wal_dir))?;
+    report.wal_records = report.last_lsn;
+
+    // Страницы пишутся после WAL: правило WAL соблюдается и для миграции
+    let data_file = target.data_file();
+    pages.write(&data_file).map_err(io_err(&data_file))?;
+    Ok(report)
+}
+
+/// Запись в странице: `kind u8 | id u32 | count u16 | (len u16 | bytes)*`.
+/// Для таблицы первая строка — имя, остальные — колонки; для строки
+/// таблицы `id` — номер таблицы по порядку CREATE (с 1).
+pub fn encode_entry<'a>(kind: u8, id: u32, strings: impl IntoIterator<Item = &'a String>) -> Vec<u8> {
+    let strings: Vec<&String> = strings.into_iter().collect();
+    let mut out = vec![kind];
+    out.extend_from_slice(&id.to_le_bytes());
+    out.extend_from_slice(&(strings.len() as u16).to_le_bytes());
+    for s in strings {
+        out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+    out
+}
+
+/// Раскладка записей по страницам подряд
+struct PageBuilder {
+    page_size: usize,
+    pages: Vec<Vec<u8>>,
+}
+
+impl PageBuilder {
+    fn new(page_size: usize) -> Self {
+        Self { page_size, pages: Vec::new() }
+    }
+
+    /// Положить запись; `None` — не влезает даже в пустую страницу
+    fn place(&mut self, entry: &[u8]) -> Option<(PageId, u16)> {
+        if entry.len() > self.page_size - PAGE_HEADER_SIZE {
+            return None;
+        }
+        let fits = self
+            .pages
+            .last()
+            .is_some_and(|p| usize::from(PageHeader::decode(p).free_space) >= entry.len());
+        if !fits {
+            let mut page = vec![0u8; self.page_size];
+            PageHeader::new(self.pages.len() as PageId, self.page_size).encode(&mut page);
+            self.pages.push(page);
+        }
+        let page_id = self.pages.len() - 1;
+        let page = &mut self.pages[page_id];
+        let mut header = PageHeader::decode(page);
+        let offset = self.page_size - usize::from(header.free_space);
+        page[offset..offset + entry.len()].copy_from_slice(entry);
+        header.free_space -= entry.len() as u16;
+        header.encode(page);
+        Some((page_id as PageId, offset as u16))
+    }
+
+    fn set_lsn(&mut self, page_id: PageId, lsn: Lsn) {
+        let page = &mut self.pages[page_id as usize];
+        let mut header = PageHeader::decode(page);
+        header.page_lsn = lsn;
+        header.encode(page);
+    }
+
+    fn write(mut self, path: &Path) -> io::Result<()> {
+        let mut data = Vec::with_capacity(self.pages.len() * self.page_size);
+        for page in &mut self.pages {
+            update_checksum(page);
+            data.extend_from_slice(page);
+        }
+        std::fs::write(path, &data)?;
+        std::fs::File::open(path)?.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsck;
+    use crate::wal::WalReader;
+
+    #[test]
+    fn parses_like_the_legacy_engine() {
+        let log = parse(
+            b"CREATE TABLE users (id, name)\n\
+              INSERT INTO users VALUES (1, admin)\n\
+              \n\
+              insert into users values ( 2 ,  bob );\r\n\
+              INSERT INTO users VALUES (3)\n\
+              INSERT INTO ghosts VALUES (1, x)\n\
+              CREATE TABLE users (a)\n\
+              SELECT * FROM users\n\
+              CREATE INDEX i (id)\n\
+              INSERT INTO users VALUES 4, dave\n\
+              \xff\xfe\n",
+        );
+        assert_eq!(log.statements.len(), 3);
+        assert_eq!(
+            log.statements[2],
+            (4, LegacyStatement::Insert { table: "users".into(), values: vec!["2".into(), "bob".into()] })
+        );
+        let rejected: Vec<usize> = log.rejected.iter().map(|r| r.line).collect();
+        assert_eq!(rejected, [5, 6, 7, 8, 9, 10, 11]);
+        assert!(log.rejected[0].reason.contains("2 columns, got 1"));
+        assert!(log.rejected[3].reason.contains("unsupported"));
+    }
+
+    #[test]
+    fn migrates_into_a_clean_directory() {
+        let dir = std::env::temp_dir().join(format!("datyredb_legacy_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join(LEGACY_WAL_FILE);
+        let mut text = String::from("CREATE TABLE users (id, name)\nbroken line\n");
+        for i in 0..300 {
+            text.push_str(&format!("INSERT INTO users VALUES ({i}, user{i})\n"));
+        }
+        std::fs::write(&legacy, text).unwrap();
+
+        let target = DatabaseConfig { data_path: dir.join("data"), ..DatabaseConfig::default() };
+        assert!(matches!(
+            migrate(&legacy, &target, &MigrateOptions { strict: true, ..MigrateOptions::default() }),
+            Err(MigrateError::Rejected(_))
+        ));
+
+        let report = migrate(&legacy, &target, &MigrateOptions::default()).unwrap();
+        assert_eq!((report.tables, report.rows), (1, 300));
+        assert!(report.pages > 1);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].line, 2);
+
+        let scan = WalReader::open(&target).unwrap().scan().unwrap();
+        assert_eq!(scan.records.len() as u64, report.wal_records);
+        assert_eq!(scan.chains().len(), 301);
+        assert!(scan.chains().iter().all(|c| c.outcome == Some(LogRecordType::TxnCommit)));
+
+        let check = fsck::check(&target).unwrap();
+        assert_eq!(check.status(), fsck::Status::Clean, "{check}");
+
+        assert!(matches!(
+            migrate(&legacy, &target, &MigrateOptions::default()),
+            Err(MigrateError::TargetNotEmpty(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rows_of_a_rejected_table_are_not_migrated() {
+        let dir = std::env::temp_dir().join(format!("datyredb_legacy_wide_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join(LEGACY_WAL_FILE);
+        let columns: Vec<String> = (0..500).map(|i| format!("column_{i}")).collect();
+        let text = format!(
+            "CREATE TABLE wide ({})\n\
+             INSERT INTO wide VALUES ({})\n\
+             CREATE TABLE users (id)\n\
+             INSERT INTO users VALUES (1)\n",
+            columns.join(", "),
+            vec!["x"; 500].join(", "),
+        );
+        std::fs::write(&legacy, text).unwrap();
+
+        let target = DatabaseConfig { data_path: dir.join("data"), ..DatabaseConfig::default() };
+        let options = MigrateOptions { dry_run: true, ..MigrateOptions::default() };
+        let report = migrate(&legacy, &target, &options).unwrap();
+        assert_eq!((report.tables, report.rows), (1, 1));
+        let rejected: Vec<usize> = report.rejected.iter().map(|r| r.line).collect();
+        assert_eq!(rejected, [1, 2]);
+        assert!(report.rejected[0].reason.contains("do not fit"));
+        assert!(report.rejected[1].reason.contains("was not migrated"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
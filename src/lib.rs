@@ -0,0 +1,25 @@
+//! DatyreDB — Rust-часть проекта: конфигурация и инструменты
+//! для работы с форматами хранения движка.
+
+pub mod backup;
+pub mod btree;
+pub mod buffer_pool;
+pub mod catalog;
+pub mod checkpoint;
+pub mod cli;
+pub mod config;
+pub mod disk_manager;
+pub mod free_space;
+pub mod fsck;
+pub mod legacy_wal;
+pub mod overflow;
+pub mod page;
+pub mod page_file;
+pub mod page_io;
+pub mod recovery;
+pub mod restore;
+pub mod schema;
+pub mod slotted_page;
+pub mod sql;
+pub mod value;
+pub mod wal;
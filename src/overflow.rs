@@ -0,0 +1,386 @@
+//! Строки переменной длины в slotted pages и цепочки overflow-страниц для
+//! строк, которые в страницу не помещаются.
+//!
+//! Кортеж в слоте начинается с байта вида:
+//!
+//! ```text
+//! 0x00  data...                       строка целиком
+//! 0x01  len u32, first_page u32       строка в цепочке overflow-страниц
+//! ```
+//! Строка хранится в странице, если кортеж не больше `inline_limit` —
+//! четверти тела страницы, так что в странице помещается хотя бы четыре
+//! строки. Длинные строки уходят в цепочку страниц с флагом
+//! `PageFlags::OVERFLOW`, тело которых:
+//!
+//! ```text
+//! offset  size  field
+//!     24     4  next_page (INVALID_PAGE_ID — последняя)
+//!     28     2  chunk_len
+//!     30     …  данные
+//! ```
+//! У overflow-страниц `free_space` = 0, чтобы карта свободного места не
+//! предлагала их под кортежи. Страницы цепочки берутся `BufferPool::new_page`
+//! (сначала освобождённые) и возвращаются `delete_page` (переиспользуются
+//! после checkpoint'а).
+//!
+//! Журналирование изменений — забота вызывающего, как и выбор страницы:
+//! `FreeSpaceMap::find_page_with_space(row_space(..))`.
+
+use crate::buffer_pool::{BufferPool, BufferPoolError};
+use crate::page::{PageFlags, PageHeader, PageId, INVALID_PAGE_ID, PAGE_HEADER_SIZE};
+use crate::slotted_page::{SlotId, SlottedPage, SlottedPageError, SLOTTED_HEADER_SIZE, SLOT_SIZE};
+use std::fmt;
+
+const INLINE: u8 = 0;
+const OVERFLOW: u8 = 1;
+
+/// Размер ссылки на цепочку в слоте
+const POINTER_SIZE: usize = 9;
+
+/// Начало данных в overflow-странице
+const CHUNK_OFFSET: usize = PAGE_HEADER_SIZE + 6;
+
+/// Самый большой кортеж, который хранится в странице, а не в цепочке
+pub fn inline_limit(page_size: usize) -> usize {
+    (page_size - SLOTTED_HEADER_SIZE) / 4 - SLOT_SIZE
+}
+
+/// Сколько места в странице займёт строка длиной `len` (кортеж и слот)
+pub fn row_space(page_size: usize, len: usize) -> usize {
+    let inline = len + 1;
+    SLOT_SIZE + if inline <= inline_limit(page_size) { inline } else { POINTER_SIZE }
+}
+
+/// Ошибка чтения или записи строки
+#[derive(Debug)]
+pub enum RowError {
+    Page(SlottedPageError),
+    Pool(BufferPoolError),
+    /// Цепочка overflow-страниц оборвана или не той длины
+    BrokenChain { page_id: PageId, message: String },
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowError::Page(e) => e.fmt(f),
+            RowError::Pool(e) => e.fmt(f),
+            RowError::BrokenChain { page_id, message } => {
+                write!(f, "overflow page {page_id}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RowError::Page(e) => Some(e),
+            RowError::Pool(e) => Some(e),
+            RowError::BrokenChain { .. } => None,
+        }
+    }
+}
+
+impl From<SlottedPageError> for RowError {
+    fn from(e: SlottedPageError) -> Self {
+        RowError::Page(e)
+    }
+}
+
+impl From<BufferPoolError> for RowError {
+    fn from(e: BufferPoolError) -> Self {
+        RowError::Pool(e)
+    }
+}
+
+// ============================================================================
+// Строки
+// ============================================================================
+
+/// Вставить строку в страницу `page_id`
+pub fn insert_row(pool: &BufferPool, page_id: PageId, row: &[u8]) -> Result<SlotId, RowError> {
+    let tuple = encode(pool, row)?;
+    let page = pool.fetch_page(page_id)?;
+    let inserted = SlottedPage::open(&mut page.write()[..]).and_then(|mut p| p.insert(&tuple));
+    drop(page);
+    inserted.or_else(|e| {
+        release(pool, &tuple)?;
+        Err(e.into())
+    })
+}
+
+/// Строка слота; `None` — слот свободен
+pub fn read_row(
+    pool: &BufferPool,
+    page_id: PageId,
+    slot: SlotId,
+) -> Result<Option<Vec<u8>>, RowError> {
+    let page = pool.fetch_page(page_id)?;
+    let tuple = match SlottedPage::open(&page.read()[..])?.get(slot) {
+        Some(tuple) => tuple.to_vec(),
+        None => return Ok(None),
+    };
+    drop(page);
+    decode(pool, &tuple).map(Some)
+}
+
+/// Заменить строку слота. Если новая не помещается в страницу, возвращается
+/// `SlottedPageError::PageFull`, и старая остаётся на месте.
+pub fn update_row(
+    pool: &BufferPool,
+    page_id: PageId,
+    slot: SlotId,
+    row: &[u8],
+) -> Result<(), RowError> {
+    let tuple = encode(pool, row)?;
+    let page = pool.fetch_page(page_id)?;
+    let replaced = SlottedPage::open(&mut page.write()[..]).and_then(|mut slotted| {
+        let old = slotted.get(slot).map(<[u8]>::to_vec);
+        slotted.update(slot, &tuple).map(|()| old.unwrap_or_default())
+    });
+    drop(page);
+    match replaced {
+        Ok(old) => release(pool, &old),
+        Err(e) => {
+            release(pool, &tuple)?;
+            Err(e.into())
+        }
+    }
+}
+
+/// Удалить строку слота вместе с её цепочкой
+pub fn delete_row(pool: &BufferPool, page_id: PageId, slot: SlotId) -> Result<(), RowError> {
+    let page = pool.fetch_page(page_id)?;
+    let old = {
+        let mut data = page.write();
+        let mut slotted = SlottedPage::open(&mut data[..])?;
+        let old = slotted.get(slot).map(<[u8]>::to_vec);
+        slotted.delete(slot)?;
+        old.unwrap_or_default()
+    };
+    drop(page);
+    release(pool, &old)
+}
+
+/// Кортеж для строки: сама строка или ссылка на новую цепочку
+fn encode(pool: &BufferPool, row: &[u8]) -> Result<Vec<u8>, RowError> {
+    if row.len() < inline_limit(pool.disk().page_size()) {
+        let mut tuple = Vec::with_capacity(row.len() + 1);
+        tuple.push(INLINE);
+        tuple.extend_from_slice(row);
+        return Ok(tuple);
+    }
+    let first = write_chain(pool, row)?;
+    let mut tuple = Vec::with_capacity(POINTER_SIZE);
+    tuple.push(OVERFLOW);
+    tuple.extend_from_slice(&(row.len() as u32).to_le_bytes());
+    tuple.extend_from_slice(&first.to_le_bytes());
+    Ok(tuple)
+}
+
+fn decode(pool: &BufferPool, tuple: &[u8]) -> Result<Vec<u8>, RowError> {
+    match tuple.split_first() {
+        Some((&INLINE, row)) => Ok(row.to_vec()),
+        Some((&OVERFLOW, pointer)) if pointer.len() == POINTER_SIZE - 1 => {
+            let len = u32::from_le_bytes(pointer[0..4].try_into().unwrap());
+            let first = PageId::from_le_bytes(pointer[4..8].try_into().unwrap());
+            read_chain(pool, first, len as usize)
+        }
+        _ => Err(RowError::Page(SlottedPageError::Corrupt {
+            page_id: INVALID_PAGE_ID,
+            message: format!("tuple of {} bytes is neither a row nor a chain pointer", tuple.len()),
+        })),
+    }
+}
+
+/// Освободить цепочку, на которую ссылается кортеж
+fn release(pool: &BufferPool, tuple: &[u8]) -> Result<(), RowError> {
+    if tuple.len() == POINTER_SIZE && tuple[0] == OVERFLOW {
+        free_chain(pool, PageId::from_le_bytes(tuple[5..9].try_into().unwrap()))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Цепочки
+// ============================================================================
+
+/// Записать `data` в новую цепочку overflow-страниц; возвращает первую
+pub fn write_chain(pool: &BufferPool, data: &[u8]) -> Result<PageId, RowError> {
+    let chunk = pool.disk().page_size() - CHUNK_OFFSET;
+    let pages = data.len().div_ceil(chunk).max(1);
+    let mut ids = Vec::with_capacity(pages);
+    for _ in 0..pages {
+        match pool.new_page() {
+            Ok(page) => ids.push(page.page_id()),
+            Err(e) => {
+                for &page_id in &ids {
+                    pool.delete_page(page_id)?;
+                }
+                return Err(e.into());
+            }
+        }
+    }
+    for (i, part) in data.chunks(chunk).chain(data.is_empty().then_some(&[][..])).enumerate() {
+        let page = pool.fetch_page(ids[i])?;
+        let mut buf = page.write();
+        let mut header = PageHeader::decode(&buf);
+        header.flags |= PageFlags::OVERFLOW;
+        header.free_space = 0;
+        header.encode(&mut buf);
+        let next = ids.get(i + 1).copied().unwrap_or(INVALID_PAGE_ID);
+        buf[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + 4].copy_from_slice(&next.to_le_bytes());
+        let len = part.len() as u16;
+        buf[PAGE_HEADER_SIZE + 4..CHUNK_OFFSET].copy_from_slice(&len.to_le_bytes());
+        buf[CHUNK_OFFSET..CHUNK_OFFSET + part.len()].copy_from_slice(part);
+    }
+    Ok(ids[0])
+}
+
+/// Прочитать `len` байт цепочки, начинающейся с `first`
+pub fn read_chain(pool: &BufferPool, first: PageId, len: usize) -> Result<Vec<u8>, RowError> {
+    let mut data = Vec::with_capacity(len);
+    let mut page_id = first;
+    loop {
+        let (next, chunk) = chain_page(pool, page_id)?;
+        data.extend_from_slice(&chunk);
+        if data.len() > len {
+            return Err(broken(page_id, format!("chain is longer than {len} bytes")));
+        }
+        if next == INVALID_PAGE_ID {
+            break;
+        }
+        page_id = next;
+    }
+    if data.len() != len {
+        return Err(broken(page_id, format!("chain ends after {} of {len} bytes", data.len())));
+    }
+    Ok(data)
+}
+
+/// Освободить все страницы цепочки; возвращает их число
+pub fn free_chain(pool: &BufferPool, first: PageId) -> Result<usize, RowError> {
+    let mut page_id = first;
+    let mut freed = 0;
+    while page_id != INVALID_PAGE_ID {
+        let (next, _) = chain_page(pool, page_id)?;
+        pool.delete_page(page_id)?;
+        freed += 1;
+        page_id = next;
+    }
+    Ok(freed)
+}
+
+/// Следующая страница и данные overflow-страницы
+fn chain_page(pool: &BufferPool, page_id: PageId) -> Result<(PageId, Vec<u8>), RowError> {
+    let page = pool.fetch_page(page_id)?;
+    let buf = page.read();
+    if !PageHeader::decode(&buf).flags.contains(PageFlags::OVERFLOW) {
+        return Err(broken(page_id, "not an overflow page".into()));
+    }
+    let next = PageId::from_le_bytes(buf[PAGE_HEADER_SIZE..][..4].try_into().unwrap());
+    let len = u16::from_le_bytes(buf[PAGE_HEADER_SIZE + 4..][..2].try_into().unwrap());
+    let len = usize::from(len);
+    if CHUNK_OFFSET + len > buf.len() {
+        return Err(broken(page_id, format!("chunk of {len} bytes overruns the page")));
+    }
+    Ok((next, buf[CHUNK_OFFSET..CHUNK_OFFSET + len].to_vec()))
+}
+
+fn broken(page_id: PageId, message: String) -> RowError {
+    RowError::BrokenChain { page_id, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    fn open_pool(name: &str) -> (DatabaseConfig, BufferPool) {
+        let dir =
+            std::env::temp_dir().join(format!("datyredb_overflow_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = DatabaseConfig {
+            data_path: dir,
+            page_size: 512,
+            buffer_pool_size: 8 * 512,
+            ..DatabaseConfig::default()
+        };
+        let pool = BufferPool::new(&config).unwrap();
+        (config, pool)
+    }
+
+    /// Освобождённые страницы отдаются карте, как после checkpoint'а
+    fn checkpointed(pool: &BufferPool) {
+        pool.disk().seal_freed();
+        pool.disk().release_freed().unwrap();
+    }
+
+    fn row(len: usize, seed: u8) -> Vec<u8> {
+        (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    #[test]
+    fn rows_spill_into_overflow_chains() {
+        let (config, pool) = open_pool("spill");
+        let page_id = pool.new_page().unwrap().page_id();
+        let small = row(40, 1);
+        let large = row(3000, 2);
+        let a = insert_row(&pool, page_id, &small).unwrap();
+        let b = insert_row(&pool, page_id, &large).unwrap();
+        assert_eq!(read_row(&pool, page_id, a).unwrap(), Some(small.clone()));
+        assert_eq!(read_row(&pool, page_id, b).unwrap(), Some(large.clone()));
+
+        // 3000 байт по 482 в странице — 7 страниц цепочки
+        let chain_pages = pool.disk().page_count() - 1;
+        assert_eq!(chain_pages, 7);
+        let header = pool.fetch_page(page_id + 1).unwrap().header();
+        assert!(header.flags.contains(PageFlags::OVERFLOW));
+        assert_eq!(header.free_space, 0);
+
+        // Строка переживает вытеснение и повторное открытие
+        drop(pool);
+        let pool = BufferPool::new(&config).unwrap();
+        assert_eq!(read_row(&pool, page_id, b).unwrap(), Some(large));
+
+        // Замена длинной строки короткой освобождает цепочку, и её страницы
+        // достаются следующей цепочке
+        update_row(&pool, page_id, b, &small).unwrap();
+        assert_eq!(pool.disk().pending_free_pages(), 7);
+        checkpointed(&pool);
+        assert_eq!(pool.disk().free_space().free_pages(), 7);
+        let c = insert_row(&pool, page_id, &row(1000, 3)).unwrap();
+        assert_eq!(pool.disk().free_space().free_pages(), 4);
+        assert_eq!(pool.disk().page_count(), 8);
+        delete_row(&pool, page_id, c).unwrap();
+        assert_eq!(read_row(&pool, page_id, c).unwrap(), None);
+        checkpointed(&pool);
+        assert_eq!(pool.disk().free_space().free_pages(), 7);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn full_page_keeps_the_old_row() {
+        let (config, pool) = open_pool("full");
+        let page_id = pool.new_page().unwrap().page_id();
+        let limit = inline_limit(512);
+        let mut slots = Vec::new();
+        while let Ok(slot) = insert_row(&pool, page_id, &row(limit - 2, slots.len() as u8)) {
+            slots.push(slot);
+        }
+        assert_eq!(slots.len(), 4);
+        let page = pool.fetch_page(page_id).unwrap();
+        let free = usize::from(page.header().free_space);
+        drop(page);
+        assert!(free < row_space(512, limit - 2));
+
+        // Цепочка, записанная для неудавшейся вставки, освобождается
+        let err = insert_row(&pool, page_id, &row(2000, 9)).unwrap_err();
+        assert!(matches!(err, RowError::Page(SlottedPageError::PageFull { .. })), "{err}");
+        assert_eq!(pool.disk().page_count(), 6);
+        assert_eq!(pool.disk().pending_free_pages(), 5);
+        assert_eq!(read_row(&pool, page_id, slots[0]).unwrap(), Some(row(limit - 2, 0)));
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+//! Формат страницы DatyreDB: 24-байтный `PageHeader`, `PageFlags`
+//! и CRC32 страницы — байт-в-байт как `storage::Page` в C++.
+//!
+//! ```text
+//! offset  size  field
+//!      0     4  page_id
+//!      4     8  page_lsn
+//!     12     2  free_space
+//!     14     2  flags
+//!     16     4  checksum   (CRC32 всей страницы, кроме этих 4 байт)
+//!     20     4  reserved
+//! ```
+//! Все поля little-endian (C++ пишет `#pragma pack(1)` структуру на x86-64).
+
+use std::fmt;
+
+/// ID страницы
+pub type PageId = u32;
+
+/// Log Sequence Number
+pub type Lsn = u64;
+
+/// ID транзакции
+pub type TxnId = u64;
+
+/// Невалидный page ID
+pub const INVALID_PAGE_ID: PageId = PageId::MAX;
+
+/// Невалидный LSN
+pub const INVALID_LSN: Lsn = 0;
+
+/// Размер заголовка страницы
+pub const PAGE_HEADER_SIZE: usize = 24;
+
+/// Смещение поля checksum в заголовке
+pub const CHECKSUM_OFFSET: usize = 16;
+
+// ============================================================================
+// Флаги страницы
+// ============================================================================
+
+/// Флаги страницы (`storage::PageFlags`)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PageFlags(u16);
+
+impl PageFlags {
+    pub const NONE: PageFlags = PageFlags(0);
+    pub const DIRTY: PageFlags = PageFlags(1 << 0);
+    pub const PINNED: PageFlags = PageFlags(1 << 1);
+    pub const LEAF: PageFlags = PageFlags(1 << 2);
+    pub const INTERNAL: PageFlags = PageFlags(1 << 3);
+    pub const OVERFLOW: PageFlags = PageFlags(1 << 4);
+
+    const NAMED: [(PageFlags, &'static str); 5] = [
+        (PageFlags::DIRTY, "DIRTY"),
+        (PageFlags::PINNED, "PINNED"),
+        (PageFlags::LEAF, "LEAF"),
+        (PageFlags::INTERNAL, "INTERNAL"),
+        (PageFlags::OVERFLOW, "OVERFLOW"),
+    ];
+
+    pub const fn from_bits(bits: u16) -> Self {
+        PageFlags(bits)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// `has_flag` из C++
+    pub const fn contains(self, flag: PageFlags) -> bool {
+        self.0 & flag.0 == flag.0 && flag.0 != 0
+    }
+
+    /// Биты, которых нет в C++ `PageFlags`
+    pub const fn unknown_bits(self) -> u16 {
+        self.0 & !0x1f
+    }
+}
+
+impl std::ops::BitOr for PageFlags {
+    type Output = PageFlags;
+    fn bitor(self, rhs: PageFlags) -> PageFlags {
+        PageFlags(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for PageFlags {
+    fn bitor_assign(&mut self, rhs: PageFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Display for PageFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("NONE");
+        }
+        let mut first = true;
+        for (flag, name) in PageFlags::NAMED {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str("|")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        if self.unknown_bits() != 0 {
+            if !first {
+                f.write_str("|")?;
+            }
+            write!(f, "{:#x}", self.unknown_bits())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PageFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PageFlags({self})")
+    }
+}
+
+// ============================================================================
+// Заголовок страницы
+// ============================================================================
+
+/// Заголовок страницы (`storage::PageHeader`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageHeader {
+    pub page_id: PageId,
+    pub page_lsn: Lsn,
+    pub free_space: u16,
+    pub flags: PageFlags,
+    pub checksum: u32,
+    pub reserved: u32,
+}
+
+impl PageHeader {
+    /// Заголовок новой пустой страницы
+    pub fn new(page_id: PageId, page_size: usize) -> Self {
+        Self {
+            page_id,
+            page_lsn: INVALID_LSN,
+            free_space: (page_size - PAGE_HEADER_SIZE) as u16,
+            flags: PageFlags::NONE,
+            checksum: 0,
+            reserved: 0,
+        }
+    }
+
+    /// Разбор первых 24 байт страницы
+    pub fn decode(page: &[u8]) -> Self {
+        let h = &page[..PAGE_HEADER_SIZE];
+        Self {
+            page_id: u32::from_le_bytes(h[0..4].try_into().unwrap()),
+            page_lsn: u64::from_le_bytes(h[4..12].try_into().unwrap()),
+            free_space: u16::from_le_bytes(h[12..14].try_into().unwrap()),
+            flags: PageFlags(u16::from_le_bytes(h[14..16].try_into().unwrap())),
+            checksum: u32::from_le_bytes(h[16..20].try_into().unwrap()),
+            reserved: u32::from_le_bytes(h[20..24].try_into().unwrap()),
+        }
+    }
+
+    /// Запись в первые 24 байта страницы
+    pub fn encode(&self, page: &mut [u8]) {
+        let h = &mut page[..PAGE_HEADER_SIZE];
+        h[0..4].copy_from_slice(&self.page_id.to_le_bytes());
+        h[4..12].copy_from_slice(&self.page_lsn.to_le_bytes());
+        h[12..14].copy_from_slice(&self.free_space.to_le_bytes());
+        h[14..16].copy_from_slice(&self.flags.0.to_le_bytes());
+        h[16..20].copy_from_slice(&self.checksum.to_le_bytes());
+        h[20..24].copy_from_slice(&self.reserved.to_le_bytes());
+    }
+}
+
+// ============================================================================
+// Checksum
+// ============================================================================
+
+/// CRC32 lookup table (IEEE polynomial, reflected)
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Продолжение CRC32 (состояние до финального XOR)
+pub fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &b in bytes {
+        crc = CRC32_TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// CRC32 (IEEE) произвольных данных
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0xFFFF_FFFF, bytes) ^ 0xFFFF_FFFF
+}
+
+/// CRC32 страницы без поля checksum (`Page::compute_checksum`)
+pub fn compute_checksum(page: &[u8]) -> u32 {
+    let crc = crc32_update(0xFFFF_FFFF, &page[..CHECKSUM_OFFSET]);
+    let crc = crc32_update(crc, &page[CHECKSUM_OFFSET + 4..]);
+    crc ^ 0xFFFF_FFFF
+}
+
+/// `Page::verify_checksum`
+pub fn verify_checksum(page: &[u8]) -> bool {
+    compute_checksum(page) == PageHeader::decode(page).checksum
+}
+
+/// `Page::update_checksum`
+pub fn update_checksum(page: &mut [u8]) {
+    let checksum = compute_checksum(page);
+    page[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4].copy_from_slice(&checksum.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_DATA: &[u8] = include_bytes!("../example_data/data.db");
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(CRC32_TABLE[1], 0x7707_3096);
+        assert_eq!(CRC32_TABLE[255], 0x2D02_EF8D);
+    }
+
+    #[test]
+    fn decodes_and_verifies_example_pages() {
+        for (id, page) in EXAMPLE_DATA.chunks(4096).enumerate() {
+            let header = PageHeader::decode(page);
+            assert_eq!(header.page_id, id as PageId);
+            assert_eq!(header.free_space as usize, 4096 - PAGE_HEADER_SIZE);
+            assert_eq!(header.flags, PageFlags::NONE);
+            assert!(verify_checksum(page), "page {id}");
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let mut page = vec![0u8; 4096];
+        let header = PageHeader {
+            page_id: 7,
+            page_lsn: 0x0102_0304_0506,
+            free_space: 100,
+            flags: PageFlags::LEAF | PageFlags::OVERFLOW,
+            checksum: 0,
+            reserved: 0,
+        };
+        header.encode(&mut page);
+        update_checksum(&mut page);
+        let decoded = PageHeader::decode(&page);
+        assert_eq!(decoded.page_lsn, header.page_lsn);
+        assert_eq!(decoded.flags.to_string(), "LEAF|OVERFLOW");
+        assert!(verify_checksum(&page));
+
+        page[100] ^= 1;
+        assert!(!verify_checksum(&page));
+    }
+}
@@ -0,0 +1,307 @@
+//! Чтение файла данных (`data.db`) без C++ движка.
+//!
+//! Страница N лежит по смещению `N * page_size` (`DiskManager`). Страницы
+//! читаются по одной при итерации, поэтому файл любого размера проверяется
+//! с постоянным расходом памяти.
+
+use crate::config::DatabaseConfig;
+use crate::page::{compute_checksum, PageHeader, PageId, PAGE_HEADER_SIZE};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+/// Гранулярность атомарной записи диска: по ней ищется граница torn write
+pub const SECTOR_SIZE: usize = 512;
+
+/// Состояние страницы на диске
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageStatus {
+    /// Checksum совпадает
+    Valid,
+    /// Страница из одних нулей: выделена `allocate_page`, но не записана
+    Unwritten,
+    /// Checksum не совпадает, страница начиная с сектора `from` — нули, а
+    /// заголовок до них говорит, что данные страницы заходят в эти нули:
+    /// запись страницы оборвалась на середине
+    Torn { stored: u32, computed: u32, from: usize },
+    /// Checksum не совпадает
+    Corrupt { stored: u32, computed: u32 },
+    /// Файл обрывается внутри страницы: на диске только `len` байт
+    Truncated { len: usize },
+}
+
+impl PageStatus {
+    /// Страница пригодна для чтения движком (или ещё не записывалась)
+    pub fn is_ok(&self) -> bool {
+        matches!(self, PageStatus::Valid | PageStatus::Unwritten)
+    }
+}
+
+impl fmt::Display for PageStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageStatus::Valid => f.write_str("ok"),
+            PageStatus::Unwritten => f.write_str("unwritten"),
+            PageStatus::Torn { stored, computed, from } => write!(
+                f,
+                "torn write: zeros from byte {from}, checksum {stored:#010x} != {computed:#010x}"
+            ),
+            PageStatus::Corrupt { stored, computed } => {
+                write!(f, "checksum mismatch: stored {stored:#010x}, computed {computed:#010x}")
+            }
+            PageStatus::Truncated { len } => write!(f, "truncated: only {len} bytes on disk"),
+        }
+    }
+}
+
+/// Результат проверки одной страницы
+#[derive(Debug, Clone)]
+pub struct PageReport {
+    /// Номер страницы по позиции в файле
+    pub page_no: PageId,
+    /// Смещение страницы в файле
+    pub offset: u64,
+    /// Заголовок (`None`, если на диске меньше `PAGE_HEADER_SIZE` байт)
+    pub header: Option<PageHeader>,
+    pub status: PageStatus,
+}
+
+impl fmt::Display for PageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "page {} @ {:#x}: {}", self.page_no, self.offset, self.status)
+    }
+}
+
+/// Открытый на чтение файл данных
+#[derive(Debug)]
+pub struct PageFile {
+    file: File,
+    path: PathBuf,
+    page_size: usize,
+    len: u64,
+}
+
+impl PageFile {
+    /// `data_path/data.db` с размером страницы из конфига
+    pub fn open(config: &DatabaseConfig) -> io::Result<Self> {
+        Self::open_path(config.data_file(), config.page_size)
+    }
+
+    /// Произвольный файл данных
+    pub fn open_path(path: impl AsRef<Path>, page_size: usize) -> io::Result<Self> {
+        if page_size <= PAGE_HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("page size {page_size} does not fit a {PAGE_HEADER_SIZE}-byte header"),
+            ));
+        }
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self { file, path, page_size, len })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Размер файла в байтах
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Число страниц, включая неполную последнюю
+    pub fn page_count(&self) -> u64 {
+        self.len.div_ceil(self.page_size as u64)
+    }
+
+    /// Сырые байты страницы (для неполной последней — сколько есть)
+    pub fn read_page(&self, page_no: PageId) -> io::Result<Vec<u8>> {
+        let offset = u64::from(page_no) * self.page_size as u64;
+        if offset >= self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("page {page_no} is beyond the end of {}", self.path.display()),
+            ));
+        }
+        let len = (self.len - offset).min(self.page_size as u64) as usize;
+        let mut buf = vec![0u8; len];
+        self.file.read_exact_at(&mut buf, offset)?;
+        Ok(buf)
+    }
+
+    /// Проверка одной страницы
+    pub fn check_page(&self, page_no: PageId) -> io::Result<PageReport> {
+        let page = self.read_page(page_no)?;
+        Ok(PageReport {
+            page_no,
+            offset: u64::from(page_no) * self.page_size as u64,
+            header: (page.len() >= PAGE_HEADER_SIZE).then(|| PageHeader::decode(&page)),
+            status: classify(&page, page_no, self.page_size),
+        })
+    }
+
+    /// Ленивый обход всех страниц файла
+    pub fn pages(&self) -> Pages<'_> {
+        Pages { file: self, next: 0 }
+    }
+
+    /// Только проблемные страницы
+    pub fn damaged_pages(&self) -> impl Iterator<Item = io::Result<PageReport>> + '_ {
+        self.pages()
+            .filter(|r| r.as_ref().map_or(true, |r| !r.status.is_ok()))
+    }
+}
+
+/// Итератор по страницам (`PageFile::pages`)
+pub struct Pages<'a> {
+    file: &'a PageFile,
+    next: u64,
+}
+
+impl Iterator for Pages<'_> {
+    type Item = io::Result<PageReport>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.file.page_count() {
+            return None;
+        }
+        let page_no = PageId::try_from(self.next).ok()?;
+        self.next += 1;
+        Some(self.file.check_page(page_no))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.file.page_count().saturating_sub(self.next) as usize;
+        (left, Some(left))
+    }
+}
+
+fn classify(page: &[u8], page_no: PageId, page_size: usize) -> PageStatus {
+    if page.len() < page_size {
+        return PageStatus::Truncated { len: page.len() };
+    }
+    if page.iter().all(|&b| b == 0) {
+        return PageStatus::Unwritten;
+    }
+
+    let stored = PageHeader::decode(page).checksum;
+    let computed = compute_checksum(page);
+    if stored == computed {
+        return PageStatus::Valid;
+    }
+
+    // Секторы пишутся по порядку: оборванная запись оставляет целые секторы
+    // новой страницы и нули там, где страница не записывалась. Нулевой
+    // хвост бывает и у почти пустой страницы с испорченным байтом, поэтому
+    // torn write — только если заголовок в записанных секторах цел и по
+    // `free_space` занятая часть страницы заходит в нули (у узлов B+tree и
+    // overflow-страниц `free_space` = 0: занята вся страница)
+    let data_end = page.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    let from = data_end.next_multiple_of(SECTOR_SIZE);
+    let header = PageHeader::decode(page);
+    let used = page_size.saturating_sub(usize::from(header.free_space));
+    let header_ok = header.page_id == page_no && header.flags.unknown_bits() == 0;
+    if from < page_size && header_ok && used > from {
+        PageStatus::Torn { stored, computed, from }
+    } else {
+        PageStatus::Corrupt { stored, computed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::{update_checksum, PageFlags};
+
+    const EXAMPLE_DATA: &[u8] = include_bytes!("../example_data/data.db");
+
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("datyredb_page_file_{}_{}.db", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn example_data_is_valid() {
+        let path = temp_file("example", EXAMPLE_DATA);
+        let file = PageFile::open_path(&path, 4096).unwrap();
+        assert_eq!(file.page_count(), 5);
+
+        let reports: Vec<_> = file.pages().map(Result::unwrap).collect();
+        for (i, report) in reports.iter().enumerate() {
+            assert_eq!(report.status, PageStatus::Valid, "{report}");
+            assert_eq!(report.header.unwrap().page_id, i as PageId);
+        }
+        assert_eq!(file.damaged_pages().count(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reports_damage_with_offsets() {
+        let page_size = 4096;
+        let mut data = vec![0u8; page_size * 4 + 100];
+        for (id, page) in data.chunks_mut(page_size).take(4).enumerate() {
+            let mut header = PageHeader::new(id as PageId, page_size);
+            header.flags = PageFlags::LEAF;
+            header.free_space = 0;
+            header.encode(page);
+            page[PAGE_HEADER_SIZE..page_size].fill(0xAB);
+            update_checksum(page);
+        }
+        // 1: испорчен байт, 2: записан только первый сектор, 3: не записана
+        data[page_size + 2000] ^= 0xFF;
+        data[2 * page_size + SECTOR_SIZE..3 * page_size].fill(0);
+        data[3 * page_size..4 * page_size].fill(0);
+
+        let path = temp_file("damage", &data);
+        let file = PageFile::open_path(&path, page_size).unwrap();
+        let reports: Vec<_> = file.pages().map(Result::unwrap).collect();
+        assert_eq!(reports.len(), 5);
+        assert_eq!(reports[0].status, PageStatus::Valid);
+        assert!(matches!(reports[1].status, PageStatus::Corrupt { .. }));
+        assert_eq!(reports[1].offset, page_size as u64);
+        assert!(matches!(reports[2].status, PageStatus::Torn { from: SECTOR_SIZE, .. }));
+        assert_eq!(reports[3].status, PageStatus::Unwritten);
+        assert_eq!(reports[4].status, PageStatus::Truncated { len: 100 });
+        assert_eq!(reports[4].offset, 4 * page_size as u64);
+
+        let damaged: Vec<_> = file.damaged_pages().map(|r| r.unwrap().page_no).collect();
+        assert_eq!(damaged, [1, 2, 4]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn zero_tail_alone_is_not_a_torn_write() {
+        let page_size = 4096;
+        let mut page = vec![0u8; page_size];
+        PageHeader::new(7, page_size).encode(&mut page);
+        page[PAGE_HEADER_SIZE..100].fill(0xAB);
+        update_checksum(&mut page);
+        assert_eq!(classify(&page, 7, page_size), PageStatus::Valid);
+
+        // Почти пустая страница с испорченным байтом: данные по заголовку
+        // кончаются в первом секторе
+        page[50] ^= 0xFF;
+        assert!(matches!(classify(&page, 7, page_size), PageStatus::Corrupt { .. }));
+
+        // По заголовку занята вся страница: нули после первого сектора —
+        // оборванная запись, если заголовок от этой страницы
+        let mut header = PageHeader::decode(&page);
+        header.free_space = 0;
+        header.encode(&mut page);
+        assert!(matches!(classify(&page, 7, page_size), PageStatus::Torn { from: 512, .. }));
+        assert!(matches!(classify(&page, 8, page_size), PageStatus::Corrupt { .. }));
+    }
+}
@@ -0,0 +1,102 @@
+//! Бэкенд записи страниц: `pwrite` или io_uring.
+//!
+//! C++ `DiskManager` пишет страницы по одной через `fstream` под общим
+//! `io_mutex_`. Здесь батч страниц checkpoint'а отдаётся бэкенду целиком:
+//! `Pwrite` пишет их последовательно, `IoUring` отправляет все записи одним
+//! `io_uring_enter` и так же делает fsync. io_uring включается флагом
+//! `CheckpointConfig::async_checkpoint`; если ядро его не даёт (старое ядро,
+//! seccomp в контейнере, `kernel.io_uring_disabled`), используется `pwrite`.
+
+mod uring;
+
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+
+pub use uring::Ring;
+
+/// Глубина очереди io_uring: батч по умолчанию (`checkpoint_batch_size`)
+/// уходит одним вызовом
+pub const QUEUE_DEPTH: u32 = 256;
+
+/// Бэкенд записи страниц
+#[derive(Debug)]
+pub enum PageIo {
+    /// Синхронные `pwrite` + `fdatasync`
+    Pwrite,
+    /// Батчи через io_uring
+    IoUring(Ring),
+}
+
+impl PageIo {
+    /// Бэкенд для checkpoint'а: io_uring при `async_checkpoint`, если ядро
+    /// его поддерживает, иначе `pwrite`
+    pub fn for_checkpoint(async_checkpoint: bool) -> Self {
+        if async_checkpoint {
+            if let Ok(ring) = Ring::new(QUEUE_DEPTH) {
+                return PageIo::IoUring(ring);
+            }
+        }
+        PageIo::Pwrite
+    }
+
+    /// Имя для отчётов: "pwrite" / "io_uring"
+    pub fn name(&self) -> &'static str {
+        match self {
+            PageIo::Pwrite => "pwrite",
+            PageIo::IoUring(_) => "io_uring",
+        }
+    }
+
+    /// Записать все буферы по их смещениям
+    pub fn write_all(&mut self, file: &File, writes: &[(u64, &[u8])]) -> io::Result<()> {
+        match self {
+            PageIo::Pwrite => {
+                writes.iter().try_for_each(|&(offset, buf)| file.write_all_at(buf, offset))
+            }
+            PageIo::IoUring(ring) => ring.write_all(file, writes),
+        }
+    }
+
+    /// fdatasync файла
+    pub fn sync(&mut self, file: &File) -> io::Result<()> {
+        match self {
+            PageIo::Pwrite => file.sync_data(),
+            PageIo::IoUring(ring) => ring.sync(file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backends_write_the_same_bytes() {
+        let dir = std::env::temp_dir().join(format!("datyredb_page_io_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let pages: Vec<Vec<u8>> = (0..600u32).map(|i| vec![(i % 251) as u8; 512]).collect();
+        // Обратный порядок смещений: запись не обязана быть последовательной
+        let writes: Vec<(u64, &[u8])> =
+            pages.iter().enumerate().rev().map(|(i, p)| (i as u64 * 512, p.as_slice())).collect();
+
+        let mut backends = vec![PageIo::Pwrite];
+        // Без io_uring (seccomp, старое ядро) проверяется только pwrite
+        if let Ok(ring) = Ring::new(QUEUE_DEPTH) {
+            backends.push(PageIo::IoUring(ring));
+        }
+        for mut io in backends {
+            let path = dir.join(io.name());
+            let file = File::create(&path).unwrap();
+            // Больше записей, чем глубина очереди: несколько заходов в кольцо
+            io.write_all(&file, &writes).unwrap();
+            io.sync(&file).unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), pages.concat(), "{}", io.name());
+        }
+
+        assert_eq!(PageIo::for_checkpoint(false).name(), "pwrite");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,490 @@
+//! Минимальное кольцо io_uring на сырых системных вызовах (без внешних
+//! крейтов): `IORING_OP_WRITE` для страниц и `IORING_OP_FSYNC` с
+//! `IORING_FSYNC_DATASYNC`. Нужно ядро 5.6+.
+//!
+//! Раскладка структур — из `include/uapi/linux/io_uring.h`. Номера
+//! системных вызовов 425 и 426 общие не для всех архитектур: у alpha, ia64
+//! и MIPS они свои, поэтому на архитектурах вне `URING_ARCH` кольцо не
+//! создаётся (`Unsupported`), и checkpoint пишет через `pwrite`.
+
+use std::ffi::{c_int, c_long, c_void};
+use std::fs::File;
+use std::io;
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::FileExt;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const SYS_IO_URING_SETUP: c_long = 425;
+const SYS_IO_URING_ENTER: c_long = 426;
+
+/// Архитектуры, где у io_uring общие номера вызовов (`asm-generic` и
+/// сверенные с их `syscall.tbl`)
+const URING_ARCH: bool = cfg!(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "riscv32",
+    target_arch = "riscv64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "s390x",
+    target_arch = "loongarch64",
+    target_arch = "sparc64",
+));
+
+const IORING_OFF_SQ_RING: i64 = 0;
+const IORING_OFF_CQ_RING: i64 = 0x0800_0000;
+const IORING_OFF_SQES: i64 = 0x1000_0000;
+
+const IORING_OP_FSYNC: u8 = 3;
+const IORING_OP_WRITE: u8 = 23;
+const IORING_FSYNC_DATASYNC: u32 = 1;
+const IORING_ENTER_GETEVENTS: c_long = 1;
+
+/// `io_uring_enter` возвращается после первого же завершения
+const MIN_COMPLETE: c_long = 1;
+
+const PROT_READ: c_int = 1;
+const PROT_WRITE: c_int = 2;
+const MAP_SHARED: c_int = 1;
+const MAP_POPULATE: c_int = 0x8000;
+const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+extern "C" {
+    fn syscall(number: c_long, ...) -> c_long;
+    fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: i64)
+        -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct SqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqringOffsets,
+    cq_off: CqringOffsets,
+}
+
+/// `struct io_uring_sqe` (64 байта)
+#[repr(C)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+/// `struct io_uring_cqe` (16 байт)
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+/// Отображённая в память область кольца
+struct Mmap {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fd: c_int, len: usize, offset: i64) -> io::Result<Self> {
+        let (prot, flags) = (PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE);
+        // SAFETY: новое отображение, ничьи данные не затрагиваются
+        let ptr = unsafe { mmap(ptr::null_mut(), len, prot, flags, fd, offset) };
+        if ptr == MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    /// Указатель на поле по смещению из `Params`
+    fn at<T>(&self, offset: u32) -> *mut T {
+        // SAFETY: смещения получены от ядра и лежат внутри отображения
+        unsafe { self.ptr.cast::<u8>().add(offset as usize).cast() }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        // SAFETY: отображение создано в `Mmap::new` и больше не используется
+        unsafe { munmap(self.ptr, self.len) };
+    }
+}
+
+/// Кольцо io_uring. Каждый вызов ждёт завершения всех своих запросов, в
+/// том числе когда возвращает ошибку, поэтому буферы живут дольше операций
+/// над ними.
+pub struct Ring {
+    fd: OwnedFd,
+    /// Отображения колец: указатели ниже ссылаются в них
+    _sq_map: Mmap,
+    _cq_map: Mmap,
+    sqes: Mmap,
+    sq_entries: u32,
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_array: *mut u32,
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const Cqe,
+}
+
+// SAFETY: указатели ссылаются на собственные отображения кольца; операции
+// требуют `&mut self`, так что кольцо используется одним потоком за раз
+unsafe impl Send for Ring {}
+
+impl std::fmt::Debug for Ring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ring").field("fd", &self.fd).field("sq_entries", &self.sq_entries).finish()
+    }
+}
+
+impl Ring {
+    /// Новое кольцо на `entries` запросов
+    pub fn new(entries: u32) -> io::Result<Self> {
+        if !URING_ARCH {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "io_uring syscall numbers are not known for this architecture",
+            ));
+        }
+        let mut params = Params::default();
+        // SAFETY: `params` — корректная `struct io_uring_params`
+        let fd = unsafe { syscall(SYS_IO_URING_SETUP, c_long::from(entries), &mut params as *mut _) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: дескриптор только что выдан ядром и принадлежит нам
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as c_int) };
+        let raw = fd.as_raw_fd();
+
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * size_of::<Cqe>();
+        let sqes_len = params.sq_entries as usize * size_of::<Sqe>();
+        let sq = Mmap::new(raw, sq_len, IORING_OFF_SQ_RING)?;
+        let cq = Mmap::new(raw, cq_len, IORING_OFF_CQ_RING)?;
+        let sqes = Mmap::new(raw, sqes_len, IORING_OFF_SQES)?;
+
+        // SAFETY: смещения из `params` указывают внутрь отображений
+        let (sq_mask, cq_mask) = unsafe {
+            (*sq.at::<u32>(params.sq_off.ring_mask), *cq.at::<u32>(params.cq_off.ring_mask))
+        };
+        Ok(Self {
+            sq_entries: params.sq_entries,
+            sq_head: sq.at(params.sq_off.head),
+            sq_tail: sq.at(params.sq_off.tail),
+            sq_mask,
+            sq_array: sq.at(params.sq_off.array),
+            cq_head: cq.at(params.cq_off.head),
+            cq_tail: cq.at(params.cq_off.tail),
+            cq_mask,
+            cqes: cq.at(params.cq_off.cqes),
+            fd,
+            _sq_map: sq,
+            _cq_map: cq,
+            sqes,
+        })
+    }
+
+    /// Записать буферы; запросов в полёте не больше размера кольца
+    pub fn write_all(&mut self, file: &File, writes: &[(u64, &[u8])]) -> io::Result<()> {
+        let fd = file.as_raw_fd();
+        for chunk in writes.chunks(self.sq_entries as usize) {
+            for (i, &(offset, buf)) in chunk.iter().enumerate() {
+                self.push(Sqe {
+                    opcode: IORING_OP_WRITE,
+                    fd,
+                    off: offset,
+                    addr: buf.as_ptr() as u64,
+                    len: buf.len() as u32,
+                    user_data: i as u64,
+                    ..Sqe::empty()
+                });
+            }
+            let mut first_error = None;
+            self.submit_and_wait(chunk.len() as u32, |user_data, res| {
+                if let Err(e) = finish_write(file, chunk[user_data as usize], res) {
+                    first_error.get_or_insert(e);
+                }
+            })?;
+            if let Some(e) = first_error {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// fdatasync через кольцо
+    pub fn sync(&mut self, file: &File) -> io::Result<()> {
+        self.push(Sqe {
+            opcode: IORING_OP_FSYNC,
+            fd: file.as_raw_fd(),
+            op_flags: IORING_FSYNC_DATASYNC,
+            ..Sqe::empty()
+        });
+        let mut result = Ok(());
+        self.submit_and_wait(1, |_, res| {
+            if res < 0 {
+                result = Err(io::Error::from_raw_os_error(-res));
+            }
+        })?;
+        result
+    }
+
+    /// Положить запрос в очередь отправки (места хватает: каждый вызов
+    /// дожидается всех своих завершений)
+    fn push(&mut self, sqe: Sqe) {
+        // SAFETY: head/tail/array/sqes — поля кольца в наших отображениях;
+        // хвост пишет только этот поток
+        unsafe {
+            let tail = (*self.sq_tail).load(Ordering::Relaxed);
+            debug_assert!(tail.wrapping_sub((*self.sq_head).load(Ordering::Acquire)) < self.sq_entries);
+            let index = tail & self.sq_mask;
+            self.sqes.at::<Sqe>(0).add(index as usize).write(sqe);
+            self.sq_array.add(index as usize).write(index);
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+    }
+
+    /// Отправить `count` запросов и дождаться стольких же завершений.
+    /// При ошибке `io_uring_enter` неотправленные запросы снимаются с
+    /// очереди, а отправленные дожидаются: они читают буферы вызывающего,
+    /// и кольцо после возврата пустое.
+    fn submit_and_wait(&mut self, count: u32, mut on_complete: impl FnMut(u64, i32)) -> io::Result<()> {
+        let mut to_submit = count;
+        let mut completed = 0;
+        while completed < count {
+            match self.enter(to_submit) {
+                Ok(submitted) => to_submit -= submitted,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.unqueue();
+                    let mut in_flight = count - to_submit - completed;
+                    while in_flight > 0 {
+                        match self.enter(0) {
+                            Ok(_) => in_flight -= self.reap(&mut on_complete),
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                            // Вернуть управление нельзя: ядро ещё пишет из
+                            // буферов, которые вызывающий освободит
+                            Err(_) => std::process::abort(),
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+            completed += self.reap(&mut on_complete);
+        }
+        Ok(())
+    }
+
+    /// `io_uring_enter`: отправить `to_submit` запросов и дождаться хотя бы
+    /// одного завершения; возвращает число отправленных
+    fn enter(&mut self, to_submit: u32) -> io::Result<u32> {
+        // SAFETY: аргументы соответствуют `io_uring_enter(2)`, маска сигналов не передаётся
+        let ret = unsafe {
+            syscall(
+                SYS_IO_URING_ENTER,
+                c_long::from(self.fd.as_raw_fd()),
+                c_long::from(to_submit),
+                MIN_COMPLETE,
+                IORING_ENTER_GETEVENTS,
+                ptr::null::<c_void>(),
+                0usize,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ret as u32)
+    }
+
+    /// Разобрать готовые завершения; возвращает их число
+    fn reap(&mut self, on_complete: &mut impl FnMut(u64, i32)) -> u32 {
+        let mut reaped = 0;
+        // SAFETY: CQE между head и tail заполнены ядром; head двигаем мы
+        unsafe {
+            let mut head = (*self.cq_head).load(Ordering::Relaxed);
+            let tail = (*self.cq_tail).load(Ordering::Acquire);
+            while head != tail {
+                let cqe = &*self.cqes.add((head & self.cq_mask) as usize);
+                on_complete(cqe.user_data, cqe.res);
+                head = head.wrapping_add(1);
+                reaped += 1;
+            }
+            (*self.cq_head).store(head, Ordering::Release);
+        }
+        reaped
+    }
+
+    /// Снять с очереди запросы, которые ядро ещё не забрало (без SQPOLL
+    /// ядро читает очередь только внутри `io_uring_enter`)
+    fn unqueue(&mut self) {
+        // SAFETY: head/tail — поля кольца в наших отображениях
+        unsafe {
+            let head = (*self.sq_head).load(Ordering::Acquire);
+            (*self.sq_tail).store(head, Ordering::Release);
+        }
+    }
+}
+
+/// Итог записи `buf` по смещению `offset` с результатом CQE `res`
+fn finish_write(file: &File, (offset, buf): (u64, &[u8]), res: i32) -> io::Result<()> {
+    match usize::try_from(res) {
+        // Короткая запись: дописываем остаток синхронно
+        Ok(n) if n < buf.len() => file.write_all_at(&buf[n..], offset + n as u64),
+        Ok(_) => Ok(()),
+        Err(_) => Err(io::Error::from_raw_os_error(-res)),
+    }
+}
+
+impl Sqe {
+    fn empty() -> Self {
+        Sqe {
+            opcode: 0,
+            flags: 0,
+            ioprio: 0,
+            fd: -1,
+            off: 0,
+            addr: 0,
+            len: 0,
+            op_flags: 0,
+            user_data: 0,
+            buf_index: 0,
+            personality: 0,
+            splice_fd_in: 0,
+            addr3: 0,
+            pad: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPERM: i32 = 1;
+    const EIO: i32 = 5;
+    const EINVAL: i32 = 22;
+    const ENOSYS: i32 = 38;
+
+    /// Кольцо, если ядро его даёт: без io_uring (ENOSYS, EPERM от seccomp)
+    /// тест пропускается
+    fn ring() -> Option<Ring> {
+        match Ring::new(8) {
+            Ok(ring) => Some(ring),
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => None,
+            Err(e) if matches!(e.raw_os_error(), Some(ENOSYS | EPERM)) => None,
+            Err(e) => panic!("io_uring_setup: {e}"),
+        }
+    }
+
+    fn temp_file(name: &str) -> (std::path::PathBuf, File) {
+        let name = format!("datyredb_uring_{name}_{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let file = File::options().read(true).write(true).create(true).truncate(true).open(&path);
+        (path, file.unwrap())
+    }
+
+    #[test]
+    fn failed_setup_is_reported() {
+        if ring().is_none() {
+            return;
+        }
+        let err = Ring::new(0).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EINVAL), "{err}");
+    }
+
+    #[test]
+    fn short_write_is_finished_synchronously() {
+        if ring().is_none() {
+            return;
+        }
+        let (path, file) = temp_file("short");
+        let buf = [7u8; 512];
+        // Ядро записало 100 байт из 512: остаток дописывается через pwrite
+        finish_write(&file, (512, &buf), 100).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 1024);
+        assert!(bytes[..612].iter().all(|&b| b == 0));
+        assert!(bytes[612..].iter().all(|&b| b == 7));
+
+        finish_write(&file, (0, &buf), 512).unwrap();
+        let err = finish_write(&file, (0, &buf), -EIO).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EIO));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn failed_write_waits_for_the_rest() {
+        let Some(mut ring) = ring() else {
+            return;
+        };
+        let (path, file) = temp_file("error");
+        let pages = [[1u8; 512], [2u8; 512], [3u8; 512], [4u8; 512]];
+        // Отрицательное смещение: эта запись завершается с EINVAL, соседние
+        // в том же заходе — успешно
+        let writes: Vec<(u64, &[u8])> =
+            vec![(0, &pages[0]), (1 << 63, &pages[1]), (512, &pages[2])];
+        let err = ring.write_all(&file, &writes).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EINVAL), "{err}");
+        assert_eq!(std::fs::read(&path).unwrap(), [pages[0], pages[2]].concat());
+
+        // Ни запросов, ни завершений от прошлого вызова в кольце не осталось
+        ring.write_all(&file, &[(1024, &pages[3])]).unwrap();
+        ring.sync(&file).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), [pages[0], pages[2], pages[3]].concat());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
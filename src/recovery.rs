@@ -0,0 +1,423 @@
+//! Восстановление после сбоя в духе ARIES: анализ, redo, undo.
+//!
+//! - Анализ начинается с последнего завершённого checkpoint'а: таблица
+//!   активных транзакций берётся из его CHECKPOINT_END и дополняется
+//!   записями после CHECKPOINT_BEGIN. Если END без таблицы (WAL C++
+//!   движка) или checkpoint'а нет, анализ идёт с начала WAL.
+//! - Redo повторяет записи данных начиная с CHECKPOINT_BEGIN: всё, что было
+//!   грязным до него, сброшено на диск до END. Запись применяется, только
+//!   если `page_lsn` страницы меньше её LSN, поэтому повторный redo ничего
+//!   не меняет.
+//! - Undo откатывает проигравшие транзакции (без COMMIT/ABORT) от новых
+//!   записей к старым. Каждый откат пишет CLR, а встреченный CLR переносит
+//!   откат сразу к своему `undo_next_lsn`: сбой посреди undo не приводит к
+//!   повторному откату. Откаченная транзакция завершается TXN_ABORT.
+//!
+//! В конце WAL сбрасывается на диск и выполняется checkpoint, так что
+//! следующий запуск начинает с уже восстановленного состояния.
+//!
+//! Файл данных базовой копии (`restore`) соответствует checkpoint'у копии,
+//! а не более поздним checkpoint'ам из архива: `recover_from_backup`
+//! запрещает начинать с них.
+
+use crate::buffer_pool::{BufferPool, BufferPoolError};
+use crate::config::DatabaseConfig;
+use crate::page::{Lsn, TxnId, INVALID_LSN, INVALID_PAGE_ID, PAGE_HEADER_SIZE};
+use crate::wal::{
+    LogRecord, LogRecordType, RecordFormat, TornTail, WalArchive, WalReader, WalScan, WalWriter,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// Результат и ошибки
+// ============================================================================
+
+/// Итог восстановления
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// CHECKPOINT_BEGIN последнего завершённого checkpoint'а
+    pub checkpoint_lsn: Option<Lsn>,
+    /// Первая запись, просмотренная анализом
+    pub analysis_start: Lsn,
+    pub redo_start: Lsn,
+    /// Последняя валидная запись WAL до восстановления
+    pub wal_end_lsn: Lsn,
+    /// Оборванный хвост WAL, отброшенный при открытии
+    pub torn: Option<TornTail>,
+    /// Записей данных применено / пропущено по `page_lsn`
+    pub redone: u64,
+    pub redo_skipped: u64,
+    /// Транзакции, завершённые COMMIT после начала анализа
+    pub committed: usize,
+    /// Откаченные транзакции
+    pub losers: Vec<TxnId>,
+    pub clrs_written: u64,
+    /// CHECKPOINT_END, записанный в конце восстановления
+    pub end_checkpoint_lsn: Lsn,
+    pub duration: Duration,
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.checkpoint_lsn {
+            Some(lsn) => write!(f, "recovery from checkpoint at LSN {lsn}")?,
+            None => f.write_str("recovery from the start of the WAL")?,
+        }
+        write!(
+            f,
+            " (WAL end LSN {}): redo from LSN {}: {} applied, {} skipped; {} committed, \
+             {} rolled back with {} CLRs; {}ms",
+            self.wal_end_lsn,
+            self.redo_start,
+            self.redone,
+            self.redo_skipped,
+            self.committed,
+            self.losers.len(),
+            self.clrs_written,
+            self.duration.as_millis()
+        )
+    }
+}
+
+/// Восстановление не завершено; состояние на диске остаётся пригодным для
+/// повторного запуска
+#[derive(Debug)]
+pub enum RecoveryError {
+    /// Чтение или запись WAL
+    Wal(io::Error),
+    /// Чтение или запись страниц
+    Pages(BufferPoolError),
+    /// Запись не применяется к странице
+    Corrupt { lsn: Lsn, message: String },
+    /// Запись, нужная для отката транзакции, отсутствует в WAL
+    MissingRecord { txn_id: TxnId, lsn: Lsn },
+}
+
+impl fmt::Display for RecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecoveryError::Wal(e) => write!(f, "recovery failed: WAL: {e}"),
+            RecoveryError::Pages(e) => write!(f, "recovery failed: pages: {e}"),
+            RecoveryError::Corrupt { lsn, message } => {
+                write!(f, "recovery failed: record at LSN {lsn}: {message}")
+            }
+            RecoveryError::MissingRecord { txn_id, lsn } => {
+                write!(
+                    f,
+                    "recovery failed: transaction {txn_id} needs LSN {lsn}, which is not in the WAL"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecoveryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecoveryError::Wal(e) => Some(e),
+            RecoveryError::Pages(e) => Some(e),
+            RecoveryError::Corrupt { .. } | RecoveryError::MissingRecord { .. } => None,
+        }
+    }
+}
+
+impl From<BufferPoolError> for RecoveryError {
+    fn from(e: BufferPoolError) -> Self {
+        RecoveryError::Pages(e)
+    }
+}
+
+// ============================================================================
+// Восстановление
+// ============================================================================
+
+/// Восстановить директорию данных `config`
+pub fn recover(config: &DatabaseConfig) -> Result<RecoveryReport, RecoveryError> {
+    recover_from_backup(config, None)
+}
+
+/// Восстановление директории, собранной из базовой копии: checkpoint'ы
+/// после `checkpoint_limit` (CHECKPOINT_BEGIN копии) не используются
+pub fn recover_from_backup(
+    config: &DatabaseConfig,
+    checkpoint_limit: Option<Lsn>,
+) -> Result<RecoveryReport, RecoveryError> {
+    // WAL читается до открытия writer'а: тот обрезает оборванный хвост
+    let scan = WalReader::open(config).and_then(|r| r.scan()).map_err(RecoveryError::Wal)?;
+    let mut wal =
+        WalWriter::open(config.wal_dir(), config.wal_segment_size).map_err(RecoveryError::Wal)?;
+    wal.set_archive(WalArchive::from_config(config).map_err(RecoveryError::Wal)?);
+    let pool = BufferPool::new(config).map_err(|e| RecoveryError::Pages(e.into()))?;
+    recover_with(&pool, &mut wal, &scan, checkpoint_limit)
+}
+
+/// Восстановление поверх уже открытых pool и WAL. `scan` — WAL, прочитанный
+/// до `WalWriter::open`.
+pub fn recover_with(
+    pool: &BufferPool,
+    wal: &mut WalWriter,
+    scan: &WalScan,
+    checkpoint_limit: Option<Lsn>,
+) -> Result<RecoveryReport, RecoveryError> {
+    let started = Instant::now();
+    if let Some(framed) = scan.records.iter().find(|r| r.format == RecordFormat::Framed) {
+        return Err(RecoveryError::Corrupt {
+            lsn: framed.record.lsn,
+            message: "framed WAL records carry no page images".into(),
+        });
+    }
+    let first_lsn = scan.records.first().map_or(INVALID_LSN, |r| r.record.lsn);
+    let checkpoint = scan
+        .checkpoints()
+        .into_iter()
+        .rev()
+        .filter(|c| checkpoint_limit.is_none_or(|limit| c.begin_lsn <= limit))
+        .find(|c| c.is_complete());
+
+    // ---- Анализ ----
+    let mut active: BTreeMap<TxnId, Lsn> = BTreeMap::new();
+    let mut analysis_start = first_lsn;
+    if let Some(end) = checkpoint.and_then(|c| scan.get(c.end_lsn?)) {
+        if let Some(txns) = end.record.active_txns() {
+            active.extend(txns);
+            analysis_start = checkpoint.map_or(first_lsn, |c| c.begin_lsn);
+        }
+    }
+    let mut committed = 0;
+    for record in scan.records.iter().map(|r| &r.record).filter(|r| r.lsn >= analysis_start) {
+        if record.txn_id == 0 {
+            continue;
+        }
+        match record.record_type {
+            LogRecordType::TxnCommit => {
+                active.remove(&record.txn_id);
+                committed += 1;
+            }
+            LogRecordType::TxnAbort => {
+                active.remove(&record.txn_id);
+            }
+            _ => {
+                let last = active.entry(record.txn_id).or_insert(record.lsn);
+                *last = (*last).max(record.lsn);
+            }
+        }
+    }
+
+    // ---- Redo ----
+    let redo_start = checkpoint.map_or(first_lsn, |c| c.begin_lsn);
+    let (mut redone, mut redo_skipped) = (0, 0);
+    for record in scan.records.iter().map(|r| &r.record) {
+        if record.lsn < redo_start || !record.record_type.is_data() {
+            continue;
+        }
+        let image = record.redo_image().ok_or_else(|| RecoveryError::Corrupt {
+            lsn: record.lsn,
+            message: format!("{} data does not match length {}", record.record_type, record.length),
+        })?;
+        if apply(pool, record, &image, record.lsn, true)? {
+            redone += 1;
+        } else {
+            redo_skipped += 1;
+        }
+    }
+
+    // ---- Undo ----
+    let losers: Vec<TxnId> = active.keys().copied().collect();
+    let mut last_lsn = active.clone();
+    let mut to_undo = active;
+    let mut clrs_written = 0;
+    while let Some((&txn_id, &lsn)) = to_undo.iter().max_by_key(|(_, &lsn)| lsn) {
+        let next = if lsn == INVALID_LSN {
+            None
+        } else {
+            let record = scan
+                .get(lsn)
+                .map(|r| &r.record)
+                .filter(|r| r.txn_id == txn_id)
+                .ok_or(RecoveryError::MissingRecord { txn_id, lsn })?;
+            match record.record_type {
+                LogRecordType::Clr => record.undo_next_lsn(),
+                t if t.is_data() => {
+                    let clr = LogRecord::compensation(record, last_lsn[&txn_id]).ok_or_else(|| {
+                        RecoveryError::Corrupt {
+                            lsn,
+                            message: format!("{t} data does not match length {}", record.length),
+                        }
+                    })?;
+                    let clr_lsn = wal.append(&clr).map_err(RecoveryError::Wal)?;
+                    let image = clr.redo_image().expect("CLR built from a valid record");
+                    apply(pool, &clr, &image, clr_lsn, false)?;
+                    last_lsn.insert(txn_id, clr_lsn);
+                    clrs_written += 1;
+                    Some(record.prev_lsn)
+                }
+                _ => Some(record.prev_lsn),
+            }
+        };
+        match next {
+            Some(next) if next != INVALID_LSN => {
+                to_undo.insert(txn_id, next);
+            }
+            _ => {
+                let abort = LogRecord::new(LogRecordType::TxnAbort, txn_id);
+                wal.append(&LogRecord { prev_lsn: last_lsn[&txn_id], ..abort }).map_err(RecoveryError::Wal)?;
+                to_undo.remove(&txn_id);
+            }
+        }
+    }
+
+    // ---- Checkpoint восстановленного состояния ----
+    wal.flush().map_err(RecoveryError::Wal)?;
+    let begin = wal.checkpoint_begin().map_err(RecoveryError::Wal)?;
+    pool.flush_pages(&pool.get_dirty_pages())?;
+    pool.sync_all().map_err(|e| RecoveryError::Pages(e.into()))?;
+    let end_checkpoint_lsn = wal.checkpoint_end(begin).map_err(RecoveryError::Wal)?;
+    wal.truncate_before(begin).map_err(RecoveryError::Wal)?;
+
+    Ok(RecoveryReport {
+        checkpoint_lsn: checkpoint.map(|c| c.begin_lsn),
+        analysis_start,
+        redo_start,
+        wal_end_lsn: scan.end.last_valid_lsn,
+        torn: scan.end.torn.clone(),
+        redone,
+        redo_skipped,
+        committed,
+        losers,
+        clrs_written,
+        end_checkpoint_lsn,
+        duration: started.elapsed(),
+    })
+}
+
+/// Записать `image` в диапазон записи и поставить `page_lsn = lsn`.
+/// При `redo` страница с `page_lsn >= lsn` не трогается; возвращает,
+/// изменена ли страница.
+fn apply(
+    pool: &BufferPool,
+    record: &LogRecord,
+    image: &[u8],
+    lsn: Lsn,
+    redo: bool,
+) -> Result<bool, RecoveryError> {
+    let page_size = pool.disk().page_size();
+    let start = usize::from(record.offset);
+    if record.page_id == INVALID_PAGE_ID {
+        return Err(RecoveryError::Corrupt { lsn: record.lsn, message: "no page id".into() });
+    }
+    if start < PAGE_HEADER_SIZE || start + image.len() > page_size {
+        return Err(RecoveryError::Corrupt {
+            lsn: record.lsn,
+            message: format!(
+                "range {start}..{} is outside the page body ({PAGE_HEADER_SIZE}..{page_size})",
+                start + image.len()
+            ),
+        });
+    }
+    // Страница могла быть выделена, но не попасть на диск до сбоя
+    pool.disk().extend_to(record.page_id + 1).map_err(|e| RecoveryError::Pages(e.into()))?;
+
+    let page = pool.fetch_page(record.page_id)?;
+    let mut header = page.header();
+    if redo && header.page_lsn >= lsn {
+        return Ok(false);
+    }
+    let mut data = page.write();
+    data[start..start + image.len()].copy_from_slice(image);
+    header.page_lsn = lsn;
+    header.encode(&mut data);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk_manager::DiskManager;
+    use std::path::PathBuf;
+
+    fn config(name: &str) -> DatabaseConfig {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("datyredb_recovery_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        DatabaseConfig {
+            data_path: dir,
+            page_size: 512,
+            buffer_pool_size: 8 * 512,
+            wal_segment_size: 4096,
+            ..DatabaseConfig::default()
+        }
+    }
+
+    fn read(config: &DatabaseConfig, page_id: u32, range: std::ops::Range<usize>) -> Vec<u8> {
+        let disk = DiskManager::open(config).unwrap();
+        let mut page = vec![0u8; config.page_size];
+        disk.read_page(page_id, &mut page).unwrap();
+        page[range].to_vec()
+    }
+
+    #[test]
+    fn redoes_winners_and_undoes_losers() {
+        let config = config("basic");
+        let mut wal = WalWriter::create(config.wal_dir(), config.wal_segment_size).unwrap();
+        let b1 = wal.append(&LogRecord::new(LogRecordType::TxnBegin, 1)).unwrap();
+        let b2 = wal.append(&LogRecord::new(LogRecordType::TxnBegin, 2)).unwrap();
+        let u1 = wal.append(&LogRecord::update(1, b1, 0, 100, &[0; 4], b"win!")).unwrap();
+        wal.append(&LogRecord::update(2, b2, 1, 100, &[0; 4], b"lose")).unwrap();
+        wal.append(&LogRecord { prev_lsn: u1, ..LogRecord::new(LogRecordType::TxnCommit, 1) })
+            .unwrap();
+        wal.flush().unwrap();
+        drop(wal);
+
+        // Страницы ни разу не сбрасывались: файла данных ещё нет
+        let report = recover(&config).unwrap();
+        assert_eq!(report.checkpoint_lsn, None);
+        assert_eq!((report.redone, report.redo_skipped), (2, 0));
+        assert_eq!(report.committed, 1);
+        assert_eq!(report.losers, vec![2]);
+        assert_eq!(report.clrs_written, 1);
+        assert_eq!(read(&config, 0, 100..104), b"win!");
+        assert_eq!(read(&config, 1, 100..104), [0; 4]);
+
+        // Повторный запуск начинает с checkpoint'а предыдущего и ничего не меняет
+        let again = recover(&config).unwrap();
+        assert_eq!(again.checkpoint_lsn, Some(report.end_checkpoint_lsn - 1));
+        assert_eq!((again.redone, again.losers.len(), again.clrs_written), (0, 0, 0));
+        assert_eq!(read(&config, 0, 100..104), b"win!");
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn resumes_interrupted_rollback_from_checkpoint() {
+        let config = config("resume");
+        let mut wal = WalWriter::create(config.wal_dir(), config.wal_segment_size).unwrap();
+        let b = wal.append(&LogRecord::new(LogRecordType::TxnBegin, 7)).unwrap();
+        let a = wal.append(&LogRecord::update(7, b, 0, 40, b"aaaa", b"AAAA")).unwrap();
+        let begin = wal.checkpoint_begin().unwrap();
+        wal.checkpoint_end(begin).unwrap();
+        let c = wal.append(&LogRecord::insert(7, a, 0, 60, b"cccc")).unwrap();
+        // Откат транзакции начат до сбоя: INSERT уже компенсирован
+        let undone = LogRecord { lsn: c, ..LogRecord::insert(7, a, 0, 60, b"cccc") };
+        wal.append(&LogRecord::compensation(&undone, c).unwrap()).unwrap();
+        wal.flush().unwrap();
+        drop(wal);
+
+        let report = recover(&config).unwrap();
+        assert_eq!(report.checkpoint_lsn, Some(begin));
+        assert_eq!(report.analysis_start, begin);
+        // UPDATE до BEGIN не повторяется, INSERT и CLR — да
+        assert_eq!(report.redone, 2);
+        assert_eq!(report.losers, vec![7]);
+        assert_eq!(report.clrs_written, 1);
+        assert_eq!(read(&config, 0, 40..44), b"aaaa");
+        assert_eq!(read(&config, 0, 60..64), [0; 4]);
+
+        let scan = WalReader::open(&config).unwrap().scan().unwrap();
+        let chain = scan.chain(7).unwrap();
+        assert_eq!(chain.outcome, Some(LogRecordType::TxnAbort));
+        assert_eq!(chain.broken_at, None);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+}
@@ -0,0 +1,414 @@
+//! Восстановление на момент времени (PITR): базовая копия + архив WAL.
+//!
+//! Базовая копия — директория данных (`data.db` и `wal/`), скопированная
+//! при остановленной базе, или онлайн копия `backup::create_backup`: её
+//! манифест задаёт checkpoint, с которого начинается redo, и LSN, до
+//! которого копия согласована. Восстановление:
+//!
+//! 1. копирует `data.db` и сегменты копии в новую директорию;
+//! 2. добавляет сегменты из источников WAL (архив, уцелевший WAL исходной
+//!    базы) начиная с первого сегмента копии; из двух копий одного сегмента
+//!    берётся более длинная — архивная копия завершённого сегмента длиннее
+//!    той, что попала в базовую копию;
+//! 3. проверяет, что LSN идут подряд, и отрезает WAL после цели:
+//!    LSN включительно или последней записи перед первым COMMIT позже
+//!    заданного времени;
+//! 4. запускает `recover_from_backup` от checkpoint'а копии: транзакции, не
+//!    успевшие завершиться до цели, откатываются.
+//!
+//! Время есть только у COMMIT, записанных Rust writer'ом
+//! (`LogRecord::commit`); COMMIT C++ движка цель по времени не останавливают.
+//!
+//! Восстановленная база начинает новую историю WAL: писать её сегменты
+//! в архив исходной нельзя, поэтому восстановление идёт без архива.
+
+use crate::backup::BackupManifest;
+use crate::config::units::format_timestamp;
+use crate::config::DatabaseConfig;
+use crate::page::{Lsn, TxnId, INVALID_LSN};
+use crate::recovery::{recover_from_backup, RecoveryError, RecoveryReport};
+use crate::wal::{segment_file_name, WalReader};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// ============================================================================
+// Параметры
+// ============================================================================
+
+/// До какого момента восстанавливать
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreTarget {
+    /// Всё, что есть в источниках
+    End,
+    /// Записи до LSN включительно
+    Lsn(Lsn),
+    /// Транзакции, завершённые COMMIT не позже этого времени
+    Time(SystemTime),
+}
+
+impl fmt::Display for RestoreTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestoreTarget::End => f.write_str("end of WAL"),
+            RestoreTarget::Lsn(lsn) => write!(f, "LSN {lsn}"),
+            RestoreTarget::Time(t) => write!(f, "{}", format_timestamp(*t)),
+        }
+    }
+}
+
+/// Откуда восстанавливать
+#[derive(Debug, Clone)]
+pub struct RestoreOptions {
+    /// Базовая копия: директория данных с `data.db` и `wal/`
+    pub base: PathBuf,
+    /// Директории с сегментами после копии: архив WAL, уцелевший WAL
+    pub wal_sources: Vec<PathBuf>,
+    pub target: RestoreTarget,
+}
+
+// ============================================================================
+// Результат и ошибки
+// ============================================================================
+
+/// Итог восстановления
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreReport {
+    /// Последняя запись WAL базовой копии
+    pub backup_end_lsn: Lsn,
+    /// CHECKPOINT_BEGIN базовой копии, с которого идёт redo
+    pub backup_checkpoint_lsn: Option<Lsn>,
+    /// Сегментов взято из источников WAL
+    pub segments_from_sources: usize,
+    /// Последняя сохранённая запись
+    pub stop_lsn: Lsn,
+    /// Первый COMMIT позже целевого времени: с него история отброшена
+    pub stopped_before: Option<(TxnId, SystemTime)>,
+    /// Записей после цели, отброшенных из WAL
+    pub discarded_records: usize,
+    pub recovery: RecoveryReport,
+}
+
+impl fmt::Display for RestoreReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "base backup ends at LSN {}, {} segments taken from WAL sources",
+            self.backup_end_lsn, self.segments_from_sources
+        )?;
+        write!(f, "stopped at LSN {}", self.stop_lsn)?;
+        if let Some((txn_id, time)) = self.stopped_before {
+            write!(f, " before commit of txn {txn_id} at {}", format_timestamp(time))?;
+        }
+        writeln!(f, ", {} later records discarded", self.discarded_records)?;
+        write!(f, "{}", self.recovery)
+    }
+}
+
+/// Восстановление не выполнено
+#[derive(Debug)]
+pub enum RestoreError {
+    Io { path: PathBuf, source: io::Error },
+    /// В директории назначения уже есть файлы
+    TargetNotEmpty(PathBuf),
+    /// В базовой копии нет `data.db` или `wal/`
+    NoBaseBackup(PathBuf),
+    /// Манифест онлайн копии не читается
+    BadManifest(String),
+    /// Между записями `after` и `next` нет сегмента
+    MissingWal { after: Lsn, next: Lsn },
+    /// WAL не читается до цели: повреждён сегмент в середине
+    DamagedWal(String),
+    /// Цель раньше конца базовой копии: её страницы уже новее цели
+    TargetBeforeBackup { target: Lsn, backup_end: Lsn },
+    /// В источниках нет записей до цели
+    TargetNotReached { target: Lsn, wal_end: Lsn },
+    Recovery(RecoveryError),
+}
+
+impl fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestoreError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            RestoreError::TargetNotEmpty(path) => {
+                write!(f, "{} is not empty; restore needs a new directory", path.display())
+            }
+            RestoreError::NoBaseBackup(path) => {
+                write!(f, "{} is not a base backup: no data.db or wal/", path.display())
+            }
+            RestoreError::BadManifest(message) => write!(f, "base backup: {message}"),
+            RestoreError::MissingWal { after, next } => write!(
+                f,
+                "WAL records {}..={} are missing from the backup and the WAL sources",
+                after + 1,
+                next - 1
+            ),
+            RestoreError::DamagedWal(message) => write!(f, "damaged WAL: {message}"),
+            RestoreError::TargetBeforeBackup { target, backup_end } => write!(
+                f,
+                "target LSN {target} is before the end of the base backup (LSN {backup_end}); \
+                 use an older backup"
+            ),
+            RestoreError::TargetNotReached { target, wal_end } => {
+                write!(f, "target LSN {target} is beyond the available WAL (ends at LSN {wal_end})")
+            }
+            RestoreError::Recovery(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RestoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RestoreError::Io { source, .. } => Some(source),
+            RestoreError::Recovery(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<RecoveryError> for RestoreError {
+    fn from(e: RecoveryError) -> Self {
+        RestoreError::Recovery(e)
+    }
+}
+
+fn io_err(path: &Path) -> impl FnOnce(io::Error) -> RestoreError + '_ {
+    move |source| RestoreError::Io { path: path.to_path_buf(), source }
+}
+
+// ============================================================================
+// Восстановление
+// ============================================================================
+
+/// Собрать в `config.data_path` базу на момент `options.target`.
+/// Геометрия (размер страницы, сегмента WAL) берётся из `config`.
+pub fn restore(
+    config: &DatabaseConfig,
+    options: &RestoreOptions,
+) -> Result<RestoreReport, RestoreError> {
+    let config = DatabaseConfig { wal_archive_dir: None, ..config.clone() };
+    let base = DatabaseConfig { data_path: options.base.clone(), ..config.clone() };
+    if !base.data_file().is_file() || !base.wal_dir().is_dir() {
+        return Err(RestoreError::NoBaseBackup(options.base.clone()));
+    }
+    let target_dir = &config.data_path;
+    match std::fs::read_dir(target_dir) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(RestoreError::TargetNotEmpty(target_dir.clone()));
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(io_err(target_dir)(e)),
+    }
+
+    // ---- Базовая копия ----
+    let base_wal = base.wal_dir();
+    let base_reader = WalReader::open_dir(&base_wal).map_err(io_err(&base_wal))?;
+    let base_scan = base_reader.scan().map_err(io_err(&base_wal))?;
+    let manifest = BackupManifest::read_if_present(&options.base)
+        .map_err(|e| RestoreError::BadManifest(e.to_string()))?;
+    // Поздние checkpoint'ы онлайн копии описывают живой data.db, а не копию
+    let (backup_end_lsn, backup_checkpoint_lsn) = match manifest {
+        Some(manifest) => (manifest.end_lsn, Some(manifest.start_lsn)),
+        None => {
+            let checkpoints = base_scan.checkpoints();
+            let last_complete = checkpoints.iter().rev().find(|c| c.is_complete());
+            (base_scan.end.last_valid_lsn, last_complete.map(|c| c.begin_lsn))
+        }
+    };
+
+    // Номер сегмента -> (путь, длина, из источника ли)
+    let first_segment = base_reader.segments().first().map_or(0, |s| s.id);
+    let mut segments: BTreeMap<u64, (PathBuf, u64, bool)> = base_reader
+        .segments()
+        .iter()
+        .map(|s| (s.id, (s.path.clone(), s.len, false)))
+        .collect();
+    for source in &options.wal_sources {
+        let reader = WalReader::open_dir(source).map_err(io_err(source))?;
+        for s in reader.segments().iter().filter(|s| s.id >= first_segment) {
+            let entry = segments.entry(s.id).or_insert((s.path.clone(), s.len, true));
+            if s.len > entry.1 {
+                *entry = (s.path.clone(), s.len, true);
+            }
+        }
+    }
+
+    let wal_dir = config.wal_dir();
+    std::fs::create_dir_all(&wal_dir).map_err(io_err(&wal_dir))?;
+    let data_file = config.data_file();
+    std::fs::copy(base.data_file(), &data_file).map_err(io_err(&data_file))?;
+    for (&id, (path, _, _)) in &segments {
+        let copy = wal_dir.join(segment_file_name(id));
+        std::fs::copy(path, &copy).map_err(io_err(&copy))?;
+    }
+    let segments_from_sources = segments.values().filter(|(_, _, source)| *source).count();
+
+    // ---- Точка остановки ----
+    let scan = WalReader::open_dir(&wal_dir)
+        .and_then(|r| r.scan())
+        .map_err(io_err(&wal_dir))?;
+    if let Some(torn) = scan.end.torn.as_ref().filter(|t| t.unread_segments > 0) {
+        return Err(RestoreError::DamagedWal(torn.to_string()));
+    }
+    let wal_end = scan.end.last_valid_lsn;
+    let mut stopped_before = None;
+    let stop_lsn = match options.target {
+        RestoreTarget::End => wal_end,
+        RestoreTarget::Lsn(target) if target > wal_end => {
+            return Err(RestoreError::TargetNotReached { target, wal_end })
+        }
+        RestoreTarget::Lsn(target) => target,
+        RestoreTarget::Time(time) => {
+            let late_commit = scan.records.iter().map(|r| &r.record).find_map(|r| {
+                let committed = r.commit_time()?;
+                (committed > time).then_some((r.lsn, r.txn_id, committed))
+            });
+            match late_commit {
+                Some((lsn, txn_id, committed)) => {
+                    stopped_before = Some((txn_id, committed));
+                    lsn - 1
+                }
+                None => wal_end,
+            }
+        }
+    };
+    if stop_lsn < backup_end_lsn {
+        return Err(RestoreError::TargetBeforeBackup {
+            target: stop_lsn,
+            backup_end: backup_end_lsn,
+        });
+    }
+    let mut previous = INVALID_LSN;
+    for record in scan.records.iter().map(|r| &r.record).take_while(|r| r.lsn <= stop_lsn) {
+        if previous != INVALID_LSN && record.lsn != previous + 1 {
+            return Err(RestoreError::MissingWal { after: previous, next: record.lsn });
+        }
+        previous = record.lsn;
+    }
+
+    // ---- Обрезка WAL после цели ----
+    let kept = scan.records.iter().take_while(|r| r.record.lsn <= stop_lsn).count();
+    let discarded_records = scan.records.len() - kept;
+    if let Some(last) = kept.checked_sub(1).map(|i| &scan.records[i]) {
+        let path = wal_dir.join(segment_file_name(last.segment_id));
+        let file = OpenOptions::new().write(true).open(&path).map_err(io_err(&path))?;
+        file.set_len(last.offset + last.size as u64).map_err(io_err(&path))?;
+        for &id in segments.keys().filter(|&&id| id > last.segment_id) {
+            let path = wal_dir.join(segment_file_name(id));
+            std::fs::remove_file(&path).map_err(io_err(&path))?;
+        }
+    }
+
+    let recovery = recover_from_backup(&config, backup_checkpoint_lsn)?;
+    Ok(RestoreReport {
+        backup_end_lsn,
+        backup_checkpoint_lsn,
+        segments_from_sources,
+        stop_lsn,
+        stopped_before,
+        discarded_records,
+        recovery,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk_manager::DiskManager;
+    use crate::recovery::recover;
+    use crate::wal::{LogRecord, LogRecordType, WalArchive, WalWriter};
+    use std::time::Duration;
+
+    fn config(root: &Path, name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            data_path: root.join(name),
+            page_size: 512,
+            buffer_pool_size: 8 * 512,
+            wal_segment_size: 256,
+            wal_archive_dir: Some(root.join("archive")),
+            ..DatabaseConfig::default()
+        }
+    }
+
+    /// Транзакция из одной записи данных, которая строится по `prev_lsn`
+    fn run_txn(config: &DatabaseConfig, txn_id: TxnId, change: impl Fn(Lsn) -> LogRecord) {
+        let mut wal = WalWriter::open(config.wal_dir(), config.wal_segment_size).unwrap();
+        wal.set_archive(WalArchive::from_config(config).unwrap());
+        let begin = wal.append(&LogRecord::new(LogRecordType::TxnBegin, txn_id)).unwrap();
+        let change = wal.append(&change(begin)).unwrap();
+        wal.append(&LogRecord::commit(txn_id, change)).unwrap();
+        wal.flush().unwrap();
+    }
+
+    fn read_row(config: &DatabaseConfig) -> Vec<u8> {
+        let mut page = vec![0u8; config.page_size];
+        DiskManager::open(config).unwrap().read_page(0, &mut page).unwrap();
+        page[100..104].to_vec()
+    }
+
+    #[test]
+    fn restores_before_a_bad_delete() {
+        let root = std::env::temp_dir().join(format!("datyredb_restore_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let live = config(&root, "live");
+        run_txn(&live, 1, |prev| LogRecord::insert(1, prev, 0, 100, b"row1"));
+        recover(&live).unwrap();
+
+        // Базовая копия остановленной базы
+        std::fs::create_dir_all(root.join("base/wal")).unwrap();
+        std::fs::copy(live.data_file(), root.join("base/data.db")).unwrap();
+        for entry in std::fs::read_dir(live.wal_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            std::fs::copy(&path, root.join("base/wal").join(path.file_name().unwrap())).unwrap();
+        }
+
+        // Checkpoint после копии сбрасывает row2 только в исходный data.db
+        run_txn(&live, 2, |prev| LogRecord::update(2, prev, 0, 100, b"row1", b"row2"));
+        recover(&live).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let before_delete = SystemTime::now();
+        std::thread::sleep(Duration::from_millis(5));
+        run_txn(&live, 3, |prev| LogRecord::delete(3, prev, 0, 100, b"row2"));
+
+        let options = |target| RestoreOptions {
+            base: root.join("base"),
+            wal_sources: vec![root.join("archive"), live.wal_dir()],
+            target,
+        };
+        let restored = config(&root, "restored");
+        let report = restore(&restored, &options(RestoreTarget::Time(before_delete))).unwrap();
+        assert_eq!(read_row(&restored), b"row2");
+        assert_eq!(report.stopped_before.map(|(txn_id, _)| txn_id), Some(3));
+        assert!(report.segments_from_sources > 0);
+        assert_eq!(report.recovery.checkpoint_lsn, report.backup_checkpoint_lsn);
+        // DELETE остался в WAL без COMMIT и откачен
+        assert_eq!(report.recovery.losers, vec![3]);
+
+        let to_end = config(&root, "to_end");
+        let report = restore(&to_end, &options(RestoreTarget::End)).unwrap();
+        assert_eq!(read_row(&to_end), [0; 4]);
+        assert_eq!(report.discarded_records, 0);
+
+        // Цель перед COMMIT DELETE'а по LSN
+        let by_lsn = config(&root, "by_lsn");
+        let report = restore(&by_lsn, &options(RestoreTarget::Lsn(report.stop_lsn - 1))).unwrap();
+        assert_eq!(report.discarded_records, 1);
+        assert_eq!(read_row(&by_lsn), b"row2");
+
+        assert!(matches!(
+            restore(&config(&root, "early"), &options(RestoreTarget::Lsn(1))),
+            Err(RestoreError::TargetBeforeBackup { target: 1, .. })
+        ));
+        assert!(matches!(
+            restore(&restored, &options(RestoreTarget::End)),
+            Err(RestoreError::TargetNotEmpty(_))
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
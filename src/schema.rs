@@ -0,0 +1,495 @@
+//! Схема таблицы: типизированные столбцы с NOT NULL и DEFAULT, первичный
+//! ключ, проверка строк при вставке и двоичный формат строки.
+//!
+//! Формат строки (`TableSchema::encode_row`):
+//!
+//! ```text
+//! null bitmap   ceil(columns / 8) байт, бит i (младший первый) — столбец i NULL
+//! значения не-NULL столбцов по порядку:
+//!   BOOLEAN  1 байт
+//!   INT      4 байта little-endian
+//!   BIGINT   8 байт little-endian
+//!   DOUBLE   8 байт little-endian (IEEE 754)
+//!   TEXT     длина varint (LEB128), UTF-8
+//! ```
+//! Типов в строке нет: она читается только своей схемой.
+//!
+//! Имена столбцов сравниваются без учёта регистра ASCII. Столбцы первичного
+//! ключа всегда NOT NULL.
+
+use crate::value::{encode_key, CoerceError, DataType, Value};
+use std::fmt;
+
+/// Ошибка схемы или строки
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    NoColumns {
+        table: String,
+    },
+    DuplicateColumn(String),
+    UnknownColumn(String),
+    /// DEFAULT не приводится к типу столбца или NULL у NOT NULL
+    InvalidDefault {
+        column: String,
+        value: Value,
+    },
+    ColumnCount {
+        expected: usize,
+        found: usize,
+    },
+    NotNull {
+        column: String,
+    },
+    TypeMismatch {
+        column: String,
+        expected: DataType,
+        value: Value,
+    },
+    OutOfRange {
+        column: String,
+        expected: DataType,
+        value: Value,
+    },
+    CorruptRow(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::NoColumns { table } => write!(f, "table '{table}' has no columns"),
+            SchemaError::DuplicateColumn(column) => {
+                write!(f, "column '{column}' is specified more than once")
+            }
+            SchemaError::UnknownColumn(column) => write!(f, "unknown column '{column}'"),
+            SchemaError::InvalidDefault { column, value } => {
+                write!(f, "column '{column}': invalid default {value}")
+            }
+            SchemaError::ColumnCount { expected, found } => {
+                write!(f, "expected {expected} values, got {found}")
+            }
+            SchemaError::NotNull { column } => write!(f, "column '{column}' is NOT NULL"),
+            SchemaError::TypeMismatch { column, expected, value } => write!(
+                f,
+                "column '{column}': expected {expected}, got {} {value}",
+                value.type_name()
+            ),
+            SchemaError::OutOfRange { column, expected, value } => {
+                write!(f, "column '{column}': {value} is out of range for {expected}")
+            }
+            SchemaError::CorruptRow(message) => write!(f, "corrupt row: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+// ============================================================================
+// Схема
+// ============================================================================
+
+/// Столбец таблицы
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub data_type: DataType,
+    pub not_null: bool,
+    /// Значение для вставки без этого столбца (иначе NULL)
+    pub default: Option<Value>,
+}
+
+impl Column {
+    /// Столбец без NOT NULL и DEFAULT
+    pub fn new(name: impl Into<String>, data_type: DataType) -> Self {
+        Self { name: name.into(), data_type, not_null: false, default: None }
+    }
+}
+
+/// Схема таблицы
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    name: String,
+    columns: Vec<Column>,
+    /// Номера столбцов первичного ключа по порядку ключа
+    primary_key: Vec<usize>,
+}
+
+impl TableSchema {
+    /// Проверить определение таблицы. DEFAULT приводится к типу столбца,
+    /// столбцы `primary_key` становятся NOT NULL.
+    pub fn new(
+        name: impl Into<String>,
+        mut columns: Vec<Column>,
+        primary_key: &[&str],
+    ) -> Result<Self, SchemaError> {
+        let name = name.into();
+        if columns.is_empty() {
+            return Err(SchemaError::NoColumns { table: name });
+        }
+        for (i, column) in columns.iter().enumerate() {
+            if columns[..i].iter().any(|c| c.name.eq_ignore_ascii_case(&column.name)) {
+                return Err(SchemaError::DuplicateColumn(column.name.clone()));
+            }
+        }
+        let primary_key = positions(&columns, primary_key)?;
+        for &i in &primary_key {
+            columns[i].not_null = true;
+        }
+        for column in &mut columns {
+            if let Some(default) = &column.default {
+                let invalid = || SchemaError::InvalidDefault {
+                    column: column.name.clone(),
+                    value: default.clone(),
+                };
+                let value = default.coerce(column.data_type).map_err(|_| invalid())?;
+                if value.is_null() && column.not_null {
+                    return Err(invalid());
+                }
+                column.default = Some(value);
+            }
+        }
+        Ok(Self { name, columns, primary_key })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Номер столбца по имени
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Номера столбцов по именам; имя не может повторяться
+    pub fn column_indexes(&self, names: &[&str]) -> Result<Vec<usize>, SchemaError> {
+        positions(&self.columns, names)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.column_index(name).map(|i| &self.columns[i])
+    }
+
+    /// Номера столбцов первичного ключа (пусто — ключа нет)
+    pub fn primary_key(&self) -> &[usize] {
+        &self.primary_key
+    }
+
+    // ========================================================================
+    // Проверка строк
+    // ========================================================================
+
+    /// Строка из значений всех столбцов по порядку, приведённых к типам
+    /// столбцов
+    pub fn row(&self, values: Vec<Value>) -> Result<Vec<Value>, SchemaError> {
+        if values.len() != self.columns.len() {
+            return Err(SchemaError::ColumnCount {
+                expected: self.columns.len(),
+                found: values.len(),
+            });
+        }
+        self.columns.iter().zip(values).map(|(column, value)| coerce(column, value)).collect()
+    }
+
+    /// Строка из значений перечисленных столбцов (`INSERT INTO t (a, b)`);
+    /// остальные получают DEFAULT или NULL
+    pub fn row_for(&self, columns: &[&str], values: Vec<Value>) -> Result<Vec<Value>, SchemaError> {
+        if values.len() != columns.len() {
+            return Err(SchemaError::ColumnCount { expected: columns.len(), found: values.len() });
+        }
+        let positions = self.column_indexes(columns)?;
+        let mut row: Vec<Option<Value>> = vec![None; self.columns.len()];
+        for (i, value) in positions.into_iter().zip(values) {
+            row[i] = Some(value);
+        }
+        self.columns
+            .iter()
+            .zip(row)
+            .map(|(column, value)| match value {
+                Some(value) => coerce(column, value),
+                None => coerce(column, column.default.clone().unwrap_or(Value::Null)),
+            })
+            .collect()
+    }
+
+    /// Ключ первичного ключа строки для `btree` (`None` — ключа нет)
+    pub fn primary_key_bytes(&self, row: &[Value]) -> Option<Vec<u8>> {
+        if self.primary_key.is_empty() {
+            return None;
+        }
+        Some(encode_key(self.primary_key.iter().map(|&i| &row[i])))
+    }
+
+    // ========================================================================
+    // Формат строки
+    // ========================================================================
+
+    /// Закодировать строку; значения должны иметь ровно типы столбцов
+    /// (как после `row`)
+    pub fn encode_row(&self, row: &[Value]) -> Result<Vec<u8>, SchemaError> {
+        if row.len() != self.columns.len() {
+            return Err(SchemaError::ColumnCount {
+                expected: self.columns.len(),
+                found: row.len(),
+            });
+        }
+        let bitmap_len = self.columns.len().div_ceil(8);
+        let mut out = vec![0u8; bitmap_len];
+        for (i, (column, value)) in self.columns.iter().zip(row).enumerate() {
+            if value.data_type().is_some_and(|t| t != column.data_type) {
+                return Err(mismatch(column, value.clone()));
+            }
+            match value {
+                Value::Null if column.not_null => {
+                    return Err(SchemaError::NotNull { column: column.name.clone() })
+                }
+                Value::Null => out[i / 8] |= 1 << (i % 8),
+                Value::Bool(b) => out.push(u8::from(*b)),
+                Value::Int32(v) => out.extend_from_slice(&v.to_le_bytes()),
+                Value::Int64(v) => out.extend_from_slice(&v.to_le_bytes()),
+                Value::Double(v) if !v.is_finite() => {
+                    return Err(SchemaError::OutOfRange {
+                        column: column.name.clone(),
+                        expected: column.data_type,
+                        value: value.clone(),
+                    })
+                }
+                Value::Double(v) => out.extend_from_slice(&v.to_le_bytes()),
+                Value::Text(s) => {
+                    write_varint(&mut out, s.len() as u64);
+                    out.extend_from_slice(s.as_bytes());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Разобрать строку, закодированную `encode_row` этой схемы
+    pub fn decode_row(&self, bytes: &[u8]) -> Result<Vec<Value>, SchemaError> {
+        let bitmap_len = self.columns.len().div_ceil(8);
+        if bytes.len() < bitmap_len {
+            return Err(SchemaError::CorruptRow(format!(
+                "{} bytes is shorter than the null bitmap",
+                bytes.len()
+            )));
+        }
+        let (bitmap, mut rest) = bytes.split_at(bitmap_len);
+        let mut row = Vec::with_capacity(self.columns.len());
+        for (i, column) in self.columns.iter().enumerate() {
+            if bitmap[i / 8] & (1 << (i % 8)) != 0 {
+                row.push(Value::Null);
+                continue;
+            }
+            let short =
+                || SchemaError::CorruptRow(format!("column '{}' is truncated", column.name));
+            let mut take = |len: usize| -> Result<&[u8], SchemaError> {
+                let (head, tail) = rest.split_at_checked(len).ok_or_else(short)?;
+                rest = tail;
+                Ok(head)
+            };
+            let value = match column.data_type {
+                DataType::Boolean => Value::Bool(take(1)?[0] != 0),
+                DataType::Int => Value::Int32(i32::from_le_bytes(take(4)?.try_into().unwrap())),
+                DataType::BigInt => Value::Int64(i64::from_le_bytes(take(8)?.try_into().unwrap())),
+                DataType::Double => Value::Double(f64::from_le_bytes(take(8)?.try_into().unwrap())),
+                DataType::Text => {
+                    let len = read_varint(&mut rest).ok_or_else(short)?;
+                    let bytes =
+                        usize::try_from(len).ok().and_then(|len| rest.split_at_checked(len));
+                    let (text, tail) = bytes.ok_or_else(short)?;
+                    rest = tail;
+                    let text = String::from_utf8(text.to_vec()).map_err(|_| {
+                        SchemaError::CorruptRow(format!("column '{}' is not UTF-8", column.name))
+                    })?;
+                    Value::Text(text)
+                }
+            };
+            row.push(value);
+        }
+        if !rest.is_empty() {
+            return Err(SchemaError::CorruptRow(format!("{} trailing bytes", rest.len())));
+        }
+        Ok(row)
+    }
+}
+
+/// Номера столбцов `names` в `columns`
+fn positions(columns: &[Column], names: &[&str]) -> Result<Vec<usize>, SchemaError> {
+    let mut positions = Vec::with_capacity(names.len());
+    for name in names {
+        let i = columns
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| SchemaError::UnknownColumn(name.to_string()))?;
+        if positions.contains(&i) {
+            return Err(SchemaError::DuplicateColumn(name.to_string()));
+        }
+        positions.push(i);
+    }
+    Ok(positions)
+}
+
+fn coerce(column: &Column, value: Value) -> Result<Value, SchemaError> {
+    if value.is_null() && column.not_null {
+        return Err(SchemaError::NotNull { column: column.name.clone() });
+    }
+    value.coerce(column.data_type).map_err(|e| match e {
+        CoerceError::TypeMismatch => mismatch(column, value),
+        CoerceError::OutOfRange => SchemaError::OutOfRange {
+            column: column.name.clone(),
+            expected: column.data_type,
+            value,
+        },
+    })
+}
+
+fn mismatch(column: &Column, value: Value) -> SchemaError {
+    SchemaError::TypeMismatch { column: column.name.clone(), expected: column.data_type, value }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users() -> TableSchema {
+        let columns = vec![
+            Column::new("id", DataType::BigInt),
+            Column { not_null: true, ..Column::new("name", DataType::Text) },
+            Column { default: Some(Value::from("18")), ..Column::new("age", DataType::Int) },
+            Column::new("score", DataType::Double),
+            Column { default: Some(Value::Bool(true)), ..Column::new("active", DataType::Boolean) },
+        ];
+        TableSchema::new("users", columns, &["ID"]).unwrap()
+    }
+
+    #[test]
+    fn inserts_are_coerced_and_validated() {
+        let schema = users();
+        assert!(schema.columns()[0].not_null);
+        assert_eq!(schema.column("AGE").unwrap().default, Some(Value::Int32(18)));
+
+        let row = schema
+            .row(vec![Value::Int32(1), "ann".into(), "30".into(), Value::Int32(7), Value::Null])
+            .unwrap();
+        assert_eq!(
+            row,
+            vec![Value::Int64(1), "ann".into(), Value::Int32(30), Value::Double(7.0), Value::Null]
+        );
+        let row = schema.row_for(&["name", "id"], vec!["bob".into(), Value::Int64(2)]).unwrap();
+        assert_eq!(row[2..], [Value::Int32(18), Value::Null, Value::Bool(true)]);
+
+        let err = schema.row_for(&["id"], vec![Value::Int64(3)]).unwrap_err();
+        assert_eq!(err.to_string(), "column 'name' is NOT NULL");
+        let err =
+            schema.row_for(&["id", "name", "age"], vec![3i64.into(), "c".into(), "old".into()]);
+        assert_eq!(err.unwrap_err().to_string(), "column 'age': expected INT, got TEXT 'old'");
+        let err = schema
+            .row_for(&["id", "name", "age"], vec![3i64.into(), "c".into(), (1i64 << 40).into()]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "column 'age': 1099511627776 is out of range for INT"
+        );
+        let err = schema
+            .row_for(&["id", "name", "score"], vec![3i64.into(), "c".into(), f64::NAN.into()]);
+        assert_eq!(err.unwrap_err().to_string(), "column 'score': NaN is out of range for DOUBLE");
+        let err = schema.row_for(&["id", "nick"], vec![Value::Int64(3), "c".into()]).unwrap_err();
+        assert_eq!(err, SchemaError::UnknownColumn("nick".into()));
+        assert_eq!(
+            schema.row(vec![Value::Int64(1)]).unwrap_err(),
+            SchemaError::ColumnCount { expected: 5, found: 1 }
+        );
+
+        let bad =
+            |columns: Vec<Column>, key: &[&str]| TableSchema::new("t", columns, key).unwrap_err();
+        let a = Column::new("a", DataType::Int);
+        assert_eq!(bad(vec![], &[]), SchemaError::NoColumns { table: "t".into() });
+        assert_eq!(
+            bad(vec![a.clone(), Column::new("A", DataType::Text)], &[]),
+            SchemaError::DuplicateColumn("A".into())
+        );
+        assert_eq!(bad(vec![a.clone()], &["a", "a"]), SchemaError::DuplicateColumn("a".into()));
+        let default = Column { default: Some(Value::Null), ..a.clone() };
+        assert!(matches!(bad(vec![default], &["a"]), SchemaError::InvalidDefault { .. }));
+    }
+
+    #[test]
+    fn rows_round_trip_through_the_codec() {
+        let schema = users();
+        let rows = [
+            vec![
+                Value::Int64(-5),
+                "ann".into(),
+                Value::Int32(30),
+                Value::Double(-0.5),
+                Value::Bool(false),
+            ],
+            vec![Value::Int64(i64::MAX), "".into(), Value::Null, Value::Null, Value::Null],
+            vec![
+                Value::Int64(1),
+                "x".repeat(300).into(),
+                Value::Int32(i32::MIN),
+                Value::Double(1e300),
+                true.into(),
+            ],
+        ];
+        for row in &rows {
+            let bytes = schema.encode_row(row).unwrap();
+            assert_eq!(&schema.decode_row(&bytes).unwrap(), row);
+        }
+        // Битовая карта (1 байт), BIGINT и пустой TEXT: NULL места не занимают
+        assert_eq!(schema.encode_row(&rows[1]).unwrap().len(), 1 + 8 + 1);
+        // Длина 300 — два байта varint
+        assert_eq!(schema.encode_row(&rows[2]).unwrap().len(), 1 + 8 + 2 + 300 + 4 + 8 + 1);
+
+        let uncoerced = [Value::Int32(1), "a".into(), Value::Null, Value::Null, Value::Null];
+        assert!(matches!(schema.encode_row(&uncoerced), Err(SchemaError::TypeMismatch { .. })));
+        let null_name = [Value::Int64(1), Value::Null, Value::Null, Value::Null, Value::Null];
+        assert!(matches!(schema.encode_row(&null_name), Err(SchemaError::NotNull { .. })));
+        let nan = [Value::Int64(1), "a".into(), Value::Null, Value::Double(f64::NAN), Value::Null];
+        assert!(matches!(schema.encode_row(&nan), Err(SchemaError::OutOfRange { .. })));
+
+        let bytes = schema.encode_row(&rows[0]).unwrap();
+        assert!(matches!(
+            schema.decode_row(&bytes[..bytes.len() - 1]),
+            Err(SchemaError::CorruptRow(_))
+        ));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(matches!(schema.decode_row(&longer), Err(SchemaError::CorruptRow(_))));
+
+        let key = |id: i64| {
+            schema.primary_key_bytes(&[
+                Value::Int64(id),
+                "".into(),
+                Value::Null,
+                Value::Null,
+                Value::Null,
+            ])
+        };
+        assert!(key(-1) < key(1));
+        assert_eq!(key(1), Some(encode_key(&[Value::Int64(1)])));
+    }
+}
@@ -0,0 +1,460 @@
+//! Slotted page: каталог слотов и кортежи переменной длины в теле страницы.
+//!
+//! C++ `Page` отдаёт только сырой `payload()`. Здесь тело страницы после
+//! 24-байтного `PageHeader` размечено так:
+//!
+//! ```text
+//! offset             size  field
+//!     24                2  slot_count
+//!     26                2  tuples_start: начало области кортежей (0 — конец страницы)
+//!     28   4*slot_count    слоты: offset u16, len u16 (offset 0 — tombstone)
+//!    ...                   свободно
+//! tuples_start..page_size  кортежи, растут от конца страницы к каталогу
+//! ```
+//! Все поля little-endian, как в заголовке. Нулевое тело — пустая страница,
+//! так что страница из `BufferPool::new_page` уже годится. Номер слота
+//! кортежа не меняется ни при обновлении, ни при сжатии; освобождённые слоты
+//! занимаются новыми кортежами.
+//!
+//! `PageHeader::free_space` — место под новый кортеж вместе со слотом после
+//! сжатия: его видит карта свободного места. Удаление и укорачивание
+//! оставляют дыры; вставка, которой не хватает непрерывного места, сначала
+//! сжимает страницу.
+
+use crate::page::{PageHeader, PageId, PAGE_HEADER_SIZE};
+use std::fmt;
+
+/// Начало каталога слотов
+pub const SLOTTED_HEADER_SIZE: usize = PAGE_HEADER_SIZE + 4;
+
+/// Размер записи каталога
+pub const SLOT_SIZE: usize = 4;
+
+/// Номер слота в странице
+pub type SlotId = u16;
+
+/// Самый большой кортеж, который помещается в пустую страницу
+pub fn max_tuple_size(page_size: usize) -> usize {
+    page_size - SLOTTED_HEADER_SIZE - SLOT_SIZE
+}
+
+/// Ошибка операции над slotted page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlottedPageError {
+    /// Кортеж не помещается даже после сжатия
+    PageFull { needed: usize, available: usize },
+    /// Кортеж больше пустой страницы
+    TupleTooLarge { len: usize, max: usize },
+    /// Пустые кортежи не хранятся: у них нет места в области кортежей
+    EmptyTuple,
+    /// Слот свободен или его нет
+    NoSuchSlot(SlotId),
+    /// Каталог выходит за страницу
+    Corrupt { page_id: PageId, message: String },
+}
+
+impl fmt::Display for SlottedPageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlottedPageError::PageFull { needed, available } => {
+                write!(f, "page full: {needed} bytes needed, {available} available")
+            }
+            SlottedPageError::TupleTooLarge { len, max } => {
+                write!(f, "tuple of {len} bytes exceeds the page limit of {max} bytes")
+            }
+            SlottedPageError::EmptyTuple => write!(f, "empty tuples are not stored"),
+            SlottedPageError::NoSuchSlot(slot) => write!(f, "slot {slot} is empty"),
+            SlottedPageError::Corrupt { page_id, message } => {
+                write!(f, "page {page_id}: corrupt slot directory: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SlottedPageError {}
+
+// ============================================================================
+// Страница
+// ============================================================================
+
+/// Slotted page поверх байт страницы (`&[u8]` на чтение, `&mut [u8]` на
+/// запись)
+#[derive(Debug)]
+pub struct SlottedPage<B> {
+    buf: B,
+}
+
+impl<B: AsRef<[u8]>> SlottedPage<B> {
+    /// Страница с проверенным каталогом
+    pub fn open(buf: B) -> Result<Self, SlottedPageError> {
+        let page = Self { buf };
+        let data = page.data();
+        let corrupt = |message: String| SlottedPageError::Corrupt {
+            page_id: PageHeader::decode(data).page_id,
+            message,
+        };
+        let (dir_end, start) = (page.directory_end(), page.tuples_start());
+        if dir_end > start || start > data.len() {
+            return Err(corrupt(format!(
+                "{} slots end at {dir_end}, tuples start at {start}",
+                page.slot_count()
+            )));
+        }
+        for slot in 0..page.slot_count() {
+            let (offset, len) = page.slot(slot);
+            if offset != 0 && (offset < start || offset + len > data.len()) {
+                return Err(corrupt(format!(
+                    "slot {slot} points to {offset}..{} outside {start}..{}",
+                    offset + len,
+                    data.len()
+                )));
+            }
+        }
+        Ok(page)
+    }
+
+    pub fn page_id(&self) -> PageId {
+        PageHeader::decode(self.data()).page_id
+    }
+
+    /// Слотов в каталоге, включая освобождённые
+    pub fn slot_count(&self) -> SlotId {
+        u16::from_le_bytes([self.data()[24], self.data()[25]])
+    }
+
+    /// Кортеж слота; `None` — слот свободен или его нет
+    pub fn get(&self, slot: SlotId) -> Option<&[u8]> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+        match self.slot(slot) {
+            (0, _) => None,
+            (offset, len) => Some(&self.data()[offset..offset + len]),
+        }
+    }
+
+    /// Живые кортежи по возрастанию номеров слотов
+    pub fn iter(&self) -> impl Iterator<Item = (SlotId, &[u8])> + '_ {
+        (0..self.slot_count()).filter_map(|slot| self.get(slot).map(|tuple| (slot, tuple)))
+    }
+
+    /// Живых кортежей
+    pub fn live_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Место под кортежи и новые слоты после сжатия
+    pub fn free_space(&self) -> usize {
+        let live: usize = self.iter().map(|(_, tuple)| tuple.len()).sum();
+        self.data().len() - self.directory_end() - live
+    }
+
+    /// Поместится ли кортеж длиной `len` (с учётом нового слота)
+    pub fn fits(&self, len: usize) -> bool {
+        len + self.slot_overhead() <= self.free_space()
+    }
+
+    fn data(&self) -> &[u8] {
+        self.buf.as_ref()
+    }
+
+    fn tuples_start(&self) -> usize {
+        match u16::from_le_bytes([self.data()[26], self.data()[27]]) {
+            0 => self.data().len(),
+            start => usize::from(start),
+        }
+    }
+
+    fn directory_end(&self) -> usize {
+        SLOTTED_HEADER_SIZE + SLOT_SIZE * usize::from(self.slot_count())
+    }
+
+    /// Непрерывное место между каталогом и кортежами (0, если каталог уже
+    /// налез на кортежи)
+    fn gap(&self) -> usize {
+        self.tuples_start().saturating_sub(self.directory_end())
+    }
+
+    fn slot(&self, slot: SlotId) -> (usize, usize) {
+        let at = SLOTTED_HEADER_SIZE + SLOT_SIZE * usize::from(slot);
+        let d = self.data();
+        (
+            usize::from(u16::from_le_bytes([d[at], d[at + 1]])),
+            usize::from(u16::from_le_bytes([d[at + 2], d[at + 3]])),
+        )
+    }
+
+    /// Свободный слот для вставки, если он есть
+    fn free_slot(&self) -> Option<SlotId> {
+        (0..self.slot_count()).find(|&slot| self.slot(slot).0 == 0)
+    }
+
+    /// Сколько займёт каталог при вставке: 0, если есть свободный слот
+    fn slot_overhead(&self) -> usize {
+        if self.free_slot().is_some() {
+            0
+        } else {
+            SLOT_SIZE
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> SlottedPage<B> {
+    /// Разметить пустую страницу (заголовок, кроме `free_space`, не меняется)
+    pub fn init(buf: B) -> Self {
+        let mut page = Self { buf };
+        page.data_mut()[PAGE_HEADER_SIZE..SLOTTED_HEADER_SIZE].fill(0);
+        page.update_free_space();
+        page
+    }
+
+    /// Вставить кортеж; сжимает страницу, если непрерывного места мало
+    pub fn insert(&mut self, tuple: &[u8]) -> Result<SlotId, SlottedPageError> {
+        self.check_len(tuple.len())?;
+        let needed = tuple.len() + self.slot_overhead();
+        let available = self.free_space();
+        if needed > available {
+            return Err(SlottedPageError::PageFull { needed, available });
+        }
+        let slot = match self.free_slot() {
+            Some(slot) => slot,
+            None => {
+                // Новый слот растёт в сторону кортежей: место под него
+                // освобождает сжатие (`needed` его уже учитывает)
+                if self.gap() < SLOT_SIZE {
+                    self.compact();
+                }
+                let slot = self.slot_count();
+                self.set_slot_count(slot + 1);
+                self.set_slot(slot, 0, 0);
+                slot
+            }
+        };
+        self.place(slot, tuple);
+        Ok(slot)
+    }
+
+    /// Заменить кортеж слота, сохранив номер слота
+    pub fn update(&mut self, slot: SlotId, tuple: &[u8]) -> Result<(), SlottedPageError> {
+        self.check_len(tuple.len())?;
+        let Some(old) = self.get(slot).map(<[u8]>::len) else {
+            return Err(SlottedPageError::NoSuchSlot(slot));
+        };
+        if tuple.len() <= old {
+            // Короче или той же длины — на месте, хвост становится дырой
+            let (offset, _) = self.slot(slot);
+            self.data_mut()[offset..offset + tuple.len()].copy_from_slice(tuple);
+            self.set_slot(slot, offset, tuple.len());
+            self.update_free_space();
+            return Ok(());
+        }
+        let available = self.free_space() + old;
+        if tuple.len() > available {
+            return Err(SlottedPageError::PageFull { needed: tuple.len(), available });
+        }
+        self.set_slot(slot, 0, 0);
+        self.place(slot, tuple);
+        Ok(())
+    }
+
+    /// Освободить слот; хвостовые свободные слоты убираются из каталога
+    pub fn delete(&mut self, slot: SlotId) -> Result<(), SlottedPageError> {
+        if self.get(slot).is_none() {
+            return Err(SlottedPageError::NoSuchSlot(slot));
+        }
+        self.set_slot(slot, 0, 0);
+        let mut count = self.slot_count();
+        while count > 0 && self.slot(count - 1).0 == 0 {
+            count -= 1;
+        }
+        self.set_slot_count(count);
+        if count == 0 {
+            self.set_tuples_start(self.data().len());
+        }
+        self.update_free_space();
+        Ok(())
+    }
+
+    /// Сдвинуть живые кортежи к концу страницы, убрав дыры
+    pub fn compact(&mut self) {
+        let live: Vec<(SlotId, Vec<u8>)> =
+            self.iter().map(|(slot, tuple)| (slot, tuple.to_vec())).collect();
+        let dir_end = self.directory_end();
+        let mut start = self.data().len();
+        for (slot, tuple) in &live {
+            start -= tuple.len();
+            self.data_mut()[start..start + tuple.len()].copy_from_slice(tuple);
+            self.set_slot(*slot, start, tuple.len());
+        }
+        self.data_mut()[dir_end..start].fill(0);
+        self.set_tuples_start(start);
+        self.update_free_space();
+    }
+
+    /// Записать кортеж в слот `slot` (свободный), сжав страницу при нужде
+    fn place(&mut self, slot: SlotId, tuple: &[u8]) {
+        if self.gap() < tuple.len() {
+            self.compact();
+        }
+        let offset = self.tuples_start() - tuple.len();
+        self.data_mut()[offset..offset + tuple.len()].copy_from_slice(tuple);
+        self.set_slot(slot, offset, tuple.len());
+        self.set_tuples_start(offset);
+        self.update_free_space();
+    }
+
+    fn check_len(&self, len: usize) -> Result<(), SlottedPageError> {
+        let max = max_tuple_size(self.data().len());
+        match len {
+            0 => Err(SlottedPageError::EmptyTuple),
+            len if len > max => Err(SlottedPageError::TupleTooLarge { len, max }),
+            _ => Ok(()),
+        }
+    }
+
+    fn data_mut(&mut self) -> &mut [u8] {
+        self.buf.as_mut()
+    }
+
+    fn set_slot_count(&mut self, count: SlotId) {
+        self.data_mut()[24..26].copy_from_slice(&count.to_le_bytes());
+    }
+
+    /// Конец страницы кодируется нулём: 65536 не помещается в u16
+    fn set_tuples_start(&mut self, start: usize) {
+        let start = if start == self.data().len() { 0 } else { start as u16 };
+        self.data_mut()[26..28].copy_from_slice(&start.to_le_bytes());
+    }
+
+    fn set_slot(&mut self, slot: SlotId, offset: usize, len: usize) {
+        let at = SLOTTED_HEADER_SIZE + SLOT_SIZE * usize::from(slot);
+        let d = self.data_mut();
+        d[at..at + 2].copy_from_slice(&(offset as u16).to_le_bytes());
+        d[at + 2..at + 4].copy_from_slice(&(len as u16).to_le_bytes());
+    }
+
+    fn update_free_space(&mut self) {
+        let mut header = PageHeader::decode(self.data());
+        header.free_space = self.free_space().min(usize::from(u16::MAX)) as u16;
+        header.encode(self.data_mut());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: usize = 512;
+
+    fn new_page() -> Vec<u8> {
+        let mut buf = vec![0u8; PAGE];
+        PageHeader::new(3, PAGE).encode(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn insert_update_delete_keep_slot_ids() {
+        let mut buf = new_page();
+        let mut page = SlottedPage::init(&mut buf[..]);
+        assert_eq!(page.free_space(), PAGE - SLOTTED_HEADER_SIZE);
+
+        let a = page.insert(b"alpha").unwrap();
+        let b = page.insert(b"bravo-bravo").unwrap();
+        let c = page.insert(b"charlie").unwrap();
+        assert_eq!((a, b, c), (0, 1, 2));
+        assert_eq!(page.free_space(), PAGE - SLOTTED_HEADER_SIZE - 3 * SLOT_SIZE - 23);
+
+        page.update(a, b"a").unwrap();
+        page.update(b, b"bravo, but much longer than before").unwrap();
+        page.delete(c).unwrap();
+        assert_eq!(page.slot_count(), 2, "trailing tombstones are dropped");
+        page.delete(a).unwrap();
+        assert_eq!(page.get(a), None);
+        assert_eq!(page.delete(a), Err(SlottedPageError::NoSuchSlot(a)));
+        assert_eq!(page.insert(b"delta").unwrap(), a, "free slots are reused");
+
+        let live: Vec<_> = page.iter().collect();
+        assert_eq!(
+            live,
+            vec![(0, &b"delta"[..]), (1, &b"bravo, but much longer than before"[..])]
+        );
+        let free = page.free_space();
+        assert_eq!(usize::from(PageHeader::decode(&buf).free_space), free);
+
+        // Каталог переживает запись и чтение страницы
+        let page = SlottedPage::open(&buf[..]).unwrap();
+        assert_eq!(page.get(1), Some(&b"bravo, but much longer than before"[..]));
+    }
+
+    #[test]
+    fn compacts_when_fragmented() {
+        let mut buf = new_page();
+        let mut page = SlottedPage::init(&mut buf[..]);
+        let tuple = [7u8; 100];
+        let slots: Vec<_> = (0..4).map(|_| page.insert(&tuple).unwrap()).collect();
+        assert!(matches!(page.insert(&tuple), Err(SlottedPageError::PageFull { .. })));
+
+        // Дыры от удалённых кортежей не подряд: нужно сжатие
+        page.delete(slots[0]).unwrap();
+        page.delete(slots[2]).unwrap();
+        let big = [9u8; 190];
+        let slot = page.insert(&big).unwrap();
+        assert_eq!(slot, slots[0]);
+        assert_eq!(page.get(slot), Some(&big[..]));
+        assert_eq!(page.get(slots[1]), Some(&tuple[..]));
+        assert_eq!(page.get(slots[3]), Some(&tuple[..]));
+
+        // Не поместившееся обновление оставляет старый кортеж
+        let available = page.free_space() + tuple.len();
+        assert_eq!(
+            page.update(slots[1], &vec![1u8; available + 1]),
+            Err(SlottedPageError::PageFull { needed: available + 1, available })
+        );
+        assert_eq!(page.get(slots[1]), Some(&tuple[..]));
+        page.update(slots[1], &vec![1u8; available]).unwrap();
+        assert_eq!(page.free_space(), 0);
+
+        assert_eq!(
+            page.insert(&[0u8; PAGE]),
+            Err(SlottedPageError::TupleTooLarge { len: PAGE, max: max_tuple_size(PAGE) })
+        );
+        assert_eq!(page.insert(b""), Err(SlottedPageError::EmptyTuple));
+    }
+
+    #[test]
+    fn new_slot_does_not_overwrite_tuples() {
+        let mut buf = vec![0u8; 128];
+        PageHeader::new(3, 128).encode(&mut buf);
+        let mut page = SlottedPage::init(&mut buf[..]);
+        page.insert(&[b'A'; 40]).unwrap();
+        let b = page.insert(&[b'B'; 40]).unwrap();
+        page.insert(&[b'C'; 8]).unwrap();
+        // Хвост укороченного кортежа — дыра, а перед каталогом места нет
+        page.update(b, &[b'b'; 10]).unwrap();
+        let e = page.insert(&[b'E'; 8]).unwrap();
+
+        let live: Vec<_> = page.iter().map(|(slot, tuple)| (slot, tuple.to_vec())).collect();
+        let expected = [(b'A', 40), (b'b', 10), (b'C', 8), (b'E', 8)];
+        let expected: Vec<_> =
+            expected.iter().zip(0..).map(|(&(byte, len), slot)| (slot, vec![byte; len])).collect();
+        assert_eq!(live, expected);
+        assert_eq!(e, 3);
+        assert!(SlottedPage::open(&buf[..]).is_ok());
+    }
+
+    #[test]
+    fn fresh_page_is_empty_and_bad_directory_is_rejected() {
+        let mut buf = new_page();
+        assert_eq!(SlottedPage::open(&buf[..]).unwrap().live_count(), 0);
+
+        // 64 КБ: конец страницы не помещается в u16 и кодируется нулём
+        let mut big = vec![0u8; 64 * 1024];
+        PageHeader::new(0, big.len()).encode(&mut big);
+        let mut page = SlottedPage::init(&mut big[..]);
+        let slot = page.insert(&[1u8; 40_000]).unwrap();
+        assert_eq!(page.get(slot).unwrap().len(), 40_000);
+
+        buf[24..26].copy_from_slice(&200u16.to_le_bytes());
+        assert!(matches!(SlottedPage::open(&buf[..]), Err(SlottedPageError::Corrupt { .. })));
+    }
+}
@@ -0,0 +1,11 @@
+//! Разбор SQL на стороне Rust. C++ `sql::Lexer` и `sql::Parser` знают
+//! только SELECT/INSERT/CREATE TABLE; модули здесь покрывают остальной
+//! диалект и сообщают о позициях ошибок.
+
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+
+pub use ast::{Expr, Statement};
+pub use lexer::{tokenize, Diagnostic, Keyword, Lexer, Position, Span, Token, TokenKind};
+pub use parser::{parse, ParseError};
@@ -0,0 +1,447 @@
+//! Дерево разбора SQL (`parser`). В отличие от C++ `sql::Statement` с
+//! тремя видами операторов и значениями-строками, выражения здесь —
+//! деревья с литералами `Value`.
+//!
+//! `Display` печатает оператор обратно в SQL (как C++ `to_string`):
+//! каждое составное выражение в скобках, идентификаторы, совпадающие с
+//! ключевыми словами или не похожие на слово, — в двойных кавычках.
+//! Напечатанный оператор разбирается в то же дерево.
+
+use super::lexer::Keyword;
+use crate::value::{DataType, Value};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select(Box<Select>),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    CreateTable(CreateTable),
+    DropTable(DropTable),
+    CreateIndex(CreateIndex),
+}
+
+/// `SELECT`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Select {
+    pub distinct: bool,
+    pub projection: Vec<SelectItem>,
+    pub from: Option<TableRef>,
+    pub joins: Vec<Join>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<Expr>,
+    pub offset: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    /// `*`
+    Wildcard,
+    /// `t.*`
+    QualifiedWildcard(String),
+    Expr {
+        expr: Expr,
+        alias: Option<String>,
+    },
+}
+
+/// Таблица во `FROM` или `JOIN`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    /// `CROSS JOIN` или таблица через запятую
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableRef,
+    /// Условие `ON`; у `Cross` его нет
+    pub on: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+/// `INSERT INTO table (columns) VALUES (...), (...)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    /// Пусто — все столбцы по порядку
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Expr>>,
+}
+
+/// `UPDATE table SET column = value, ... WHERE ...`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub table: String,
+    pub assignments: Vec<(String, Expr)>,
+    pub filter: Option<Expr>,
+}
+
+/// `DELETE FROM table WHERE ...`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delete {
+    pub table: String,
+    pub filter: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub columns: Vec<ColumnDef>,
+    /// Ключ из `PRIMARY KEY (...)` или из столбцов с `PRIMARY KEY`
+    pub primary_key: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+    pub not_null: bool,
+    pub default: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTable {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndex {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+// ============================================================================
+// Выражения
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Числа из запроса — `Int64` или `Double`
+    Literal(Value),
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    /// `*` — только аргумент функции: `COUNT(*)`
+    Star,
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOp,
+        right: Box<Expr>,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        negated: bool,
+    },
+    Function {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Concat,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "OR",
+            BinaryOp::And => "AND",
+            BinaryOp::Eq => "=",
+            BinaryOp::NotEq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::Concat => "||",
+            BinaryOp::Plus => "+",
+            BinaryOp::Minus => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+        }
+    }
+}
+
+impl Expr {
+    pub fn binary(left: Expr, op: BinaryOp, right: Expr) -> Expr {
+        Expr::Binary { left: Box::new(left), op, right: Box::new(right) }
+    }
+}
+
+// ============================================================================
+// Печать
+// ============================================================================
+
+/// Идентификатор, в кавычках при необходимости
+struct Ident<'a>(&'a str);
+
+impl fmt::Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        let plain = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && Keyword::lookup(name).is_none();
+        if plain {
+            f.write_str(name)
+        } else {
+            write!(f, "\"{}\"", name.replace('"', "\"\""))
+        }
+    }
+}
+
+/// Элементы через запятую
+fn list<T>(f: &mut fmt::Formatter<'_>, items: &[T], item: impl Fn(&T) -> String) -> fmt::Result {
+    let items: Vec<String> = items.iter().map(item).collect();
+    f.write_str(&items.join(", "))
+}
+
+fn names(f: &mut fmt::Formatter<'_>, names: &[String]) -> fmt::Result {
+    list(f, names, |name| Ident(name).to_string())
+}
+
+fn exprs(f: &mut fmt::Formatter<'_>, exprs: &[Expr]) -> fmt::Result {
+    list(f, exprs, Expr::to_string)
+}
+
+fn filter(f: &mut fmt::Formatter<'_>, filter: &Option<Expr>) -> fmt::Result {
+    match filter {
+        Some(expr) => write!(f, " WHERE {expr}"),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let not = |negated: bool| if negated { "NOT " } else { "" };
+        match self {
+            Expr::Literal(value) => write!(f, "{value}"),
+            Expr::Column { table: Some(table), name } => {
+                write!(f, "{}.{}", Ident(table), Ident(name))
+            }
+            Expr::Column { table: None, name } => write!(f, "{}", Ident(name)),
+            Expr::Star => f.write_str("*"),
+            Expr::Unary { op: UnaryOp::Not, expr } => write!(f, "(NOT {expr})"),
+            Expr::Unary { op: UnaryOp::Neg, expr } => write!(f, "(- {expr})"),
+            Expr::Binary { left, op, right } => write!(f, "({left} {} {right})", op.symbol()),
+            Expr::IsNull { expr, negated } => write!(f, "({expr} IS {}NULL)", not(*negated)),
+            Expr::InList { expr, list, negated } => {
+                write!(f, "({expr} {}IN (", not(*negated))?;
+                exprs(f, list)?;
+                f.write_str("))")
+            }
+            Expr::Between { expr, low, high, negated } => {
+                write!(f, "({expr} {}BETWEEN {low} AND {high})", not(*negated))
+            }
+            Expr::Like { expr, pattern, negated } => {
+                write!(f, "({expr} {}LIKE {pattern})", not(*negated))
+            }
+            Expr::Function { name, args } => {
+                write!(f, "{}(", Ident(name))?;
+                exprs(f, args)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for TableRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Ident(&self.name))?;
+        match &self.alias {
+            Some(alias) => write!(f, " AS {}", Ident(alias)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectItem::Wildcard => f.write_str("*"),
+            SelectItem::QualifiedWildcard(table) => write!(f, "{}.*", Ident(table)),
+            SelectItem::Expr { expr, alias: Some(alias) } => {
+                write!(f, "{expr} AS {}", Ident(alias))
+            }
+            SelectItem::Expr { expr, alias: None } => write!(f, "{expr}"),
+        }
+    }
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.distinct { "SELECT DISTINCT " } else { "SELECT " })?;
+        list(f, &self.projection, SelectItem::to_string)?;
+        if let Some(from) = &self.from {
+            write!(f, " FROM {from}")?;
+        }
+        for join in &self.joins {
+            let kind = match join.kind {
+                JoinKind::Inner => "JOIN",
+                JoinKind::Left => "LEFT JOIN",
+                JoinKind::Right => "RIGHT JOIN",
+                JoinKind::Cross => "CROSS JOIN",
+            };
+            write!(f, " {kind} {}", join.table)?;
+            if let Some(on) = &join.on {
+                write!(f, " ON {on}")?;
+            }
+        }
+        filter(f, &self.filter)?;
+        if !self.group_by.is_empty() {
+            f.write_str(" GROUP BY ")?;
+            exprs(f, &self.group_by)?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {having}")?;
+        }
+        if !self.order_by.is_empty() {
+            f.write_str(" ORDER BY ")?;
+            list(f, &self.order_by, |o| {
+                format!("{}{}", o.expr, if o.descending { " DESC" } else { "" })
+            })?;
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, " LIMIT {limit}")?;
+        }
+        if let Some(offset) = &self.offset {
+            write!(f, " OFFSET {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Select(select) => write!(f, "{select}"),
+            Statement::Insert(insert) => {
+                write!(f, "INSERT INTO {}", Ident(&insert.table))?;
+                if !insert.columns.is_empty() {
+                    f.write_str(" (")?;
+                    names(f, &insert.columns)?;
+                    f.write_str(")")?;
+                }
+                f.write_str(" VALUES ")?;
+                list(f, &insert.rows, |row| {
+                    let values: Vec<String> = row.iter().map(Expr::to_string).collect();
+                    format!("({})", values.join(", "))
+                })
+            }
+            Statement::Update(update) => {
+                write!(f, "UPDATE {} SET ", Ident(&update.table))?;
+                list(f, &update.assignments, |(column, value)| {
+                    format!("{} = {value}", Ident(column))
+                })?;
+                filter(f, &update.filter)
+            }
+            Statement::Delete(delete) => {
+                write!(f, "DELETE FROM {}", Ident(&delete.table))?;
+                filter(f, &delete.filter)
+            }
+            Statement::CreateTable(create) => {
+                f.write_str("CREATE TABLE ")?;
+                if create.if_not_exists {
+                    f.write_str("IF NOT EXISTS ")?;
+                }
+                write!(f, "{} (", Ident(&create.name))?;
+                list(f, &create.columns, |column| {
+                    let mut def = format!("{} {}", Ident(&column.name), column.data_type);
+                    if column.not_null {
+                        def.push_str(" NOT NULL");
+                    }
+                    if let Some(default) = &column.default {
+                        def.push_str(&format!(" DEFAULT {default}"));
+                    }
+                    def
+                })?;
+                if !create.primary_key.is_empty() {
+                    f.write_str(", PRIMARY KEY (")?;
+                    names(f, &create.primary_key)?;
+                    f.write_str(")")?;
+                }
+                f.write_str(")")
+            }
+            Statement::DropTable(drop) => {
+                let if_exists = if drop.if_exists { "IF EXISTS " } else { "" };
+                write!(f, "DROP TABLE {if_exists}{}", Ident(&drop.name))
+            }
+            Statement::CreateIndex(index) => {
+                let unique = if index.unique { "UNIQUE " } else { "" };
+                write!(
+                    f,
+                    "CREATE {unique}INDEX {} ON {} (",
+                    Ident(&index.name),
+                    Ident(&index.table)
+                )?;
+                names(f, &index.columns)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
@@ -0,0 +1,667 @@
+//! Лексер SQL. В отличие от C++ `Lexer`, который знает восемь ключевых
+//! слов и останавливается на первом `ILLEGAL`, он разбирает весь вход:
+//! непонятный символ, незакрытая строка или комментарий становятся
+//! диагностикой, и разбор идёт дальше.
+//!
+//! - ключевые слова (`Keyword`) — без учёта регистра; прочие слова —
+//!   идентификаторы, в том числе имена типов (`INT`, `TEXT`, ...);
+//! - `"Quoted ""name"""` — идентификатор с сохранённым регистром, `""`
+//!   внутри — кавычка;
+//! - `'it''s'` — строка; внутри `''` и экранирование `\'`, `\\`, `\n`,
+//!   `\r`, `\t`, `\0`;
+//! - числа `42`, `3.14`, `.5`, `1e10`, `2.5E-3` хранятся текстом;
+//! - комментарии `-- до конца строки` и `/* ... */`.
+//!
+//! Позиции как у C++ `Token`: строки и столбцы с 1, столбец считается в
+//! символах, а не в байтах.
+
+use std::fmt;
+
+/// Место во входе
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// Смещение в байтах
+    pub offset: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Диапазон входа: `end` — позиция сразу после последнего символа
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Текст диапазона
+    pub fn text(self, input: &str) -> &str {
+        &input[self.start.offset..self.end.offset]
+    }
+}
+
+/// Ошибка лексера; разбор после неё продолжается
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span.start, self.message)
+    }
+}
+
+// ============================================================================
+// Токены
+// ============================================================================
+
+/// Ключевое слово SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keyword {
+    Add,
+    All,
+    Alter,
+    And,
+    As,
+    Asc,
+    Between,
+    By,
+    Column,
+    Create,
+    Cross,
+    Default,
+    Delete,
+    Desc,
+    Distinct,
+    Drop,
+    Exists,
+    False,
+    From,
+    Group,
+    Having,
+    If,
+    In,
+    Index,
+    Inner,
+    Insert,
+    Into,
+    Is,
+    Join,
+    Key,
+    Left,
+    Like,
+    Limit,
+    Not,
+    Null,
+    Offset,
+    On,
+    Or,
+    Order,
+    Outer,
+    Primary,
+    Right,
+    Select,
+    Set,
+    Table,
+    True,
+    Unique,
+    Update,
+    Values,
+    Where,
+}
+
+impl Keyword {
+    pub const ALL: [Keyword; 50] = [
+        Keyword::Add,
+        Keyword::All,
+        Keyword::Alter,
+        Keyword::And,
+        Keyword::As,
+        Keyword::Asc,
+        Keyword::Between,
+        Keyword::By,
+        Keyword::Column,
+        Keyword::Create,
+        Keyword::Cross,
+        Keyword::Default,
+        Keyword::Delete,
+        Keyword::Desc,
+        Keyword::Distinct,
+        Keyword::Drop,
+        Keyword::Exists,
+        Keyword::False,
+        Keyword::From,
+        Keyword::Group,
+        Keyword::Having,
+        Keyword::If,
+        Keyword::In,
+        Keyword::Index,
+        Keyword::Inner,
+        Keyword::Insert,
+        Keyword::Into,
+        Keyword::Is,
+        Keyword::Join,
+        Keyword::Key,
+        Keyword::Left,
+        Keyword::Like,
+        Keyword::Limit,
+        Keyword::Not,
+        Keyword::Null,
+        Keyword::Offset,
+        Keyword::On,
+        Keyword::Or,
+        Keyword::Order,
+        Keyword::Outer,
+        Keyword::Primary,
+        Keyword::Right,
+        Keyword::Select,
+        Keyword::Set,
+        Keyword::Table,
+        Keyword::True,
+        Keyword::Unique,
+        Keyword::Update,
+        Keyword::Values,
+        Keyword::Where,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Keyword::Add => "ADD",
+            Keyword::All => "ALL",
+            Keyword::Alter => "ALTER",
+            Keyword::And => "AND",
+            Keyword::As => "AS",
+            Keyword::Asc => "ASC",
+            Keyword::Between => "BETWEEN",
+            Keyword::By => "BY",
+            Keyword::Column => "COLUMN",
+            Keyword::Create => "CREATE",
+            Keyword::Cross => "CROSS",
+            Keyword::Default => "DEFAULT",
+            Keyword::Delete => "DELETE",
+            Keyword::Desc => "DESC",
+            Keyword::Distinct => "DISTINCT",
+            Keyword::Drop => "DROP",
+            Keyword::Exists => "EXISTS",
+            Keyword::False => "FALSE",
+            Keyword::From => "FROM",
+            Keyword::Group => "GROUP",
+            Keyword::Having => "HAVING",
+            Keyword::If => "IF",
+            Keyword::In => "IN",
+            Keyword::Index => "INDEX",
+            Keyword::Inner => "INNER",
+            Keyword::Insert => "INSERT",
+            Keyword::Into => "INTO",
+            Keyword::Is => "IS",
+            Keyword::Join => "JOIN",
+            Keyword::Key => "KEY",
+            Keyword::Left => "LEFT",
+            Keyword::Like => "LIKE",
+            Keyword::Limit => "LIMIT",
+            Keyword::Not => "NOT",
+            Keyword::Null => "NULL",
+            Keyword::Offset => "OFFSET",
+            Keyword::On => "ON",
+            Keyword::Or => "OR",
+            Keyword::Order => "ORDER",
+            Keyword::Outer => "OUTER",
+            Keyword::Primary => "PRIMARY",
+            Keyword::Right => "RIGHT",
+            Keyword::Select => "SELECT",
+            Keyword::Set => "SET",
+            Keyword::Table => "TABLE",
+            Keyword::True => "TRUE",
+            Keyword::Unique => "UNIQUE",
+            Keyword::Update => "UPDATE",
+            Keyword::Values => "VALUES",
+            Keyword::Where => "WHERE",
+        }
+    }
+
+    /// Ключевое слово, которым является `word` (без учёта регистра)
+    pub fn lookup(word: &str) -> Option<Keyword> {
+        Keyword::ALL.into_iter().find(|k| k.name().eq_ignore_ascii_case(word))
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Вид токена
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword(Keyword),
+    Identifier(String),
+    /// `"Name"`: регистр сохраняется, ключевым словом не бывает
+    QuotedIdentifier(String),
+    /// Строка без кавычек, экранирование уже раскрыто
+    String(String),
+    /// Число как в тексте: `42`, `1.5e3`
+    Number(String),
+    Star,
+    Comma,
+    Dot,
+    Semicolon,
+    LParen,
+    RParen,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    /// `||`
+    Concat,
+    Eq,
+    /// `<>` или `!=`
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    /// Токен так, как он выглядит в запросе
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            TokenKind::Keyword(keyword) => return write!(f, "{keyword}"),
+            TokenKind::Identifier(name) => return f.write_str(name),
+            TokenKind::QuotedIdentifier(name) => {
+                return write!(f, "\"{}\"", name.replace('"', "\"\""))
+            }
+            TokenKind::String(s) => return write!(f, "'{}'", s.replace('\'', "''")),
+            TokenKind::Number(n) => return f.write_str(n),
+            TokenKind::Star => "*",
+            TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
+            TokenKind::Semicolon => ";",
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Concat => "||",
+            TokenKind::Eq => "=",
+            TokenKind::NotEq => "<>",
+            TokenKind::Lt => "<",
+            TokenKind::LtEq => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::GtEq => ">=",
+            TokenKind::Eof => "end of input",
+        };
+        f.write_str(symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+// ============================================================================
+// Лексер
+// ============================================================================
+
+/// Разбить весь вход на токены (последний — `Eof`) и собрать диагностики
+pub fn tokenize(input: &str) -> (Vec<Token>, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        let eof = token.kind == TokenKind::Eof;
+        tokens.push(token);
+        if eof {
+            return (tokens, lexer.diagnostics);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    pos: Position,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let pos = Position { offset: 0, line: 1, column: 1 };
+        Self { input, pos, diagnostics: Vec::new() }
+    }
+
+    /// Диагностики, собранные до сих пор
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Следующий токен; в конце входа — `Eof`, сколько ни вызывай
+    pub fn next_token(&mut self) -> Token {
+        loop {
+            self.skip_trivia();
+            let start = self.pos;
+            let Some(c) = self.bump() else {
+                return Token { kind: TokenKind::Eof, span: Span { start, end: start } };
+            };
+            let kind = match c {
+                '*' => TokenKind::Star,
+                ',' => TokenKind::Comma,
+                ';' => TokenKind::Semicolon,
+                '(' => TokenKind::LParen,
+                ')' => TokenKind::RParen,
+                '+' => TokenKind::Plus,
+                '-' => TokenKind::Minus,
+                '/' => TokenKind::Slash,
+                '%' => TokenKind::Percent,
+                '=' => TokenKind::Eq,
+                '|' if self.eat('|') => TokenKind::Concat,
+                '!' if self.eat('=') => TokenKind::NotEq,
+                '<' if self.eat('=') => TokenKind::LtEq,
+                '<' if self.eat('>') => TokenKind::NotEq,
+                '<' => TokenKind::Lt,
+                '>' if self.eat('=') => TokenKind::GtEq,
+                '>' => TokenKind::Gt,
+                '.' if self.peek().is_some_and(|c| c.is_ascii_digit()) => self.number(start),
+                '.' => TokenKind::Dot,
+                '\'' => TokenKind::String(self.string(start)),
+                '"' => TokenKind::QuotedIdentifier(self.quoted_identifier(start)),
+                c if c.is_ascii_digit() => self.number(start),
+                c if c.is_alphabetic() || c == '_' => {
+                    while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                        self.bump();
+                    }
+                    let word = &self.input[start.offset..self.pos.offset];
+                    match Keyword::lookup(word) {
+                        Some(keyword) => TokenKind::Keyword(keyword),
+                        None => TokenKind::Identifier(word.to_string()),
+                    }
+                }
+                c => {
+                    self.report(start, format!("unexpected character {c:?}"));
+                    continue;
+                }
+            };
+            return Token { kind, span: Span { start, end: self.pos } };
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos.offset..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.pos.offset..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos.offset += c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Съесть `c`, если он следующий
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn report(&mut self, start: Position, message: impl Into<String>) {
+        let span = Span { start, end: self.pos };
+        self.diagnostics.push(Diagnostic { message: message.into(), span });
+    }
+
+    /// Пропустить пробелы и комментарии
+    fn skip_trivia(&mut self) {
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('-'), Some('-')) => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let start = self.pos;
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match self.bump() {
+                            Some('*') if self.eat('/') => break,
+                            Some(_) => {}
+                            None => {
+                                self.report(start, "unterminated comment");
+                                return;
+                            }
+                        }
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Цифры, дробная часть и порядок; первый символ уже съеден
+    fn number(&mut self, start: Position) -> TokenKind {
+        let digits = |lexer: &mut Self| {
+            let mut any = false;
+            while lexer.peek().is_some_and(|c| c.is_ascii_digit()) {
+                lexer.bump();
+                any = true;
+            }
+            any
+        };
+        digits(self);
+        let fraction_started = self.input[start.offset..self.pos.offset].contains('.');
+        if !fraction_started && self.peek() == Some('.') {
+            self.bump();
+            digits(self);
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.bump();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.bump();
+            }
+            if !digits(self) {
+                self.report(start, "missing digits in exponent");
+            }
+        }
+        TokenKind::Number(self.input[start.offset..self.pos.offset].to_string())
+    }
+
+    /// Строка в одинарных кавычках; открывающая кавычка уже съедена
+    fn string(&mut self, start: Position) -> String {
+        let mut value = String::new();
+        loop {
+            let escape_start = self.pos;
+            match self.bump() {
+                Some('\'') if self.eat('\'') => value.push('\''),
+                Some('\'') => return value,
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('0') => value.push('\0'),
+                    Some(c @ ('\\' | '\'' | '"')) => value.push(c),
+                    Some(c) => {
+                        self.report(escape_start, format!("unknown escape sequence \\{c}"));
+                        value.push(c);
+                    }
+                    None => break,
+                },
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        self.report(start, "unterminated string literal");
+        value
+    }
+
+    /// Идентификатор в двойных кавычках; открывающая кавычка уже съедена
+    fn quoted_identifier(&mut self, start: Position) -> String {
+        let mut name = String::new();
+        loop {
+            match self.bump() {
+                Some('"') if self.eat('"') => name.push('"'),
+                Some('"') => break,
+                Some(c) => name.push(c),
+                None => {
+                    self.report(start, "unterminated quoted identifier");
+                    return name;
+                }
+            }
+        }
+        if name.is_empty() {
+            self.report(start, "empty quoted identifier");
+        }
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        let (tokens, diagnostics) = tokenize(input);
+        assert_eq!(diagnostics, vec![], "{input}");
+        tokens.into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn statements_tokenize_with_positions() {
+        use TokenKind::{
+            Comma, Concat, Dot, Eof, Eq, Gt, GtEq, Identifier, Lt, LtEq, NotEq, Number,
+            QuotedIdentifier, Semicolon, String,
+        };
+        let kw = TokenKind::Keyword;
+        let id = |s: &str| Identifier(s.to_string());
+        assert_eq!(
+            kinds("select \"Order\".id, name AS n FROM t -- comment\nWHERE a<>1 AND NOT b>=2.5e-3"),
+            vec![
+                kw(Keyword::Select),
+                QuotedIdentifier("Order".into()),
+                Dot,
+                id("id"),
+                Comma,
+                id("name"),
+                kw(Keyword::As),
+                id("n"),
+                kw(Keyword::From),
+                id("t"),
+                kw(Keyword::Where),
+                id("a"),
+                NotEq,
+                Number("1".into()),
+                kw(Keyword::And),
+                kw(Keyword::Not),
+                id("b"),
+                GtEq,
+                Number("2.5e-3".into()),
+                Eof,
+            ]
+        );
+        assert_eq!(
+            kinds("x!=.5 /* a\n * b */||'it''s\\n' \"a\"\"b\" <= < > 1.E4 t.c;"),
+            vec![
+                id("x"),
+                NotEq,
+                Number(".5".into()),
+                Concat,
+                String("it's\n".into()),
+                QuotedIdentifier("a\"b".into()),
+                LtEq,
+                Lt,
+                Gt,
+                Number("1.E4".into()),
+                id("t"),
+                Dot,
+                id("c"),
+                Semicolon,
+                Eof,
+            ]
+        );
+        assert_eq!(
+            kinds("update Users set ORDER_ = 1 order by group limit"),
+            [
+                kw(Keyword::Update),
+                id("Users"),
+                kw(Keyword::Set),
+                id("ORDER_"),
+                Eq,
+                Number("1".into()),
+                kw(Keyword::Order),
+                kw(Keyword::By),
+                kw(Keyword::Group),
+                kw(Keyword::Limit),
+                Eof,
+            ]
+        );
+
+        let input = "SELECT\n  привет, 'x'\n";
+        let (tokens, _) = tokenize(input);
+        let at = |t: &Token| (t.span.start.line, t.span.start.column, t.span.end.column);
+        assert_eq!(
+            tokens.iter().map(at).collect::<Vec<_>>(),
+            [(1, 1, 7), (2, 3, 9), (2, 9, 10), (2, 11, 14), (3, 1, 1)]
+        );
+        assert_eq!(tokens[1].span.text(input), "привет");
+        for keyword in Keyword::ALL {
+            assert_eq!(Keyword::lookup(&keyword.name().to_lowercase()), Some(keyword));
+        }
+    }
+
+    #[test]
+    fn errors_are_collected_and_lexing_goes_on() {
+        let input = "SELECT # a,\n  @ 'open";
+        let (tokens, diagnostics) = tokenize(input);
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.to_string()).collect();
+        assert_eq!(kinds, ["SELECT", "a", ",", "'open'", "end of input"]);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "1:8: unexpected character '#'",
+                "2:3: unexpected character '@'",
+                "2:5: unterminated string literal"
+            ]
+        );
+
+        let (tokens, diagnostics) = tokenize("1e+ x 'a\\q' \"\" /* never closed");
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "1:1: missing digits in exponent",
+                "1:9: unknown escape sequence \\q",
+                "1:13: empty quoted identifier",
+                "1:16: unterminated comment"
+            ]
+        );
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[2].kind, TokenKind::String("aq".into()));
+    }
+}
@@ -0,0 +1,812 @@
+//! Парсер SQL: рекурсивный спуск по операторам и Pratt-разбор выражений.
+//! Заменяет C++ `sql::Parser` и поиск скобок в строке у `DatabaseEngine`.
+//!
+//! ```text
+//! SELECT [DISTINCT | ALL] items [FROM table [[AS] alias]
+//!     {, table | [INNER | LEFT [OUTER] | RIGHT [OUTER]] JOIN table ON expr | CROSS JOIN table}]
+//!     [WHERE expr] [GROUP BY exprs [HAVING expr]] [ORDER BY expr [ASC | DESC], ...]
+//!     [LIMIT expr] [OFFSET expr]
+//! INSERT INTO table [(columns)] VALUES (exprs), ...
+//! UPDATE table SET column = expr, ... [WHERE expr]
+//! DELETE FROM table [WHERE expr]
+//! CREATE TABLE [IF NOT EXISTS] table (column type [NOT NULL | NULL | DEFAULT expr
+//!     | PRIMARY KEY]..., [PRIMARY KEY (columns)])
+//! DROP TABLE [IF EXISTS] table
+//! CREATE [UNIQUE] INDEX name ON table (columns)
+//! ```
+//! Операторы разделяются `;`. Приоритеты операторов от слабых к сильным:
+//! `OR`; `AND`; `NOT`; сравнения, `IS [NOT] NULL`, `[NOT] IN`,
+//! `[NOT] BETWEEN`, `[NOT] LIKE`; `||`; `+ -`; `* / %`; унарные `- +`.
+//! Бинарные операторы левоассоциативны.
+//!
+//! Ошибка указывает на токен, на котором разбор остановился, и перечисляет
+//! всё, что парсер пробовал в этом месте. Диагностики лексера — тоже
+//! ошибки: возвращается первая из них.
+//!
+//! Выражения разбираются рекурсивно, поэтому вложенность (скобки, унарные
+//! операторы) ограничена `MAX_DEPTH`: иначе запрос из тысяч скобок
+//! переполнил бы стек.
+
+use super::ast::{
+    BinaryOp, ColumnDef, CreateIndex, CreateTable, Delete, DropTable, Expr, Insert, Join, JoinKind,
+    OrderBy, Select, SelectItem, Statement, TableRef, UnaryOp, Update,
+};
+use super::lexer::{tokenize, Diagnostic, Keyword, Span, Token, TokenKind};
+use crate::value::{DataType, Value};
+use std::fmt;
+
+/// Ошибка разбора
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub span: Span,
+    pub message: String,
+    /// Что могло стоять на месте `span`
+    pub expected: Vec<String>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span.start, self.message)?;
+        if let Some((last, rest)) = self.expected.split_last() {
+            f.write_str("; expected ")?;
+            if !rest.is_empty() {
+                write!(f, "{} or ", rest.join(", "))?;
+            }
+            f.write_str(last)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Разобрать операторы через `;`
+pub fn parse(input: &str) -> Result<Vec<Statement>, ParseError> {
+    let (tokens, diagnostics) = tokenize(input);
+    if let Some(diagnostic) = diagnostics.into_iter().next() {
+        let Diagnostic { message, span } = diagnostic;
+        return Err(ParseError { span, message, expected: Vec::new() });
+    }
+    let mut parser = Parser { tokens, pos: 0, expected: Vec::new(), depth: 0 };
+    let mut statements = Vec::new();
+    while parser.peek().kind != TokenKind::Eof {
+        // Пустой оператор не стоит упоминать в ошибках
+        if parser.peek().kind == TokenKind::Semicolon {
+            parser.advance();
+            continue;
+        }
+        statements.push(parser.statement()?);
+        if !parser.check(&TokenKind::Eof) {
+            parser.expect(&TokenKind::Semicolon)?;
+        }
+    }
+    Ok(statements)
+}
+
+/// Наибольшая вложенность выражения
+pub const MAX_DEPTH: usize = 256;
+
+// Приоритеты: оператор с приоритетом p разбирает правую часть с p + 1
+const OR: u8 = 1;
+const AND: u8 = 2;
+const NOT: u8 = 3;
+const COMPARISON: u8 = 4;
+const CONCAT: u8 = 5;
+const SUM: u8 = 6;
+const PRODUCT: u8 = 7;
+const UNARY: u8 = 8;
+
+fn binary_op(kind: &TokenKind) -> Option<(u8, BinaryOp)> {
+    let op = match kind {
+        TokenKind::Keyword(Keyword::Or) => (OR, BinaryOp::Or),
+        TokenKind::Keyword(Keyword::And) => (AND, BinaryOp::And),
+        TokenKind::Eq => (COMPARISON, BinaryOp::Eq),
+        TokenKind::NotEq => (COMPARISON, BinaryOp::NotEq),
+        TokenKind::Lt => (COMPARISON, BinaryOp::Lt),
+        TokenKind::LtEq => (COMPARISON, BinaryOp::LtEq),
+        TokenKind::Gt => (COMPARISON, BinaryOp::Gt),
+        TokenKind::GtEq => (COMPARISON, BinaryOp::GtEq),
+        TokenKind::Concat => (CONCAT, BinaryOp::Concat),
+        TokenKind::Plus => (SUM, BinaryOp::Plus),
+        TokenKind::Minus => (SUM, BinaryOp::Minus),
+        TokenKind::Star => (PRODUCT, BinaryOp::Multiply),
+        TokenKind::Slash => (PRODUCT, BinaryOp::Divide),
+        TokenKind::Percent => (PRODUCT, BinaryOp::Modulo),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Токен в сообщении об ошибке
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Keyword(keyword) => keyword.name().to_string(),
+        TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_) => format!("identifier {kind}"),
+        TokenKind::String(_) => format!("string {kind}"),
+        TokenKind::Number(_) => format!("number {kind}"),
+        TokenKind::Eof => kind.to_string(),
+        _ => format!("'{kind}'"),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Что пробовали на текущем токене
+    expected: Vec<String>,
+    /// Вложенность `expr_with`
+    depth: usize,
+}
+
+impl Parser {
+    // ========================================================================
+    // Токены
+    // ========================================================================
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    /// Токен через `n` от текущего (или `Eof`)
+    fn peek_nth(&self, n: usize) -> &TokenKind {
+        &self.tokens[(self.pos + n).min(self.tokens.len() - 1)].kind
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        self.expected.clear();
+        token
+    }
+
+    /// Стоит ли `kind`; если нет, он попадёт в список ожидаемого
+    fn check(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().kind == *kind {
+            return true;
+        }
+        self.expect_here(describe(kind));
+        false
+    }
+
+    fn expect_here(&mut self, description: impl Into<String>) {
+        let description = description.into();
+        if !self.expected.contains(&description) {
+            self.expected.push(description);
+        }
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.check(kind);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: Keyword) -> bool {
+        self.eat(&TokenKind::Keyword(keyword))
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> Result<(), ParseError> {
+        if self.eat(kind) {
+            return Ok(());
+        }
+        Err(self.error())
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), ParseError> {
+        self.expect(&TokenKind::Keyword(keyword))
+    }
+
+    /// Ошибка на текущем токене со списком ожидаемого
+    fn error(&self) -> ParseError {
+        let token = self.peek();
+        ParseError {
+            span: token.span,
+            message: format!("unexpected {}", describe(&token.kind)),
+            expected: self.expected.clone(),
+        }
+    }
+
+    fn at_identifier(&self) -> bool {
+        matches!(self.peek().kind, TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_))
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        if self.at_identifier() {
+            let (TokenKind::Identifier(name) | TokenKind::QuotedIdentifier(name)) =
+                self.advance().kind
+            else {
+                unreachable!("checked by at_identifier")
+            };
+            return Ok(name);
+        }
+        self.expect_here("identifier");
+        Err(self.error())
+    }
+
+    /// `item, item, ...`
+    fn comma_list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![item(self)?];
+        while self.eat(&TokenKind::Comma) {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    /// `(item, item, ...)`
+    fn parenthesized<T>(
+        &mut self,
+        item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        self.expect(&TokenKind::LParen)?;
+        let items = self.comma_list(item)?;
+        self.expect(&TokenKind::RParen)?;
+        Ok(items)
+    }
+
+    // ========================================================================
+    // Операторы
+    // ========================================================================
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.eat_keyword(Keyword::Select) {
+            return Ok(Statement::Select(Box::new(self.select()?)));
+        }
+        if self.eat_keyword(Keyword::Insert) {
+            return self.insert();
+        }
+        if self.eat_keyword(Keyword::Update) {
+            return self.update();
+        }
+        if self.eat_keyword(Keyword::Delete) {
+            self.expect_keyword(Keyword::From)?;
+            let table = self.ident()?;
+            let filter = self.filter()?;
+            return Ok(Statement::Delete(Delete { table, filter }));
+        }
+        if self.eat_keyword(Keyword::Create) {
+            if self.eat_keyword(Keyword::Table) {
+                return self.create_table();
+            }
+            let unique = self.eat_keyword(Keyword::Unique);
+            self.expect_keyword(Keyword::Index)?;
+            let name = self.ident()?;
+            self.expect_keyword(Keyword::On)?;
+            let table = self.ident()?;
+            let columns = self.parenthesized(Self::ident)?;
+            return Ok(Statement::CreateIndex(CreateIndex { name, table, columns, unique }));
+        }
+        if self.eat_keyword(Keyword::Drop) {
+            self.expect_keyword(Keyword::Table)?;
+            let if_exists = self.eat_keyword(Keyword::If);
+            if if_exists {
+                self.expect_keyword(Keyword::Exists)?;
+            }
+            let name = self.ident()?;
+            return Ok(Statement::DropTable(DropTable { name, if_exists }));
+        }
+        Err(self.error())
+    }
+
+    fn select(&mut self) -> Result<Select, ParseError> {
+        let distinct = self.eat_keyword(Keyword::Distinct);
+        if !distinct {
+            self.eat_keyword(Keyword::All);
+        }
+        let mut select = Select { distinct, ..Select::default() };
+        select.projection = self.comma_list(Self::select_item)?;
+        if self.eat_keyword(Keyword::From) {
+            select.from = Some(self.table_ref()?);
+            while let Some(kind) = self.join_kind()? {
+                let table = self.table_ref()?;
+                let on = match kind {
+                    JoinKind::Cross => None,
+                    _ => {
+                        self.expect_keyword(Keyword::On)?;
+                        Some(self.expr()?)
+                    }
+                };
+                select.joins.push(Join { kind, table, on });
+            }
+        }
+        select.filter = self.filter()?;
+        if self.eat_keyword(Keyword::Group) {
+            self.expect_keyword(Keyword::By)?;
+            select.group_by = self.comma_list(Self::expr)?;
+            if self.eat_keyword(Keyword::Having) {
+                select.having = Some(self.expr()?);
+            }
+        }
+        if self.eat_keyword(Keyword::Order) {
+            self.expect_keyword(Keyword::By)?;
+            select.order_by = self.comma_list(|parser| {
+                let expr = parser.expr()?;
+                let descending = parser.eat_keyword(Keyword::Desc);
+                if !descending {
+                    parser.eat_keyword(Keyword::Asc);
+                }
+                Ok(OrderBy { expr, descending })
+            })?;
+        }
+        if self.eat_keyword(Keyword::Limit) {
+            select.limit = Some(self.expr()?);
+        }
+        if self.eat_keyword(Keyword::Offset) {
+            select.offset = Some(self.expr()?);
+        }
+        Ok(select)
+    }
+
+    fn select_item(&mut self) -> Result<SelectItem, ParseError> {
+        if self.eat(&TokenKind::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        if self.at_identifier()
+            && *self.peek_nth(1) == TokenKind::Dot
+            && *self.peek_nth(2) == TokenKind::Star
+        {
+            let table = self.ident()?;
+            self.advance();
+            self.advance();
+            return Ok(SelectItem::QualifiedWildcard(table));
+        }
+        let expr = self.expr()?;
+        let alias = self.alias()?;
+        Ok(SelectItem::Expr { expr, alias })
+    }
+
+    /// `[AS] alias`
+    fn alias(&mut self) -> Result<Option<String>, ParseError> {
+        if self.eat_keyword(Keyword::As) || self.at_identifier() {
+            return self.ident().map(Some);
+        }
+        self.expect_here("identifier");
+        Ok(None)
+    }
+
+    fn table_ref(&mut self) -> Result<TableRef, ParseError> {
+        let name = self.ident()?;
+        let alias = self.alias()?;
+        Ok(TableRef { name, alias })
+    }
+
+    /// Начало следующего соединения
+    fn join_kind(&mut self) -> Result<Option<JoinKind>, ParseError> {
+        if self.eat(&TokenKind::Comma) {
+            return Ok(Some(JoinKind::Cross));
+        }
+        if self.eat_keyword(Keyword::Join) {
+            return Ok(Some(JoinKind::Inner));
+        }
+        let kind = if self.eat_keyword(Keyword::Inner) {
+            JoinKind::Inner
+        } else if self.eat_keyword(Keyword::Cross) {
+            JoinKind::Cross
+        } else if self.eat_keyword(Keyword::Left) {
+            self.eat_keyword(Keyword::Outer);
+            JoinKind::Left
+        } else if self.eat_keyword(Keyword::Right) {
+            self.eat_keyword(Keyword::Outer);
+            JoinKind::Right
+        } else {
+            return Ok(None);
+        };
+        self.expect_keyword(Keyword::Join)?;
+        Ok(Some(kind))
+    }
+
+    /// `[WHERE expr]`
+    fn filter(&mut self) -> Result<Option<Expr>, ParseError> {
+        if self.eat_keyword(Keyword::Where) {
+            return self.expr().map(Some);
+        }
+        Ok(None)
+    }
+
+    fn insert(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword(Keyword::Into)?;
+        let table = self.ident()?;
+        let columns =
+            if self.check(&TokenKind::LParen) { self.parenthesized(Self::ident)? } else { vec![] };
+        self.expect_keyword(Keyword::Values)?;
+        let rows = self.comma_list(|parser| parser.parenthesized(Self::expr))?;
+        Ok(Statement::Insert(Insert { table, columns, rows }))
+    }
+
+    fn update(&mut self) -> Result<Statement, ParseError> {
+        let table = self.ident()?;
+        self.expect_keyword(Keyword::Set)?;
+        let assignments = self.comma_list(|parser| {
+            let column = parser.ident()?;
+            parser.expect(&TokenKind::Eq)?;
+            Ok((column, parser.expr()?))
+        })?;
+        let filter = self.filter()?;
+        Ok(Statement::Update(Update { table, assignments, filter }))
+    }
+
+    fn create_table(&mut self) -> Result<Statement, ParseError> {
+        let if_not_exists = self.eat_keyword(Keyword::If);
+        if if_not_exists {
+            self.expect_keyword(Keyword::Not)?;
+            self.expect_keyword(Keyword::Exists)?;
+        }
+        let name = self.ident()?;
+        let mut create =
+            CreateTable { name, if_not_exists, columns: Vec::new(), primary_key: Vec::new() };
+        self.expect(&TokenKind::LParen)?;
+        loop {
+            let start = self.peek().span;
+            if self.eat_keyword(Keyword::Primary) {
+                self.expect_keyword(Keyword::Key)?;
+                let key = self.parenthesized(Self::ident)?;
+                set_primary_key(&mut create, key, start)?;
+            } else {
+                self.column_def(&mut create)?;
+            }
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(&TokenKind::RParen)?;
+        Ok(Statement::CreateTable(create))
+    }
+
+    fn column_def(&mut self, create: &mut CreateTable) -> Result<(), ParseError> {
+        let name = self.ident()?;
+        let type_span = self.peek().span;
+        let type_name = self.ident().map_err(|mut e| {
+            e.expected = vec!["data type".to_string()];
+            e
+        })?;
+        let data_type: DataType =
+            type_name.parse().map_err(|message| invalid(type_span, message, &["data type"]))?;
+        let mut column = ColumnDef { name, data_type, not_null: false, default: None };
+        loop {
+            let start = self.peek().span;
+            if self.eat_keyword(Keyword::Not) {
+                self.expect_keyword(Keyword::Null)?;
+                column.not_null = true;
+            } else if self.eat_keyword(Keyword::Null) {
+                column.not_null = false;
+            } else if self.eat_keyword(Keyword::Default) {
+                column.default = Some(self.expr()?);
+            } else if self.eat_keyword(Keyword::Primary) {
+                self.expect_keyword(Keyword::Key)?;
+                set_primary_key(create, vec![column.name.clone()], start)?;
+            } else {
+                break;
+            }
+        }
+        create.columns.push(column);
+        Ok(())
+    }
+
+    // ========================================================================
+    // Выражения
+    // ========================================================================
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.expr_with(OR)
+    }
+
+    /// Выражение из операторов с приоритетом не ниже `min`
+    fn expr_with(&mut self, min: u8) -> Result<Expr, ParseError> {
+        if self.depth == MAX_DEPTH {
+            let message = format!("expression is nested deeper than {MAX_DEPTH} levels");
+            return Err(ParseError { span: self.peek().span, message, expected: Vec::new() });
+        }
+        // После ошибки разбор прекращается, так что глубину восстанавливать
+        // нужно только при успехе
+        self.depth += 1;
+        let mut left = self.prefix()?;
+        loop {
+            let kind = &self.peek().kind;
+            let negated = *kind == TokenKind::Keyword(Keyword::Not)
+                && matches!(
+                    self.peek_nth(1),
+                    TokenKind::Keyword(Keyword::In | Keyword::Between | Keyword::Like)
+                );
+            let predicate = negated
+                || matches!(
+                    kind,
+                    TokenKind::Keyword(
+                        Keyword::Is | Keyword::In | Keyword::Between | Keyword::Like
+                    )
+                );
+            if predicate {
+                if min > COMPARISON {
+                    break;
+                }
+                left = self.predicate(left)?;
+                continue;
+            }
+            match binary_op(kind) {
+                Some((precedence, op)) if precedence >= min => {
+                    self.advance();
+                    let right = self.expr_with(precedence + 1)?;
+                    left = Expr::binary(left, op, right);
+                }
+                _ => break,
+            }
+        }
+        self.depth -= 1;
+        Ok(left)
+    }
+
+    /// `IS [NOT] NULL`, `[NOT] IN (...)`, `[NOT] BETWEEN a AND b`,
+    /// `[NOT] LIKE pattern` после `expr`
+    fn predicate(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        let expr = Box::new(expr);
+        if self.eat_keyword(Keyword::Is) {
+            let negated = self.eat_keyword(Keyword::Not);
+            self.expect_keyword(Keyword::Null)?;
+            return Ok(Expr::IsNull { expr, negated });
+        }
+        let negated = self.eat_keyword(Keyword::Not);
+        if self.eat_keyword(Keyword::In) {
+            let list = self.parenthesized(Self::expr)?;
+            return Ok(Expr::InList { expr, list, negated });
+        }
+        if self.eat_keyword(Keyword::Between) {
+            let low = Box::new(self.expr_with(COMPARISON + 1)?);
+            self.expect_keyword(Keyword::And)?;
+            let high = Box::new(self.expr_with(COMPARISON + 1)?);
+            return Ok(Expr::Between { expr, low, high, negated });
+        }
+        self.expect_keyword(Keyword::Like)?;
+        let pattern = Box::new(self.expr_with(COMPARISON + 1)?);
+        Ok(Expr::Like { expr, pattern, negated })
+    }
+
+    /// Литерал, столбец, вызов функции, скобки или унарный оператор
+    fn prefix(&mut self) -> Result<Expr, ParseError> {
+        let token = self.peek().clone();
+        let literal = match &token.kind {
+            TokenKind::Keyword(Keyword::Not) => {
+                self.advance();
+                let expr = Box::new(self.expr_with(NOT)?);
+                return Ok(Expr::Unary { op: UnaryOp::Not, expr });
+            }
+            TokenKind::Minus => {
+                self.advance();
+                let expr = Box::new(self.expr_with(UNARY)?);
+                return Ok(Expr::Unary { op: UnaryOp::Neg, expr });
+            }
+            TokenKind::Plus => {
+                self.advance();
+                return self.expr_with(UNARY);
+            }
+            TokenKind::LParen => {
+                self.advance();
+                let expr = self.expr()?;
+                self.expect(&TokenKind::RParen)?;
+                return Ok(expr);
+            }
+            TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_) => return self.name(),
+            TokenKind::Number(text) => number(text, token.span)?,
+            TokenKind::String(s) => Value::Text(s.clone()),
+            TokenKind::Keyword(Keyword::True) => Value::Bool(true),
+            TokenKind::Keyword(Keyword::False) => Value::Bool(false),
+            TokenKind::Keyword(Keyword::Null) => Value::Null,
+            _ => {
+                self.expect_here("expression");
+                return Err(self.error());
+            }
+        };
+        self.advance();
+        Ok(Expr::Literal(literal))
+    }
+
+    /// Столбец, `table.column` или вызов функции
+    fn name(&mut self) -> Result<Expr, ParseError> {
+        let name = self.ident()?;
+        if self.eat(&TokenKind::Dot) {
+            let column = self.ident()?;
+            return Ok(Expr::Column { table: Some(name), name: column });
+        }
+        if !self.eat(&TokenKind::LParen) {
+            return Ok(Expr::Column { table: None, name });
+        }
+        if self.eat(&TokenKind::RParen) {
+            return Ok(Expr::Function { name, args: Vec::new() });
+        }
+        let args = if *self.peek_nth(1) == TokenKind::RParen && self.eat(&TokenKind::Star) {
+            vec![Expr::Star]
+        } else {
+            self.comma_list(Self::expr)?
+        };
+        self.expect(&TokenKind::RParen)?;
+        Ok(Expr::Function { name, args })
+    }
+}
+
+/// Ошибка в самом токене (не то число, не тот тип)
+fn invalid(span: Span, message: String, expected: &[&str]) -> ParseError {
+    let expected = expected.iter().map(|s| s.to_string()).collect();
+    ParseError { span, message, expected }
+}
+
+/// Целые — `Int64`, числа с точкой или порядком — `Double`
+fn number(text: &str, span: Span) -> Result<Value, ParseError> {
+    if text.contains(['.', 'e', 'E']) {
+        return match text.parse::<f64>() {
+            Ok(d) if d.is_finite() => Ok(Value::Double(d)),
+            _ => Err(invalid(span, format!("number {text} is out of range"), &[])),
+        };
+    }
+    text.parse::<i64>()
+        .map(Value::Int64)
+        .map_err(|_| invalid(span, format!("integer {text} is out of range"), &[]))
+}
+
+/// Первичный ключ задаётся один раз: у столбца или списком
+fn set_primary_key(
+    create: &mut CreateTable,
+    key: Vec<String>,
+    span: Span,
+) -> Result<(), ParseError> {
+    if !create.primary_key.is_empty() {
+        let message = format!("table '{}' already has a primary key", create.name);
+        return Err(ParseError { span, message, expected: Vec::new() });
+    }
+    create.primary_key = key;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one(sql: &str) -> Statement {
+        let mut statements = parse(sql).unwrap_or_else(|e| panic!("{sql}: {e}"));
+        assert_eq!(statements.len(), 1, "{sql}");
+        let statement = statements.pop().unwrap();
+        // Напечатанный оператор разбирается в то же дерево
+        assert_eq!(parse(&statement.to_string()).unwrap(), vec![statement.clone()], "{sql}");
+        statement
+    }
+
+    fn printed(sql: &str) -> String {
+        one(sql).to_string()
+    }
+
+    fn error(sql: &str) -> String {
+        parse(sql).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn expressions_follow_precedence() {
+        let expr = |sql: &str| printed(&format!("SELECT {sql}"))["SELECT ".len()..].to_string();
+        assert_eq!(
+            expr("a OR b AND NOT c = 1 + 2 * -3"),
+            "(a OR (b AND (NOT (c = (1 + (2 * (- 3)))))))"
+        );
+        assert_eq!(expr("1 - 2 - 3 || 'x'"), "(((1 - 2) - 3) || 'x')");
+        assert_eq!(expr("(a OR b) AND c"), "((a OR b) AND c)");
+        assert_eq!(
+            expr("x NOT BETWEEN 1 AND 2 AND y IS NOT NULL OR z NOT IN (1, 2.5e1) AND n LIKE 'a%'"),
+            "(((x NOT BETWEEN 1 AND 2) AND (y IS NOT NULL)) \
+             OR ((z NOT IN (1, 25.0)) AND (n LIKE 'a%')))"
+        );
+        assert_eq!(
+            expr("NOT a IS NULL, t.\"Select\", count(*), now()"),
+            "(NOT (a IS NULL)), t.\"Select\", count(*), now()"
+        );
+        assert_eq!(expr("+ -1.5, TRUE <> FALSE, NULL"), "(- 1.5), (TRUE <> FALSE), NULL");
+        let Statement::Select(select) = one("SELECT 9223372036854775807, 0.5") else { panic!() };
+        let literals: Vec<_> = select
+            .projection
+            .into_iter()
+            .map(|item| match item {
+                SelectItem::Expr { expr: Expr::Literal(value), .. } => value,
+                item => panic!("{item}"),
+            })
+            .collect();
+        assert_eq!(literals, [Value::Int64(i64::MAX), Value::Double(0.5)]);
+    }
+
+    #[test]
+    fn statements_parse_into_the_ast() {
+        assert_eq!(
+            printed(
+                "select distinct u.*, name n, count(*) AS total from users u \
+                 join orders o on o.user_id = u.id left outer join items on true, tags \
+                 cross join x where o.total > 10 group by u.id, name having count(*) > 1 \
+                 order by total desc, name asc limit 10 offset 5"
+            ),
+            "SELECT DISTINCT u.*, name AS n, count(*) AS total FROM users AS u \
+             JOIN orders AS o ON (o.user_id = u.id) LEFT JOIN items ON TRUE CROSS JOIN tags \
+             CROSS JOIN x WHERE (o.total > 10) GROUP BY u.id, name HAVING (count(*) > 1) \
+             ORDER BY total DESC, name LIMIT 10 OFFSET 5"
+        );
+        assert_eq!(
+            printed("INSERT INTO t (a, \"b c\") VALUES (1, 'x'), (-2, NULL);"),
+            "INSERT INTO t (a, \"b c\") VALUES (1, 'x'), ((- 2), NULL)"
+        );
+        assert_eq!(
+            printed("update t set a = a + 1, b = 'y' where id in (1, 2)"),
+            "UPDATE t SET a = (a + 1), b = 'y' WHERE (id IN (1, 2))"
+        );
+        assert_eq!(printed("DELETE FROM t"), "DELETE FROM t");
+        assert_eq!(
+            printed(
+                "CREATE TABLE IF NOT EXISTS users (id BIGINT PRIMARY KEY, name varchar NOT NULL, \
+                 age INTEGER DEFAULT 18 NULL, score REAL DEFAULT -1.5 NOT NULL)"
+            ),
+            "CREATE TABLE IF NOT EXISTS users (id BIGINT, name TEXT NOT NULL, age INT DEFAULT 18, \
+             score DOUBLE NOT NULL DEFAULT (- 1.5), PRIMARY KEY (id))"
+        );
+        assert_eq!(printed("drop table if exists t"), "DROP TABLE IF EXISTS t");
+        assert_eq!(
+            printed("create unique index by_name on users (name, \"order\")"),
+            "CREATE UNIQUE INDEX by_name ON users (name, \"order\")"
+        );
+        let script = parse(";SELECT 1;; DROP TABLE a; DROP TABLE b").unwrap();
+        assert_eq!(script.len(), 3);
+    }
+
+    #[test]
+    fn errors_point_at_the_token_with_what_was_expected() {
+        assert_eq!(error("SELECT a FROM"), "1:14: unexpected end of input; expected identifier");
+        assert_eq!(
+            error("SELECT a,\n  FROM t"),
+            "2:3: unexpected FROM; expected '*' or expression"
+        );
+        assert_eq!(
+            error("INSERT INTO t VALUES (1 2)"),
+            "1:25: unexpected number 2; expected ',' or ')'"
+        );
+        assert_eq!(
+            error("SELEC x"),
+            "1:1: unexpected identifier SELEC; \
+             expected SELECT, INSERT, UPDATE, DELETE, CREATE or DROP"
+        );
+        assert_eq!(
+            error("SELECT a FROM t u v"),
+            "1:19: unexpected identifier v; expected ',', JOIN, INNER, CROSS, LEFT, RIGHT, WHERE, \
+             GROUP, ORDER, LIMIT, OFFSET, end of input or ';'"
+        );
+        assert_eq!(error("DELETE t"), "1:8: unexpected identifier t; expected FROM");
+        assert_eq!(
+            error("CREATE TABLE t (a INTEGR)"),
+            "1:19: unknown data type 'INTEGR'; expected data type"
+        );
+        assert_eq!(
+            error("CREATE TABLE t (a INT PRIMARY KEY, PRIMARY KEY (a))"),
+            "1:36: table 't' already has a primary key"
+        );
+        assert_eq!(
+            error("SELECT 99999999999999999999"),
+            "1:8: integer 99999999999999999999 is out of range"
+        );
+        assert_eq!(error("SELECT a # b"), "1:10: unexpected character '#'");
+        let err = parse("UPDATE t SET a = 1 WHERE").unwrap_err();
+        assert_eq!((err.span.start.column, err.span.end.column), (25, 25));
+        assert_eq!(err.expected, ["expression"]);
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |open: &str, n: usize| format!("SELECT {}1{}", open.repeat(n), ")".repeat(n));
+        assert!(parse(&nested("(", MAX_DEPTH - 1)).is_ok());
+        assert!(parse(&format!("SELECT {}", "NOT ".repeat(MAX_DEPTH - 1) + "x")).is_ok());
+        // Глубина не копится между соседними выражениями
+        let siblings = vec![nested("(", MAX_DEPTH - 2)["SELECT ".len()..].to_string(); 3];
+        assert!(parse(&format!("SELECT {}", siblings.join(" + "))).is_ok());
+
+        assert_eq!(
+            error(&nested("(", 10_000)),
+            "1:264: expression is nested deeper than 256 levels"
+        );
+        let nots = format!("SELECT {}x", "NOT ".repeat(10_000));
+        assert_eq!(error(&nots), "1:1032: expression is nested deeper than 256 levels");
+        assert!(parse(&format!("SELECT {}1", "- ".repeat(10_000))).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+#include "storage/config_file.hpp"
+#include "utils/logger.hpp"
+
+#include <fstream>
+#include <map>
+#include <string>
+
+namespace datyredb::storage {
+
+namespace {
+
+constexpr const char* HEADER = "# datyre-storage-config v1";
+
+/// Все ключи формата, в порядке записи
+constexpr const char* KEYS[] = {
+    "data_path",
+    "buffer_pool_pages",
+    "wal_segment_size",
+    "page_size",
+    "checkpoint.max_interval",
+    "checkpoint.min_interval",
+    "checkpoint.max_wal_size",
+    "checkpoint.dirty_page_soft_limit_pct",
+    "checkpoint.dirty_page_hard_limit_pct",
+    "checkpoint.checkpoint_batch_size",
+    "checkpoint.batch_throttle_us",
+    "checkpoint.async_checkpoint",
+};
+
+std::string trim(const std::string& s) {
+    auto begin = s.find_first_not_of(" \t\r");
+    if (begin == std::string::npos) return "";
+    auto end = s.find_last_not_of(" \t\r");
+    return s.substr(begin, end - begin + 1);
+}
+
+bool is_known_key(const std::string& key) {
+    for (const char* known : KEYS) {
+        if (key == known) return true;
+    }
+    return false;
+}
+
+} // namespace
+
+std::optional<StorageConfig> load_storage_config(const std::filesystem::path& path) {
+    std::ifstream in(path);
+    if (!in.is_open()) {
+        Logger::error("Config: cannot open {}", path.string());
+        return std::nullopt;
+    }
+    
+    std::string line;
+    if (!std::getline(in, line) || trim(line) != HEADER) {
+        Logger::error("Config: {} has no '{}' header", path.string(), HEADER);
+        return std::nullopt;
+    }
+    
+    std::map<std::string, std::string> values;
+    std::size_t line_no = 1;
+    while (std::getline(in, line)) {
+        ++line_no;
+        line = trim(line);
+        if (line.empty() || line[0] == '#') continue;
+        
+        auto eq = line.find('=');
+        if (eq == std::string::npos) {
+            Logger::error("Config: {}:{}: expected 'key = value'", path.string(), line_no);
+            return std::nullopt;
+        }
+        
+        auto key = trim(line.substr(0, eq));
+        if (!is_known_key(key)) {
+            Logger::error("Config: {}:{}: unknown key '{}'", path.string(), line_no, key);
+            return std::nullopt;
+        }
+        if (!values.emplace(key, trim(line.substr(eq + 1))).second) {
+            Logger::error("Config: {}:{}: duplicate key '{}'", path.string(), line_no, key);
+            return std::nullopt;
+        }
+    }
+    
+    for (const char* key : KEYS) {
+        if (values.find(key) == values.end()) {
+            Logger::error("Config: {}: missing key '{}'", path.string(), key);
+            return std::nullopt;
+        }
+    }
+    
+    StorageConfig config;
+    try {
+        if (std::stoull(values["page_size"]) != PAGE_SIZE) {
+            Logger::error("Config: page_size {} does not match PAGE_SIZE {}",
+                          values["page_size"], PAGE_SIZE);
+            return std::nullopt;
+        }
+        
+        config.data_path = values["data_path"];
+        config.buffer_pool_pages = std::stoull(values["buffer_pool_pages"]);
+        config.wal_segment_size = std::stoull(values["wal_segment_size"]);
+        
+        auto& cp = config.checkpoint;
+        cp.max_interval = std::chrono::seconds{std::stoll(values["checkpoint.max_interval"])};
+        cp.min_interval = std::chrono::seconds{std::stoll(values["checkpoint.min_interval"])};
+        cp.max_wal_size = std::stoull(values["checkpoint.max_wal_size"]);
+        cp.dirty_page_soft_limit_pct = std::stof(values["checkpoint.dirty_page_soft_limit_pct"]);
+        cp.dirty_page_hard_limit_pct = std::stof(values["checkpoint.dirty_page_hard_limit_pct"]);
+        cp.checkpoint_batch_size = std::stoull(values["checkpoint.checkpoint_batch_size"]);
+        cp.batch_throttle_us = std::chrono::microseconds{
+            std::stoll(values["checkpoint.batch_throttle_us"])};
+        cp.async_checkpoint = std::stoi(values["checkpoint.async_checkpoint"]) != 0;
+    } catch (const std::exception& e) {
+        Logger::error("Config: {}: invalid value: {}", path.string(), e.what());
+        return std::nullopt;
+    }
+    
+    return config;
+}
+
+bool save_storage_config(const StorageConfig& config, const std::filesystem::path& path) {
+    std::ofstream out(path, std::ios::trunc);
+    if (!out.is_open()) {
+        Logger::error("Config: cannot write {}", path.string());
+        return false;
+    }
+    
+    const auto& cp = config.checkpoint;
+    out << HEADER << "\n"
+        << "data_path = " << config.data_path << "\n"
+        << "buffer_pool_pages = " << config.buffer_pool_pages << "\n"
+        << "wal_segment_size = " << config.wal_segment_size << "\n"
+        << "page_size = " << PAGE_SIZE << "\n"
+        << "checkpoint.max_interval = " << cp.max_interval.count() << "\n"
+        << "checkpoint.min_interval = " << cp.min_interval.count() << "\n"
+        << "checkpoint.max_wal_size = " << cp.max_wal_size << "\n"
+        << "checkpoint.dirty_page_soft_limit_pct = " << cp.dirty_page_soft_limit_pct << "\n"
+        << "checkpoint.dirty_page_hard_limit_pct = " << cp.dirty_page_hard_limit_pct << "\n"
+        << "checkpoint.checkpoint_batch_size = " << cp.checkpoint_batch_size << "\n"
+        << "checkpoint.batch_throttle_us = " << cp.batch_throttle_us.count() << "\n"
+        << "checkpoint.async_checkpoint = " << (cp.async_checkpoint ? 1 : 0) << "\n";
+    
+    return static_cast<bool>(out);
+}
+
+} // namespace datyredb::storage
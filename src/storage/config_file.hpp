@@ -0,0 +1,24 @@
+#pragma once
+
+#include "storage/storage_types.hpp"
+
+#include <filesystem>
+#include <optional>
+
+namespace datyredb::storage {
+
+/// Interchange-файл конфигурации storage layer ("datyre-storage-config v1").
+///
+/// Формат: строка версии, затем `key = value` по одной на строку.
+/// Ключи — имена полей StorageConfig / CheckpointConfig (последние с
+/// префиксом `checkpoint.`), значения — в единицах полей. Тот же формат
+/// пишет и читает Rust-модуль `config::interchange`.
+/// Требуется полный набор ключей; неизвестные ключи — ошибка.
+
+/// Загрузка (nullopt при ошибке, причина в логе)
+std::optional<StorageConfig> load_storage_config(const std::filesystem::path& path);
+
+/// Сохранение
+bool save_storage_config(const StorageConfig& config, const std::filesystem::path& path);
+
+} // namespace datyredb::storage
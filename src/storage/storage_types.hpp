@@ -216,6 +216,9 @@ struct CheckpointConfig {
     
     /// Throttle delay между батчами (микросекунды)
     std::chrono::microseconds batch_throttle_us{100};
+    
+    /// Асинхронный checkpoint I/O (io_uring)
+    bool async_checkpoint = true;
 };
 
 // ============================================================================
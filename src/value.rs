@@ -0,0 +1,364 @@
+//! Значения столбцов (`Value` из `src/common/type.hpp`) и их типы.
+//!
+//! `Value::coerce` приводит значение к типу столбца: целые расширяются
+//! (INT → BIGINT → DOUBLE; BIGINT — только если DOUBLE представляет его
+//! точно), BIGINT сужается до INT, если значение помещается, а текст
+//! разбирается в тип столбца — C++ движок передаёт все значения строками.
+//! Других неявных приведений нет. DOUBLE бывает только конечным: NaN не
+//! упорядочен и сломал бы порядок ключей `btree`.
+//!
+//! `encode_key` кодирует значения так, что побайтное сравнение ключей
+//! совпадает со сравнением значений — для ключей `btree`:
+//!
+//! ```text
+//! NULL     0x00                                  (меньше любого значения)
+//! иначе    0x01, затем
+//!   BOOLEAN  1 байт
+//!   INT      4 байта big-endian, знаковый бит инвертирован
+//!   BIGINT   8 байт так же
+//!   DOUBLE   8 байт big-endian: у неотрицательных инвертирован знаковый
+//!            бит, у отрицательных — все биты
+//!   TEXT     UTF-8, где 0x00 записан как 0x00 0xFF; в конце 0x00 0x00
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::num::IntErrorKind;
+use std::str::FromStr;
+
+// ============================================================================
+// Типы
+// ============================================================================
+
+/// Тип столбца
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DataType {
+    Boolean,
+    Int,
+    BigInt,
+    Double,
+    Text,
+}
+
+impl DataType {
+    pub const ALL: [DataType; 5] =
+        [DataType::Boolean, DataType::Int, DataType::BigInt, DataType::Double, DataType::Text];
+
+    /// Имя в SQL
+    pub fn name(self) -> &'static str {
+        match self {
+            DataType::Boolean => "BOOLEAN",
+            DataType::Int => "INT",
+            DataType::BigInt => "BIGINT",
+            DataType::Double => "DOUBLE",
+            DataType::Text => "TEXT",
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for DataType {
+    type Err = String;
+
+    /// Имя типа или синоним: BOOL, INTEGER, INT4, INT8, FLOAT, REAL, VARCHAR…
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data_type = match s.trim().to_ascii_uppercase().as_str() {
+            "BOOLEAN" | "BOOL" => DataType::Boolean,
+            "INT" | "INTEGER" | "INT4" => DataType::Int,
+            "BIGINT" | "INT8" => DataType::BigInt,
+            "DOUBLE" | "FLOAT" | "FLOAT8" | "REAL" => DataType::Double,
+            "TEXT" | "VARCHAR" | "STRING" => DataType::Text,
+            _ => return Err(format!("unknown data type '{s}'")),
+        };
+        Ok(data_type)
+    }
+}
+
+// ============================================================================
+// Значения
+// ============================================================================
+
+/// Значение столбца (`datyredb::Value`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    Double(f64),
+    Text(String),
+}
+
+/// Значение не приводится к типу
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoerceError {
+    /// Тип не приводится (или текст не разбирается)
+    TypeMismatch,
+    /// Число не помещается в тип
+    OutOfRange,
+}
+
+impl Value {
+    /// Тип значения; у NULL типа нет
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            Value::Null => None,
+            Value::Bool(_) => Some(DataType::Boolean),
+            Value::Int32(_) => Some(DataType::Int),
+            Value::Int64(_) => Some(DataType::BigInt),
+            Value::Double(_) => Some(DataType::Double),
+            Value::Text(_) => Some(DataType::Text),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Имя типа для сообщений (`NULL` у NULL)
+    pub fn type_name(&self) -> &'static str {
+        self.data_type().map_or("NULL", DataType::name)
+    }
+
+    /// Привести к типу `to`; NULL остаётся NULL
+    pub fn coerce(&self, to: DataType) -> Result<Value, CoerceError> {
+        use CoerceError::{OutOfRange, TypeMismatch};
+        let value = match (self, to) {
+            (Value::Null, _) => Value::Null,
+            (Value::Bool(b), DataType::Boolean) => Value::Bool(*b),
+            (Value::Int32(i), DataType::Int) => Value::Int32(*i),
+            (Value::Int32(i), DataType::BigInt) => Value::Int64(i64::from(*i)),
+            (Value::Int32(i), DataType::Double) => Value::Double(f64::from(*i)),
+            (Value::Int64(i), DataType::Int) => {
+                Value::Int32(i32::try_from(*i).map_err(|_| OutOfRange)?)
+            }
+            (Value::Int64(i), DataType::BigInt) => Value::Int64(*i),
+            (Value::Int64(i), DataType::Double) => {
+                // Выше 2^53 не всякое целое представимо; сравнение через
+                // i128, чтобы i64::MAX не совпал с округлённым 2^63
+                let d = *i as f64;
+                if d as i128 != i128::from(*i) {
+                    return Err(OutOfRange);
+                }
+                Value::Double(d)
+            }
+            (Value::Double(d), DataType::Double) if !d.is_finite() => return Err(OutOfRange),
+            (Value::Double(d), DataType::Double) => Value::Double(*d),
+            (Value::Text(s), DataType::Text) => Value::Text(s.clone()),
+            (Value::Text(s), _) => return parse_text(s.trim(), to),
+            _ => return Err(TypeMismatch),
+        };
+        Ok(value)
+    }
+
+    /// Сравнение значений одного типа (целые сравниваются и между собой);
+    /// NULL меньше всего. `None` — типы несравнимы.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            (Value::Null, _) => Some(Ordering::Less),
+            (_, Value::Null) => Some(Ordering::Greater),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            (a, b) => Some(a.as_i64()?.cmp(&b.as_i64()?)),
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int32(i) => Some(i64::from(*i)),
+            Value::Int64(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+fn parse_text(s: &str, to: DataType) -> Result<Value, CoerceError> {
+    let int = |s: &str| {
+        s.parse::<i64>().map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => CoerceError::OutOfRange,
+            _ => CoerceError::TypeMismatch,
+        })
+    };
+    match to {
+        DataType::Boolean => match s.to_ascii_lowercase().as_str() {
+            "true" | "t" => Ok(Value::Bool(true)),
+            "false" | "f" => Ok(Value::Bool(false)),
+            _ => Err(CoerceError::TypeMismatch),
+        },
+        DataType::Int => Value::Int64(int(s)?).coerce(DataType::Int),
+        DataType::BigInt => Ok(Value::Int64(int(s)?)),
+        DataType::Double => match s.parse::<f64>() {
+            Ok(d) if d.is_finite() => Ok(Value::Double(d)),
+            Ok(_) => Err(CoerceError::OutOfRange),
+            Err(_) => Err(CoerceError::TypeMismatch),
+        },
+        DataType::Text => Ok(Value::Text(s.to_string())),
+    }
+}
+
+impl fmt::Display for Value {
+    /// Литерал SQL: `NULL`, `TRUE`, `42`, `1.5`, `'it''s'`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Bool(true) => f.write_str("TRUE"),
+            Value::Bool(false) => f.write_str("FALSE"),
+            Value::Int32(i) => write!(f, "{i}"),
+            Value::Int64(i) => write!(f, "{i}"),
+            Value::Double(d) => write!(f, "{d:?}"),
+            Value::Text(s) => write!(f, "'{}'", s.replace('\'', "''")),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Value::Int32(i)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Int64(i)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(d: f64) -> Self {
+        Value::Double(d)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Text(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Text(s)
+    }
+}
+
+// ============================================================================
+// Ключи
+// ============================================================================
+
+/// Ключ из значений (составной — по порядку), сравнимый побайтно
+pub fn encode_key<'a>(values: impl IntoIterator<Item = &'a Value>) -> Vec<u8> {
+    let mut key = Vec::new();
+    for value in values {
+        if value.is_null() {
+            key.push(0);
+            continue;
+        }
+        key.push(1);
+        match value {
+            Value::Null => unreachable!(),
+            Value::Bool(b) => key.push(u8::from(*b)),
+            Value::Int32(i) => key.extend_from_slice(&((*i as u32) ^ (1 << 31)).to_be_bytes()),
+            Value::Int64(i) => key.extend_from_slice(&((*i as u64) ^ (1 << 63)).to_be_bytes()),
+            Value::Double(d) => {
+                // -0.0 и 0.0 равны
+                let bits = if *d == 0.0 { 0 } else { d.to_bits() };
+                let bits = if bits >> 63 == 1 { !bits } else { bits | (1 << 63) };
+                key.extend_from_slice(&bits.to_be_bytes());
+            }
+            Value::Text(s) => {
+                for &b in s.as_bytes() {
+                    key.push(b);
+                    if b == 0 {
+                        key.push(0xFF);
+                    }
+                }
+                key.extend_from_slice(&[0, 0]);
+            }
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coercion_widens_parses_and_rejects() {
+        assert_eq!(Value::Int32(7).coerce(DataType::BigInt), Ok(Value::Int64(7)));
+        assert_eq!(Value::Int64(7).coerce(DataType::Double), Ok(Value::Double(7.0)));
+        assert_eq!(Value::Int64(1 << 40).coerce(DataType::Int), Err(CoerceError::OutOfRange));
+        assert_eq!(
+            Value::Int64(1 << 60).coerce(DataType::Double),
+            Ok(Value::Double(2f64.powi(60)))
+        );
+        for i in [(1 << 53) + 1, i64::MAX, i64::MIN + 1] {
+            assert_eq!(Value::Int64(i).coerce(DataType::Double), Err(CoerceError::OutOfRange));
+        }
+        assert_eq!(Value::from(" 42 ").coerce(DataType::Int), Ok(Value::Int32(42)));
+        assert_eq!(Value::from("3000000000").coerce(DataType::Int), Err(CoerceError::OutOfRange));
+        assert_eq!(
+            Value::from("99999999999999999999").coerce(DataType::BigInt),
+            Err(CoerceError::OutOfRange)
+        );
+        assert_eq!(Value::from("1e3").coerce(DataType::Double), Ok(Value::Double(1000.0)));
+        for d in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(Value::Double(d).coerce(DataType::Double), Err(CoerceError::OutOfRange));
+        }
+        assert_eq!(Value::from("NaN").coerce(DataType::Double), Err(CoerceError::OutOfRange));
+        assert_eq!(Value::from("TRUE").coerce(DataType::Boolean), Ok(Value::Bool(true)));
+        assert_eq!(Value::from("abc").coerce(DataType::Int), Err(CoerceError::TypeMismatch));
+        assert_eq!(Value::Double(1.5).coerce(DataType::Int), Err(CoerceError::TypeMismatch));
+        assert_eq!(Value::Int32(1).coerce(DataType::Text), Err(CoerceError::TypeMismatch));
+        assert_eq!(Value::Null.coerce(DataType::Text), Ok(Value::Null));
+
+        assert_eq!("integer".parse(), Ok(DataType::Int));
+        assert_eq!(" varchar ".parse(), Ok(DataType::Text));
+        assert!("blob".parse::<DataType>().is_err());
+        assert_eq!(Value::from("it's").to_string(), "'it''s'");
+        assert_eq!(Value::Double(1.0).to_string(), "1.0");
+    }
+
+    #[test]
+    fn keys_sort_like_values() {
+        let sorted = [
+            vec![Value::Null],
+            vec![Value::Int64(i64::MIN)],
+            vec![Value::Int64(-1)],
+            vec![Value::Int64(0)],
+            vec![Value::Int64(5)],
+            vec![Value::Int64(i64::MAX)],
+        ];
+        let doubles = [f64::NEG_INFINITY, -2.5, -0.0, 0.0, 1e-300, 3.0, f64::INFINITY];
+        let texts = ["", "\0", "\0\0", "a", "a\0b", "ab", "b"];
+        let keys = |rows: Vec<Vec<Value>>| rows.iter().map(encode_key).collect::<Vec<_>>();
+
+        let ints = keys(sorted.to_vec());
+        assert!(ints.windows(2).all(|w| w[0] < w[1]));
+        let doubles = keys(doubles.iter().map(|&d| vec![Value::Double(d)]).collect());
+        assert!(doubles.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(doubles[2], doubles[3]);
+        let texts = keys(texts.iter().map(|&s| vec![Value::from(s)]).collect());
+        assert!(texts.windows(2).all(|w| w[0] < w[1]));
+
+        // Составной ключ: первый столбец важнее второго
+        let a = encode_key(&[Value::from("a"), Value::Int32(9)]);
+        let b = encode_key(&[Value::from("ab"), Value::Int32(1)]);
+        assert!(a < b);
+        assert_eq!(Value::Int32(3).compare(&Value::Int64(3)), Some(Ordering::Equal));
+        assert_eq!(Value::from("a").compare(&Value::Int32(1)), None);
+    }
+}
@@ -0,0 +1,510 @@
+//! Бинарный WAL движка: `LogRecordType`, `LogRecord` и имена сегментов.
+//!
+//! Формат записи — как `LogRecord::serialize` в C++ (packed, little-endian):
+//!
+//! ```text
+//! offset  size  field
+//!      0     1  type
+//!      1     8  lsn
+//!      9     8  txn_id
+//!     17     4  page_id
+//!     21     2  offset
+//!     23     2  length
+//!     25     8  prev_lsn
+//!     33     4  data_size
+//!     37     N  data
+//! ```
+//! У записи нет ни checksum, ни маркера конца: оборванный хвост
+//! распознаётся по неполной записи или по нарушенной монотонности LSN.
+//!
+//! Записи данных физические: `offset`/`length` задают диапазон байт
+//! страницы `page_id`, `data` — образы этого диапазона:
+//!
+//! ```text
+//! INSERT          after              (до вставки диапазон нулевой)
+//! UPDATE          before || after
+//! DELETE          before             (после удаления диапазон нулевой)
+//! CLR             undo_next_lsn u64 || образ, который пишет undo
+//! CHECKPOINT_END  (txn_id u64, last_lsn u64)*  — активные транзакции
+//! TXN_COMMIT      время commit'а: мкс от UNIX epoch u64 (у C++ пусто)
+//! ```
+
+pub mod archive;
+pub mod group_commit;
+pub mod reader;
+pub mod writer;
+
+pub use archive::WalArchive;
+pub use group_commit::{GroupCommitStats, GroupCommitWal, LatencyHistogram, LatencySnapshot};
+pub use reader::{
+    Chain, CheckpointPair, CheckpointTracker, RecordFormat, Records, Segment, TailReason, TornTail,
+    WalEnd, WalReader, WalRecord, WalScan,
+};
+pub use writer::WalWriter;
+
+use crate::page::{Lsn, PageId, TxnId, INVALID_LSN, INVALID_PAGE_ID};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Размер заголовка записи до данных
+pub const RECORD_HEADER_SIZE: usize = 37;
+
+// ============================================================================
+// Типы записей
+// ============================================================================
+
+/// Тип WAL записи (`storage::LogRecordType`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum LogRecordType {
+    Invalid = 0,
+
+    // Data operations
+    Insert,
+    Update,
+    Delete,
+
+    // Transactions
+    TxnBegin,
+    TxnCommit,
+    TxnAbort,
+
+    // Checkpoint
+    CheckpointBegin,
+    CheckpointEnd,
+
+    // Compensation (for undo)
+    Clr,
+}
+
+impl LogRecordType {
+    pub const ALL: [LogRecordType; 10] = [
+        LogRecordType::Invalid,
+        LogRecordType::Insert,
+        LogRecordType::Update,
+        LogRecordType::Delete,
+        LogRecordType::TxnBegin,
+        LogRecordType::TxnCommit,
+        LogRecordType::TxnAbort,
+        LogRecordType::CheckpointBegin,
+        LogRecordType::CheckpointEnd,
+        LogRecordType::Clr,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(usize::from(value)).copied()
+    }
+
+    /// Имя как в `log_record_type_name`
+    pub fn name(self) -> &'static str {
+        match self {
+            LogRecordType::Invalid => "INVALID",
+            LogRecordType::Insert => "INSERT",
+            LogRecordType::Update => "UPDATE",
+            LogRecordType::Delete => "DELETE",
+            LogRecordType::TxnBegin => "TXN_BEGIN",
+            LogRecordType::TxnCommit => "TXN_COMMIT",
+            LogRecordType::TxnAbort => "TXN_ABORT",
+            LogRecordType::CheckpointBegin => "CHECKPOINT_BEGIN",
+            LogRecordType::CheckpointEnd => "CHECKPOINT_END",
+            LogRecordType::Clr => "CLR",
+        }
+    }
+
+    /// Запись изменяет страницу
+    pub fn is_data(self) -> bool {
+        matches!(
+            self,
+            LogRecordType::Insert | LogRecordType::Update | LogRecordType::Delete | LogRecordType::Clr
+        )
+    }
+}
+
+impl fmt::Display for LogRecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for LogRecordType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_uppercase();
+        LogRecordType::ALL
+            .into_iter()
+            .find(|t| t.name() == name)
+            .ok_or_else(|| format!("unknown log record type '{s}'"))
+    }
+}
+
+// ============================================================================
+// Запись
+// ============================================================================
+
+/// WAL запись (`storage::LogRecord`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub record_type: LogRecordType,
+    pub lsn: Lsn,
+    pub txn_id: TxnId,
+    pub page_id: PageId,
+    pub offset: u16,
+    pub length: u16,
+    pub prev_lsn: Lsn,
+    pub data: Vec<u8>,
+}
+
+impl Default for LogRecord {
+    fn default() -> Self {
+        Self {
+            record_type: LogRecordType::Invalid,
+            lsn: INVALID_LSN,
+            txn_id: 0,
+            page_id: INVALID_PAGE_ID,
+            offset: 0,
+            length: 0,
+            prev_lsn: INVALID_LSN,
+            data: Vec::new(),
+        }
+    }
+}
+
+impl LogRecord {
+    pub fn new(record_type: LogRecordType, txn_id: TxnId) -> Self {
+        Self { record_type, txn_id, ..Self::default() }
+    }
+
+    /// Размер записи при сериализации
+    pub fn serialized_size(&self) -> usize {
+        RECORD_HEADER_SIZE + self.data.len()
+    }
+
+    /// Сериализация в конец буфера
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.reserve(self.serialized_size());
+        buf.push(self.record_type as u8);
+        buf.extend_from_slice(&self.lsn.to_le_bytes());
+        buf.extend_from_slice(&self.txn_id.to_le_bytes());
+        buf.extend_from_slice(&self.page_id.to_le_bytes());
+        buf.extend_from_slice(&self.offset.to_le_bytes());
+        buf.extend_from_slice(&self.length.to_le_bytes());
+        buf.extend_from_slice(&self.prev_lsn.to_le_bytes());
+        buf.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&self.data);
+    }
+
+    /// Разбор записи в начале `buf`; возвращает запись и её размер
+    pub fn deserialize(buf: &[u8]) -> Result<(LogRecord, usize), DecodeError> {
+        if buf.len() < RECORD_HEADER_SIZE {
+            return Err(DecodeError::Incomplete { needed: RECORD_HEADER_SIZE, available: buf.len() });
+        }
+        let data_size = u32::from_le_bytes(buf[33..37].try_into().unwrap()) as usize;
+        let total = RECORD_HEADER_SIZE + data_size;
+        if buf.len() < total {
+            return Err(DecodeError::Incomplete { needed: total, available: buf.len() });
+        }
+        let record_type = LogRecordType::from_u8(buf[0]).ok_or(DecodeError::UnknownType(buf[0]))?;
+        let record = LogRecord {
+            record_type,
+            lsn: u64::from_le_bytes(buf[1..9].try_into().unwrap()),
+            txn_id: u64::from_le_bytes(buf[9..17].try_into().unwrap()),
+            page_id: u32::from_le_bytes(buf[17..21].try_into().unwrap()),
+            offset: u16::from_le_bytes(buf[21..23].try_into().unwrap()),
+            length: u16::from_le_bytes(buf[23..25].try_into().unwrap()),
+            prev_lsn: u64::from_le_bytes(buf[25..33].try_into().unwrap()),
+            data: buf[RECORD_HEADER_SIZE..total].to_vec(),
+        };
+        Ok((record, total))
+    }
+}
+
+// ============================================================================
+// Образы страниц
+// ============================================================================
+
+impl LogRecord {
+    /// INSERT: `bytes` записаны в страницу с `offset`
+    pub fn insert(
+        txn_id: TxnId,
+        prev_lsn: Lsn,
+        page_id: PageId,
+        offset: u16,
+        bytes: &[u8],
+    ) -> Self {
+        let (len, data) = (bytes.len(), bytes.to_vec());
+        Self::data_record(LogRecordType::Insert, txn_id, prev_lsn, page_id, offset, len, data)
+    }
+
+    /// UPDATE: `before` заменён на `after` (одинаковой длины)
+    pub fn update(
+        txn_id: TxnId,
+        prev_lsn: Lsn,
+        page_id: PageId,
+        offset: u16,
+        before: &[u8],
+        after: &[u8],
+    ) -> Self {
+        assert_eq!(before.len(), after.len(), "UPDATE images must have the same length");
+        let data = [before, after].concat();
+        let len = after.len();
+        Self::data_record(LogRecordType::Update, txn_id, prev_lsn, page_id, offset, len, data)
+    }
+
+    /// DELETE: `before` затёрт нулями
+    pub fn delete(
+        txn_id: TxnId,
+        prev_lsn: Lsn,
+        page_id: PageId,
+        offset: u16,
+        before: &[u8],
+    ) -> Self {
+        let (len, data) = (before.len(), before.to_vec());
+        Self::data_record(LogRecordType::Delete, txn_id, prev_lsn, page_id, offset, len, data)
+    }
+
+    /// CLR для отката `undone`: пишет его undo-образ, дальше откат
+    /// продолжается с `undone.prev_lsn`. `None`, если `undone` не
+    /// откатывается (не INSERT/UPDATE/DELETE или повреждённые данные).
+    pub fn compensation(undone: &LogRecord, prev_lsn: Lsn) -> Option<Self> {
+        if undone.record_type == LogRecordType::Clr {
+            return None;
+        }
+        let image = undone.undo_image()?;
+        let mut data = undone.prev_lsn.to_le_bytes().to_vec();
+        data.extend_from_slice(&image);
+        Some(Self::data_record(
+            LogRecordType::Clr,
+            undone.txn_id,
+            prev_lsn,
+            undone.page_id,
+            undone.offset,
+            image.len(),
+            data,
+        ))
+    }
+
+    /// TXN_COMMIT с текущим временем (по нему восстановление на момент
+    /// времени находит точку остановки)
+    pub fn commit(txn_id: TxnId, prev_lsn: Lsn) -> Self {
+        let micros = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros();
+        let data = (micros as u64).to_le_bytes().to_vec();
+        Self { prev_lsn, data, ..Self::new(LogRecordType::TxnCommit, txn_id) }
+    }
+
+    fn data_record(
+        record_type: LogRecordType,
+        txn_id: TxnId,
+        prev_lsn: Lsn,
+        page_id: PageId,
+        offset: u16,
+        length: usize,
+        data: Vec<u8>,
+    ) -> Self {
+        let length = length as u16;
+        Self { page_id, offset, length, prev_lsn, data, ..Self::new(record_type, txn_id) }
+    }
+
+    /// Байты, которые redo пишет в `offset..offset + length`. `None` — не
+    /// запись данных или `data` не совпадает с `length`.
+    pub fn redo_image(&self) -> Option<Cow<'_, [u8]>> {
+        let len = usize::from(self.length);
+        match self.record_type {
+            LogRecordType::Insert if self.data.len() == len => Some(Cow::Borrowed(&self.data)),
+            LogRecordType::Update if self.data.len() == 2 * len => {
+                Some(Cow::Borrowed(&self.data[len..]))
+            }
+            LogRecordType::Delete if self.data.len() == len => Some(Cow::Owned(vec![0; len])),
+            LogRecordType::Clr if self.data.len() == 8 + len => {
+                Some(Cow::Borrowed(&self.data[8..]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Байты, которые undo пишет вместо изменения (у CLR undo нет)
+    pub fn undo_image(&self) -> Option<Cow<'_, [u8]>> {
+        let len = usize::from(self.length);
+        match self.record_type {
+            LogRecordType::Insert if self.data.len() == len => Some(Cow::Owned(vec![0; len])),
+            LogRecordType::Update if self.data.len() == 2 * len => {
+                Some(Cow::Borrowed(&self.data[..len]))
+            }
+            LogRecordType::Delete if self.data.len() == len => Some(Cow::Borrowed(&self.data)),
+            _ => None,
+        }
+    }
+
+    /// `undo_next_lsn` CLR: следующая запись транзакции, которую нужно откатить
+    pub fn undo_next_lsn(&self) -> Option<Lsn> {
+        match self.record_type {
+            LogRecordType::Clr if self.data.len() >= 8 => {
+                Some(u64::from_le_bytes(self.data[..8].try_into().unwrap()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Таблица активных транзакций из CHECKPOINT_END: `(txn_id, last_lsn)`.
+    /// `None` — записи без таблицы (C++ движок) или повреждённые данные.
+    pub fn active_txns(&self) -> Option<Vec<(TxnId, Lsn)>> {
+        if self.record_type != LogRecordType::CheckpointEnd
+            || self.data.is_empty()
+            || !self.data.len().is_multiple_of(16)
+        {
+            return None;
+        }
+        let entries = self.data.chunks_exact(16).map(|e| {
+            let txn_id = u64::from_le_bytes(e[..8].try_into().unwrap());
+            (txn_id, u64::from_le_bytes(e[8..].try_into().unwrap()))
+        });
+        Some(entries.filter(|&(txn_id, _)| txn_id != 0).collect())
+    }
+
+    /// Время TXN_COMMIT; `None` — не commit или commit C++ движка без времени
+    pub fn commit_time(&self) -> Option<SystemTime> {
+        if self.record_type != LogRecordType::TxnCommit || self.data.len() != 8 {
+            return None;
+        }
+        let micros = u64::from_le_bytes(self.data[..].try_into().unwrap());
+        UNIX_EPOCH.checked_add(Duration::from_micros(micros))
+    }
+}
+
+/// Данные CHECKPOINT_END для `active`. Пустая таблица кодируется одной
+/// записью `(0, INVALID_LSN)`, чтобы отличаться от END без таблицы.
+pub fn encode_active_txns(active: impl IntoIterator<Item = (TxnId, Lsn)>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (txn_id, last_lsn) in active {
+        data.extend_from_slice(&txn_id.to_le_bytes());
+        data.extend_from_slice(&last_lsn.to_le_bytes());
+    }
+    if data.is_empty() {
+        data.resize(16, 0);
+    }
+    data
+}
+
+/// Почему байты не разбираются как `LogRecord`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Запись обрывается: нужно `needed` байт, есть `available`
+    Incomplete { needed: usize, available: usize },
+    /// Байт типа вне `LogRecordType`
+    UnknownType(u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Incomplete { needed, available } => {
+                write!(f, "incomplete record: need {needed} bytes, {available} left")
+            }
+            DecodeError::UnknownType(t) => write!(f, "unknown record type {t:#04x}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// ============================================================================
+// Сегменты
+// ============================================================================
+
+/// Имя сегмента, которое создаёт `WriteAheadLog::segment_path`
+pub fn segment_file_name(segment_id: u64) -> String {
+    format!("wal_{segment_id}")
+}
+
+/// Номер сегмента по имени файла: `wal_7` (C++ движок) или
+/// `wal_00000007.log` (как в `example_data/wal`)
+pub fn parse_segment_name(path: &Path) -> Option<u64> {
+    let name = path.file_name()?.to_str()?;
+    let digits = name.strip_prefix("wal_")?;
+    let digits = digits.strip_suffix(".log").unwrap_or(digits);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_round_trip() {
+        let record = LogRecord {
+            record_type: LogRecordType::Update,
+            lsn: 42,
+            txn_id: 7,
+            page_id: 3,
+            offset: 100,
+            length: 4,
+            prev_lsn: 41,
+            data: b"oldvnew!".to_vec(),
+        };
+        let mut buf = Vec::new();
+        record.serialize(&mut buf);
+        assert_eq!(buf.len(), record.serialized_size());
+        assert_eq!(buf[0], 2);
+
+        let (decoded, size) = LogRecord::deserialize(&buf).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(size, buf.len());
+        assert_eq!(
+            LogRecord::deserialize(&buf[..buf.len() - 1]),
+            Err(DecodeError::Incomplete { needed: 45, available: 44 })
+        );
+
+        buf[0] = 200;
+        assert_eq!(LogRecord::deserialize(&buf), Err(DecodeError::UnknownType(200)));
+    }
+
+    #[test]
+    fn data_images() {
+        let update = LogRecord { lsn: 9, ..LogRecord::update(3, 8, 1, 64, b"old!", b"new!") };
+        assert_eq!(update.redo_image().as_deref(), Some(&b"new!"[..]));
+        assert_eq!(update.undo_image().as_deref(), Some(&b"old!"[..]));
+
+        let clr = LogRecord::compensation(&update, 9).unwrap();
+        assert_eq!((clr.record_type, clr.prev_lsn, clr.length), (LogRecordType::Clr, 9, 4));
+        assert_eq!(clr.undo_next_lsn(), Some(8));
+        assert_eq!(clr.redo_image().as_deref(), Some(&b"old!"[..]));
+        assert_eq!(clr.undo_image(), None);
+        assert_eq!(LogRecord::compensation(&clr, 10), None);
+
+        let delete = LogRecord::delete(3, 9, 1, 64, b"gone");
+        assert_eq!(delete.redo_image().as_deref(), Some(&[0u8; 4][..]));
+        let broken = LogRecord { length: 5, ..delete };
+        assert_eq!(broken.redo_image(), None);
+
+        let end = LogRecord {
+            data: encode_active_txns([]),
+            ..LogRecord::new(LogRecordType::CheckpointEnd, 0)
+        };
+        assert_eq!(end.active_txns(), Some(vec![]));
+        let end = LogRecord { data: encode_active_txns([(4, 12)]), ..end };
+        assert_eq!(end.active_txns(), Some(vec![(4, 12)]));
+        assert_eq!(LogRecord::new(LogRecordType::CheckpointEnd, 0).active_txns(), None);
+
+        let before = SystemTime::now() - Duration::from_millis(1);
+        let commit = LogRecord::commit(4, 12);
+        assert_eq!((commit.txn_id, commit.prev_lsn), (4, 12));
+        assert!(commit.commit_time().is_some_and(|t| t >= before && t <= SystemTime::now()));
+        assert_eq!(LogRecord::new(LogRecordType::TxnCommit, 4).commit_time(), None);
+    }
+
+    #[test]
+    fn segment_names() {
+        assert_eq!(parse_segment_name(Path::new("wal/wal_12")), Some(12));
+        assert_eq!(parse_segment_name(Path::new("wal_00000003.log")), Some(3));
+        assert_eq!(parse_segment_name(Path::new("wal_.log")), None);
+        assert_eq!(parse_segment_name(Path::new("wal_1.tmp")), None);
+        assert_eq!(parse_segment_name(Path::new(&segment_file_name(5))), Some(5));
+        assert_eq!("checkpoint_end".parse::<LogRecordType>(), Ok(LogRecordType::CheckpointEnd));
+    }
+}
@@ -0,0 +1,137 @@
+//! Архив WAL: копии завершённых сегментов вне директории данных.
+//!
+//! C++ `WriteAheadLog::truncate_before` просто удаляет сегменты после
+//! checkpoint'а. Writer с архивом (`WalWriter::set_archive`) сначала копирует
+//! каждый завершённый сегмент в `wal_archive_dir` и только потом удаляет.
+//! Копия пишется во временный файл, сбрасывается на диск и переименовывается,
+//! так что в архиве не бывает наполовину скопированных сегментов. Базовая
+//! копия и архив вместе позволяют восстановить базу на любой момент после
+//! копии (`restore`).
+//!
+//! Сегмент с тем же номером, но другим содержимым в архиве — ошибка. Так
+//! бывает, когда восстановленная база пишет в архив исходной: её история
+//! расходится с архивной, и ей нужен свой архив.
+
+use super::reader::{Segment, WalReader};
+use super::segment_file_name;
+use crate::config::DatabaseConfig;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Директория архива сегментов
+#[derive(Debug, Clone)]
+pub struct WalArchive {
+    dir: PathBuf,
+}
+
+impl WalArchive {
+    /// Архив в `dir` (создаётся при необходимости)
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Архив `config.wal_archive_dir`, если он задан
+    pub fn from_config(config: &DatabaseConfig) -> io::Result<Option<Self>> {
+        config.wal_archive_dir.as_ref().map(Self::open).transpose()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Скопировать сегмент `path` под номером `segment_id`. `false` — такая
+    /// же копия уже в архиве.
+    pub fn archive(&self, segment_id: u64, path: &Path) -> io::Result<bool> {
+        let target = self.dir.join(segment_file_name(segment_id));
+        let bytes = std::fs::read(path)?;
+        match std::fs::read(&target) {
+            Ok(archived) if archived == bytes => return Ok(false),
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} holds a different segment {segment_id}: the WAL history diverged \
+                         from the archive (a restored database needs its own archive)",
+                        target.display()
+                    ),
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let tmp = self.dir.join(format!("{}.tmp", segment_file_name(segment_id)));
+        std::fs::write(&tmp, &bytes)?;
+        File::open(&tmp)?.sync_all()?;
+        std::fs::rename(&tmp, &target)?;
+        File::open(&self.dir)?.sync_all()?;
+        Ok(true)
+    }
+
+    /// Сегменты архива по возрастанию номеров
+    pub fn segments(&self) -> io::Result<Vec<Segment>> {
+        Ok(self.reader()?.segments().to_vec())
+    }
+
+    /// Чтение архива как WAL
+    pub fn reader(&self) -> io::Result<WalReader> {
+        WalReader::open_dir(&self.dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::{LogRecord, LogRecordType, WalWriter};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("datyredb_archive_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn truncation_archives_segments_first() {
+        let root = temp_dir("truncate");
+        let mut wal = WalWriter::create(root.join("wal"), 128).unwrap();
+        wal.set_archive(Some(WalArchive::open(root.join("archive")).unwrap()));
+        for txn in 1..=8 {
+            let begin = wal.append(&LogRecord::new(LogRecordType::TxnBegin, txn)).unwrap();
+            wal.append(&LogRecord::commit(txn, begin)).unwrap();
+        }
+        let begin = wal.checkpoint_begin().unwrap();
+        wal.checkpoint_end(begin).unwrap();
+        wal.flush().unwrap();
+        assert!(wal.truncate_before(begin).unwrap() > 0);
+
+        // Удалённое из WAL есть в архиве, вместе они дают всю историю
+        let archive = WalArchive::open(root.join("archive")).unwrap();
+        let archived = archive.reader().unwrap().scan().unwrap();
+        let live = WalReader::open_dir(root.join("wal")).unwrap().scan().unwrap();
+        assert_eq!(archived.records[0].record.lsn, 1);
+        assert!(archived.end.last_valid_lsn + 1 >= live.records[0].record.lsn);
+        assert_eq!(archived.end.torn, None);
+
+        // Повторное архивирование той же копии — не ошибка
+        let first = &archive.segments().unwrap()[0];
+        assert!(!archive.archive(first.id, &first.path).unwrap());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rejects_diverged_segment() {
+        let root = temp_dir("diverged");
+        let archive = WalArchive::open(root.join("archive")).unwrap();
+        std::fs::write(root.join("a"), b"one history").unwrap();
+        std::fs::write(root.join("b"), b"another history").unwrap();
+        assert!(archive.archive(3, &root.join("a")).unwrap());
+        let err = archive.archive(3, &root.join("b")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(root.join("archive/wal_3")).unwrap(), b"one history");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,441 @@
+//! Групповой commit поверх `WalWriter`.
+//!
+//! C++ `WriteAheadLog::append` берёт mutex на каждую запись, а `force(lsn)`
+//! сбрасывает WAL для каждого commit'а отдельно. Здесь commit'ы, пришедшие,
+//! пока идёт сброс, ждут и уходят следующим одним fsync: первый из них
+//! становится лидером группы, остальные ждут его результата. Лидер сбрасывает
+//! дескриптор из `WalWriter::sync_handle`, не держа writer, так что сессии
+//! продолжают дописывать записи во время fsync.
+//!
+//! Ошибка сброса не повторяется: она достаётся всем текущим и будущим
+//! ожидающим, пока WAL не откроют заново.
+//!
+//! Лидер может подождать ещё `wal_commit_delay`, пока в очереди не
+//! наберётся `wal_max_group_size` commit'ов. Задержка и размер группы
+//! меняются на лету (`update_config`), режим сброса — только при открытии.
+
+use super::archive::WalArchive;
+use super::writer::{sync_file, WalWriter};
+use super::LogRecord;
+use crate::config::DatabaseConfig;
+use crate::page::{Lsn, TxnId};
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// Гистограмма задержек
+// ============================================================================
+
+/// Число корзин: корзина `i` — задержки до 2^(i+1) мкс (последняя — всё больше)
+pub const LATENCY_BUCKETS: usize = 32;
+
+/// Гистограмма задержек с корзинами по степеням двойки (в микросекундах)
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+    count: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, latency: Duration) {
+        let us = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let bucket = (us.max(1).ilog2() as usize).min(LATENCY_BUCKETS - 1);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        LatencySnapshot {
+            buckets: std::array::from_fn(|i| self.buckets[i].load(Ordering::Relaxed)),
+            count: self.count.load(Ordering::Relaxed),
+            total: Duration::from_micros(self.total_us.load(Ordering::Relaxed)),
+            max: Duration::from_micros(self.max_us.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Снимок гистограммы
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencySnapshot {
+    pub buckets: [u64; LATENCY_BUCKETS],
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl LatencySnapshot {
+    /// Верхняя граница корзины `i`
+    pub fn bucket_bound(i: usize) -> Duration {
+        Duration::from_micros(2u64 << i)
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.total.checked_div(self.count as u32).unwrap_or_default()
+    }
+
+    /// Оценка квантиля `q` (0..=1) сверху: граница корзины, в которую он
+    /// попадает, но не больше максимума
+    pub fn percentile(&self, q: f64) -> Duration {
+        let target = ((self.count as f64 * q).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return Self::bucket_bound(i).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+impl fmt::Display for LatencySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} commits: mean {}us, p50 <= {}us, p99 <= {}us, max {}us",
+            self.count,
+            self.mean().as_micros(),
+            self.percentile(0.5).as_micros(),
+            self.percentile(0.99).as_micros(),
+            self.max.as_micros()
+        )?;
+        let widest = self.buckets.iter().copied().max().unwrap_or(0).max(1);
+        for (i, &n) in self.buckets.iter().enumerate().filter(|(_, &n)| n > 0) {
+            let bar = "#".repeat(((n * 40).div_ceil(widest)) as usize);
+            let bound = format!("{}us", Self::bucket_bound(i).as_micros());
+            writeln!(f, "  <= {bound:>10} {n:>10} {bar}")?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Групповой commit
+// ============================================================================
+
+/// Накопленные метрики группового commit'а
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GroupCommitStats {
+    pub commits: u64,
+    /// Сбросов WAL, выполненных лидерами групп
+    pub syncs: u64,
+    /// Больше всего ожидающих, покрытых одним сбросом
+    pub largest_group: u64,
+    /// Задержка commit'а: от вызова до долговечности записи COMMIT
+    pub latency: LatencySnapshot,
+}
+
+impl GroupCommitStats {
+    pub fn commits_per_sync(&self) -> f64 {
+        self.commits as f64 / self.syncs.max(1) as f64
+    }
+}
+
+impl fmt::Display for GroupCommitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "group commit: {} commits, {} syncs ({:.1} commits/sync, largest group {})",
+            self.commits,
+            self.syncs,
+            self.commits_per_sync(),
+            self.largest_group
+        )?;
+        write!(f, "{}", self.latency)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GroupSettings {
+    commit_delay: Duration,
+    max_group_size: usize,
+}
+
+impl GroupSettings {
+    fn from_config(config: &DatabaseConfig) -> Self {
+        Self {
+            commit_delay: config.wal_commit_delay,
+            max_group_size: config.wal_max_group_size.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SyncState {
+    /// Все записи до этого LSN сброшены
+    durable_lsn: Lsn,
+    /// Лидер группы выполняет сброс
+    syncing: bool,
+    /// Потоков в `flush_to`
+    waiting: usize,
+    /// Неудавшийся сброс (вид и текст ошибки). После ошибки fsync ядро
+    /// могло уже отбросить грязные страницы сегмента, и повторный fsync
+    /// ничего не доказывает: до повторного открытия WAL все ждущие получают
+    /// ошибку
+    failed: Option<(io::ErrorKind, String)>,
+}
+
+/// WAL с групповым commit'ом
+#[derive(Debug)]
+pub struct GroupCommitWal {
+    wal: Arc<Mutex<WalWriter>>,
+    settings: RwLock<GroupSettings>,
+    state: Mutex<SyncState>,
+    /// Будит ведомых после сброса и лидера, ждущего `commit_delay`
+    wakeup: Condvar,
+    commits: AtomicU64,
+    syncs: AtomicU64,
+    largest_group: AtomicU64,
+    latency: LatencyHistogram,
+}
+
+impl GroupCommitWal {
+    /// WAL `config.wal_dir()` (создаётся или продолжается) с архивом
+    /// `config.wal_archive_dir`
+    pub fn open(config: &DatabaseConfig) -> io::Result<Self> {
+        let mut wal = WalWriter::open(config.wal_dir(), config.wal_segment_size)?;
+        wal.set_archive(WalArchive::from_config(config)?);
+        Ok(Self::new(Arc::new(Mutex::new(wal)), config))
+    }
+
+    /// Групповой commit поверх общего writer'а (его же получает
+    /// `CheckpointManager`); режим сброса writer'а берётся из `config`
+    pub fn new(wal: Arc<Mutex<WalWriter>>, config: &DatabaseConfig) -> Self {
+        let durable_lsn = {
+            let mut writer = wal.lock().unwrap();
+            writer.set_sync_mode(config.wal_sync_mode);
+            writer.flushed_lsn()
+        };
+        Self {
+            wal,
+            settings: RwLock::new(GroupSettings::from_config(config)),
+            state: Mutex::new(SyncState { durable_lsn, ..SyncState::default() }),
+            wakeup: Condvar::new(),
+            commits: AtomicU64::new(0),
+            syncs: AtomicU64::new(0),
+            largest_group: AtomicU64::new(0),
+            latency: LatencyHistogram::default(),
+        }
+    }
+
+    /// Общий writer
+    pub fn writer(&self) -> &Arc<Mutex<WalWriter>> {
+        &self.wal
+    }
+
+    /// Дописать запись без ожидания сброса
+    pub fn append(&self, record: &LogRecord) -> io::Result<Lsn> {
+        self.wal.lock().unwrap().append(record)
+    }
+
+    /// TXN_COMMIT транзакции; возвращается, когда запись долговечна
+    pub fn commit(&self, txn_id: TxnId, prev_lsn: Lsn) -> io::Result<Lsn> {
+        let started = Instant::now();
+        let lsn = self.append(&LogRecord::commit(txn_id, prev_lsn))?;
+        self.flush_to(lsn)?;
+        self.commits.fetch_add(1, Ordering::Relaxed);
+        self.latency.record(started.elapsed());
+        Ok(lsn)
+    }
+
+    /// Дождаться, пока записи до `lsn` будут сброшены (`WriteAheadLog::force`)
+    pub fn flush_to(&self, lsn: Lsn) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.waiting += 1;
+        // Лидер, ждущий `commit_delay`, пересчитывает очередь
+        self.wakeup.notify_all();
+        let result = loop {
+            if state.durable_lsn >= lsn {
+                break Ok(());
+            }
+            if let Some((kind, message)) = &state.failed {
+                let message = format!("WAL sync failed earlier ({message}); reopen the WAL");
+                break Err(io::Error::new(*kind, message));
+            }
+            if state.syncing {
+                state = self.wakeup.wait(state).unwrap();
+                continue;
+            }
+
+            // Лидер группы
+            state.syncing = true;
+            let settings = *self.settings.read().unwrap();
+            if !settings.commit_delay.is_zero() {
+                let deadline = Instant::now() + settings.commit_delay;
+                while state.waiting < settings.max_group_size {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    state = self.wakeup.wait_timeout(state, deadline - now).unwrap().0;
+                }
+            }
+            let group = state.waiting as u64;
+            drop(state);
+            let synced = self.sync();
+            state = self.state.lock().unwrap();
+            state.syncing = false;
+            self.wakeup.notify_all();
+            match synced {
+                Ok(durable) => {
+                    state.durable_lsn = state.durable_lsn.max(durable);
+                    self.syncs.fetch_add(1, Ordering::Relaxed);
+                    self.largest_group.fetch_max(group, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    state.failed = Some((e.kind(), e.to_string()));
+                    break Err(e);
+                }
+            }
+        };
+        state.waiting -= 1;
+        result
+    }
+
+    /// Сбросить всё записанное; возвращает LSN, до которого WAL долговечен
+    fn sync(&self) -> io::Result<Lsn> {
+        let (file, target, mode) = {
+            let wal = self.wal.lock().unwrap();
+            let (file, target) = wal.sync_handle()?;
+            if target <= wal.flushed_lsn() {
+                return Ok(target);
+            }
+            (file, target, wal.sync_mode())
+        };
+        sync_file(&file, mode)?;
+        self.wal.lock().unwrap().mark_flushed(target);
+        Ok(target)
+    }
+
+    /// Применить `wal_commit_delay` и `wal_max_group_size` на лету
+    pub fn update_config(&self, config: &DatabaseConfig) {
+        *self.settings.write().unwrap() = GroupSettings::from_config(config);
+    }
+
+    pub fn stats(&self) -> GroupCommitStats {
+        GroupCommitStats {
+            commits: self.commits.load(Ordering::Relaxed),
+            syncs: self.syncs.load(Ordering::Relaxed),
+            largest_group: self.largest_group.load(Ordering::Relaxed),
+            latency: self.latency.snapshot(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WalSyncMode;
+    use crate::wal::{LogRecordType, WalReader};
+    use std::path::PathBuf;
+
+    fn config(name: &str) -> DatabaseConfig {
+        let dir: PathBuf = std::env::temp_dir()
+            .join(format!("datyredb_group_commit_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        DatabaseConfig { data_path: dir, wal_segment_size: 4096, ..DatabaseConfig::default() }
+    }
+
+    #[test]
+    fn histogram_percentiles() {
+        let histogram = LatencyHistogram::default();
+        for us in [0, 1, 3, 90, 100, 110, 120, 5000] {
+            histogram.record(Duration::from_micros(us));
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 8);
+        assert_eq!(snapshot.buckets[0], 2); // 0 и 1 мкс
+        assert_eq!(snapshot.buckets[6], 4); // 64..128 мкс
+        assert_eq!(snapshot.percentile(0.5), Duration::from_micros(128));
+        assert_eq!(snapshot.percentile(1.0), Duration::from_micros(5000));
+        assert_eq!(snapshot.mean(), Duration::from_micros(5424 / 8));
+        assert!(snapshot.to_string().contains("<=      128us          4"));
+    }
+
+    #[test]
+    fn concurrent_commits_share_syncs() {
+        let config = DatabaseConfig {
+            wal_commit_delay: Duration::from_millis(20),
+            wal_max_group_size: 8,
+            ..config("shared")
+        };
+        let wal = Arc::new(GroupCommitWal::open(&config).unwrap());
+        let threads: Vec<_> = (1..=8)
+            .map(|txn| {
+                let wal = Arc::clone(&wal);
+                std::thread::spawn(move || {
+                    let begin = wal.append(&LogRecord::new(LogRecordType::TxnBegin, txn)).unwrap();
+                    wal.commit(txn, begin).unwrap()
+                })
+            })
+            .collect();
+        let lsns: Vec<Lsn> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        let stats = wal.stats();
+        assert_eq!(stats.commits, 8);
+        assert_eq!(stats.latency.count, 8);
+        // Лидер ждёт, пока в очереди не будет всех восьми
+        assert!(stats.syncs < 8, "{stats}");
+        let writer = wal.writer().lock().unwrap();
+        assert!(lsns.iter().all(|&lsn| lsn <= writer.flushed_lsn()));
+        drop(writer);
+
+        let scan = WalReader::open(&config).unwrap().scan().unwrap();
+        let committed =
+            scan.chains().iter().filter(|c| c.outcome == Some(LogRecordType::TxnCommit)).count();
+        assert_eq!(committed, 8);
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn failed_sync_is_sticky() {
+        let config = config("failed");
+        let wal = GroupCommitWal::open(&config).unwrap();
+        let durable = wal.commit(1, 0).unwrap();
+
+        // Как после ошибки fsync у лидера
+        let error = io::Error::other("EIO");
+        wal.state.lock().unwrap().failed = Some((error.kind(), error.to_string()));
+        wal.flush_to(durable).unwrap();
+        for txn in 2..4 {
+            let err = wal.commit(txn, 0).unwrap_err();
+            assert!(err.to_string().contains("(EIO); reopen the WAL"), "{err}");
+        }
+        drop(wal);
+
+        let wal = GroupCommitWal::open(&config).unwrap();
+        wal.commit(4, 0).unwrap();
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+
+    #[test]
+    fn live_settings_and_sync_mode() {
+        let config = DatabaseConfig { wal_sync_mode: WalSyncMode::None, ..config("settings") };
+        let wal = GroupCommitWal::open(&config).unwrap();
+        assert_eq!(wal.writer().lock().unwrap().sync_mode(), WalSyncMode::None);
+
+        let lsn = wal.commit(1, 0).unwrap();
+        assert_eq!(wal.writer().lock().unwrap().flushed_lsn(), lsn);
+        // Уже сброшенное не сбрасывается повторно
+        wal.flush_to(lsn).unwrap();
+        assert_eq!(wal.stats().syncs, 1);
+
+        // Одиночный commit ждёт всю задержку: группа из одного не набирается
+        wal.update_config(&DatabaseConfig {
+            wal_commit_delay: Duration::from_millis(30),
+            wal_max_group_size: 2,
+            ..config.clone()
+        });
+        let started = Instant::now();
+        wal.commit(2, 0).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        std::fs::remove_dir_all(&config.data_path).unwrap();
+    }
+}
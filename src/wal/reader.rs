@@ -0,0 +1,625 @@
+//! Последовательное чтение WAL по всем сегментам.
+//!
+//! Сегменты читаются по одному в порядке номеров, записи проверяются на
+//! монотонность LSN. Первая неразбираемая запись — оборванный хвост:
+//! чтение останавливается, `WalEnd` сообщает последний валидный LSN и
+//! место обрыва.
+//!
+//! Кроме формата `LogRecord::serialize` распознаётся framed-формат сегментов
+//! из `example_data/wal`: 40-байтный заголовок (lsn, txn_id, prev_lsn,
+//! размер записи, CRC32 записи с обнулённым полем checksum) и u32 код типа.
+//! Коды этого формата (0x1e, 0x1f, ...) не совпадают с `LogRecordType`,
+//! поэтому тип таких записей не угадывается, а отдаётся как есть.
+
+use super::{parse_segment_name, DecodeError, LogRecord, LogRecordType};
+use crate::config::DatabaseConfig;
+use crate::page::{crc32, Lsn, TxnId, INVALID_LSN, INVALID_PAGE_ID};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Размер framed-записи без данных (заголовок + код типа)
+const FRAMED_HEADER_SIZE: usize = 44;
+const FRAMED_CHECKSUM_OFFSET: usize = 32;
+
+/// Формат записей сегмента
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// `LogRecord::serialize` (C++ `WriteAheadLog`)
+    Packed,
+    /// Записи с CRC32, как в `example_data/wal`
+    Framed,
+}
+
+/// Файл сегмента
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub id: u64,
+    pub path: PathBuf,
+    pub len: u64,
+}
+
+/// Прочитанная запись и её место в WAL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalRecord {
+    pub segment_id: u64,
+    /// Смещение записи внутри сегмента
+    pub offset: u64,
+    /// Размер записи на диске
+    pub size: usize,
+    pub format: RecordFormat,
+    /// Код типа на диске (для framed-записей — u32 из хвоста заголовка)
+    pub raw_type: u32,
+    /// Для framed-записей `record_type == Invalid`, `page_id == INVALID_PAGE_ID`
+    pub record: LogRecord,
+}
+
+impl WalRecord {
+    /// Тип записи, если он из `LogRecordType`
+    pub fn record_type(&self) -> Option<LogRecordType> {
+        match self.format {
+            RecordFormat::Packed => Some(self.record.record_type),
+            RecordFormat::Framed => None,
+        }
+    }
+
+    /// Имя типа для вывода: `INSERT` или код framed-записи (`0x1e`)
+    pub fn type_name(&self) -> String {
+        match self.record_type() {
+            Some(t) => t.name().to_string(),
+            None => format!("{:#04x}", self.raw_type),
+        }
+    }
+}
+
+/// Почему чтение остановилось раньше конца WAL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TailReason {
+    /// Запись не разобрана (неполная или неизвестный тип)
+    Decode(DecodeError),
+    /// LSN не больше предыдущего
+    LsnNotIncreasing { lsn: Lsn, previous: Lsn },
+    /// Framed-запись с неверным CRC32
+    ChecksumMismatch { stored: u32, computed: u32 },
+}
+
+impl fmt::Display for TailReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TailReason::Decode(e) => e.fmt(f),
+            TailReason::LsnNotIncreasing { lsn, previous } => {
+                write!(f, "LSN {lsn} does not follow {previous}")
+            }
+            TailReason::ChecksumMismatch { stored, computed } => {
+                write!(f, "record checksum {stored:#010x} != {computed:#010x}")
+            }
+        }
+    }
+}
+
+/// Место, где WAL перестаёт читаться
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TornTail {
+    pub segment_id: u64,
+    pub path: PathBuf,
+    pub offset: u64,
+    pub reason: TailReason,
+    /// Байт сегмента после точки обрыва
+    pub discarded_bytes: u64,
+    /// Непрочитанные сегменты после сегмента с обрывом. Больше нуля —
+    /// повреждена середина WAL, а не хвост после crash'а.
+    pub unread_segments: usize,
+}
+
+impl fmt::Display for TornTail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} @ {}: {} ({} bytes discarded",
+            self.path.display(),
+            self.offset,
+            self.reason,
+            self.discarded_bytes
+        )?;
+        if self.unread_segments > 0 {
+            write!(f, ", {} later segments unread", self.unread_segments)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Итог чтения WAL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalEnd {
+    /// LSN последней валидной записи (`INVALID_LSN`, если записей нет)
+    pub last_valid_lsn: Lsn,
+    pub records: u64,
+    pub torn: Option<TornTail>,
+}
+
+/// WAL директория
+#[derive(Debug, Clone)]
+pub struct WalReader {
+    dir: PathBuf,
+    segments: Vec<Segment>,
+}
+
+impl WalReader {
+    /// `DatabaseConfig::wal_dir()`
+    pub fn open(config: &DatabaseConfig) -> io::Result<Self> {
+        Self::open_dir(config.wal_dir())
+    }
+
+    /// Произвольная директория сегментов
+    pub fn open_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(id) = parse_segment_name(&path) else {
+                continue;
+            };
+            let meta = entry.metadata()?;
+            if meta.is_file() {
+                segments.push(Segment { id, path, len: meta.len() });
+            }
+        }
+        segments.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.path.cmp(&b.path)));
+        Ok(Self { dir, segments })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Суммарный размер сегментов
+    pub fn total_size(&self) -> u64 {
+        self.segments.iter().map(|s| s.len).sum()
+    }
+
+    /// Ленивый обход записей; после исчерпания `Records::end()` — итог
+    pub fn records(&self) -> Records<'_> {
+        Records {
+            reader: self,
+            next_segment: 0,
+            current: None,
+            last_lsn: INVALID_LSN,
+            count: 0,
+            torn: None,
+        }
+    }
+
+    /// Прочитать весь WAL в память
+    pub fn scan(&self) -> io::Result<WalScan> {
+        let mut iter = self.records();
+        let records = iter.by_ref().collect::<io::Result<Vec<_>>>()?;
+        Ok(WalScan { records, end: iter.end() })
+    }
+}
+
+struct OpenSegment {
+    index: usize,
+    bytes: Vec<u8>,
+    pos: usize,
+    format: RecordFormat,
+}
+
+/// Итератор записей (`WalReader::records`)
+pub struct Records<'a> {
+    reader: &'a WalReader,
+    next_segment: usize,
+    current: Option<OpenSegment>,
+    last_lsn: Lsn,
+    count: u64,
+    torn: Option<TornTail>,
+}
+
+impl Records<'_> {
+    /// Итог чтения на текущий момент
+    pub fn end(&self) -> WalEnd {
+        WalEnd { last_valid_lsn: self.last_lsn, records: self.count, torn: self.torn.clone() }
+    }
+
+    fn stop(&mut self, seg: &OpenSegment, reason: TailReason) {
+        let segment = &self.reader.segments[seg.index];
+        self.torn = Some(TornTail {
+            segment_id: segment.id,
+            path: segment.path.clone(),
+            offset: seg.pos as u64,
+            reason,
+            discarded_bytes: (seg.bytes.len() - seg.pos) as u64,
+            unread_segments: self.reader.segments.len() - seg.index - 1,
+        });
+        self.next_segment = self.reader.segments.len();
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = io::Result<WalRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.torn.is_some() {
+                return None;
+            }
+            let Some(seg) = self.current.as_ref() else {
+                let index = self.next_segment;
+                let segment = self.reader.segments.get(index)?;
+                self.next_segment += 1;
+                let bytes = match std::fs::read(&segment.path) {
+                    Ok(bytes) => bytes,
+                    Err(e) => return Some(Err(e)),
+                };
+                let format = detect_format(&bytes);
+                self.current = Some(OpenSegment { index, bytes, pos: 0, format });
+                continue;
+            };
+            if seg.pos == seg.bytes.len() {
+                self.current = None;
+                continue;
+            }
+
+            let rest = &seg.bytes[seg.pos..];
+            let decoded = match seg.format {
+                RecordFormat::Packed => LogRecord::deserialize(rest)
+                    .map(|(r, size)| (u32::from(r.record_type as u8), r, size))
+                    .map_err(TailReason::Decode),
+                RecordFormat::Framed => decode_framed(rest),
+            };
+            let decoded = decoded.and_then(|(raw_type, record, size)| {
+                if record.lsn == INVALID_LSN || record.lsn <= self.last_lsn {
+                    return Err(TailReason::LsnNotIncreasing { lsn: record.lsn, previous: self.last_lsn });
+                }
+                if seg.format == RecordFormat::Packed && record.record_type == LogRecordType::Invalid {
+                    return Err(TailReason::Decode(DecodeError::UnknownType(0)));
+                }
+                Ok((raw_type, record, size))
+            });
+
+            let seg = self.current.take().unwrap();
+            let (raw_type, record, size) = match decoded {
+                Ok(decoded) => decoded,
+                Err(reason) => {
+                    self.stop(&seg, reason);
+                    return None;
+                }
+            };
+
+            let item = WalRecord {
+                segment_id: self.reader.segments[seg.index].id,
+                offset: seg.pos as u64,
+                size,
+                format: seg.format,
+                raw_type,
+                record,
+            };
+            self.last_lsn = item.record.lsn;
+            self.count += 1;
+            self.current = Some(OpenSegment { pos: seg.pos + size, ..seg });
+            return Some(Ok(item));
+        }
+    }
+}
+
+/// Framed-формат узнаётся по первой записи: разумный размер и верный CRC
+fn detect_format(bytes: &[u8]) -> RecordFormat {
+    match decode_framed(bytes) {
+        Ok(_) => RecordFormat::Framed,
+        Err(_) => RecordFormat::Packed,
+    }
+}
+
+fn decode_framed(buf: &[u8]) -> Result<(u32, LogRecord, usize), TailReason> {
+    let incomplete = |needed| TailReason::Decode(DecodeError::Incomplete { needed, available: buf.len() });
+    if buf.len() < FRAMED_HEADER_SIZE {
+        return Err(incomplete(FRAMED_HEADER_SIZE));
+    }
+    let u32_at = |off: usize| u32::from_le_bytes(buf[off..off + 4].try_into().unwrap());
+    let u64_at = |off: usize| u64::from_le_bytes(buf[off..off + 8].try_into().unwrap());
+
+    let size = (u32_at(24) as usize).max(FRAMED_HEADER_SIZE);
+    if buf.len() < size {
+        return Err(incomplete(size));
+    }
+    let stored = u32_at(FRAMED_CHECKSUM_OFFSET);
+    let mut copy = buf[..size].to_vec();
+    copy[FRAMED_CHECKSUM_OFFSET..FRAMED_CHECKSUM_OFFSET + 4].fill(0);
+    let computed = crc32(&copy);
+    if stored != computed || u32_at(24) as usize != size {
+        return Err(TailReason::ChecksumMismatch { stored, computed });
+    }
+
+    let record = LogRecord {
+        record_type: LogRecordType::Invalid,
+        lsn: u64_at(0),
+        txn_id: u64_at(8),
+        page_id: INVALID_PAGE_ID,
+        prev_lsn: u64_at(16),
+        data: buf[FRAMED_HEADER_SIZE..size].to_vec(),
+        ..LogRecord::default()
+    };
+    Ok((u32_at(40), record, size))
+}
+
+// ============================================================================
+// Цепочки транзакций
+// ============================================================================
+
+/// Записи транзакции, связанные через `prev_lsn`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chain {
+    pub txn_id: TxnId,
+    /// LSN записей от первой к последней
+    pub lsns: Vec<Lsn>,
+    /// `prev_lsn`, по которому цепочка не продолжается: записи нет в WAL
+    /// (обрезан) или она принадлежит другой транзакции
+    pub broken_at: Option<Lsn>,
+    /// `TxnCommit` / `TxnAbort`, если транзакция завершена
+    pub outcome: Option<LogRecordType>,
+}
+
+impl Chain {
+    /// Транзакция без COMMIT/ABORT — кандидат на undo
+    pub fn is_open(&self) -> bool {
+        self.outcome.is_none()
+    }
+}
+
+/// Весь WAL в памяти
+#[derive(Debug, Clone)]
+pub struct WalScan {
+    /// Записи в порядке LSN
+    pub records: Vec<WalRecord>,
+    pub end: WalEnd,
+}
+
+impl WalScan {
+    /// Запись по LSN
+    pub fn get(&self, lsn: Lsn) -> Option<&WalRecord> {
+        self.records
+            .binary_search_by_key(&lsn, |r| r.record.lsn)
+            .ok()
+            .map(|i| &self.records[i])
+    }
+
+    /// Цепочка транзакции от её последней записи назад по `prev_lsn`
+    pub fn chain(&self, txn_id: TxnId) -> Option<Chain> {
+        let last = self.records.iter().rev().find(|r| r.record.txn_id == txn_id)?;
+        let outcome = last
+            .record_type()
+            .filter(|t| matches!(t, LogRecordType::TxnCommit | LogRecordType::TxnAbort));
+
+        let mut lsns = vec![last.record.lsn];
+        let mut broken_at = None;
+        let mut prev = last.record.prev_lsn;
+        while prev != INVALID_LSN {
+            match self.get(prev) {
+                Some(r) if r.record.txn_id == txn_id && prev < *lsns.last().unwrap() => {
+                    lsns.push(prev);
+                    prev = r.record.prev_lsn;
+                }
+                _ => {
+                    broken_at = Some(prev);
+                    break;
+                }
+            }
+        }
+        lsns.reverse();
+        Some(Chain { txn_id, lsns, broken_at, outcome })
+    }
+
+    /// Цепочки всех транзакций (txn_id 0 — системные записи — пропускаются)
+    pub fn chains(&self) -> Vec<Chain> {
+        let txns: BTreeMap<TxnId, ()> = self
+            .records
+            .iter()
+            .filter(|r| r.record.txn_id != 0)
+            .map(|r| (r.record.txn_id, ()))
+            .collect();
+        txns.keys().filter_map(|&txn| self.chain(txn)).collect()
+    }
+
+    /// Пары CHECKPOINT_BEGIN / CHECKPOINT_END
+    pub fn checkpoints(&self) -> Vec<CheckpointPair> {
+        let mut tracker = CheckpointTracker::default();
+        self.records.iter().for_each(|r| tracker.observe(r));
+        tracker.finish()
+    }
+}
+
+// ============================================================================
+// Checkpoint'ы
+// ============================================================================
+
+/// CHECKPOINT_BEGIN и соответствующий CHECKPOINT_END (`prev_lsn` END = LSN BEGIN)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointPair {
+    pub begin_lsn: Lsn,
+    /// `false` — END ссылается на BEGIN, которого нет в WAL (обрезан)
+    pub begin_found: bool,
+    /// `None` — checkpoint не завершён
+    pub end_lsn: Option<Lsn>,
+}
+
+impl CheckpointPair {
+    pub fn is_complete(&self) -> bool {
+        self.begin_found && self.end_lsn.is_some()
+    }
+}
+
+/// Сопоставление BEGIN/END по ходу чтения
+#[derive(Debug, Default)]
+pub struct CheckpointTracker {
+    pairs: Vec<CheckpointPair>,
+    open: BTreeMap<Lsn, usize>,
+}
+
+impl CheckpointTracker {
+    pub fn observe(&mut self, record: &WalRecord) {
+        match record.record_type() {
+            Some(LogRecordType::CheckpointBegin) => {
+                self.open.insert(record.record.lsn, self.pairs.len());
+                self.pairs.push(CheckpointPair {
+                    begin_lsn: record.record.lsn,
+                    begin_found: true,
+                    end_lsn: None,
+                });
+            }
+            Some(LogRecordType::CheckpointEnd) => match self.open.remove(&record.record.prev_lsn) {
+                Some(i) => self.pairs[i].end_lsn = Some(record.record.lsn),
+                None => self.pairs.push(CheckpointPair {
+                    begin_lsn: record.record.prev_lsn,
+                    begin_found: false,
+                    end_lsn: Some(record.record.lsn),
+                }),
+            },
+            _ => {}
+        }
+    }
+
+    /// Пары в порядке появления в WAL
+    pub fn finish(self) -> Vec<CheckpointPair> {
+        self.pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::segment_file_name;
+
+    struct TempWal(PathBuf);
+
+    impl TempWal {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("datyredb_wal_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write_segment(&self, id: u64, records: &[LogRecord]) -> PathBuf {
+            let mut buf = Vec::new();
+            for r in records {
+                r.serialize(&mut buf);
+            }
+            let path = self.0.join(segment_file_name(id));
+            std::fs::write(&path, buf).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempWal {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn rec(record_type: LogRecordType, lsn: Lsn, txn_id: TxnId, prev_lsn: Lsn) -> LogRecord {
+        LogRecord { record_type, lsn, txn_id, prev_lsn, page_id: 1, data: vec![7; 8], ..LogRecord::default() }
+    }
+
+    #[test]
+    fn reads_across_segments_and_stops_at_torn_tail() {
+        use LogRecordType::*;
+        let wal = TempWal::new("torn");
+        wal.write_segment(0, &[rec(TxnBegin, 1, 5, 0), rec(Insert, 2, 5, 1)]);
+        let last = wal.write_segment(1, &[rec(TxnBegin, 3, 6, 0), rec(Update, 4, 5, 2), rec(TxnCommit, 5, 5, 4)]);
+        // Обрываем последнюю запись посередине
+        let bytes = std::fs::read(&last).unwrap();
+        std::fs::write(&last, &bytes[..bytes.len() - 10]).unwrap();
+
+        let reader = WalReader::open_dir(&wal.0).unwrap();
+        assert_eq!(reader.segments().len(), 2);
+        let scan = reader.scan().unwrap();
+        let lsns: Vec<_> = scan.records.iter().map(|r| r.record.lsn).collect();
+        assert_eq!(lsns, [1, 2, 3, 4]);
+        assert_eq!(scan.records[2].segment_id, 1);
+        assert_eq!(scan.records[2].offset, 0);
+
+        assert_eq!(scan.end.last_valid_lsn, 4);
+        let torn = scan.end.torn.clone().unwrap();
+        assert_eq!(torn.segment_id, 1);
+        assert_eq!(torn.unread_segments, 0);
+        assert!(matches!(torn.reason, TailReason::Decode(DecodeError::Incomplete { .. })));
+
+        let chain = scan.chain(5).unwrap();
+        assert_eq!(chain.lsns, [1, 2, 4]);
+        assert!(chain.is_open());
+        assert_eq!(chain.broken_at, None);
+        assert_eq!(scan.chains().len(), 2);
+    }
+
+    #[test]
+    fn detects_lsn_regression_and_broken_chains() {
+        use LogRecordType::*;
+        let wal = TempWal::new("lsn");
+        wal.write_segment(3, &[rec(Insert, 10, 1, 7), rec(TxnCommit, 11, 1, 10), rec(Insert, 4, 2, 0)]);
+
+        let scan = WalReader::open_dir(&wal.0).unwrap().scan().unwrap();
+        assert_eq!(scan.records.len(), 2);
+        let torn = scan.end.torn.clone().unwrap();
+        assert_eq!(torn.reason, TailReason::LsnNotIncreasing { lsn: 4, previous: 11 });
+        assert_eq!(torn.offset, 2 * 45);
+
+        let chain = scan.chain(1).unwrap();
+        assert_eq!(chain.outcome, Some(TxnCommit));
+        assert_eq!(chain.broken_at, Some(7));
+    }
+
+    #[test]
+    fn pairs_checkpoints() {
+        use LogRecordType::*;
+        let wal = TempWal::new("checkpoints");
+        wal.write_segment(0, &[
+            rec(CheckpointEnd, 3, 0, 1),
+            rec(CheckpointBegin, 4, 0, 0),
+            rec(Insert, 5, 1, 0),
+            rec(CheckpointEnd, 6, 0, 4),
+            rec(CheckpointBegin, 7, 0, 0),
+        ]);
+
+        let scan = WalReader::open_dir(&wal.0).unwrap().scan().unwrap();
+        let pairs = scan.checkpoints();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(pairs[0], CheckpointPair { begin_lsn: 1, begin_found: false, end_lsn: Some(3) });
+        assert!(pairs[1].is_complete());
+        assert_eq!(pairs[1].end_lsn, Some(6));
+        assert_eq!(pairs[2].end_lsn, None);
+    }
+
+    #[test]
+    fn decodes_example_wal() {
+        let wal = TempWal::new("example");
+        std::fs::write(
+            wal.0.join("wal_00000000.log"),
+            include_bytes!("../../example_data/wal/wal_00000000.log"),
+        )
+        .unwrap();
+
+        let scan = WalReader::open_dir(&wal.0).unwrap().scan().unwrap();
+        assert_eq!(scan.end.torn, None);
+        assert_eq!(scan.end.last_valid_lsn, 4);
+        let summary: Vec<_> = scan
+            .records
+            .iter()
+            .map(|r| (r.format, r.record.lsn, r.record.prev_lsn, r.type_name()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (RecordFormat::Framed, 1, 0, "0x1e".to_string()),
+                (RecordFormat::Framed, 2, 1, "0x1f".to_string()),
+                (RecordFormat::Framed, 3, 0, "0x1e".to_string()),
+                (RecordFormat::Framed, 4, 3, "0x1f".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Последовательная запись WAL (аналог `WriteAheadLog::append`).
+//!
+//! LSN назначает writer, запись не разрывается между сегментами: если
+//! она не помещается в текущий сегмент, открывается следующий.
+//!
+//! Writer помнит первый LSN каждого сегмента и первую и последнюю запись
+//! каждой незавершённой транзакции: `truncate_before` удаляет только
+//! сегменты, не нужные ни после checkpoint'а, ни для undo активных
+//! транзакций, а CHECKPOINT_END несёт их таблицу для анализа при recovery.
+//! С архивом (`set_archive`) завершённые сегменты копируются в него до
+//! удаления.
+
+use super::archive::WalArchive;
+use super::reader::WalReader;
+use super::{encode_active_txns, segment_file_name, LogRecord, LogRecordType};
+use crate::config::WalSyncMode;
+use crate::page::{Lsn, TxnId, INVALID_LSN, INVALID_PAGE_ID};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Writer сегментов WAL
+#[derive(Debug)]
+pub struct WalWriter {
+    dir: PathBuf,
+    segment_size: u64,
+    segment_id: u64,
+    segment: File,
+    segment_pos: u64,
+    next_lsn: Lsn,
+    flushed_lsn: Lsn,
+    /// Байт во всех сегментах
+    size: u64,
+    /// Первый LSN каждого непустого сегмента
+    segment_first_lsn: BTreeMap<u64, Lsn>,
+    /// Первый и последний LSN каждой транзакции без COMMIT/ABORT
+    active_txns: BTreeMap<TxnId, (Lsn, Lsn)>,
+    /// Наибольший id транзакции в записях
+    last_txn_id: TxnId,
+    sync_mode: WalSyncMode,
+    archive: Option<WalArchive>,
+    /// Сегменты до этого номера уже скопированы в архив
+    archived_before: u64,
+    /// Записи начиная с этого LSN не удаляются (`set_retain_from`)
+    retain_from: Option<Lsn>,
+    /// Недописанную запись не удалось отрезать: сегмент заканчивается
+    /// мусором, и дальнейшие записи легли бы за ним
+    broken: bool,
+    buf: Vec<u8>,
+}
+
+impl WalWriter {
+    /// Новый WAL в пустой (или несуществующей) директории
+    pub fn create(dir: impl AsRef<Path>, segment_size: u64) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        if WalReader::open_dir(&dir)?.segments().iter().any(|s| s.len > 0) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already contains WAL segments", dir.display()),
+            ));
+        }
+        let segment = open_segment(&dir, 0, true)?;
+        Ok(Self::with_state(dir, segment_size, 0, segment, 0, 1))
+    }
+
+    /// Продолжить существующий WAL: оборванный хвост последнего сегмента
+    /// обрезается, LSN продолжается с последнего валидного
+    pub fn open(dir: impl AsRef<Path>, segment_size: u64) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let reader = WalReader::open_dir(&dir)?;
+        let mut records = reader.records();
+        let mut tail = None;
+        let mut size = 0;
+        let mut segment_first_lsn = BTreeMap::new();
+        let mut active_txns = BTreeMap::new();
+        let mut last_txn_id = 0;
+        for record in records.by_ref() {
+            let record = record?;
+            tail = Some((record.segment_id, record.offset + record.size as u64));
+            size += record.size as u64;
+            segment_first_lsn.entry(record.segment_id).or_insert(record.record.lsn);
+            track_txn(&mut active_txns, &record.record, record.record.lsn);
+            last_txn_id = last_txn_id.max(record.record.txn_id);
+        }
+        let end = records.end();
+
+        if let Some(torn) = &end.torn {
+            if torn.unread_segments > 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("WAL is damaged before its last segment: {torn}"),
+                ));
+            }
+            OpenOptions::new().write(true).open(&torn.path)?.set_len(torn.offset)?;
+        }
+
+        let (segment_id, pos) = match (tail, reader.segments().last()) {
+            (Some((id, pos)), _) => (id, pos),
+            (None, Some(last)) => (last.id, 0),
+            (None, None) => (0, 0),
+        };
+        let segment = open_segment(&dir, segment_id, pos == 0)?;
+        let next_lsn = end.last_valid_lsn + 1;
+        let mut writer = Self::with_state(dir, segment_size, segment_id, segment, pos, next_lsn);
+        writer.flushed_lsn = end.last_valid_lsn;
+        writer.size = size;
+        writer.segment_first_lsn = segment_first_lsn;
+        writer.active_txns = active_txns;
+        writer.last_txn_id = last_txn_id;
+        Ok(writer)
+    }
+
+    fn with_state(
+        dir: PathBuf,
+        segment_size: u64,
+        segment_id: u64,
+        segment: File,
+        pos: u64,
+        next_lsn: Lsn,
+    ) -> Self {
+        Self {
+            dir,
+            segment_size,
+            segment_id,
+            segment,
+            segment_pos: pos,
+            next_lsn,
+            flushed_lsn: INVALID_LSN,
+            size: 0,
+            segment_first_lsn: BTreeMap::new(),
+            active_txns: BTreeMap::new(),
+            last_txn_id: 0,
+            sync_mode: WalSyncMode::default(),
+            archive: None,
+            archived_before: 0,
+            retain_from: None,
+            broken: false,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// LSN, который получит следующая запись
+    pub fn next_lsn(&self) -> Lsn {
+        self.next_lsn
+    }
+
+    /// Последний LSN, сброшенный на диск `flush()`
+    pub fn flushed_lsn(&self) -> Lsn {
+        self.flushed_lsn
+    }
+
+    /// Как `flush()` и ротация сбрасывают сегменты (по умолчанию fdatasync)
+    pub fn set_sync_mode(&mut self, mode: WalSyncMode) {
+        self.sync_mode = mode;
+    }
+
+    pub fn sync_mode(&self) -> WalSyncMode {
+        self.sync_mode
+    }
+
+    /// Дескриптор текущего сегмента и последний записанный LSN: сброс этого
+    /// дескриптора делает долговечными все записи до LSN включительно
+    /// (предыдущие сегменты сброшены при ротации). Нужен, чтобы сбрасывать
+    /// WAL, не держа writer.
+    pub fn sync_handle(&self) -> io::Result<(File, Lsn)> {
+        Ok((self.segment.try_clone()?, self.next_lsn - 1))
+    }
+
+    /// Отметить записи до `lsn` сброшенными через `sync_handle`
+    pub fn mark_flushed(&mut self, lsn: Lsn) {
+        self.flushed_lsn = self.flushed_lsn.max(lsn);
+    }
+
+    /// Архив, в который копируются сегменты перед удалением
+    pub fn set_archive(&mut self, archive: Option<WalArchive>) {
+        self.archive = archive;
+        self.archived_before = 0;
+    }
+
+    pub fn archive(&self) -> Option<&WalArchive> {
+        self.archive.as_ref()
+    }
+
+    /// Скопировать в архив все завершённые сегменты (кроме текущего);
+    /// возвращает число новых копий. Без архива ничего не делает.
+    pub fn archive_completed(&mut self) -> io::Result<usize> {
+        let Some(archive) = &self.archive else {
+            return Ok(0);
+        };
+        let mut copied = 0;
+        let completed = self.segment_first_lsn.range(self.archived_before..self.segment_id);
+        for &id in completed.map(|(id, _)| id) {
+            if archive.archive(id, &self.dir.join(segment_file_name(id)))? {
+                copied += 1;
+            }
+            self.archived_before = id + 1;
+        }
+        Ok(copied)
+    }
+
+    /// Запретить `truncate_before` удалять записи начиная с `lsn` (пока идёт
+    /// базовая копия); `None` снимает запрет
+    pub fn set_retain_from(&mut self, lsn: Option<Lsn>) {
+        self.retain_from = lsn;
+    }
+
+    /// Первый LSN самой старой незавершённой транзакции
+    pub fn oldest_active_lsn(&self) -> Option<Lsn> {
+        self.active_txns.values().map(|&(first, _)| first).min()
+    }
+
+    /// Сегмент, в котором лежит запись `lsn` (или лежала бы, если её ещё
+    /// нет); для записей старше WAL — самый старый сегмент
+    pub fn segment_of(&self, lsn: Lsn) -> u64 {
+        let segments = self.segment_first_lsn.range(..=self.segment_id);
+        let oldest = segments.clone().next().map_or(self.segment_id, |(&id, _)| id);
+        segments.rev().find(|(_, &first)| first <= lsn).map_or(oldest, |(&id, _)| id)
+    }
+
+    /// Незавершённые транзакции: `(txn_id, last_lsn)`
+    pub fn active_txns(&self) -> Vec<(TxnId, Lsn)> {
+        self.active_txns.iter().map(|(&txn_id, &(_, last))| (txn_id, last)).collect()
+    }
+
+    /// Id для новой транзакции: больше любого id в записях WAL, прочитанных
+    /// при открытии или записанных после. Id занят, когда записана первая
+    /// запись транзакции.
+    pub fn next_txn_id(&self) -> TxnId {
+        self.last_txn_id + 1
+    }
+
+    /// Суммарный размер сегментов (`WriteAheadLog::current_size`)
+    pub fn current_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Записать запись; `record.lsn` заменяется назначенным LSN.
+    ///
+    /// Если запись легла не целиком, её начало отрезается; не вышло — writer
+    /// отказывает во всех следующих записях, пока WAL не откроют заново
+    /// (`open` отрежет хвост сам).
+    pub fn append(&mut self, record: &LogRecord) -> io::Result<Lsn> {
+        if self.broken {
+            return Err(io::Error::other(format!(
+                "WAL segment {} ends with a partial record; reopen the WAL",
+                self.segment_id
+            )));
+        }
+        let lsn = self.next_lsn;
+        self.buf.clear();
+        LogRecord { lsn, ..record.clone() }.serialize(&mut self.buf);
+
+        let size = self.buf.len() as u64;
+        if self.segment_pos > 0 && self.segment_pos + size > self.segment_size {
+            self.rotate()?;
+        }
+        if let Err(e) = self.segment.write_all(&self.buf) {
+            // Сегмент открыт на дозапись: после обрезки следующая запись
+            // ляжет с `segment_pos`
+            self.broken = self.segment.set_len(self.segment_pos).is_err();
+            return Err(e);
+        }
+        self.segment_first_lsn.entry(self.segment_id).or_insert(lsn);
+        track_txn(&mut self.active_txns, record, lsn);
+        self.last_txn_id = self.last_txn_id.max(record.txn_id);
+        self.segment_pos += size;
+        self.size += size;
+        self.next_lsn += 1;
+        Ok(lsn)
+    }
+
+    /// CHECKPOINT_BEGIN
+    pub fn checkpoint_begin(&mut self) -> io::Result<Lsn> {
+        self.append(&LogRecord::new(LogRecordType::CheckpointBegin, 0))
+    }
+
+    /// CHECKPOINT_END (`prev_lsn` = LSN BEGIN) с таблицей активных
+    /// транзакций и принудительным flush
+    pub fn checkpoint_end(&mut self, begin_lsn: Lsn) -> io::Result<Lsn> {
+        let record = LogRecord {
+            page_id: INVALID_PAGE_ID,
+            prev_lsn: begin_lsn,
+            data: encode_active_txns(self.active_txns()),
+            ..LogRecord::new(LogRecordType::CheckpointEnd, 0)
+        };
+        let lsn = self.append(&record)?;
+        self.flush()?;
+        Ok(lsn)
+    }
+
+    /// fsync текущего сегмента (`WriteAheadLog::force`)
+    pub fn flush(&mut self) -> io::Result<()> {
+        sync_file(&self.segment, self.sync_mode)?;
+        self.flushed_lsn = self.next_lsn - 1;
+        Ok(())
+    }
+
+    /// Сбросить WAL, если записи до `lsn` ещё не сброшены: так страница с
+    /// `page_lsn` = `lsn` попадает на диск не раньше своих записей
+    pub fn flush_to(&mut self, lsn: Lsn) -> io::Result<()> {
+        if lsn > self.flushed_lsn {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Удалить сегменты, все записи которых старше `lsn`.
+    ///
+    /// Граница сдвигается к первой записи самой старой активной транзакции
+    /// и к `set_retain_from`, текущий сегмент не удаляется никогда. С архивом
+    /// сначала копируются все завершённые сегменты. Возвращает освобождённые
+    /// байты.
+    pub fn truncate_before(&mut self, lsn: Lsn) -> io::Result<u64> {
+        self.archive_completed()?;
+        let keep_from =
+            self.oldest_active_lsn().into_iter().chain(self.retain_from).fold(lsn, Lsn::min);
+        let removable: Vec<u64> = self
+            .segment_first_lsn
+            .iter()
+            .zip(self.segment_first_lsn.values().skip(1))
+            .take_while(|((&id, _), &next_first)| id < self.segment_id && next_first <= keep_from)
+            .map(|((&id, _), _)| id)
+            .collect();
+
+        let mut freed = 0;
+        for id in removable {
+            let path = self.dir.join(segment_file_name(id));
+            let len = std::fs::metadata(&path)?.len();
+            std::fs::remove_file(&path)?;
+            self.segment_first_lsn.remove(&id);
+            self.size -= len;
+            freed += len;
+        }
+        Ok(freed)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        sync_file(&self.segment, self.sync_mode)?;
+        self.segment_id += 1;
+        self.segment = open_segment(&self.dir, self.segment_id, true)?;
+        self.segment_pos = 0;
+        Ok(())
+    }
+}
+
+/// Учёт незавершённых транзакций (txn_id 0 — системные записи)
+fn track_txn(active: &mut BTreeMap<TxnId, (Lsn, Lsn)>, record: &LogRecord, lsn: Lsn) {
+    if record.txn_id == 0 {
+        return;
+    }
+    match record.record_type {
+        LogRecordType::TxnCommit | LogRecordType::TxnAbort => {
+            active.remove(&record.txn_id);
+        }
+        _ => active.entry(record.txn_id).or_insert((lsn, lsn)).1 = lsn,
+    }
+}
+
+/// Сброс файла сегмента согласно `mode`
+pub fn sync_file(file: &File, mode: WalSyncMode) -> io::Result<()> {
+    match mode {
+        WalSyncMode::Fsync => file.sync_all(),
+        WalSyncMode::Fdatasync => file.sync_data(),
+        WalSyncMode::None => Ok(()),
+    }
+}
+
+fn open_segment(dir: &Path, segment_id: u64, create: bool) -> io::Result<File> {
+    let path = dir.join(segment_file_name(segment_id));
+    let mut options = OpenOptions::new();
+    options.append(true);
+    if create {
+        options.create(true);
+    }
+    options.open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::reader::WalReader;
+
+    #[test]
+    fn failed_append_leaves_no_partial_record() {
+        let dir = std::env::temp_dir()
+            .join(format!("datyredb_wal_writer_broken_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut wal = WalWriter::create(&dir, 4096).unwrap();
+        wal.append(&LogRecord::new(LogRecordType::TxnBegin, 1)).unwrap();
+
+        // Дескриптор только на чтение: ни запись, ни обрезка не проходят
+        let path = dir.join(segment_file_name(0));
+        wal.segment = File::open(&path).unwrap();
+        assert!(wal.append(&LogRecord::new(LogRecordType::TxnBegin, 2)).is_err());
+        let err = wal.append(&LogRecord::new(LogRecordType::TxnBegin, 3)).unwrap_err();
+        assert!(err.to_string().contains("reopen the WAL"), "{err}");
+        assert_eq!(wal.next_lsn(), 2);
+        drop(wal);
+
+        let mut wal = WalWriter::open(&dir, 4096).unwrap();
+        assert_eq!(wal.append(&LogRecord::new(LogRecordType::TxnBegin, 2)).unwrap(), 2);
+        assert_eq!(WalReader::open_dir(&dir).unwrap().scan().unwrap().end.last_valid_lsn, 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_and_resumes() {
+        let dir = std::env::temp_dir().join(format!("datyredb_wal_writer_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut wal = WalWriter::create(&dir, 100).unwrap();
+        for txn in 1..=4 {
+            wal.append(&LogRecord::new(LogRecordType::TxnBegin, txn)).unwrap();
+        }
+        wal.flush().unwrap();
+        assert_eq!(wal.flushed_lsn(), 4);
+        drop(wal);
+
+        // 37 байт на запись: по две в сегмент на 100 байт
+        let reader = WalReader::open_dir(&dir).unwrap();
+        assert_eq!(reader.segments().len(), 2);
+
+        // Недописанная запись в конце последнего сегмента
+        let last = reader.segments().last().unwrap().path.clone();
+        OpenOptions::new().append(true).open(&last).unwrap().write_all(&[5, 1, 2]).unwrap();
+
+        let mut wal = WalWriter::open(&dir, 100).unwrap();
+        assert_eq!(wal.next_lsn(), 5);
+        let begin = wal.checkpoint_begin().unwrap();
+        wal.checkpoint_end(begin).unwrap();
+
+        let scan = WalReader::open_dir(&dir).unwrap().scan().unwrap();
+        assert_eq!(scan.end.torn, None);
+        assert_eq!(scan.end.last_valid_lsn, 6);
+        assert!(scan.checkpoints()[0].is_complete());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncation_keeps_active_transactions() {
+        let dir = std::env::temp_dir().join(format!("datyredb_wal_truncate_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // По две записи в сегмент: [1 2] [3 4] [5 6] [7 ...]
+        let mut wal = WalWriter::create(&dir, 100).unwrap();
+        wal.append(&LogRecord::new(LogRecordType::TxnBegin, 1)).unwrap();
+        wal.append(&LogRecord::new(LogRecordType::TxnCommit, 1)).unwrap();
+        wal.append(&LogRecord::new(LogRecordType::TxnBegin, 2)).unwrap();
+        wal.append(&LogRecord::new(LogRecordType::TxnBegin, 3)).unwrap();
+        wal.append(&LogRecord::new(LogRecordType::TxnCommit, 3)).unwrap();
+        let begin = wal.checkpoint_begin().unwrap();
+        let end = wal.checkpoint_end(begin).unwrap();
+        // END несёт таблицу активных транзакций: (2, LSN 3)
+        assert_eq!(wal.active_txns(), vec![(2, 3)]);
+        assert_eq!(wal.current_size(), 7 * 37 + 16);
+        let scan = WalReader::open_dir(&dir).unwrap().scan().unwrap();
+        assert_eq!(scan.get(end).unwrap().record.active_txns(), Some(vec![(2, 3)]));
+
+        // Транзакция 2 начата в LSN 3: её сегмент остаётся
+        assert_eq!(wal.truncate_before(begin).unwrap(), 74);
+        assert_eq!(wal.current_size(), 5 * 37 + 16);
+
+        wal.append(&LogRecord::new(LogRecordType::TxnCommit, 2)).unwrap();
+        wal.flush().unwrap();
+        drop(wal);
+
+        let mut wal = WalWriter::open(&dir, 100).unwrap();
+        assert_eq!(wal.current_size(), 6 * 37 + 16);
+        assert!(wal.active_txns().is_empty());
+        assert_eq!(wal.next_txn_id(), 4);
+        assert_eq!(wal.truncate_before(begin).unwrap(), 74);
+        let reader = WalReader::open_dir(&dir).unwrap();
+        assert_eq!(reader.segments().iter().map(|s| s.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(reader.records().next().unwrap().unwrap().record.lsn, 5);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+//! Синхронизация Rust `DatabaseConfig` с C++ `storage::StorageConfig`.
+//!
+//! Тест разбирает `src/storage/storage_types.hpp` и `config_file.cpp`,
+//! поэтому падает, если поле добавлено только на одной из сторон.
+
+use datyredb::config::{config_keys, interchange, DatabaseConfig};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const STORAGE_TYPES_HPP: &str = include_str!("../src/storage/storage_types.hpp");
+const CONFIG_FILE_CPP: &str = include_str!("../src/storage/config_file.cpp");
+
+/// Ключи `datyredb.toml`, которых намеренно нет в C++ движке
+const RUST_ONLY_KEYS: &[&str] =
+    &["wal_commit_delay", "wal_max_group_size", "wal_sync_mode", "wal_archive_dir"];
+
+/// Имена полей `struct <name> { ... };` из C++ заголовка
+fn cxx_struct_fields(name: &str) -> Vec<String> {
+    let start = STORAGE_TYPES_HPP
+        .find(&format!("struct {name} {{"))
+        .unwrap_or_else(|| panic!("struct {name} not found in storage_types.hpp"));
+    let body = &STORAGE_TYPES_HPP[start..];
+    let body = &body[body.find('{').unwrap() + 1..body.find("\n};").unwrap()];
+
+    body.lines()
+        .map(|l| l.split("//").next().unwrap().trim())
+        .filter(|l| l.ends_with(';'))
+        .filter(|l| !l.starts_with("static") && !l.contains('('))
+        .map(|l| {
+            let decl = l.split(['=', '{', ';']).next().unwrap().trim();
+            decl.rsplit(' ').next().unwrap().to_string()
+        })
+        .collect()
+}
+
+fn cxx_keys() -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for field in cxx_struct_fields("StorageConfig") {
+        if field != "checkpoint" {
+            keys.insert(field);
+        }
+    }
+    for field in cxx_struct_fields("CheckpointConfig") {
+        keys.insert(format!("checkpoint.{field}"));
+    }
+    // Размер страницы в C++ — константа PAGE_SIZE, а не поле
+    keys.insert("page_size".to_string());
+    keys
+}
+
+#[test]
+fn rust_and_cxx_know_the_same_fields() {
+    let rust: BTreeSet<String> = interchange::KEYS.iter().map(|(k, _)| k.to_string()).collect();
+    assert_eq!(rust, cxx_keys(), "interchange keys drifted from storage_types.hpp");
+
+    for (key, _) in interchange::KEYS {
+        assert!(
+            CONFIG_FILE_CPP.contains(&format!("\"{key}\"")),
+            "config_file.cpp does not handle '{key}'"
+        );
+    }
+}
+
+#[test]
+fn every_config_key_is_exchanged() {
+    let exchanged: BTreeSet<&str> = interchange::KEYS.iter().map(|(_, k)| *k).collect();
+    for key in config_keys() {
+        assert!(
+            exchanged.contains(key) || RUST_ONLY_KEYS.contains(&key),
+            "'{key}' is neither exported to C++ nor listed as Rust-only"
+        );
+    }
+}
+
+#[test]
+fn round_trip_preserves_every_field() {
+    let mut config = DatabaseConfig {
+        data_path: PathBuf::from("/var/lib/datyredb"),
+        buffer_pool_size: 2048 * 4096,
+        wal_segment_size: 16 << 20,
+        ..DatabaseConfig::default()
+    };
+    config.checkpoint.max_interval = Duration::from_secs(300);
+    config.checkpoint.min_interval = Duration::from_secs(15);
+    config.checkpoint.max_wal_size = 2 << 30;
+    config.checkpoint.dirty_page_soft_limit_pct = 0.5;
+    config.checkpoint.dirty_page_hard_limit_pct = 0.75;
+    config.checkpoint.checkpoint_batch_size = 128;
+    config.checkpoint.batch_throttle_us = Duration::from_micros(250);
+    config.checkpoint.async_checkpoint = false;
+
+    let text = interchange::export(&config).unwrap();
+    assert!(text.starts_with(interchange::HEADER));
+    assert_eq!(interchange::import(&text).unwrap(), config);
+}
+
+#[test]
+fn import_rejects_unknown_and_missing_keys() {
+    let text = interchange::export(&DatabaseConfig::default()).unwrap();
+
+    let extra = format!("{text}checkpoint.future_knob = 1\n");
+    assert!(matches!(
+        interchange::import(&extra),
+        Err(interchange::InterchangeError::UnknownKey { .. })
+    ));
+
+    let missing: String = text
+        .lines()
+        .filter(|l| !l.starts_with("wal_segment_size"))
+        .map(|l| format!("{l}\n"))
+        .collect();
+    assert!(matches!(
+        interchange::import(&missing),
+        Err(interchange::InterchangeError::MissingKey("wal_segment_size"))
+    ));
+}
+
+#[test]
+fn export_refuses_lossy_values() {
+    let mut config = DatabaseConfig::default();
+    config.checkpoint.max_interval = Duration::from_millis(1500);
+    assert!(matches!(
+        interchange::export(&config),
+        Err(interchange::InterchangeError::Precision { .. })
+    ));
+}
@@ -0,0 +1,396 @@
+//! Crash-injection для recovery.
+//!
+//! Дочерний процесс (этот же тестовый бинарник, тест `crash_child`)
+//! выполняет детерминированную нагрузку — пары чередующихся транзакций,
+//! откаты с CLR, checkpoint'ы посреди транзакций, вытеснение грязных
+//! страниц из маленького buffer pool — и вызывает `abort()` сразу после
+//! записи заданного LSN. Затем директория восстанавливается, и каждая
+//! ячейка сравнивается с моделью: видны ровно транзакции с COMMIT не
+//! позже точки сбоя.
+//!
+//! `abort()` не теряет записи, которые WAL не успел сбросить: они уже в
+//! кэше ОС. Чтобы проверить правило WAL (страница попадает на диск только
+//! после своих записей), второй режим перед recovery отрезает от WAL всё
+//! после `flushed_lsn`, который дочерний процесс оставляет в файле
+//! `flushed_lsn`; точкой сбоя тогда считается он.
+
+use datyredb::buffer_pool::BufferPool;
+use datyredb::config::DatabaseConfig;
+use datyredb::disk_manager::DiskManager;
+use datyredb::page::{Lsn, PageHeader, PageId, TxnId, INVALID_LSN, PAGE_HEADER_SIZE};
+use datyredb::recovery::recover;
+use datyredb::wal::{LogRecord, LogRecordType, WalReader, WalWriter};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+const CRASH_DIR_ENV: &str = "DATYRE_CRASH_DIR";
+const CRASH_AT_ENV: &str = "DATYRE_CRASH_AT";
+/// Файл в директории сбоя с `flushed_lsn` WAL в момент `abort()`
+const FLUSHED_FILE: &str = "flushed_lsn";
+
+const PAGE_SIZE: usize = 512;
+const PAGES: u32 = 8;
+/// Кадров меньше, чем страниц: грязные страницы вытесняются посреди транзакций
+const FRAMES: usize = 4;
+const CELLS_PER_PAGE: usize = (PAGE_SIZE - PAGE_HEADER_SIZE) / 8;
+const CELLS: usize = PAGES as usize * CELLS_PER_PAGE;
+
+// ============================================================================
+// Нагрузка
+// ============================================================================
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Begin(TxnId),
+    Update { txn: TxnId, cell: usize, value: u64 },
+    Commit(TxnId),
+    /// Откат всех изменений транзакции: CLR на каждое и TXN_ABORT
+    Rollback(TxnId),
+    Checkpoint,
+}
+
+/// Детерминированный генератор (LCG из Numerical Recipes)
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) % bound
+    }
+}
+
+/// Пары транзакций (нечётная пишет в чётные ячейки, чётная — в нечётные),
+/// checkpoint посреди каждой третьей пары
+fn workload() -> Vec<Op> {
+    let mut rng = Rng(0x5eed);
+    let mut ops = Vec::new();
+    for pair in 0..12u64 {
+        let txns = [2 * pair + 1, 2 * pair + 2];
+        ops.extend(txns.map(Op::Begin));
+        let updates = 6 + rng.next(6);
+        for seq in 0..updates {
+            let side = rng.next(2) as usize;
+            let txn = txns[side];
+            let cell = (rng.next(CELLS as u64 / 2) as usize) * 2 + side;
+            ops.push(Op::Update { txn, cell, value: (txn << 16) | seq });
+            if pair % 3 == 1 && seq == updates / 2 {
+                ops.push(Op::Checkpoint);
+            }
+        }
+        for txn in txns {
+            ops.push(if rng.next(4) == 0 { Op::Rollback(txn) } else { Op::Commit(txn) });
+        }
+    }
+    ops
+}
+
+/// Число WAL записей операции; `updates` — сколько изменений у транзакции
+fn op_records(op: Op, updates: &BTreeMap<TxnId, usize>) -> u64 {
+    match op {
+        Op::Rollback(txn) => updates.get(&txn).copied().unwrap_or(0) as u64 + 1,
+        Op::Checkpoint => 2,
+        _ => 1,
+    }
+}
+
+/// Ожидаемое состояние после сбоя сразу за LSN `crash_at`
+struct Model {
+    cells: Vec<u64>,
+    /// Транзакции без COMMIT/ABORT к моменту сбоя
+    losers: BTreeSet<TxnId>,
+    /// LSN всех CHECKPOINT_BEGIN
+    checkpoints: Vec<Lsn>,
+    last_lsn: Lsn,
+}
+
+fn model(ops: &[Op], crash_at: Lsn) -> Model {
+    let mut cells = vec![0u64; CELLS];
+    let mut pending: BTreeMap<TxnId, Vec<(usize, u64)>> = BTreeMap::new();
+    let mut updates: BTreeMap<TxnId, usize> = BTreeMap::new();
+    let mut losers = BTreeSet::new();
+    let mut checkpoints = Vec::new();
+    let mut lsn = INVALID_LSN;
+    for &op in ops {
+        let first = lsn + 1;
+        lsn += op_records(op, &updates);
+        if first > crash_at {
+            break;
+        }
+        match op {
+            Op::Begin(txn) => {
+                losers.insert(txn);
+            }
+            Op::Update { txn, cell, value } => {
+                pending.entry(txn).or_default().push((cell, value));
+                *updates.entry(txn).or_default() += 1;
+            }
+            Op::Commit(txn) => {
+                for (cell, value) in pending.remove(&txn).unwrap_or_default() {
+                    cells[cell] = value;
+                }
+                losers.remove(&txn);
+            }
+            // Откат завершён, только если дошёл до TXN_ABORT
+            Op::Rollback(txn) if lsn <= crash_at => {
+                losers.remove(&txn);
+            }
+            Op::Rollback(_) => {}
+            Op::Checkpoint => checkpoints.push(first),
+        }
+    }
+    Model { cells, losers, checkpoints, last_lsn: lsn }
+}
+
+// ============================================================================
+// Дочерний процесс
+// ============================================================================
+
+fn config(dir: &Path) -> DatabaseConfig {
+    DatabaseConfig {
+        data_path: dir.to_path_buf(),
+        page_size: PAGE_SIZE,
+        buffer_pool_size: FRAMES * PAGE_SIZE,
+        wal_segment_size: 2048,
+        ..DatabaseConfig::default()
+    }
+}
+
+fn cell_location(cell: usize) -> (PageId, usize) {
+    ((cell / CELLS_PER_PAGE) as PageId, PAGE_HEADER_SIZE + (cell % CELLS_PER_PAGE) * 8)
+}
+
+struct Child {
+    pool: BufferPool,
+    wal: Arc<Mutex<WalWriter>>,
+    dir: PathBuf,
+    crash_at: Lsn,
+    last: BTreeMap<TxnId, Lsn>,
+    done: BTreeMap<TxnId, Vec<LogRecord>>,
+}
+
+impl Child {
+    fn append(&mut self, record: &LogRecord) -> Lsn {
+        let lsn = self.wal.lock().unwrap().append(record).unwrap();
+        if record.txn_id != 0 {
+            self.last.insert(record.txn_id, lsn);
+        }
+        if lsn == self.crash_at && record.record_type != LogRecordType::CheckpointBegin {
+            self.crash();
+        }
+        lsn
+    }
+
+    /// Оставить `flushed_lsn` для проверки и упасть
+    fn crash(&self) -> ! {
+        let flushed = self.wal.lock().unwrap().flushed_lsn();
+        std::fs::write(self.dir.join(FLUSHED_FILE), flushed.to_string()).unwrap();
+        std::process::abort();
+    }
+
+    /// Записать `image` в страницу после записи в WAL (правило WAL)
+    fn write(&self, page_id: PageId, offset: usize, image: &[u8], lsn: Lsn) {
+        let page = self.pool.fetch_page(page_id).unwrap();
+        let mut data = page.write();
+        data[offset..offset + image.len()].copy_from_slice(image);
+        let mut header = PageHeader::decode(&data);
+        header.page_lsn = lsn;
+        header.encode(&mut data);
+    }
+
+    fn run(&mut self, op: Op) {
+        match op {
+            Op::Begin(txn) => {
+                self.append(&LogRecord::new(LogRecordType::TxnBegin, txn));
+            }
+            Op::Update { txn, cell, value } => {
+                let (page_id, offset) = cell_location(cell);
+                let page = self.pool.fetch_page(page_id).unwrap();
+                let before = page.read()[offset..offset + 8].to_vec();
+                drop(page);
+                let after = value.to_le_bytes();
+                let prev = self.last[&txn];
+                let record = LogRecord::update(txn, prev, page_id, offset as u16, &before, &after);
+                let lsn = self.append(&record);
+                self.write(page_id, offset, &after, lsn);
+                self.done.entry(txn).or_default().push(LogRecord { lsn, ..record });
+            }
+            Op::Commit(txn) => {
+                let commit = LogRecord::new(LogRecordType::TxnCommit, txn);
+                self.append(&LogRecord { prev_lsn: self.last[&txn], ..commit });
+            }
+            Op::Rollback(txn) => {
+                for undone in self.done.remove(&txn).unwrap_or_default().iter().rev() {
+                    let clr = LogRecord::compensation(undone, self.last[&txn]).unwrap();
+                    let lsn = self.append(&clr);
+                    let image = clr.redo_image().unwrap();
+                    self.write(clr.page_id, usize::from(clr.offset), &image, lsn);
+                }
+                let abort = LogRecord::new(LogRecordType::TxnAbort, txn);
+                self.append(&LogRecord { prev_lsn: self.last[&txn], ..abort });
+            }
+            Op::Checkpoint => {
+                let begin = self.append(&LogRecord::new(LogRecordType::CheckpointBegin, 0));
+                self.wal.lock().unwrap().flush().unwrap();
+                let dirty = self.pool.get_dirty_pages();
+                if begin == self.crash_at {
+                    // Сбой посреди checkpoint'а: сброшена только часть страниц
+                    self.pool.flush_pages(&dirty[..dirty.len() / 2]).unwrap();
+                    self.crash();
+                }
+                self.pool.flush_pages(&dirty).unwrap();
+                self.pool.sync_all().unwrap();
+                let end = self.wal.lock().unwrap().checkpoint_end(begin).unwrap();
+                if end == self.crash_at {
+                    self.crash();
+                }
+                self.wal.lock().unwrap().truncate_before(begin).unwrap();
+            }
+        }
+    }
+}
+
+/// Нагрузка в дочернем процессе; без переменных окружения тест ничего не делает
+#[test]
+fn crash_child() {
+    let (Ok(dir), Ok(crash_at)) = (std::env::var(CRASH_DIR_ENV), std::env::var(CRASH_AT_ENV)) else {
+        return;
+    };
+    let dir = PathBuf::from(dir);
+    let config = config(&dir);
+    let wal = WalWriter::create(config.wal_dir(), config.wal_segment_size).unwrap();
+    let wal = Arc::new(Mutex::new(wal));
+    let mut pool = BufferPool::new(&config).unwrap();
+    let hook = Arc::clone(&wal);
+    pool.set_wal_flush(move |lsn| hook.lock().unwrap().flush_to(lsn));
+    for _ in 0..PAGES {
+        pool.new_page().unwrap();
+    }
+    let crash_at = crash_at.parse().unwrap();
+    let (last, done) = (BTreeMap::new(), BTreeMap::new());
+    let mut child = Child { pool, wal, dir, crash_at, last, done };
+    for op in workload() {
+        child.run(op);
+    }
+    // Без сбоя процесс всё равно завершается без checkpoint'а при остановке
+    child.crash();
+}
+
+// ============================================================================
+// Проверка
+// ============================================================================
+
+/// Сбой после записи `crash_at`; при `lose_unflushed` WAL теряет всё,
+/// что не было сброшено
+fn crash_and_recover(root: &Path, ops: &[Op], crash_at: Lsn, lose_unflushed: bool) {
+    let dir = root.join(format!("crash_{crash_at}"));
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "crash_child", "--nocapture", "--test-threads=1"])
+        .env(CRASH_DIR_ENV, &dir)
+        .env(CRASH_AT_ENV, crash_at.to_string())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!status.success(), "child did not crash at LSN {crash_at}");
+
+    let flushed = std::fs::read_to_string(dir.join(FLUSHED_FILE)).unwrap().parse().unwrap();
+    assert!(flushed <= crash_at, "crash at LSN {crash_at}: flushed through {flushed}");
+    let crash_at = if lose_unflushed {
+        truncate_wal(&dir.join("wal"), flushed);
+        flushed
+    } else {
+        crash_at
+    };
+    let expected = model(ops, crash_at);
+    let config = config(&dir);
+    let report = recover(&config).unwrap_or_else(|e| panic!("crash at LSN {crash_at}: {e}"));
+    assert_eq!(report.wal_end_lsn, crash_at, "crash at LSN {crash_at}");
+    assert_eq!(
+        report.losers.iter().copied().collect::<BTreeSet<_>>(),
+        expected.losers,
+        "crash at LSN {crash_at}: {report}"
+    );
+    check_cells(&config, &expected.cells, crash_at);
+
+    // Второй запуск: всё уже восстановлено
+    let again = recover(&config).unwrap();
+    assert_eq!((again.redone, again.clrs_written), (0, 0), "crash at LSN {crash_at}: {again}");
+    assert!(again.losers.is_empty());
+    check_cells(&config, &expected.cells, crash_at);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Отрезать от WAL записи после `lsn`
+fn truncate_wal(dir: &Path, lsn: Lsn) {
+    let reader = WalReader::open_dir(dir).unwrap();
+    let Some(cut) = reader.records().map(Result::unwrap).find(|r| r.record.lsn > lsn) else {
+        return;
+    };
+    for segment in reader.segments() {
+        if segment.id == cut.segment_id {
+            let file = OpenOptions::new().write(true).open(&segment.path).unwrap();
+            file.set_len(cut.offset).unwrap();
+        } else if segment.id > cut.segment_id {
+            std::fs::remove_file(&segment.path).unwrap();
+        }
+    }
+}
+
+fn check_cells(config: &DatabaseConfig, expected: &[u64], crash_at: Lsn) {
+    let disk = DiskManager::open(config).unwrap();
+    let mut page = vec![0u8; PAGE_SIZE];
+    for (cell, &value) in expected.iter().enumerate() {
+        let (page_id, offset) = cell_location(cell);
+        let actual = if page_id < disk.page_count() {
+            disk.read_page(page_id, &mut page).unwrap();
+            u64::from_le_bytes(page[offset..offset + 8].try_into().unwrap())
+        } else {
+            0
+        };
+        assert_eq!(actual, value, "crash at LSN {crash_at}: cell {cell} (page {page_id})");
+    }
+}
+
+fn temp_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("datyredb_crash_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    root
+}
+
+#[test]
+fn recovers_after_crash_at_any_lsn() {
+    let ops = workload();
+    let full = model(&ops, Lsn::MAX);
+    assert!(full.last_lsn > 150 && full.checkpoints.len() == 4, "{} records", full.last_lsn);
+
+    // Каждая седьмая запись плюс каждый checkpoint (BEGIN — посреди сброса
+    // страниц, END — до удаления сегментов WAL) и последняя запись
+    let mut points: BTreeSet<Lsn> = (1..=full.last_lsn).step_by(7).collect();
+    for &begin in &full.checkpoints {
+        points.extend([begin, begin + 1]);
+    }
+    points.insert(full.last_lsn);
+
+    let root = temp_root("any_lsn");
+    for crash_at in points {
+        crash_and_recover(&root, &ops, crash_at, false);
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn recovers_after_losing_unflushed_wal() {
+    let ops = workload();
+    let last_lsn = model(&ops, Lsn::MAX).last_lsn;
+    let root = temp_root("unflushed");
+    for crash_at in (3..=last_lsn).step_by(5) {
+        crash_and_recover(&root, &ops, crash_at, true);
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
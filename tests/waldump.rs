@@ -0,0 +1,116 @@
+//! `datyre-waldump` на сгенерированном WAL: фильтры, JSON и `--stats`.
+
+use datyredb::config::DatabaseConfig;
+use datyredb::wal::{LogRecord, LogRecordType, WalWriter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("datyredb_waldump_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Две транзакции и checkpoint между ними:
+///
+/// ```text
+/// 1 TXN_BEGIN 1   2 INSERT 1 (page 3)   3 CHECKPOINT_BEGIN   4 CHECKPOINT_END
+/// 5 TXN_BEGIN 2   6 UPDATE 2 (page 5)   7 TXN_COMMIT 1       8 DELETE 2 (page 3)
+/// 9 TXN_ABORT 2
+/// ```
+fn generate(wal_dir: &Path) {
+    let mut wal = WalWriter::create(wal_dir, DatabaseConfig::default().wal_segment_size).unwrap();
+    let begin1 = wal.append(&LogRecord::new(LogRecordType::TxnBegin, 1)).unwrap();
+    let insert = wal.append(&LogRecord::insert(1, begin1, 3, 24, b"abcd")).unwrap();
+    let checkpoint = wal.checkpoint_begin().unwrap();
+    wal.checkpoint_end(checkpoint).unwrap();
+    let begin2 = wal.append(&LogRecord::new(LogRecordType::TxnBegin, 2)).unwrap();
+    let update = wal.append(&LogRecord::update(2, begin2, 5, 40, b"xy", b"zw")).unwrap();
+    wal.append(&LogRecord::commit(1, insert)).unwrap();
+    let delete = wal.append(&LogRecord::delete(2, update, 3, 24, b"abcd")).unwrap();
+    let abort = LogRecord { prev_lsn: delete, ..LogRecord::new(LogRecordType::TxnAbort, 2) };
+    assert_eq!(wal.append(&abort).unwrap(), 9);
+    wal.flush().unwrap();
+}
+
+/// Вывод `datyre-waldump` с `args`; рабочая директория пустая, чтобы не
+/// подхватить чужой `datyredb.toml`
+fn waldump(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_datyre-waldump"))
+        .args(args)
+        .arg(dir.join("wal"))
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Значение числового поля `"key":N` из строки JSON
+fn field(line: &str, key: &str) -> u64 {
+    let pattern = format!("\"{key}\":");
+    let start = line.find(&pattern).unwrap_or_else(|| panic!("no {key} in {line}")) + pattern.len();
+    let digits: String = line[start..].chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().unwrap()
+}
+
+fn lines<'a>(output: &'a str, kind: &str) -> Vec<&'a str> {
+    let kind = format!("{{\"kind\":\"{kind}\"");
+    output.lines().filter(|line| line.starts_with(&kind)).collect()
+}
+
+#[test]
+fn filters_apply_to_records_and_statistics() {
+    let dir = temp_dir("filters");
+    generate(&dir.join("wal"));
+
+    // Транзакция и тип: только изменения страниц транзакции 2
+    let output = waldump(&dir, &["--format", "json", "-x", "2", "-t", "update", "-t", "DELETE"]);
+    assert!(output.lines().all(|line| line.starts_with("{\"kind\":")), "{output}");
+    let records = lines(&output, "record");
+    let lsns: Vec<u64> = records.iter().map(|line| field(line, "lsn")).collect();
+    assert_eq!(lsns, [6, 8]);
+    assert!(records.iter().all(|line| field(line, "txn_id") == 2));
+    assert!(
+        records[0].contains("\"type\":\"UPDATE\"") && records[1].contains("\"type\":\"DELETE\"")
+    );
+    assert_eq!(field(records[0], "data_size"), 4);
+    let stats = lines(&output, "stats");
+    assert_eq!(stats.len(), 2);
+    assert!(stats.iter().all(|line| field(line, "count") == 1));
+    // Checkpoint'ы и конец WAL — по всему логу, несмотря на фильтр
+    let checkpoints = lines(&output, "checkpoint");
+    assert_eq!(checkpoints.len(), 1);
+    assert_eq!((field(checkpoints[0], "begin_lsn"), field(checkpoints[0], "end_lsn")), (3, 4));
+    let end = lines(&output, "end");
+    assert_eq!(end.len(), 1);
+    assert_eq!((field(end[0], "last_valid_lsn"), field(end[0], "records")), (9, 9));
+    assert!(end[0].ends_with("\"torn\":null}"));
+
+    // Диапазон LSN и страница
+    let output = waldump(&dir, &["--format=json", "--start", "2", "--end", "8", "-p", "3"]);
+    let lsns: Vec<u64> = lines(&output, "record").iter().map(|line| field(line, "lsn")).collect();
+    assert_eq!(lsns, [2, 8]);
+    assert_eq!(field(lines(&output, "end")[0], "last_valid_lsn"), 9);
+
+    // --stats: только таблица по типам, без записей и checkpoint'ов
+    let output = waldump(&dir, &["--stats", "--end", "4"]);
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|row| !row.is_empty())
+        .collect();
+    let names: Vec<&str> = rows.iter().map(|row| row[0]).collect();
+    assert_eq!(
+        names,
+        ["TYPE", "CHECKPOINT_BEGIN", "CHECKPOINT_END", "INSERT", "TXN_BEGIN", "TOTAL"]
+    );
+    assert!(rows[1..5].iter().all(|row| row[1] == "1"));
+    assert_eq!(rows[5][1], "4");
+    let json = waldump(&dir, &["--stats", "--format", "json"]);
+    assert_eq!(lines(&json, "stats").len(), 8);
+    assert_eq!(json.lines().count(), 8);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}